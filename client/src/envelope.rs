@@ -0,0 +1,80 @@
+//! The proof JSON accepted by `store_zk_proof`, assembled from the prover output and ID token.
+
+use serde::{Deserialize, Serialize};
+
+/// A G1 point, as base64-encoded compressed coordinates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct G1Point {
+	pub x: String,
+	pub y: String,
+}
+
+/// The coordinates of a G2 point, as base64-encoded compressed field elements.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct G2Coordinates {
+	pub c0: String,
+	pub c1: String,
+}
+
+/// A G2 point.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct G2Point {
+	pub x: G2Coordinates,
+	pub y: G2Coordinates,
+}
+
+/// What the prover outputs for a login.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProverOutput {
+	/// The curve the proof is made on, `bls12-381` (the default) or `bls12-377`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub curve: Option<String>,
+	pub a: G1Point,
+	pub b: G2Point,
+	pub c: G1Point,
+	/// The base64-encoded public input of single-input circuits.
+	pub public_hash: String,
+	/// The base64-encoded compressed verifying key.
+	pub verifying_key: String,
+	/// The base64-encoded public inputs of multi-input circuits.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub public_inputs: Vec<String>,
+}
+
+/// A proof ready to be submitted: the prover output along with the ID token it was made for and
+/// the chain it is meant for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofEnvelope {
+	#[serde(flatten)]
+	pub proof: ProverOutput,
+	pub jwt_token: String,
+	/// The hex-encoded genesis hash of the target chain.
+	pub chain_id: String,
+}
+
+impl ProofEnvelope {
+	/// Pair a prover output with the ID token it proves, for the chain with `genesis_hash`.
+	pub fn new(
+		proof: ProverOutput,
+		id_token: impl Into<String>,
+		genesis_hash: impl AsRef<[u8]>,
+	) -> Self {
+		let chain_id = genesis_hash.as_ref().iter().map(|byte| format!("{byte:02x}")).collect();
+		Self { proof, jwt_token: id_token.into(), chain_id }
+	}
+
+	/// Parse the prover output from its JSON and pair it with `id_token`, for the chain with
+	/// `genesis_hash`.
+	pub fn from_prover_json(
+		prover_json: &str,
+		id_token: impl Into<String>,
+		genesis_hash: impl AsRef<[u8]>,
+	) -> serde_json::Result<Self> {
+		Ok(Self::new(serde_json::from_str(prover_json)?, id_token, genesis_hash))
+	}
+
+	/// The JSON bytes to pass to `store_zk_proof`.
+	pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+		serde_json::to_vec(self)
+	}
+}
@@ -0,0 +1,177 @@
+//! Client for the DOT Login chain.
+//!
+//! Wraps [`subxt`] to assemble proof envelopes, submit them with `store_zk_proof` and watch the
+//! resulting `ZkProofStored` events, so integrators don't have to hand-roll the encoding.
+//!
+//! ```no_run
+//! # async fn run(prover_json: &str, id_token: &str) -> Result<(), dot_login_client::Error> {
+//! use dot_login_client::DotLoginClient;
+//! use subxt_signer::sr25519::dev;
+//!
+//! let client = DotLoginClient::new("ws://127.0.0.1:9944").await?;
+//! let envelope = client.envelope(prover_json, id_token)?;
+//! let stored = client.store_zk_proof(&dev::alice(), &envelope, None).await?;
+//! println!("proof {:?} stored in block {:?}", stored.event.hash, stored.block_hash);
+//! # Ok(())
+//! # }
+//! ```
+
+mod envelope;
+
+pub use envelope::{G1Point, G2Coordinates, G2Point, ProofEnvelope, ProverOutput};
+
+use subxt::{
+	dynamic::Value,
+	events::StaticEvent,
+	ext::scale_decode::DecodeAsType,
+	utils::{AccountId32, H256},
+	OnlineClient, PolkadotConfig,
+};
+use subxt_signer::sr25519::Keypair;
+
+/// The name of the zkproof pallet in the runtime.
+pub const PALLET: &str = "ZkProofModule";
+
+/// Errors returned by the client.
+#[derive(Debug)]
+pub enum Error {
+	/// Talking to the node failed, or the extrinsic failed on-chain.
+	Subxt(subxt::Error),
+	/// The proof envelope could not be (de)serialized.
+	Json(serde_json::Error),
+	/// The extrinsic succeeded without emitting `ZkProofStored`.
+	MissingEvent,
+}
+
+impl core::fmt::Display for Error {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Error::Subxt(e) => write!(f, "node error: {}", e),
+			Error::Json(e) => write!(f, "invalid proof envelope: {}", e),
+			Error::MissingEvent => write!(f, "no ZkProofStored event emitted"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<subxt::Error> for Error {
+	fn from(e: subxt::Error) -> Self {
+		Error::Subxt(e)
+	}
+}
+
+impl From<serde_json::Error> for Error {
+	fn from(e: serde_json::Error) -> Self {
+		Error::Json(e)
+	}
+}
+
+/// The `ZkProofStored` event of the zkproof pallet.
+#[derive(Clone, Debug, DecodeAsType)]
+#[decode_as_type(crate_path = "subxt::ext::scale_decode")]
+pub struct ZkProofStored {
+	/// The hash of the proof JSON.
+	pub hash: H256,
+	/// The submitter.
+	pub who: AccountId32,
+	/// The issuer of the token.
+	pub provider: Vec<u8>,
+	/// The commitment the proof is bound to.
+	pub commitment: [u8; 32],
+	/// The nullifier of the identity.
+	pub nullifier: [u8; 32],
+	/// The proof JSON, if the chain emits payloads.
+	pub json: Option<Vec<u8>>,
+}
+
+impl StaticEvent for ZkProofStored {
+	const PALLET: &'static str = PALLET;
+	const EVENT: &'static str = "ZkProofStored";
+}
+
+/// A proof stored on-chain.
+#[derive(Clone, Debug)]
+pub struct StoredProof {
+	/// The finalized block the proof was stored in.
+	pub block_hash: H256,
+	/// The event emitted when storing it.
+	pub event: ZkProofStored,
+}
+
+/// A connection to a DOT Login node.
+pub struct DotLoginClient {
+	api: OnlineClient<PolkadotConfig>,
+}
+
+impl DotLoginClient {
+	/// Connect to the node at `url`.
+	pub async fn new(url: &str) -> Result<Self, Error> {
+		Ok(Self { api: OnlineClient::from_url(url).await? })
+	}
+
+	/// Wrap an existing connection.
+	pub fn from_api(api: OnlineClient<PolkadotConfig>) -> Self {
+		Self { api }
+	}
+
+	/// The underlying subxt client.
+	pub fn api(&self) -> &OnlineClient<PolkadotConfig> {
+		&self.api
+	}
+
+	/// Parse the prover output from its JSON and pair it with `id_token`, bound to the connected
+	/// chain.
+	pub fn envelope(
+		&self,
+		prover_json: &str,
+		id_token: impl Into<String>,
+	) -> Result<ProofEnvelope, Error> {
+		Ok(ProofEnvelope::from_prover_json(prover_json, id_token, self.api.genesis_hash())?)
+	}
+
+	/// Submit `envelope` with `store_zk_proof`, along with the application `metadata` to store
+	/// with it, and wait until it is finalized.
+	pub async fn store_zk_proof(
+		&self,
+		signer: &Keypair,
+		envelope: &ProofEnvelope,
+		metadata: Option<&[u8]>,
+	) -> Result<StoredProof, Error> {
+		let metadata = match metadata {
+			Some(metadata) => Value::unnamed_variant("Some", [Value::from_bytes(metadata)]),
+			None => Value::unnamed_variant("None", []),
+		};
+		let call = subxt::dynamic::tx(
+			PALLET,
+			"store_zk_proof",
+			vec![Value::from_bytes(envelope.to_json()?), metadata],
+		);
+		let events = self
+			.api
+			.tx()
+			.sign_and_submit_then_watch_default(&call, signer)
+			.await?
+			.wait_for_finalized_success()
+			.await?;
+		let event = events.find_first::<ZkProofStored>()?.ok_or(Error::MissingEvent)?;
+
+		Ok(StoredProof { block_hash: events.block_hash(), event })
+	}
+
+	/// Call `on_proof` for every proof stored in the finalized blocks to come. Only returns on
+	/// error or when the node closes the subscription.
+	pub async fn watch_stored_proofs(
+		&self,
+		mut on_proof: impl FnMut(StoredProof),
+	) -> Result<(), Error> {
+		let mut blocks = self.api.blocks().subscribe_finalized().await?;
+		while let Some(block) = blocks.next().await {
+			let block = block?;
+			for event in block.events().await?.find::<ZkProofStored>() {
+				on_proof(StoredProof { block_hash: block.hash(), event: event? });
+			}
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,196 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+//! An example contract gating membership on DOT Login.
+//!
+//! Accounts join while the latest proof they stored with `pallet-zkproof` is valid, and each
+//! identity joins with a single account, which makes the member list sybil-resistant. The
+//! contract reads attestations through the runtime's chain extension, see
+//! `runtime/src/chain_extension.rs`.
+
+use ink::env::{DefaultEnvironment, Environment};
+
+/// The nullifier of an identity, which is the same for every proof of that identity.
+pub type Nullifier = [u8; 32];
+
+type AccountId = <DefaultEnvironment as Environment>::AccountId;
+
+/// The DOT Login chain extension.
+#[ink::chain_extension(extension = 1)]
+pub trait DotLogin {
+	type ErrorCode = DotLoginError;
+
+	/// The nullifier of the identity the latest proof stored by `account` attests to, and when
+	/// (unix seconds) the proof expires, if it is still valid.
+	#[ink(function = 1, handle_status = false)]
+	fn attestation(account: AccountId) -> Option<(Nullifier, u64)>;
+}
+
+/// The extension failed; it reports failures by trapping, so this is never returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum DotLoginError {
+	Failed,
+}
+
+impl ink::env::chain_extension::FromStatusCode for DotLoginError {
+	fn from_status_code(status_code: u32) -> Result<(), Self> {
+		match status_code {
+			0 => Ok(()),
+			_ => Err(Self::Failed),
+		}
+	}
+}
+
+/// The default environment, with the DOT Login chain extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(TypeInfo)]
+pub enum DotLoginEnvironment {}
+
+impl Environment for DotLoginEnvironment {
+	const MAX_EVENT_TOPICS: usize = <DefaultEnvironment as Environment>::MAX_EVENT_TOPICS;
+
+	type AccountId = AccountId;
+	type Balance = <DefaultEnvironment as Environment>::Balance;
+	type Hash = <DefaultEnvironment as Environment>::Hash;
+	type Timestamp = <DefaultEnvironment as Environment>::Timestamp;
+	type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+
+	type ChainExtension = DotLogin;
+}
+
+#[ink::contract(env = crate::DotLoginEnvironment)]
+mod attestation_gate {
+	use crate::Nullifier;
+	use ink::storage::Mapping;
+
+	/// Why an account could not join.
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	#[ink::scale_derive(Encode, Decode, TypeInfo)]
+	pub enum Error {
+		/// The account has no valid proof stored.
+		NotAttested,
+		/// The account is a member already.
+		AlreadyMember,
+		/// Another account joined with the same identity.
+		IdentityTaken,
+	}
+
+	/// An account joined.
+	#[ink(event)]
+	pub struct Joined {
+		#[ink(topic)]
+		member: AccountId,
+	}
+
+	#[ink(storage)]
+	#[derive(Default)]
+	pub struct AttestationGate {
+		/// The identity each member joined with.
+		members: Mapping<AccountId, Nullifier>,
+		/// The member each identity joined as.
+		identities: Mapping<Nullifier, AccountId>,
+	}
+
+	impl AttestationGate {
+		/// A gate without members.
+		#[ink(constructor)]
+		pub fn new() -> Self {
+			Self::default()
+		}
+
+		/// Join as the caller, with the identity its latest proof attests to.
+		#[ink(message)]
+		pub fn join(&mut self) -> Result<(), Error> {
+			let caller = self.env().caller();
+			if self.members.contains(caller) {
+				return Err(Error::AlreadyMember);
+			}
+			let (nullifier, _) =
+				self.env().extension().attestation(caller).ok_or(Error::NotAttested)?;
+			if self.identities.contains(nullifier) {
+				return Err(Error::IdentityTaken);
+			}
+
+			self.members.insert(caller, &nullifier);
+			self.identities.insert(nullifier, &caller);
+			self.env().emit_event(Joined { member: caller });
+			Ok(())
+		}
+
+		/// Whether `account` joined.
+		#[ink(message)]
+		pub fn is_member(&self, account: AccountId) -> bool {
+			self.members.contains(account)
+		}
+
+		/// When (unix seconds) the latest proof stored by `account` expires, if it is still valid.
+		#[ink(message)]
+		pub fn attested_until(&self, account: AccountId) -> Option<u64> {
+			self.env().extension().attestation(account).map(|(_, expires_at)| expires_at)
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+		use ink::{
+			env::{test, DefaultEnvironment},
+			scale::{Decode, Encode},
+		};
+
+		/// Attests accounts `alice` and `bob` to the same identity, and nobody else.
+		struct MockedDotLogin;
+
+		impl test::ChainExtension for MockedDotLogin {
+			fn ext_id(&self) -> u16 {
+				1
+			}
+
+			fn call(&mut self, func_id: u16, mut input: &[u8], output: &mut Vec<u8>) -> u32 {
+				assert_eq!(func_id, 1);
+				let accounts = test::default_accounts::<DefaultEnvironment>();
+				let account = AccountId::decode(&mut input).unwrap();
+				let attestation: Option<(Nullifier, u64)> =
+					[accounts.alice, accounts.bob].contains(&account).then_some(([7; 32], 5_600));
+				attestation.encode_to(output);
+				0
+			}
+		}
+
+		fn setup() -> (AttestationGate, test::DefaultAccounts<DefaultEnvironment>) {
+			test::register_chain_extension(MockedDotLogin);
+			(AttestationGate::new(), test::default_accounts::<DefaultEnvironment>())
+		}
+
+		#[ink::test]
+		fn attested_accounts_join_once() {
+			let (mut gate, accounts) = setup();
+
+			test::set_caller::<DefaultEnvironment>(accounts.alice);
+			assert_eq!(gate.attested_until(accounts.alice), Some(5_600));
+			assert_eq!(gate.join(), Ok(()));
+			assert!(gate.is_member(accounts.alice));
+			assert_eq!(gate.join(), Err(Error::AlreadyMember));
+		}
+
+		#[ink::test]
+		fn identities_join_with_a_single_account() {
+			let (mut gate, accounts) = setup();
+
+			test::set_caller::<DefaultEnvironment>(accounts.alice);
+			assert_eq!(gate.join(), Ok(()));
+			test::set_caller::<DefaultEnvironment>(accounts.bob);
+			assert_eq!(gate.join(), Err(Error::IdentityTaken));
+			assert!(!gate.is_member(accounts.bob));
+		}
+
+		#[ink::test]
+		fn unattested_accounts_are_turned_away() {
+			let (mut gate, accounts) = setup();
+
+			test::set_caller::<DefaultEnvironment>(accounts.charlie);
+			assert_eq!(gate.attested_until(accounts.charlie), None);
+			assert_eq!(gate.join(), Err(Error::NotAttested));
+		}
+	}
+}
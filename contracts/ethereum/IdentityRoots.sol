@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MIT-0
+pragma solidity ^0.8.20;
+
+/// @title Identity roots of a DOT Login chain.
+/// @notice Accepts "identity X logged in on DOT Login" given a Merkle proof against an identity
+/// root, as exported by the `zkproof_identityProof` RPC method.
+/// @dev The roots are relayed by `relayer`, e.g. a bridge verifying the headers carrying them in
+/// their `DLIR` digest. The proofs follow the layout of Substrate's `binary-merkle-tree` with
+/// keccak-256: leaves are the hashes of the identity commitments, a node is the hash of its
+/// children concatenated, and the last node of an odd row is promoted as is.
+contract IdentityRoots {
+    /// @notice The account allowed to post roots.
+    address public immutable relayer;
+
+    /// @notice The identity root carried by the header of each block.
+    mapping(uint32 => bytes32) public roots;
+
+    /// @notice The latest block a root was posted for.
+    uint32 public latestBlock;
+
+    event RootPosted(uint32 indexed blockNumber, bytes32 root);
+
+    error NotRelayer();
+    error StaleRoot();
+
+    constructor(address relayer_) {
+        relayer = relayer_;
+    }
+
+    /// @notice Post the identity root carried by the header of block `blockNumber`.
+    function postRoot(uint32 blockNumber, bytes32 root) external {
+        if (msg.sender != relayer) revert NotRelayer();
+        if (blockNumber <= latestBlock) revert StaleRoot();
+        roots[blockNumber] = root;
+        latestBlock = blockNumber;
+        emit RootPosted(blockNumber, root);
+    }
+
+    /// @notice Whether the identity bound to `commitment` is covered by the root of block
+    /// `blockNumber`.
+    function isVerified(
+        uint32 blockNumber,
+        bytes32 commitment,
+        bytes32[] calldata proof,
+        uint256 leafIndex,
+        uint256 leaves
+    ) external view returns (bool) {
+        bytes32 root = roots[blockNumber];
+        return root != bytes32(0) &&
+            computeRoot(keccak256(abi.encodePacked(commitment)), proof, leafIndex, leaves) == root;
+    }
+
+    /// @notice The root of the tree of `leaves` leaves in which `leaf` is at `leafIndex`, given
+    /// the siblings on its path, or zero if the proof does not fit the tree.
+    function computeRoot(
+        bytes32 leaf,
+        bytes32[] calldata proof,
+        uint256 leafIndex,
+        uint256 leaves
+    ) public pure returns (bytes32) {
+        if (leafIndex >= leaves) return bytes32(0);
+        bytes32 node = leaf;
+        uint256 position = leafIndex;
+        uint256 width = leaves;
+        uint256 i = 0;
+        while (width > 1) {
+            if (position % 2 == 1) {
+                if (i == proof.length) return bytes32(0);
+                node = keccak256(abi.encodePacked(proof[i++], node));
+            } else if (position + 1 < width) {
+                if (i == proof.length) return bytes32(0);
+                node = keccak256(abi.encodePacked(node, proof[i++]));
+            }
+            position /= 2;
+            width = (width + 1) / 2;
+        }
+        return i == proof.length ? node : bytes32(0);
+    }
+}
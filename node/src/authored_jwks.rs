@@ -0,0 +1,97 @@
+//! Fetches the providers' JWKS for the JWKS inherent.
+//!
+//! With `--authored-jwks`, a background task fetches the JWKS of every registered provider every
+//! [`FETCH_INTERVAL`], and the node supplies the latest keysets as inherent data of the blocks it
+//! authors. The runtime only applies them if it trusts block authors with the keys of the
+//! providers, and only the keysets that changed.
+
+use crate::service::FullClient;
+use node_template_runtime::pallet_zkproof::{
+	cache_expiry, inherent::InherentDataProvider, parse_jwks, runtime_api::ZkProofApi, JwkRecord,
+	JwksUpdate,
+};
+use sc_service::SpawnTaskHandle;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How often the keys are fetched.
+const FETCH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How long to wait for a JWKS endpoint to answer.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The keysets last fetched, shared with the inherent data providers.
+#[derive(Clone, Default)]
+pub struct JwksFetcher {
+	latest: Arc<Mutex<Vec<JwksUpdate>>>,
+}
+
+impl JwksFetcher {
+	/// Start fetching the keys of the providers registered at the best block of `client`.
+	pub fn start(client: Arc<FullClient>, spawn_handle: &SpawnTaskHandle) -> Self {
+		let fetcher = Self::default();
+		let latest = fetcher.latest.clone();
+		spawn_handle.spawn_blocking("authored-jwks", None, async move {
+			loop {
+				let updates = fetch_all(&client);
+				*latest.lock().expect("the lock is never poisoned; qed") = updates;
+				std::thread::sleep(FETCH_INTERVAL);
+			}
+		});
+		fetcher
+	}
+
+	/// The inherent data provider supplying the keysets last fetched.
+	pub fn inherent_data_provider(&self) -> InherentDataProvider {
+		InherentDataProvider::new(
+			self.latest.lock().expect("the lock is never poisoned; qed").clone(),
+		)
+	}
+}
+
+/// Fetch the keys of every provider registered at the best block, skipping the ones that fail.
+fn fetch_all(client: &FullClient) -> Vec<JwksUpdate> {
+	let providers = match client.runtime_api().providers(client.info().best_hash) {
+		Ok(providers) => providers,
+		Err(e) => {
+			log::warn!("Failed to list the providers to fetch the JWKS of: {}", e);
+			return Vec::new();
+		},
+	};
+
+	providers
+		.into_iter()
+		.filter_map(|(provider, info)| match fetch(&info.jwks_uri) {
+			Ok((keys, expires_at)) => Some(JwksUpdate { provider, keys, expires_at }),
+			Err(e) => {
+				log::warn!(
+					"Failed to fetch JWKS of {:?}: {}",
+					String::from_utf8_lossy(&provider),
+					e
+				);
+				None
+			},
+		})
+		.collect()
+}
+
+/// Fetch and parse a JWKS document, along with its cache expiry.
+fn fetch(uri: &[u8]) -> Result<(Vec<JwkRecord>, Option<u64>), String> {
+	let uri = std::str::from_utf8(uri).map_err(|e| e.to_string())?;
+	let response = ureq::get(uri).timeout(FETCH_TIMEOUT).call().map_err(|e| e.to_string())?;
+
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_err(|e| e.to_string())?
+		.as_secs();
+	let expires_at =
+		cache_expiry(response.header("cache-control"), response.header("expires"), now);
+
+	let body = response.into_string().map_err(|e| e.to_string())?;
+	let keys = parse_jwks(body.as_bytes()).ok_or("not a JWKS document")?;
+	Ok((keys, expires_at))
+}
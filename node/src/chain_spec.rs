@@ -1,3 +1,4 @@
+use crate::dev_oidc;
 use node_template_runtime::{AccountId, RuntimeGenesisConfig, Signature, WASM_BINARY};
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -33,15 +34,9 @@ pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
 	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
 }
 
-pub fn development_config() -> Result<ChainSpec, String> {
-	Ok(ChainSpec::builder(
-		WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?,
-		None,
-	)
-	.with_name("Development")
-	.with_id("dev")
-	.with_chain_type(ChainType::Development)
-	.with_genesis_config_patch(testnet_genesis(
+/// The development chain. With `dev_oidc`, it trusts the issuer served by `--dev-oidc`.
+pub fn development_config(dev_oidc: bool) -> Result<ChainSpec, String> {
+	let mut genesis = testnet_genesis(
 		// Initial PoA authorities
 		vec![authority_keys_from_seed("Alice")],
 		// Sudo account
@@ -54,7 +49,19 @@ pub fn development_config() -> Result<ChainSpec, String> {
 			get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
 		],
 		true,
-	))
+	);
+	if dev_oidc {
+		dev_oidc::register_in_genesis(&mut genesis);
+	}
+
+	Ok(ChainSpec::builder(
+		WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?,
+		None,
+	)
+	.with_name("Development")
+	.with_id("dev")
+	.with_chain_type(ChainType::Development)
+	.with_genesis_config_patch(genesis)
 	.build())
 }
 
@@ -109,6 +116,10 @@ fn testnet_genesis(
 		"grandpa": {
 			"authorities": initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect::<Vec<_>>(),
 		},
+		"zkProofModule": {
+			// Let the sudo account feed JWKS updates until real oracles are registered.
+			"oracles": vec![root_key.clone()],
+		},
 		"sudo": {
 			// Assign network admin rights.
 			"key": Some(root_key),
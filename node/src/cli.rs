@@ -1,4 +1,5 @@
 use sc_cli::RunCmd;
+use std::net::SocketAddr;
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -7,6 +8,47 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	/// Serve a local OpenID Connect issuer signing test tokens, trusted by the development chain.
+	#[arg(long)]
+	pub dev_oidc: bool,
+
+	/// Fetch the keys of the OIDC providers and supply them to the blocks this node authors, for
+	/// runtimes that trust block authors with them.
+	#[arg(long)]
+	pub authored_jwks: bool,
+
+	/// Serve an HTTP endpoint on the given address, e.g. `127.0.0.1:9956`, turning an ID token and
+	/// the output of the prover into a login submitted to the transaction pool.
+	#[arg(long, value_name = "ADDR")]
+	pub zklogin_gateway: Option<SocketAddr>,
+
+	/// Let `--zklogin-gateway` serve addresses other than loopback ones. Requests carry the
+	/// secret keys of users over plain HTTP, so only set this behind a TLS proxy.
+	#[arg(long)]
+	pub unsafe_zklogin_gateway_external: bool,
+
+	/// Author blocks on demand instead of in Aura slots, for tests of the login flow on
+	/// development chains. Replaces Aura and GRANDPA: `instant` finalizes blocks as it seals them,
+	/// `manual` only when asked to by the RPC calls.
+	#[arg(long, value_enum, value_name = "MODE")]
+	pub sealing: Option<Sealing>,
+
+	/// The number of verified proofs the node remembers, so that a proof verified when its
+	/// transaction entered the pool is not verified again when its block is authored or imported.
+	/// 0 disables the cache.
+	#[arg(long, value_name = "COUNT", default_value_t = 4096)]
+	pub verification_cache: usize,
+}
+
+/// How a development node authors blocks with `--sealing`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sealing {
+	/// Author a block as soon as a transaction enters the pool.
+	Instant,
+	/// Author a block on each `engine_createBlock` RPC call, finalized if the call sets
+	/// `finalize` or on a later `engine_finalizeBlock` call.
+	Manual,
 }
 
 #[derive(Debug, clap::Subcommand)]
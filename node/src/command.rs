@@ -2,7 +2,7 @@ use crate::{
 	benchmarking::{inherent_benchmark_data, RemarkBuilder, TransferKeepAliveBuilder},
 	chain_spec,
 	cli::{Cli, Subcommand},
-	service,
+	dev_oidc, service,
 };
 use frame_benchmarking_cli::{BenchmarkCmd, ExtrinsicFactory, SUBSTRATE_REFERENCE_HARDWARE};
 use node_template_runtime::{Block, EXISTENTIAL_DEPOSIT};
@@ -37,7 +37,7 @@ impl SubstrateCli for Cli {
 
 	fn load_spec(&self, id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
 		Ok(match id {
-			"dev" => Box::new(chain_spec::development_config()?),
+			"dev" => Box::new(chain_spec::development_config(self.dev_oidc)?),
 			"" | "local" => Box::new(chain_spec::local_testnet_config()?),
 			path => {
 				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?)
@@ -118,7 +118,10 @@ pub fn run() -> sc_cli::Result<()> {
 							);
 						}
 
-						cmd.run::<sp_runtime::traits::HashingFor<Block>, ()>(config)
+						cmd.run::<
+							sp_runtime::traits::HashingFor<Block>,
+							node_template_runtime::pallet_zkproof::host::HostFunctions,
+						>(config)
 					},
 					BenchmarkCmd::Block(cmd) => {
 						let PartialComponents { client, .. } = service::new_partial(&config)?;
@@ -182,8 +185,21 @@ pub fn run() -> sc_cli::Result<()> {
 		},
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
+			let serve_dev_oidc = cli.dev_oidc;
+			let authored_jwks = cli.authored_jwks;
+			let zklogin_gateway = cli
+				.zklogin_gateway
+				.map(|address| (address, cli.unsafe_zklogin_gateway_external));
+			let sealing = cli.sealing;
+			node_template_runtime::pallet_zkproof::host::cache::enable(cli.verification_cache);
 			runner.run_node_until_exit(|config| async move {
-				service::new_full(config).map_err(sc_cli::Error::Service)
+				let task_manager =
+					service::new_full(config, authored_jwks, zklogin_gateway, sealing)
+						.map_err(sc_cli::Error::Service)?;
+				if serve_dev_oidc {
+					dev_oidc::start(&task_manager.spawn_handle())?;
+				}
+				Ok(task_manager)
 			})
 		},
 	}
@@ -0,0 +1,223 @@
+//! A local OpenID Connect issuer for development chains.
+//!
+//! With `--dev-oidc`, the node serves [`DEV_OIDC_ISSUER`] itself and the development chain trusts
+//! its key from genesis, so the whole login flow can be exercised offline. The issuer serves:
+//!
+//! - `/.well-known/openid-configuration`, the discovery document;
+//! - `/jwks`, its signing key, as fetched by the JWKS oracle;
+//! - `/token?sub=..&nonce=..`, a signed ID token for `sub`. `aud`, `email` and `iat` may also be
+//!   set; `iat` defaults to the current time.
+//!
+//! The signing key is fixed, so a token only depends on its claims. Never use it outside of tests.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use node_template_runtime::{
+	pallet_zkproof::{google_jwks, JwkRecord, GOOGLE_ISSUER, GOOGLE_JWKS_URI},
+	DEV_OIDC_ISSUER,
+};
+use rsa::{
+	pkcs1::DecodeRsaPrivateKey,
+	pkcs1v15::SigningKey,
+	signature::{SignatureEncoding, Signer},
+	traits::PublicKeyParts,
+	RsaPrivateKey,
+};
+use sc_service::SpawnTaskHandle;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{
+	io::{self, BufRead, BufReader, Write},
+	net::{TcpListener, TcpStream},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The id of the signing key.
+pub const KEY_ID: &str = "dev-oidc-1";
+
+/// The audience of tokens not requesting one.
+const DEFAULT_AUDIENCE: &str = "dot-login-dev";
+
+/// How long (seconds) tokens are valid for.
+const TOKEN_LIFETIME: u64 = 3600;
+
+/// The signing key, in PKCS#1 PEM.
+const SIGNING_KEY: &str = include_str!("dev_oidc_key.pem");
+
+/// The issuer identifier, which is also its base URL.
+fn issuer() -> &'static str {
+	std::str::from_utf8(DEV_OIDC_ISSUER).expect("the issuer is ASCII; qed")
+}
+
+fn signing_key() -> RsaPrivateKey {
+	RsaPrivateKey::from_pkcs1_pem(SIGNING_KEY).expect("the development key is valid; qed")
+}
+
+/// The signing key as a JWK.
+fn jwk() -> Value {
+	let key = signing_key();
+	json!({
+		"kid": KEY_ID,
+		"alg": "RS256",
+		"kty": "RSA",
+		"use": "sig",
+		"n": URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+		"e": URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+	})
+}
+
+/// The signing key as registered on-chain.
+fn jwk_record() -> JwkRecord {
+	let jwk = jwk();
+	let field = |name: &str| jwk[name].as_str().unwrap_or_default().to_owned();
+	JwkRecord::new(&field("kid"), &field("alg"), &field("kty"), &field("n"), &field("e"))
+		.expect("the development key fits the on-chain bounds; qed")
+}
+
+/// Register the issuer and its key in a genesis config patch, next to Google.
+pub fn register_in_genesis(genesis: &mut Value) {
+	let jwks_uri = format!("{}/jwks", issuer());
+	genesis["zkProofModule"]["providers"] =
+		json!([(GOOGLE_ISSUER, GOOGLE_JWKS_URI), (DEV_OIDC_ISSUER, jwks_uri.as_bytes())]);
+	genesis["zkProofModule"]["jwks"] =
+		json!([(GOOGLE_ISSUER, google_jwks()), (DEV_OIDC_ISSUER, vec![jwk_record()])]);
+}
+
+/// A signed ID token for `sub`, the other claims being taken from `params` or defaulted.
+fn id_token(sub: &str, params: &[(String, String)]) -> Option<String> {
+	let param =
+		|name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+	let iat = match param("iat") {
+		Some(iat) => iat.parse().ok()?,
+		None => SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs(),
+	};
+
+	let header = json!({ "alg": "RS256", "kid": KEY_ID, "typ": "JWT" });
+	let mut payload = json!({
+		"iss": issuer(),
+		"sub": sub,
+		"aud": param("aud").unwrap_or(DEFAULT_AUDIENCE),
+		"iat": iat,
+		"exp": iat + TOKEN_LIFETIME,
+	});
+	if let Some(nonce) = param("nonce") {
+		payload["nonce"] = nonce.into();
+	}
+	if let Some(email) = param("email") {
+		payload["email"] = email.into();
+		payload["email_verified"] = true.into();
+	}
+
+	let signing_input = format!(
+		"{}.{}",
+		URL_SAFE_NO_PAD.encode(header.to_string()),
+		URL_SAFE_NO_PAD.encode(payload.to_string())
+	);
+	let signature = SigningKey::<Sha256>::new(signing_key()).sign(signing_input.as_bytes());
+	Some(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature.to_bytes())))
+}
+
+/// Decode the `key=value` pairs of a URL query.
+fn query_params(query: &str) -> Vec<(String, String)> {
+	query
+		.split('&')
+		.filter(|pair| !pair.is_empty())
+		.map(|pair| {
+			let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+			(percent_decode(key), percent_decode(value))
+		})
+		.collect()
+}
+
+fn percent_decode(input: &str) -> String {
+	let mut bytes = Vec::with_capacity(input.len());
+	let mut rest = input.as_bytes();
+	while let Some((&byte, tail)) = rest.split_first() {
+		let escaped = match (byte, tail) {
+			(b'%', [high, low, ..]) => std::str::from_utf8(&[*high, *low])
+				.ok()
+				.and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+			_ => None,
+		};
+		match (byte, escaped) {
+			(_, Some(escaped)) => {
+				bytes.push(escaped);
+				rest = &tail[2..];
+			},
+			(b'+', None) => {
+				bytes.push(b' ');
+				rest = tail;
+			},
+			(byte, None) => {
+				bytes.push(byte);
+				rest = tail;
+			},
+		}
+	}
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The status and body of the response to `GET target`.
+fn respond(target: &str) -> (&'static str, Value) {
+	let (path, query) = target.split_once('?').unwrap_or((target, ""));
+	match path {
+		"/.well-known/openid-configuration" => (
+			"200 OK",
+			json!({
+				"issuer": issuer(),
+				"jwks_uri": format!("{}/jwks", issuer()),
+				"token_endpoint": format!("{}/token", issuer()),
+				"id_token_signing_alg_values_supported": ["RS256"],
+			}),
+		),
+		"/jwks" => ("200 OK", json!({ "keys": [jwk()] })),
+		"/token" => {
+			let params = query_params(query);
+			let sub = params.iter().find(|(key, _)| key == "sub").map(|(_, value)| value.as_str());
+			match sub.and_then(|sub| id_token(sub, &params)) {
+				Some(token) => ("200 OK", json!({ "id_token": token, "token_type": "Bearer" })),
+				None => ("400 Bad Request", json!({ "error": "invalid_request" })),
+			}
+		},
+		_ => ("404 Not Found", json!({ "error": "not_found" })),
+	}
+}
+
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+	let mut reader = BufReader::new(&stream);
+	let mut request_line = String::new();
+	reader.read_line(&mut request_line)?;
+	// Drain the headers, the requests served having no body.
+	let mut header = String::new();
+	while reader.read_line(&mut header)? > 2 {
+		header.clear();
+	}
+
+	let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+	let (status, body) = respond(target);
+	let body = body.to_string();
+	write!(
+		stream,
+		"HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+		Cache-Control: max-age={}\r\nConnection: close\r\n\r\n{}",
+		status,
+		body.len(),
+		TOKEN_LIFETIME,
+		body
+	)
+}
+
+/// Start serving the issuer. Fails if its address is taken.
+pub fn start(spawn_handle: &SpawnTaskHandle) -> io::Result<()> {
+	let address = issuer().trim_start_matches("http://");
+	let listener = TcpListener::bind(address)?;
+	log::info!("🔑 Development OIDC issuer listening on {}", issuer());
+
+	spawn_handle.spawn_blocking("dev-oidc", None, async move {
+		for stream in listener.incoming() {
+			if let Err(e) = stream.and_then(handle) {
+				log::warn!("Development OIDC issuer failed to serve a request: {}", e);
+			}
+		}
+	});
+	Ok(())
+}
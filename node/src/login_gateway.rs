@@ -0,0 +1,256 @@
+//! An HTTP gateway submitting logins, for apps without a Substrate SDK.
+//!
+//! With `--zklogin-gateway <addr>`, the node serves `POST /login` on `addr`. The request carries
+//! the ID token, the output of the prover and the ephemeral key the token was requested for:
+//!
+//! ```json
+//! { "id_token": "<JWT>", "proof": { "a": .., "b": .., "c": .., "public_inputs": [..], .. },
+//!   "key": "<secret URI of the ephemeral sr25519 key>" }
+//! ```
+//!
+//! The gateway assembles the proof envelope for this chain, signs `store_zk_proof` with the key
+//! and submits it to the local transaction pool, answering with the account and the hash of the
+//! transaction. The key travels with the request over plain HTTP, so the gateway only binds
+//! loopback addresses unless `--unsafe-zklogin-gateway-external` is set, for operators who put it
+//! behind a TLS proxy of their own.
+
+use crate::{benchmarking::create_extrinsic, service::FullClient};
+use node_template_runtime::{opaque::Block, pallet_zkproof, AccountId, Nonce, RuntimeCall};
+use sc_service::SpawnTaskHandle;
+use sc_transaction_pool_api::{TransactionPool, TransactionSource};
+use serde_json::{json, Value};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{crypto::Ss58Codec, sr25519, Pair};
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{SocketAddr, TcpListener, TcpStream},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
+};
+use substrate_frame_rpc_system::AccountNonceApi;
+
+/// The largest request body accepted, enough for a token and a proof with its verifying key.
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+/// The longest request head accepted: the request line and the headers.
+const MAX_HEAD_LENGTH: u64 = 8 * 1024;
+
+/// The longest request line or header line accepted.
+const MAX_LINE_LENGTH: u64 = 1024;
+
+/// The most headers accepted.
+const MAX_HEADERS: usize = 32;
+
+/// The most connections served at once. Further connections are dropped until one is done.
+const MAX_CONNECTIONS: usize = 16;
+
+/// How long a client has to send its request and read the response, however slowly it trickles
+/// its bytes, before the connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The transaction pool logins are submitted to.
+type Pool = sc_transaction_pool::FullPool<Block, FullClient>;
+
+/// The proof envelope of `request`, for the chain with `genesis_hash`.
+fn envelope(request: &Value, genesis_hash: &str) -> Result<Vec<u8>, &'static str> {
+	let id_token = request["id_token"].as_str().ok_or("missing id_token")?;
+	let mut proof = request["proof"].clone();
+	let fields = proof.as_object_mut().ok_or("missing proof")?;
+	fields.insert("jwt_token".into(), id_token.into());
+	fields.insert("chain_id".into(), genesis_hash.into());
+	fields.entry("public_hash").or_insert_with(|| "".into());
+	fields.entry("verifying_key").or_insert_with(|| "".into());
+	Ok(proof.to_string().into_bytes())
+}
+
+/// Sign and submit the login carried by `body`, answering with the status and body of the
+/// response.
+fn login(client: &FullClient, pool: &Pool, body: &[u8]) -> (&'static str, Value) {
+	let bad_request = |error: &str| ("400 Bad Request", json!({ "error": error }));
+	let Ok(request) = serde_json::from_slice::<Value>(body) else {
+		return bad_request("invalid JSON");
+	};
+	let Some(Ok(key)) = request["key"].as_str().map(|suri| sr25519::Pair::from_string(suri, None))
+	else {
+		return bad_request("missing or invalid key");
+	};
+	let info = client.info();
+	let json = match envelope(&request, &format!("{:x}", info.genesis_hash)) {
+		Ok(json) => json,
+		Err(error) => return bad_request(error),
+	};
+
+	let account = AccountId::from(key.public());
+	let nonce: Nonce = match client.runtime_api().account_nonce(info.best_hash, account.clone()) {
+		Ok(nonce) => nonce,
+		Err(e) => return ("500 Internal Server Error", json!({ "error": e.to_string() })),
+	};
+	let call =
+		RuntimeCall::ZkProofModule(pallet_zkproof::Call::store_zk_proof { json, metadata: None });
+	let extrinsic = create_extrinsic(client, key, call, nonce);
+	let submission = pool.submit_one(info.best_hash, TransactionSource::External, extrinsic.into());
+	match futures::executor::block_on(submission) {
+		Ok(hash) =>
+			("200 OK", json!({ "account": account.to_ss58check(), "hash": format!("{:?}", hash) })),
+		Err(e) => bad_request(&e.to_string()),
+	}
+}
+
+/// A connection whose reads and writes fail once its deadline passed.
+struct Connection {
+	stream: TcpStream,
+	deadline: Instant,
+}
+
+impl Connection {
+	/// The time left until the deadline.
+	fn remaining(&self) -> io::Result<Duration> {
+		let remaining = self.deadline.saturating_duration_since(Instant::now());
+		if remaining.is_zero() {
+			return Err(io::ErrorKind::TimedOut.into())
+		}
+		Ok(remaining)
+	}
+}
+
+impl Read for Connection {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.stream.set_read_timeout(Some(self.remaining()?))?;
+		self.stream.read(buf)
+	}
+}
+
+impl Write for Connection {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.stream.set_write_timeout(Some(self.remaining()?))?;
+		self.stream.write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()
+	}
+}
+
+/// The request line and the `Content-Length` of the request read from `reader`, or `None` if its
+/// head exceeds `MAX_HEAD_LENGTH`, `MAX_LINE_LENGTH` or `MAX_HEADERS`.
+fn read_head(reader: &mut impl BufRead) -> io::Result<Option<(String, usize)>> {
+	let mut head = Read::take(reader, MAX_HEAD_LENGTH);
+	// Whether a whole line was read: lines cut short by a limit or the end of the stream are not.
+	let mut read_line = |line: &mut String| -> io::Result<bool> {
+		line.clear();
+		Read::take(&mut head, MAX_LINE_LENGTH).read_line(line)?;
+		Ok(line.ends_with('\n'))
+	};
+
+	let mut request_line = String::new();
+	if !read_line(&mut request_line)? {
+		return Ok(None)
+	}
+	let mut content_length = 0;
+	let mut header = String::new();
+	for _ in 0..=MAX_HEADERS {
+		if !read_line(&mut header)? {
+			return Ok(None)
+		}
+		if header.trim_end().is_empty() {
+			return Ok(Some((request_line, content_length)))
+		}
+		if let Some((name, value)) = header.split_once(':') {
+			if name.eq_ignore_ascii_case("content-length") {
+				content_length = value.trim().parse().unwrap_or(usize::MAX);
+			}
+		}
+	}
+	Ok(None)
+}
+
+fn handle(stream: TcpStream, client: &FullClient, pool: &Pool) -> io::Result<()> {
+	let mut connection = Connection { stream, deadline: Instant::now() + REQUEST_TIMEOUT };
+	let mut reader = BufReader::new(&mut connection);
+	let Some((request_line, content_length)) = read_head(&mut reader)? else {
+		return respond(
+			&mut connection,
+			"431 Request Header Fields Too Large",
+			json!({ "error": "request head too large" }),
+		)
+	};
+
+	let mut parts = request_line.split_whitespace();
+	let (status, body) = match (parts.next(), parts.next()) {
+		(Some("POST"), Some("/login")) if content_length <= MAX_BODY_LENGTH => {
+			let mut body = vec![0; content_length];
+			reader.read_exact(&mut body)?;
+			login(client, pool, &body)
+		},
+		(Some("POST"), Some("/login")) =>
+			("413 Payload Too Large", json!({ "error": "request too large" })),
+		_ => ("404 Not Found", json!({ "error": "not_found" })),
+	};
+	respond(&mut connection, status, body)
+}
+
+fn respond(connection: &mut Connection, status: &str, body: Value) -> io::Result<()> {
+	let body = body.to_string();
+	write!(
+		connection,
+		"HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+		Connection: close\r\n\r\n{}",
+		status,
+		body.len(),
+		body
+	)
+}
+
+/// Start serving the gateway on `address`, each connection on a task of its own. Fails if the
+/// address is taken, or is not a loopback address without `external`.
+pub fn start(
+	address: SocketAddr,
+	external: bool,
+	client: Arc<FullClient>,
+	pool: Arc<Pool>,
+	spawn_handle: &SpawnTaskHandle,
+) -> io::Result<()> {
+	if !address.ip().is_loopback() && !external {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"requests carry secret keys over plain HTTP, so only loopback addresses are served \
+			without --unsafe-zklogin-gateway-external",
+		))
+	}
+	let listener = TcpListener::bind(address)?;
+	log::info!("🚪 Login gateway listening on http://{}", address);
+
+	let spawner = spawn_handle.clone();
+	let connections = Arc::new(AtomicUsize::new(0));
+	spawn_handle.spawn_blocking("zklogin-gateway", None, async move {
+		for stream in listener.incoming() {
+			let stream = match stream {
+				Ok(stream) => stream,
+				Err(e) => {
+					log::warn!("Login gateway failed to accept a connection: {}", e);
+					continue
+				},
+			};
+			if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+				connections.fetch_sub(1, Ordering::SeqCst);
+				log::warn!(
+					"Login gateway dropped a connection: {} are served already",
+					MAX_CONNECTIONS
+				);
+				continue
+			}
+			let (client, pool, connections) = (client.clone(), pool.clone(), connections.clone());
+			spawner.spawn_blocking("zklogin-gateway-connection", None, async move {
+				if let Err(e) = handle(stream, &client, &pool) {
+					log::warn!("Login gateway failed to serve a request: {}", e);
+				}
+				connections.fetch_sub(1, Ordering::SeqCst);
+			});
+		}
+	});
+	Ok(())
+}
@@ -1,10 +1,13 @@
 //! Substrate Node Template CLI library.
 #![warn(missing_docs)]
 
+mod authored_jwks;
 mod benchmarking;
 mod chain_spec;
 mod cli;
 mod command;
+mod dev_oidc;
+mod login_gateway;
 mod rpc;
 mod service;
 
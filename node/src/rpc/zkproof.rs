@@ -0,0 +1,446 @@
+//! RPC methods describing the logins the chain accepts and the proofs it stores, for dApp
+//! configuration UIs, backends and operators.
+//!
+//! `zkproof_listProviders` lists the registered providers through `ZkProofApi`, along with the
+//! ids of the keys their tokens are verified against and when those keys expire.
+//!
+//! `zkproof_identityProof` exports the proof that an identity is covered by an identity root, for
+//! Ethereum contracts accepting the roots relayed from the headers, see
+//! `contracts/ethereum/IdentityRoots.sol`.
+//!
+//! `zkproof_subscribeStoredProofs` notifies the proofs stored in finalized blocks. Subscribers
+//! filter them by account, identity commitment, provider or dApp on the node, rather than streaming
+//! every `ZkProofStored` event to discard most of them.
+//!
+//! `zkproof_estimateFee` prices the transaction storing a proof with the runtime's fee
+//! calculation, for wallets to show the cost of a login before it is confirmed.
+//!
+//! `zkproof_storageStats` reports how much state the pallet holds, for operators to plan for its
+//! growth.
+
+use std::sync::Arc;
+
+use codec::{Decode, Encode};
+use futures::{stream, FutureExt, StreamExt};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::{error::ErrorObject, ErrorObjectOwned},
+	PendingSubscriptionSink,
+};
+use node_template_runtime::{
+	opaque::Block,
+	pallet_zk_session::runtime_api::ZkSessionApi,
+	pallet_zkproof::{self, runtime_api::ZkProofApi as ZkProofRuntimeApi, Commitment},
+	AccountId, Balance, BlockNumber, Hash, RuntimeCall, RuntimeEvent, Signature,
+	UncheckedExtrinsic, Weight,
+};
+use pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi;
+use sc_client_api::{BlockchainEvents, StorageProvider};
+use sc_rpc::{utils::pipe_from_stream, SubscriptionTaskExecutor};
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	hashing::{keccak_256, twox_128, twox_64},
+	sr25519,
+	storage::StorageKey,
+	Bytes, H256,
+};
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{benchmarking::signed_extra, service::FullBackend};
+
+/// A registered provider, in human-readable form.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderListing {
+	/// The issuer of the provider's tokens, which is also its base URL.
+	pub issuer: String,
+	/// The URL the provider serves its JWKS from.
+	pub jwks_uri: String,
+	/// The ids of the keys the provider's tokens are currently accepted with.
+	pub key_ids: Vec<String>,
+	/// When (unix seconds) the current keyset expires, if it ever does.
+	pub keys_expire_at: Option<u64>,
+	/// Whether no key of the provider is accepted, because its keyset is missing or expired past
+	/// the grace period, or its issuer is not trusted.
+	pub stale: bool,
+}
+
+/// A proof that an identity is covered by an identity root, in the form Ethereum contracts verify.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedIdentityProof {
+	/// The block whose header carries the root.
+	pub block: BlockNumber,
+	/// The hash of that block.
+	pub block_hash: Hash,
+	/// The identity root.
+	pub root: H256,
+	/// The leaf of the identity: the keccak-256 hash of its commitment.
+	pub leaf: H256,
+	/// The hashes of the siblings on the path from the leaf to the root, bottom up.
+	pub proof: Vec<H256>,
+	/// The position of the leaf.
+	pub leaf_index: u32,
+	/// The number of leaves of the tree.
+	pub leaves: u32,
+}
+
+/// The stored proofs a subscription is notified of: those matching every field set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProofFilter {
+	/// The account that stored the proof.
+	pub account: Option<AccountId>,
+	/// The identity commitment the proof is bound to.
+	pub commitment: Option<H256>,
+	/// The issuer of the token the proof was made for.
+	pub provider: Option<String>,
+	/// The client id of the dApp the proof was stored in the namespace of.
+	pub dapp: Option<String>,
+}
+
+impl ProofFilter {
+	fn matches(&self, who: &AccountId, provider: &[u8], commitment: &Commitment) -> bool {
+		self.account.as_ref().map_or(true, |account| account == who) &&
+			self.provider.as_ref().map_or(true, |issuer| issuer.as_bytes() == provider) &&
+			self.commitment.map_or(true, |filter| filter.0 == *commitment)
+	}
+
+	fn matches_dapp(&self, dapp: Option<&str>) -> bool {
+		self.dapp.as_deref().map_or(true, |filter| dapp == Some(filter))
+	}
+}
+
+/// A proof stored in a finalized block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredProofNotification {
+	/// The block the proof was stored in.
+	pub block_hash: Hash,
+	/// The hash of the proof.
+	pub hash: Hash,
+	/// The account that stored the proof.
+	pub who: AccountId,
+	/// The issuer of the token the proof was made for.
+	pub provider: String,
+	/// The identity commitment the proof is bound to.
+	pub commitment: H256,
+	/// The nullifier of the identity.
+	pub nullifier: H256,
+	/// The client id of the dApp the proof was stored in the namespace of, if any.
+	pub dapp: Option<String>,
+}
+
+/// The expected cost of storing a proof.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeEstimate {
+	/// The weight the transaction is charged for.
+	pub weight: Weight,
+	/// The length of the signed transaction, in bytes.
+	pub length: u32,
+	/// The fee charged for the weight and the length of the transaction, tip excluded.
+	pub fee: Balance,
+}
+
+/// The storage footprint of the pallet, and the sessions opened with its proofs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+	/// The raw proofs stored.
+	pub proofs: u64,
+	/// The total length of the raw proofs stored, in bytes.
+	pub proof_bytes: u64,
+	/// The attestations of stored proofs.
+	pub attestations: u64,
+	/// The device keys registered to identities.
+	pub device_keys: u64,
+	/// The keys of the providers' current keysets.
+	pub jwks_keys: u64,
+	/// The encoded size of the providers' current keysets, in bytes.
+	pub jwks_bytes: u64,
+	/// The sessions users opened with dApps, expired ones included until they are pruned.
+	pub sessions: u64,
+}
+
+impl From<pallet_zkproof::StorageStats> for StorageUsage {
+	fn from(stats: pallet_zkproof::StorageStats) -> Self {
+		Self {
+			proofs: stats.proofs,
+			proof_bytes: stats.proof_bytes,
+			attestations: stats.attestations,
+			device_keys: stats.device_keys,
+			jwks_keys: stats.jwks_keys,
+			jwks_bytes: stats.jwks_bytes,
+			sessions: 0,
+		}
+	}
+}
+
+/// Login configuration and proof RPC methods.
+#[rpc(server)]
+pub trait ZkProofApi<BlockHash> {
+	/// The registered providers and the keys their tokens are accepted with, as of block `at` or
+	/// the best block.
+	#[method(name = "zkproof_listProviders")]
+	fn list_providers(&self, at: Option<BlockHash>) -> RpcResult<Vec<ProviderListing>>;
+
+	/// The proof that the identity bound to `commitment` is covered by the latest identity root
+	/// as of block `at` or the best block, if it is.
+	#[method(name = "zkproof_identityProof")]
+	fn identity_proof(
+		&self,
+		commitment: H256,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<ExportedIdentityProof>>;
+
+	/// Notify the proofs stored in the blocks finalized from now on that match `filter`, or all
+	/// of them without a filter.
+	#[subscription(
+		name = "zkproof_subscribeStoredProofs" => "zkproof_storedProof",
+		unsubscribe = "zkproof_unsubscribeStoredProofs",
+		item = StoredProofNotification
+	)]
+	fn subscribe_stored_proofs(&self, filter: Option<ProofFilter>);
+
+	/// The fee of a transaction storing `proof` as of block `at` or the best block. The proof is
+	/// not verified.
+	#[method(name = "zkproof_estimateFee")]
+	fn estimate_fee(&self, proof: Bytes, at: Option<BlockHash>) -> RpcResult<FeeEstimate>;
+
+	/// The proofs, attestations, device keys and keysets the pallet stores, their size, and the
+	/// sessions opened with dApps, as of block `at` or the best block.
+	#[method(name = "zkproof_storageStats")]
+	fn storage_stats(&self, at: Option<BlockHash>) -> RpcResult<StorageUsage>;
+}
+
+/// Serves [`ZkProofApiServer`] from the runtime and the finalized blocks of `client`.
+pub struct ZkProof<C> {
+	client: Arc<C>,
+	executor: SubscriptionTaskExecutor,
+}
+
+impl<C> ZkProof<C> {
+	/// Serve the methods from `client`, running subscriptions on `executor`.
+	pub fn new(client: Arc<C>, executor: SubscriptionTaskExecutor) -> Self {
+		Self { client, executor }
+	}
+}
+
+/// The error code of failed runtime calls.
+const RUNTIME_ERROR: i32 = 1;
+
+fn runtime_error(error: impl ToString) -> ErrorObjectOwned {
+	ErrorObject::owned(RUNTIME_ERROR, "Unable to query the runtime", Some(error.to_string()))
+}
+
+/// The proofs stored in block `at` that match `filter`.
+fn stored_proofs<C>(client: &C, at: Hash, filter: &ProofFilter) -> Vec<StoredProofNotification>
+where
+	C: StorageProvider<Block, FullBackend>,
+{
+	let key = StorageKey([twox_128(b"System"), twox_128(b"Events")].concat());
+	let events = match client.storage(at, &key) {
+		Ok(Some(events)) => events,
+		Ok(None) => return Vec::new(),
+		Err(e) => {
+			log::warn!("Failed to read the events of block {:?}: {}", at, e);
+			return Vec::new();
+		},
+	};
+	let events =
+		match Vec::<frame_system::EventRecord<RuntimeEvent, Hash>>::decode(&mut &events.0[..]) {
+			Ok(events) => events,
+			Err(e) => {
+				log::warn!("Failed to decode the events of block {:?}: {}", at, e);
+				return Vec::new();
+			},
+		};
+
+	events
+		.into_iter()
+		.filter_map(|record| match record.event {
+			RuntimeEvent::ZkProofModule(pallet_zkproof::Event::ZkProofStored {
+				hash,
+				who,
+				provider,
+				commitment,
+				nullifier,
+				..
+			}) if filter.matches(&who, &provider, &commitment) => {
+				let dapp = proof_namespace(client, at, &hash);
+				filter.matches_dapp(dapp.as_deref()).then(|| StoredProofNotification {
+					block_hash: at,
+					hash,
+					who,
+					provider: String::from_utf8_lossy(&provider).into_owned(),
+					commitment: commitment.into(),
+					nullifier: nullifier.into(),
+					dapp,
+				})
+			},
+			_ => None,
+		})
+		.collect()
+}
+
+/// The client id of the dApp whose namespace the proof `hash` was stored in as of block `at`, as
+/// recorded in `ProofNamespaces`.
+fn proof_namespace<C>(client: &C, at: Hash, hash: &Hash) -> Option<String>
+where
+	C: StorageProvider<Block, FullBackend>,
+{
+	let key = StorageKey(
+		[
+			&twox_128(b"ZkProofModule")[..],
+			&twox_128(b"ProofNamespaces"),
+			&twox_64(hash.as_ref()),
+			hash.as_ref(),
+		]
+		.concat(),
+	);
+	let client_id = match client.storage(at, &key) {
+		Ok(client_id) => client_id?,
+		Err(e) => {
+			log::warn!("Failed to read the namespace of proof {:?}: {}", hash, e);
+			return None;
+		},
+	};
+	let client_id = pallet_zkproof::ClientId::decode(&mut &client_id.0[..]).ok()?;
+	Some(String::from_utf8_lossy(&client_id).into_owned())
+}
+
+impl<C> ZkProofApiServer<<Block as BlockT>::Hash> for ZkProof<C>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C: BlockchainEvents<Block> + StorageProvider<Block, FullBackend>,
+	C::Api: ZkProofRuntimeApi<Block, AccountId, BlockNumber>,
+	C::Api: ZkSessionApi<Block, AccountId, BlockNumber>,
+	C::Api: TransactionPaymentRuntimeApi<Block, Balance>,
+{
+	fn list_providers(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<ProviderListing>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.providers(at)
+			.map_err(runtime_error)?
+			.into_iter()
+			.map(|(provider, info)| {
+				let keys = api.jwks(at, provider.clone()).map_err(runtime_error)?;
+				let keys_expire_at =
+					api.jwks_expiry(at, provider.clone()).map_err(runtime_error)?;
+				Ok(ProviderListing {
+					issuer: String::from_utf8_lossy(&provider).into_owned(),
+					jwks_uri: String::from_utf8_lossy(&info.jwks_uri).into_owned(),
+					key_ids: keys
+						.iter()
+						.map(|key| String::from_utf8_lossy(&key.kid).into_owned())
+						.collect(),
+					keys_expire_at,
+					stale: keys.is_empty(),
+				})
+			})
+			.collect()
+	}
+
+	fn identity_proof(
+		&self,
+		commitment: H256,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<ExportedIdentityProof>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let Some(root) = api.identity_root(at).map_err(runtime_error)? else {
+			return Ok(None);
+		};
+
+		// The proof is made in the state the root was computed in.
+		let block_hash = self
+			.client
+			.hash(root.block)
+			.map_err(runtime_error)?
+			.ok_or_else(|| runtime_error("the block of the identity root is unknown"))?;
+		let proof = api.identity_proof(block_hash, commitment.0).map_err(runtime_error)?;
+		Ok(proof.map(|proof| ExportedIdentityProof {
+			block: root.block,
+			block_hash,
+			root: proof.root,
+			leaf: keccak_256(commitment.as_bytes()).into(),
+			proof: proof.proof,
+			leaf_index: proof.leaf_index,
+			leaves: proof.leaves,
+		}))
+	}
+
+	fn subscribe_stored_proofs(
+		&self,
+		pending: PendingSubscriptionSink,
+		filter: Option<ProofFilter>,
+	) {
+		let client = self.client.clone();
+		let filter = filter.unwrap_or_default();
+		// Blocks finalized together are notified at once; their proofs are notified in order.
+		let proofs = self
+			.client
+			.finality_notification_stream()
+			.flat_map(move |notification| {
+				let proofs = notification
+					.tree_route
+					.iter()
+					.chain([&notification.hash])
+					.flat_map(|at| stored_proofs(&*client, *at, &filter))
+					.collect::<Vec<_>>();
+				stream::iter(proofs)
+			})
+			.boxed();
+
+		self.executor.spawn(
+			"zkproof-rpc-subscription",
+			Some("rpc"),
+			pipe_from_stream(pending, proofs).boxed(),
+		);
+	}
+
+	fn estimate_fee(
+		&self,
+		proof: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<FeeEstimate> {
+		let info = self.client.info();
+		let at = at.unwrap_or(info.best_hash);
+
+		// The length is that of the signed transaction, whose signature is the same size whoever
+		// signs it.
+		let call = RuntimeCall::ZkProofModule(pallet_zkproof::Call::store_zk_proof {
+			json: proof.0,
+			metadata: None,
+		});
+		let extrinsic = UncheckedExtrinsic::new_signed(
+			call,
+			AccountId::new([0; 32]).into(),
+			Signature::Sr25519(sr25519::Signature::from_raw([0; 64])),
+			signed_extra(info.best_number, 0),
+		);
+		let length = extrinsic.encoded_size() as u32;
+		let dispatch_info = self
+			.client
+			.runtime_api()
+			.query_info(at, extrinsic.into(), length)
+			.map_err(runtime_error)?;
+		Ok(FeeEstimate { weight: dispatch_info.weight, length, fee: dispatch_info.partial_fee })
+	}
+
+	fn storage_stats(&self, at: Option<<Block as BlockT>::Hash>) -> RpcResult<StorageUsage> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let api = self.client.runtime_api();
+		let stats = api.storage_stats(at).map_err(runtime_error)?;
+		let sessions = api.total_sessions(at).map_err(runtime_error)?;
+		Ok(StorageUsage { sessions, ..stats.into() })
+	}
+}
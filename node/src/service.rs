@@ -1,22 +1,26 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
+use crate::{authored_jwks::JwksFetcher, cli::Sealing};
 use futures::FutureExt;
 use node_template_runtime::{self, opaque::Block, RuntimeApi};
 use sc_client_api::{Backend, BlockBackend};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 use sc_consensus_grandpa::SharedVoterState;
+use sc_consensus_manual_seal::consensus::aura::AuraConsensusDataProvider;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager, WarpSyncParams};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sc_transaction_pool_api::OffchainTransactionPoolFactory;
-use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
-use std::{sync::Arc, time::Duration};
+use sp_blockchain::HeaderBackend;
+use sp_consensus_aura::sr25519::{AuthorityPair as AuraPair, AuthoritySignature as AuraSignature};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
-pub(crate) type FullClient = sc_service::TFullClient<
-	Block,
-	RuntimeApi,
-	sc_executor::WasmExecutor<sp_io::SubstrateHostFunctions>,
->;
-type FullBackend = sc_service::TFullBackend<Block>;
+/// The host functions the runtime may call.
+type HostFunctions =
+	(sp_io::SubstrateHostFunctions, node_template_runtime::pallet_zkproof::host::HostFunctions);
+
+pub(crate) type FullClient =
+	sc_service::TFullClient<Block, RuntimeApi, sc_executor::WasmExecutor<HostFunctions>>;
+pub(crate) type FullBackend = sc_service::TFullBackend<Block>;
 type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
 
 /// The minimum period of blocks on which justifications will be
@@ -48,7 +52,7 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		})
 		.transpose()?;
 
-	let executor = sc_service::new_wasm_executor::<sp_io::SubstrateHostFunctions>(config);
+	let executor = sc_service::new_wasm_executor::<HostFunctions>(config);
 	let (client, backend, keystore_container, task_manager) =
 		sc_service::new_full_parts::<Block, RuntimeApi, _>(
 			config,
@@ -123,8 +127,19 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 	})
 }
 
-/// Builds a new service for a full client.
-pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
+/// Builds a new service for a full client. With `authored_jwks`, the node supplies the keys of
+/// the providers to the blocks it authors. With `zklogin_gateway`, it serves the login gateway on
+/// that address, external ones included if its flag is set. With `sealing`, it authors blocks on demand instead of running Aura and GRANDPA.
+pub fn new_full(
+	config: Configuration,
+	authored_jwks: bool,
+	zklogin_gateway: Option<(SocketAddr, bool)>,
+	sealing: Option<Sealing>,
+) -> Result<TaskManager, ServiceError> {
+	if sealing.is_some() && config.chain_spec.chain_type() != sc_service::ChainType::Development {
+		return Err(ServiceError::Other("Sealing is only available on development chains".into()))
+	}
+
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -166,6 +181,17 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		})?;
 
 	if config.offchain_worker.enabled {
+		// On development chains Alice is the genesis JWKS oracle, so give the offchain worker her
+		// key to sign submissions with.
+		if config.chain_spec.chain_type() == sc_service::ChainType::Development {
+			sp_keystore::Keystore::sr25519_generate_new(
+				&*keystore_container.keystore(),
+				node_template_runtime::pallet_zkproof::KEY_TYPE,
+				Some("//Alice"),
+			)
+			.map_err(|e| ServiceError::Other(format!("Failed to insert the oracle key: {}", e)))?;
+		}
+
 		task_manager.spawn_handle().spawn(
 			"offchain-workers-runner",
 			"offchain-worker",
@@ -193,13 +219,22 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
 
+	// With manual sealing, a block is authored on each `engine_createBlock` call.
+	let (command_sink, commands_stream) = futures::channel::mpsc::channel(1024);
+	let command_sink = (sealing == Some(Sealing::Manual)).then_some(command_sink);
+
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
 
-		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+		Box::new(move |deny_unsafe, subscription_executor| {
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				subscription_executor,
+				command_sink: command_sink.clone(),
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -219,6 +254,17 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		telemetry: telemetry.as_mut(),
 	})?;
 
+	if let Some((address, external)) = zklogin_gateway {
+		crate::login_gateway::start(
+			address,
+			external,
+			client.clone(),
+			transaction_pool.clone(),
+			&task_manager.spawn_handle(),
+		)
+		.map_err(|e| ServiceError::Other(format!("Failed to start the login gateway: {}", e)))?;
+	}
+
 	if role.is_authority() {
 		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
@@ -229,6 +275,76 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		);
 
 		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+		let jwks_fetcher = authored_jwks
+			.then(|| JwksFetcher::start(client.clone(), &task_manager.spawn_handle()))
+			.unwrap_or_default();
+
+		if let Some(sealing) = sealing {
+			// Sealed blocks still carry an Aura slot, each one past the slot of its parent, so
+			// that the runtime accepts blocks authored in quick succession. Their timestamps run
+			// ahead of the clock by as much.
+			let header_client = client.clone();
+			let create_inherent_data_providers = move |parent_hash, ()| {
+				let jwks = jwks_fetcher.inherent_data_provider();
+				let parent_slot = header_client
+					.header(parent_hash)
+					.ok()
+					.flatten()
+					.and_then(|header| {
+						sc_consensus_aura::find_pre_digest::<Block, AuraSignature>(&header).ok()
+					})
+					.map_or(0, u64::from);
+				async move {
+					let next_slot = (parent_slot + 1) * slot_duration.as_millis();
+					let now = sp_timestamp::Timestamp::current().as_millis();
+					let timestamp =
+						sp_timestamp::InherentDataProvider::new(now.max(next_slot).into());
+
+					Ok((timestamp, jwks))
+				}
+			};
+			let consensus_data_provider = AuraConsensusDataProvider::new(client.clone());
+
+			let seal = match sealing {
+				Sealing::Instant => sc_consensus_manual_seal::run_instant_seal_and_finalize(
+					sc_consensus_manual_seal::InstantSealParams {
+						block_import: client.clone(),
+						env: proposer_factory,
+						client,
+						pool: transaction_pool.clone(),
+						select_chain,
+						consensus_data_provider: Some(Box::new(consensus_data_provider)),
+						create_inherent_data_providers,
+					},
+				)
+				.boxed(),
+				Sealing::Manual => sc_consensus_manual_seal::run_manual_seal(
+					sc_consensus_manual_seal::ManualSealParams {
+						block_import: client.clone(),
+						env: proposer_factory,
+						client,
+						pool: transaction_pool.clone(),
+						commands_stream,
+						select_chain,
+						consensus_data_provider: Some(Box::new(consensus_data_provider)),
+						create_inherent_data_providers,
+					},
+				)
+				.boxed(),
+			};
+
+			// Without Aura, the node authors no block if sealing fails. GRANDPA is not started
+			// either: instant sealing finalizes blocks as it seals them, and manual sealing only
+			// finalizes blocks through `engine_createBlock` with `finalize` set, or through
+			// `engine_finalizeBlock`.
+			task_manager.spawn_essential_handle().spawn_blocking(
+				"manual-seal",
+				Some("block-authoring"),
+				seal,
+			);
+			network_starter.start_network();
+			return Ok(task_manager)
+		}
 
 		let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(
 			StartAuraParams {
@@ -237,16 +353,19 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 				select_chain,
 				block_import,
 				proposer_factory,
-				create_inherent_data_providers: move |_, ()| async move {
-					let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
-
-					let slot =
-						sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
-							*timestamp,
-							slot_duration,
-						);
-
-					Ok((slot, timestamp))
+				create_inherent_data_providers: move |_, ()| {
+					let jwks = jwks_fetcher.inherent_data_provider();
+					async move {
+						let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+
+						let slot =
+							sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+								*timestamp,
+								slot_duration,
+							);
+
+						Ok((slot, timestamp, jwks))
+					}
 				},
 				force_authoring,
 				backoff_authoring_blocks,
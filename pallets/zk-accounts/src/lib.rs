@@ -0,0 +1,256 @@
+//! # ZK Accounts Pallet
+//!
+//! Numbered sub-accounts of DOT Login identities, e.g. to keep the funds used with each dApp
+//! apart.
+//!
+//! Sub-account `n` of an identity, `identity/n`, is derived from the nullifier of the identity
+//! and `n`, so it is the same whichever account logs in with the identity. Accounts holding a
+//! valid proof of the identity create its sub-accounts, fund them, dispatch calls on their
+//! behalf, and dissolve them, sweeping their funds back.
+//!
+//! Wallets list the sub-accounts of a user through the [`runtime_api::ZkAccountsApi`].
+
+// We make sure this pallet uses `no_std` for compiling to Wasm.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod test;
+
+pub mod runtime_api;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::RuntimeDebug;
+use scale_info::TypeInfo;
+
+/// The number of a sub-account among the sub-accounts of an identity.
+pub type SubAccountIndex = u16;
+
+/// Domain separator of sub-account derivation.
+const SUB_ACCOUNT_CONTEXT: &[u8] = b"dot-login/sub-account";
+
+/// A sub-account of an identity.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct SubAccount<AccountId, BlockNumber> {
+    /// The derived account.
+    pub account: AccountId,
+    /// The block the sub-account was created in.
+    pub created_at: BlockNumber,
+}
+
+#[frame_support::pallet(dev_mode)]
+pub mod pallet {
+    use super::*;
+    use alloc::{boxed::Box, vec::Vec};
+    use frame_support::{
+        dispatch::{extract_actual_weight, GetDispatchInfo, PostDispatchInfo},
+        pallet_prelude::*,
+        traits::{
+            fungible::{Inspect, Mutate},
+            tokens::{Fortitude, Preservation},
+            OriginTrait,
+        },
+    };
+    use frame_system::pallet_prelude::*;
+    use pallet_zkproof::{AttestationInspector, Nullifier};
+    use sp_runtime::traits::{Dispatchable, TrailingZeroInput};
+
+    pub type BalanceOf<T> = <<T as Config>::Currency as Inspect<<T as frame_system::Config>::AccountId>>::Balance;
+
+    #[pallet::pallet]
+    pub struct Pallet<T>(_);
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// The overarching runtime event type.
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+        /// The calls sub-accounts dispatch.
+        type RuntimeCall: Parameter
+            + Dispatchable<RuntimeOrigin = Self::RuntimeOrigin, PostInfo = PostDispatchInfo>
+            + GetDispatchInfo;
+        /// The attestations telling which identity an account logs in with, usually
+        /// `pallet-zkproof`.
+        type Attestations: AttestationInspector<Self::AccountId>;
+        /// The currency sub-accounts are funded with.
+        type Currency: Mutate<Self::AccountId>;
+        /// The number of sub-accounts of an identity: their indexes are below it.
+        type MaxSubAccounts: Get<SubAccountIndex>;
+    }
+
+    /// The sub-accounts of each identity, keyed by nullifier and index.
+    #[pallet::storage]
+    pub type SubAccounts<T: Config> = StorageDoubleMap<
+        _,
+        Identity,
+        Nullifier,
+        Twox64Concat,
+        SubAccountIndex,
+        SubAccount<T::AccountId, BlockNumberFor<T>>,
+        OptionQuery,
+    >;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// Sub-account `index` of an identity was created.
+        SubAccountCreated { nullifier: Nullifier, index: SubAccountIndex, account: T::AccountId },
+        /// `who` funded sub-account `index` of its identity with `amount`.
+        SubAccountFunded { nullifier: Nullifier, index: SubAccountIndex, who: T::AccountId, amount: BalanceOf<T> },
+        /// Sub-account `index` of an identity dispatched a call on behalf of `who`.
+        SubAccountCalled { nullifier: Nullifier, index: SubAccountIndex, who: T::AccountId, result: DispatchResult },
+        /// Sub-account `index` of an identity was dissolved, and `amount` swept to `who`.
+        SubAccountDissolved { nullifier: Nullifier, index: SubAccountIndex, who: T::AccountId, amount: BalanceOf<T> },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// The account holds no valid proof.
+        NotAttested,
+        /// The index is not below `MaxSubAccounts`.
+        InvalidIndex,
+        /// The identity has a sub-account with this index already.
+        SubAccountExists,
+        /// The identity has no sub-account with this index.
+        UnknownSubAccount,
+        /// The account's proof expired; `renew_attestation` extends it.
+        AttestationExpired,
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Create sub-account `index` of the identity the caller logs in with.
+        ///
+        /// # Errors
+        /// - `NotAttested`: If the caller holds no valid proof.
+        /// - `AttestationExpired`: If the caller's proof expired.
+        /// - `InvalidIndex`: If `index` is not below `MaxSubAccounts`.
+        /// - `SubAccountExists`: If the identity has a sub-account with this index already.
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 1))]
+        pub fn create_sub_account(origin: OriginFor<T>, index: SubAccountIndex) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let nullifier = Self::identity_of(&who)?;
+            ensure!(index < T::MaxSubAccounts::get(), Error::<T>::InvalidIndex);
+            ensure!(!SubAccounts::<T>::contains_key(nullifier, index), Error::<T>::SubAccountExists);
+
+            let account = Self::derive(&nullifier, index);
+            let created_at = frame_system::Pallet::<T>::block_number();
+            SubAccounts::<T>::insert(nullifier, index, SubAccount { account: account.clone(), created_at });
+
+            Self::deposit_event(Event::SubAccountCreated { nullifier, index, account });
+            Ok(())
+        }
+
+        /// Transfer `amount` from the caller to sub-account `index` of its identity, keeping the
+        /// caller alive.
+        ///
+        /// # Errors
+        /// - `NotAttested`: If the caller holds no valid proof.
+        /// - `AttestationExpired`: If the caller's proof expired.
+        /// - `UnknownSubAccount`: If the identity has no sub-account with this index.
+        /// - Any error of the transfer.
+        #[pallet::weight(T::DbWeight::get().reads_writes(6, 2))]
+        pub fn fund_sub_account(origin: OriginFor<T>, index: SubAccountIndex, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let (nullifier, sub_account) = Self::sub_account_of(&who, index)?;
+
+            T::Currency::transfer(&who, &sub_account.account, amount, Preservation::Preserve)?;
+
+            Self::deposit_event(Event::SubAccountFunded { nullifier, index, who, amount });
+            Ok(())
+        }
+
+        /// Dispatch `call` with sub-account `index` of the caller's identity as the signed origin.
+        ///
+        /// The call's own result is reported by the `SubAccountCalled` event.
+        ///
+        /// # Errors
+        /// - `NotAttested`: If the caller holds no valid proof.
+        /// - `AttestationExpired`: If the caller's proof expired.
+        /// - `UnknownSubAccount`: If the identity has no sub-account with this index.
+        #[pallet::weight({
+            let info = call.get_dispatch_info();
+            (T::DbWeight::get().reads(4).saturating_add(info.weight), info.class)
+        })]
+        pub fn sub_account_call(
+            origin: OriginFor<T>,
+            index: SubAccountIndex,
+            call: Box<<T as Config>::RuntimeCall>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin.clone())?;
+            let (nullifier, sub_account) = Self::sub_account_of(&who, index)?;
+
+            let info = call.get_dispatch_info();
+            let mut origin = origin;
+            origin.set_caller_from(frame_system::RawOrigin::Signed(sub_account.account));
+            let result = call.dispatch(origin);
+
+            let weight = T::DbWeight::get().reads(4).saturating_add(extract_actual_weight(&result, &info));
+            let result = result.map(|_| ()).map_err(|e| e.error);
+            Self::deposit_event(Event::SubAccountCalled { nullifier, index, who, result });
+            Ok(Some(weight).into())
+        }
+
+        /// Dissolve sub-account `index` of the caller's identity, sweeping all its transferable
+        /// funds to the caller. The index may be used again; it derives the same account.
+        ///
+        /// # Errors
+        /// - `NotAttested`: If the caller holds no valid proof.
+        /// - `AttestationExpired`: If the caller's proof expired.
+        /// - `UnknownSubAccount`: If the identity has no sub-account with this index.
+        /// - Any error of the transfer.
+        #[pallet::weight(T::DbWeight::get().reads_writes(6, 3))]
+        pub fn dissolve_sub_account(origin: OriginFor<T>, index: SubAccountIndex) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let (nullifier, sub_account) = Self::sub_account_of(&who, index)?;
+
+            let amount =
+                T::Currency::reducible_balance(&sub_account.account, Preservation::Expendable, Fortitude::Polite);
+            T::Currency::transfer(&sub_account.account, &who, amount, Preservation::Expendable)?;
+            SubAccounts::<T>::remove(nullifier, index);
+
+            Self::deposit_event(Event::SubAccountDissolved { nullifier, index, who, amount });
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Sub-account `index` of the identity with `nullifier`, whether it was created or not.
+        pub fn derive(nullifier: &Nullifier, index: SubAccountIndex) -> T::AccountId {
+            let entropy = sp_io::hashing::blake2_256(&(SUB_ACCOUNT_CONTEXT, nullifier, index).encode());
+            Decode::decode(&mut TrailingZeroInput::new(&entropy))
+                .expect("infinite length input; no invalid inputs for type; qed")
+        }
+
+        /// The sub-accounts of the identity `who` holds a valid proof of, by index.
+        pub fn sub_accounts(who: &T::AccountId) -> Vec<(SubAccountIndex, SubAccount<T::AccountId, BlockNumberFor<T>>)> {
+            Self::identity_of(who)
+                .map(|nullifier| SubAccounts::<T>::iter_prefix(nullifier).collect())
+                .unwrap_or_default()
+        }
+
+        /// The identity `who` holds a valid proof of.
+        fn identity_of(who: &T::AccountId) -> Result<Nullifier, Error<T>> {
+            match T::Attestations::attestation_of(who) {
+                Some(attestation) => Ok(attestation.nullifier),
+                None if T::Attestations::attestation_expired(who) => Err(Error::<T>::AttestationExpired),
+                None => Err(Error::<T>::NotAttested),
+            }
+        }
+
+        /// The identity of `who` and its sub-account `index`.
+        fn sub_account_of(
+            who: &T::AccountId,
+            index: SubAccountIndex,
+        ) -> Result<(Nullifier, SubAccount<T::AccountId, BlockNumberFor<T>>), Error<T>> {
+            let nullifier = Self::identity_of(who)?;
+            let sub_account = SubAccounts::<T>::get(nullifier, index).ok_or(Error::<T>::UnknownSubAccount)?;
+            Ok((nullifier, sub_account))
+        }
+    }
+}
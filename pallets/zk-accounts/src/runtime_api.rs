@@ -0,0 +1,17 @@
+//! The runtime API of the pallet, for wallets to list the sub-accounts of their users.
+
+use crate::{SubAccount, SubAccountIndex};
+use alloc::vec::Vec;
+use codec::Codec;
+
+sp_api::decl_runtime_apis! {
+    /// The sub-accounts of identities.
+    pub trait ZkAccountsApi<AccountId, BlockNumber>
+    where
+        AccountId: Codec,
+        BlockNumber: Codec,
+    {
+        /// The sub-accounts of the identity `who` holds a valid proof of, by index.
+        fn sub_accounts(who: AccountId) -> Vec<(SubAccountIndex, SubAccount<AccountId, BlockNumber>)>;
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{mock::*, Error, Event, SubAccount, SubAccounts};
+use frame_support::{assert_noop, assert_ok};
+use pallet_zkproof::Nullifier;
+
+const IDENTITY: Nullifier = [1; 32];
+
+fn transfer(dest: u64, value: u64) -> Box<RuntimeCall> {
+    Box::new(RuntimeCall::Balances(pallet_balances::Call::transfer_allow_death { dest, value }))
+}
+
+#[test]
+fn attested_accounts_create_sub_accounts() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(ZkAccounts::create_sub_account(RuntimeOrigin::signed(1), 0), Error::<Test>::NotAttested);
+
+        Attested::set(vec![(1, IDENTITY)]);
+        assert_ok!(ZkAccounts::create_sub_account(RuntimeOrigin::signed(1), 0));
+        let account = ZkAccounts::derive(&IDENTITY, 0);
+        System::assert_last_event(Event::SubAccountCreated { nullifier: IDENTITY, index: 0, account }.into());
+        assert_eq!(ZkAccounts::sub_accounts(&1), vec![(0, SubAccount { account, created_at: 1 })]);
+        assert!(ZkAccounts::sub_accounts(&3).is_empty());
+
+        assert_noop!(ZkAccounts::create_sub_account(RuntimeOrigin::signed(1), 0), Error::<Test>::SubAccountExists);
+        assert_noop!(ZkAccounts::create_sub_account(RuntimeOrigin::signed(1), 2), Error::<Test>::InvalidIndex);
+
+        // Each identity and index derives its own account.
+        assert_ne!(ZkAccounts::derive(&IDENTITY, 1), account);
+        assert_ne!(ZkAccounts::derive(&[2; 32], 0), account);
+    });
+}
+
+#[test]
+fn accounts_of_the_identity_control_its_sub_accounts() {
+    new_test_ext().execute_with(|| {
+        Attested::set(vec![(1, IDENTITY), (2, IDENTITY)]);
+        assert_ok!(ZkAccounts::create_sub_account(RuntimeOrigin::signed(1), 0));
+        let account = ZkAccounts::derive(&IDENTITY, 0);
+
+        assert_ok!(ZkAccounts::fund_sub_account(RuntimeOrigin::signed(2), 0, 500));
+        System::assert_last_event(
+            Event::SubAccountFunded { nullifier: IDENTITY, index: 0, who: 2, amount: 500 }.into(),
+        );
+        assert_eq!(Balances::free_balance(account), 500);
+        assert_noop!(ZkAccounts::fund_sub_account(RuntimeOrigin::signed(2), 1, 500), Error::<Test>::UnknownSubAccount);
+
+        assert_ok!(ZkAccounts::sub_account_call(RuntimeOrigin::signed(1), 0, transfer(3, 100)));
+        System::assert_last_event(
+            Event::SubAccountCalled { nullifier: IDENTITY, index: 0, who: 1, result: Ok(()) }.into(),
+        );
+        assert_eq!((Balances::free_balance(account), Balances::free_balance(3)), (400, 100));
+
+        // The result of the call is reported, and its effects reverted on failure.
+        assert_ok!(ZkAccounts::sub_account_call(RuntimeOrigin::signed(1), 0, transfer(3, 10_000)));
+        assert!(matches!(
+            System::events().last().unwrap().event,
+            RuntimeEvent::ZkAccounts(Event::SubAccountCalled { result: Err(_), .. })
+        ));
+        assert_eq!(Balances::free_balance(account), 400);
+
+        assert_noop!(
+            ZkAccounts::sub_account_call(RuntimeOrigin::signed(3), 0, transfer(3, 100)),
+            Error::<Test>::NotAttested
+        );
+    });
+}
+
+#[test]
+fn dissolving_sweeps_the_funds_of_sub_accounts() {
+    new_test_ext().execute_with(|| {
+        Attested::set(vec![(1, IDENTITY), (2, IDENTITY)]);
+        assert_ok!(ZkAccounts::create_sub_account(RuntimeOrigin::signed(1), 0));
+        assert_ok!(ZkAccounts::fund_sub_account(RuntimeOrigin::signed(1), 0, 500));
+
+        assert_ok!(ZkAccounts::dissolve_sub_account(RuntimeOrigin::signed(2), 0));
+        System::assert_last_event(
+            Event::SubAccountDissolved { nullifier: IDENTITY, index: 0, who: 2, amount: 500 }.into(),
+        );
+        assert_eq!(Balances::free_balance(2), 1_500);
+        assert_eq!(Balances::free_balance(ZkAccounts::derive(&IDENTITY, 0)), 0);
+        assert!(!SubAccounts::<Test>::contains_key(IDENTITY, 0));
+        assert_noop!(ZkAccounts::dissolve_sub_account(RuntimeOrigin::signed(2), 0), Error::<Test>::UnknownSubAccount);
+
+        // The index may be used again.
+        assert_ok!(ZkAccounts::create_sub_account(RuntimeOrigin::signed(1), 0));
+    });
+}
@@ -0,0 +1,249 @@
+//! # ZK Claims Pallet
+//!
+//! Airdrops to DOT Login identities, claimed at most once per identity.
+//!
+//! A distributor publishes the Merkle root of an allowlist of identity commitments, and funds the
+//! distribution with the amount each claim pays times the number of commitments. An account
+//! holding a valid proof of an allowlisted identity then claims the amount once, proving that the
+//! commitment of its proof is in the allowlist. Claims are tracked by nullifier, so that logging
+//! in from other accounts with the same identity claims nothing more.
+//!
+//! Allowlists follow the layout of the identity roots of `pallet-zkproof`: Substrate's
+//! `binary-merkle-tree` with keccak-256, whose leaves are the hashes of the commitments.
+
+// We make sure this pallet uses `no_std` for compiling to Wasm.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod test;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{traits::ConstU32, BoundedVec, RuntimeDebug};
+use scale_info::TypeInfo;
+use sp_core::H256;
+
+/// The id of a distribution.
+pub type DistributionId = u32;
+
+/// Maximum number of hashes in a claim's Merkle proof, enough for allowlists of 2^32 commitments.
+pub const MAX_PROOF_LENGTH: u32 = 32;
+
+/// The siblings on the path from a commitment to the root of an allowlist.
+pub type MerkleProof = BoundedVec<H256, ConstU32<MAX_PROOF_LENGTH>>;
+
+/// A distribution of funds to the identities of an allowlist.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Distribution<AccountId, Balance> {
+    /// The account that created and funded the distribution.
+    pub creator: AccountId,
+    /// The Merkle root of the allowlisted commitments.
+    pub root: H256,
+    /// The number of commitments the root covers.
+    pub leaves: u32,
+    /// The amount each claim pays.
+    pub amount: Balance,
+}
+
+#[frame_support::pallet(dev_mode)]
+pub mod pallet {
+    use super::*;
+    use frame_support::{
+        pallet_prelude::*,
+        traits::{
+            fungible::{Inspect, Mutate},
+            tokens::{Fortitude, Preservation},
+        },
+        PalletId,
+    };
+    use frame_system::pallet_prelude::*;
+    use pallet_zkproof::{AttestationInspector, Nullifier};
+    use sp_runtime::traits::{AccountIdConversion, CheckedMul, Keccak256};
+
+    pub type BalanceOf<T> = <<T as Config>::Currency as Inspect<<T as frame_system::Config>::AccountId>>::Balance;
+
+    #[pallet::pallet]
+    pub struct Pallet<T>(_);
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// The overarching runtime event type.
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+        /// The attestations telling which identity an account logs in with, usually
+        /// `pallet-zkproof`.
+        type Attestations: AttestationInspector<Self::AccountId>;
+        /// The currency distributions pay in.
+        type Currency: Mutate<Self::AccountId>;
+        /// The id the accounts holding the funds of distributions are derived from.
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+    }
+
+    /// The id of the next distribution.
+    #[pallet::storage]
+    pub type NextDistributionId<T: Config> = StorageValue<_, DistributionId, ValueQuery>;
+
+    /// The open distributions.
+    #[pallet::storage]
+    pub type Distributions<T: Config> =
+        StorageMap<_, Twox64Concat, DistributionId, Distribution<T::AccountId, BalanceOf<T>>, OptionQuery>;
+
+    /// The identities that claimed each distribution, and the block they claimed it in.
+    #[pallet::storage]
+    pub type Claimed<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, DistributionId, Identity, Nullifier, BlockNumberFor<T>, OptionQuery>;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// `who` created and funded a distribution paying `amount` to each of `leaves` identities.
+        DistributionCreated { id: DistributionId, who: T::AccountId, root: H256, leaves: u32, amount: BalanceOf<T> },
+        /// An identity claimed `amount` from a distribution, paid to `who`.
+        Claimed { id: DistributionId, nullifier: Nullifier, who: T::AccountId, amount: BalanceOf<T> },
+        /// A distribution was closed, and the `remainder` of its funds returned to its creator.
+        DistributionClosed { id: DistributionId, remainder: BalanceOf<T> },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// The allowlist is empty.
+        EmptyAllowlist,
+        /// The funds of the distribution overflow.
+        Overflow,
+        /// No open distribution has this id.
+        UnknownDistribution,
+        /// The account holds no valid proof.
+        NotAttested,
+        /// The identity claimed the distribution already.
+        AlreadyClaimed,
+        /// The commitment of the caller's proof is not in the allowlist.
+        NotAllowlisted,
+        /// The caller did not create the distribution.
+        NotCreator,
+        /// The account's proof expired; `renew_attestation` extends it.
+        AttestationExpired,
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Create a distribution paying `amount` to each identity whose commitment is in the
+        /// allowlist with Merkle root `root`, funding it from the caller with `amount` times
+        /// `leaves`.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be signed).
+        /// - `root`: The Merkle root of the allowlisted commitments.
+        /// - `leaves`: The number of commitments the root covers.
+        /// - `amount`: The amount each claim pays.
+        ///
+        /// # Errors
+        /// - `EmptyAllowlist`: If `leaves` is zero.
+        /// - `Overflow`: If `amount` times `leaves` overflows.
+        /// - Any error of the transfer.
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 4))]
+        pub fn create_distribution(
+            origin: OriginFor<T>,
+            root: H256,
+            leaves: u32,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let creator = ensure_signed(origin)?;
+            ensure!(leaves > 0, Error::<T>::EmptyAllowlist);
+            let funds = amount.checked_mul(&leaves.into()).ok_or(Error::<T>::Overflow)?;
+
+            let id = NextDistributionId::<T>::get();
+            T::Currency::transfer(&creator, &Self::account(id), funds, Preservation::Preserve)?;
+            NextDistributionId::<T>::put(id.saturating_add(1));
+            Distributions::<T>::insert(id, Distribution { creator: creator.clone(), root, leaves, amount });
+
+            Self::deposit_event(Event::DistributionCreated { id, who: creator, root, leaves, amount });
+            Ok(())
+        }
+
+        /// Claim a distribution for the identity the caller logs in with, given the proof that
+        /// its commitment is in the allowlist.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be signed).
+        /// - `id`: The distribution.
+        /// - `proof`: The siblings on the path from the commitment to the root.
+        /// - `leaf_index`: The position of the commitment in the allowlist.
+        ///
+        /// # Errors
+        /// - `NotAttested`: If the caller holds no valid proof.
+        /// - `AttestationExpired`: If the caller's proof expired.
+        /// - `UnknownDistribution`: If no open distribution has this id.
+        /// - `AlreadyClaimed`: If the identity claimed the distribution already.
+        /// - `NotAllowlisted`: If `proof` does not lead from the commitment to the root.
+        /// - Any error of the transfer.
+        #[pallet::weight(T::DbWeight::get().reads_writes(6, 3))]
+        pub fn claim(origin: OriginFor<T>, id: DistributionId, proof: MerkleProof, leaf_index: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let attestation = T::Attestations::attestation_of(&who).ok_or_else(|| {
+                if T::Attestations::attestation_expired(&who) {
+                    Error::<T>::AttestationExpired
+                } else {
+                    Error::<T>::NotAttested
+                }
+            })?;
+            let distribution = Distributions::<T>::get(id).ok_or(Error::<T>::UnknownDistribution)?;
+            let nullifier = attestation.nullifier;
+            ensure!(!Claimed::<T>::contains_key(id, nullifier), Error::<T>::AlreadyClaimed);
+            ensure!(
+                binary_merkle_tree::verify_proof::<Keccak256, _, _>(
+                    &distribution.root,
+                    proof.into_inner(),
+                    distribution.leaves as usize,
+                    leaf_index as usize,
+                    &attestation.commitment,
+                ),
+                Error::<T>::NotAllowlisted
+            );
+
+            let amount = distribution.amount;
+            T::Currency::transfer(&Self::account(id), &who, amount, Preservation::Expendable)?;
+            Claimed::<T>::insert(id, nullifier, frame_system::Pallet::<T>::block_number());
+
+            Self::deposit_event(Event::Claimed { id, nullifier, who, amount });
+            Ok(())
+        }
+
+        /// Close a distribution, returning the funds left unclaimed to the caller. The identities
+        /// that claimed it stay recorded.
+        ///
+        /// # Errors
+        /// - `UnknownDistribution`: If no open distribution has this id.
+        /// - `NotCreator`: If the caller did not create the distribution.
+        /// - Any error of the transfer.
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3))]
+        pub fn close_distribution(origin: OriginFor<T>, id: DistributionId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let distribution = Distributions::<T>::get(id).ok_or(Error::<T>::UnknownDistribution)?;
+            ensure!(distribution.creator == who, Error::<T>::NotCreator);
+
+            let account = Self::account(id);
+            let remainder = T::Currency::reducible_balance(&account, Preservation::Expendable, Fortitude::Polite);
+            T::Currency::transfer(&account, &who, remainder, Preservation::Expendable)?;
+            Distributions::<T>::remove(id);
+
+            Self::deposit_event(Event::DistributionClosed { id, remainder });
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// The account holding the funds of distribution `id`.
+        pub fn account(id: DistributionId) -> T::AccountId {
+            T::PalletId::get().into_sub_account_truncating(id)
+        }
+
+        /// Whether the identity with `nullifier` claimed distribution `id`.
+        pub fn has_claimed(id: DistributionId, nullifier: &Nullifier) -> bool {
+            Claimed::<T>::contains_key(id, nullifier)
+        }
+    }
+}
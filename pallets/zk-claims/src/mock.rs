@@ -0,0 +1,92 @@
+use crate as pallet_zk_claims;
+use frame_support::{
+    derive_impl, parameter_types,
+    traits::{ConstU16, ConstU32, ConstU64},
+    PalletId,
+};
+use pallet_zkproof::{Attestation, AttestationInspector, Commitment, IssuerId, Nullifier, GOOGLE_ISSUER};
+use sp_core::H256;
+use sp_runtime::{
+    traits::{BlakeTwo256, IdentityLookup},
+    BuildStorage,
+};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        ZkClaims: pallet_zk_claims,
+    }
+);
+
+parameter_types! {
+    pub static Attested: Vec<(u64, Commitment, Nullifier)> = vec![];
+    pub const ClaimsPalletId: PalletId = PalletId(*b"zk/claim");
+}
+
+/// Attests the accounts in `Attested` to their commitment and nullifier.
+pub struct MockAttestations;
+
+impl AttestationInspector<u64> for MockAttestations {
+    fn attestation_of(who: &u64) -> Option<Attestation> {
+        Attested::get().into_iter().find(|(account, ..)| account == who).map(|(_, commitment, nullifier)| {
+            let provider = IssuerId::truncate_from(GOOGLE_ISSUER.to_vec());
+            Attestation { provider, commitment, nullifier, circuit_version: 0, verified_at: 0, expires_at: u64::MAX }
+        })
+    }
+}
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig as frame_system::DefaultConfig)]
+impl frame_system::Config for Test {
+    type BaseCallFilter = frame_support::traits::Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Nonce = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Block = Block;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ConstU16<42>;
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+#[derive_impl(pallet_balances::config_preludes::TestDefaultConfig as pallet_balances::DefaultConfig)]
+impl pallet_balances::Config for Test {
+    type AccountStore = System;
+}
+
+impl pallet_zk_claims::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Attestations = MockAttestations;
+    type Currency = Balances;
+    type PalletId = ClaimsPalletId;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+    pallet_balances::GenesisConfig::<Test> { balances: vec![(1, 1_000), (2, 1_000)] }
+        .assimilate_storage(&mut storage)
+        .unwrap();
+    let mut ext: sp_io::TestExternalities = storage.into();
+    // Go past genesis block so events get deposited.
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
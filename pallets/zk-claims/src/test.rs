@@ -0,0 +1,81 @@
+use crate::{mock::*, Claimed, Distribution, Distributions, Error, Event, MerkleProof};
+use frame_support::{assert_noop, assert_ok};
+use pallet_zkproof::Commitment;
+use sp_core::H256;
+use sp_runtime::traits::Keccak256;
+
+const ALLOWLIST: [Commitment; 3] = [[1; 32], [2; 32], [3; 32]];
+
+fn root() -> H256 {
+    binary_merkle_tree::merkle_root::<Keccak256, _>(&ALLOWLIST)
+}
+
+/// The proof that the commitment at `leaf_index` is in the allowlist.
+fn proof(leaf_index: usize) -> MerkleProof {
+    let proof = binary_merkle_tree::merkle_proof::<Keccak256, _, _>(ALLOWLIST, leaf_index).proof;
+    MerkleProof::truncate_from(proof)
+}
+
+#[test]
+fn distributions_are_funded_by_their_creator() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ZkClaims::create_distribution(RuntimeOrigin::signed(1), root(), 0, 100),
+            Error::<Test>::EmptyAllowlist
+        );
+        assert_noop!(
+            ZkClaims::create_distribution(RuntimeOrigin::signed(1), root(), 2, u64::MAX),
+            Error::<Test>::Overflow
+        );
+
+        assert_ok!(ZkClaims::create_distribution(RuntimeOrigin::signed(1), root(), 3, 100));
+        System::assert_last_event(
+            Event::DistributionCreated { id: 0, who: 1, root: root(), leaves: 3, amount: 100 }.into(),
+        );
+        assert_eq!(
+            Distributions::<Test>::get(0),
+            Some(Distribution { creator: 1, root: root(), leaves: 3, amount: 100 })
+        );
+        assert_eq!((Balances::free_balance(1), Balances::free_balance(ZkClaims::account(0))), (700, 300));
+        assert_ne!(ZkClaims::account(1), ZkClaims::account(0));
+    });
+}
+
+#[test]
+fn allowlisted_identities_claim_once() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(ZkClaims::create_distribution(RuntimeOrigin::signed(1), root(), 3, 100));
+        assert_noop!(ZkClaims::claim(RuntimeOrigin::signed(3), 0, proof(1), 1), Error::<Test>::NotAttested);
+
+        Attested::set(vec![(3, [2; 32], [20; 32]), (4, [2; 32], [20; 32]), (5, [9; 32], [90; 32])]);
+        assert_noop!(ZkClaims::claim(RuntimeOrigin::signed(3), 1, proof(1), 1), Error::<Test>::UnknownDistribution);
+        assert_noop!(ZkClaims::claim(RuntimeOrigin::signed(3), 0, proof(0), 0), Error::<Test>::NotAllowlisted);
+        assert_ok!(ZkClaims::claim(RuntimeOrigin::signed(3), 0, proof(1), 1));
+        System::assert_last_event(Event::Claimed { id: 0, nullifier: [20; 32], who: 3, amount: 100 }.into());
+        assert_eq!(Balances::free_balance(3), 100);
+        assert_eq!(Claimed::<Test>::get(0, [20; 32]), Some(1));
+        assert!(ZkClaims::has_claimed(0, &[20; 32]));
+
+        // Other accounts of the identity claim nothing more, and identities outside the allowlist
+        // nothing at all.
+        assert_noop!(ZkClaims::claim(RuntimeOrigin::signed(4), 0, proof(1), 1), Error::<Test>::AlreadyClaimed);
+        assert_noop!(ZkClaims::claim(RuntimeOrigin::signed(5), 0, proof(2), 2), Error::<Test>::NotAllowlisted);
+    });
+}
+
+#[test]
+fn closing_returns_the_unclaimed_funds() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(ZkClaims::create_distribution(RuntimeOrigin::signed(1), root(), 3, 100));
+        Attested::set(vec![(3, [1; 32], [10; 32])]);
+        assert_ok!(ZkClaims::claim(RuntimeOrigin::signed(3), 0, proof(0), 0));
+
+        assert_noop!(ZkClaims::close_distribution(RuntimeOrigin::signed(2), 0), Error::<Test>::NotCreator);
+        assert_ok!(ZkClaims::close_distribution(RuntimeOrigin::signed(1), 0));
+        System::assert_last_event(Event::DistributionClosed { id: 0, remainder: 200 }.into());
+        assert_eq!(Balances::free_balance(1), 900);
+        assert!(!Distributions::<Test>::contains_key(0));
+        assert_noop!(ZkClaims::claim(RuntimeOrigin::signed(3), 0, proof(0), 0), Error::<Test>::UnknownDistribution);
+        assert_noop!(ZkClaims::close_distribution(RuntimeOrigin::signed(1), 0), Error::<Test>::UnknownDistribution);
+    });
+}
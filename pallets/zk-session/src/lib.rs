@@ -0,0 +1,302 @@
+//! # ZK Session Pallet
+//!
+//! Turns the one-shot proofs of `pallet-zkproof` into sessions dApps authenticate users with.
+//!
+//! An account holding a valid proof opens a session with a dApp, identified by its account, and
+//! grants it scopes. The session lasts `SessionLifetime` at most, and never outlives the proof it
+//! was opened with. The user revokes sessions one by one, or all of them at once by locking the
+//! identity (see [`OnIdentityLocked`]), and expired sessions are pruned in the idle time of blocks.
+//!
+//! Other pallets check sessions through [`SessionInspector`], and dApps through the
+//! [`runtime_api::ZkSessionApi`].
+
+// We make sure this pallet uses `no_std` for compiling to Wasm.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod test;
+
+pub mod migrations;
+pub mod runtime_api;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{traits::ConstU32, BoundedVec, RuntimeDebug};
+use pallet_zkproof::{Nullifier, OnIdentityLocked};
+use scale_info::TypeInfo;
+
+/// Maximum length of a scope.
+pub const MAX_SCOPE_LENGTH: u32 = 32;
+/// Maximum number of scopes granted to a session.
+pub const MAX_SCOPES: u32 = 16;
+
+/// What a dApp may act on in a session, e.g. `profile` or `payments`.
+pub type Scope = BoundedVec<u8, ConstU32<MAX_SCOPE_LENGTH>>;
+
+/// The scopes granted to a session.
+pub type Scopes = BoundedVec<Scope, ConstU32<MAX_SCOPES>>;
+
+/// A session opened by a user with a dApp.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Session<BlockNumber> {
+    /// The identity the session was opened with.
+    pub nullifier: Nullifier,
+    /// The scopes granted to the dApp.
+    pub scopes: Scopes,
+    /// The block the session was opened in.
+    pub opened_at: BlockNumber,
+    /// When (unix seconds) the session expires.
+    pub expires_at: u64,
+}
+
+/// Read access to the sessions, for other pallets.
+pub trait SessionInspector<AccountId> {
+    /// When (unix seconds) the session `who` opened with `dapp` expires, while it is active.
+    fn session_expiry(who: &AccountId, dapp: &AccountId) -> Option<u64>;
+
+    /// Whether the session `who` opened with `dapp` is active and grants `scope`.
+    fn has_scope(who: &AccountId, dapp: &AccountId, scope: &[u8]) -> bool;
+}
+
+#[frame_support::pallet(dev_mode)]
+pub mod pallet {
+    use super::*;
+    use alloc::vec::Vec;
+    use frame_support::{pallet_prelude::*, sp_runtime::Saturating, traits::UnixTime, weights::WeightMeter};
+    use frame_system::pallet_prelude::*;
+    use pallet_zkproof::AttestationInspector;
+
+    /// The in-code storage version.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+    #[pallet::pallet]
+    #[pallet::storage_version(STORAGE_VERSION)]
+    pub struct Pallet<T>(_);
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        /// The overarching runtime event type.
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+        /// The attestations sessions are opened with, usually `pallet-zkproof`.
+        type Attestations: AttestationInspector<Self::AccountId>;
+        /// The chain's clock, which session expiries are checked against.
+        type UnixTime: UnixTime;
+        /// How long (in seconds) a session lasts at most.
+        type SessionLifetime: Get<u64>;
+        /// How many sessions an account may have open at once.
+        type MaxSessions: Get<u32>;
+        /// How many sessions are checked for expiry per block at most.
+        type MaxPrunedSessions: Get<u32>;
+    }
+
+    /// The sessions, keyed by user and dApp.
+    #[pallet::storage]
+    pub type Sessions<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        Session<BlockNumberFor<T>>,
+        OptionQuery,
+    >;
+
+    /// The number of sessions of each user, expired ones included until they are pruned.
+    #[pallet::storage]
+    pub type SessionCount<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+    /// The number of sessions of all users, expired ones included until they are pruned.
+    #[pallet::storage]
+    pub type TotalSessions<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+    /// The block each locked identity was last locked in; the sessions opened with it until then
+    /// ended.
+    #[pallet::storage]
+    pub type IdentityLockedAt<T: Config> = StorageMap<_, Identity, Nullifier, BlockNumberFor<T>, OptionQuery>;
+
+    /// The raw key of the session pruning resumes after; pruning restarts from the first session
+    /// without one.
+    #[pallet::storage]
+    pub type PruneCursor<T: Config> = StorageValue<_, Vec<u8>, OptionQuery>;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// A session was opened, or renewed if `who` had one with `dapp` already.
+        SessionOpened { who: T::AccountId, dapp: T::AccountId, scopes: Scopes, expires_at: u64 },
+        /// A session was revoked by its user.
+        SessionRevoked { who: T::AccountId, dapp: T::AccountId },
+        /// An expired session was pruned.
+        SessionExpired { who: T::AccountId, dapp: T::AccountId },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// The account holds no valid proof.
+        NotAttested,
+        /// The account has `MaxSessions` sessions open already.
+        TooManySessions,
+        /// The account has no session with the dApp.
+        UnknownSession,
+        /// The account's proof expired; `renew_attestation` extends it.
+        AttestationExpired,
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_idle(_block_number: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            Self::prune_sessions(remaining_weight)
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Open a session with `dapp`, granting it `scopes`, or renew the session opened with it.
+        ///
+        /// The session expires after `SessionLifetime`, or when the latest proof stored by the
+        /// caller does if sooner.
+        ///
+        /// # Errors
+        /// - `NotAttested`: If the caller holds no valid proof.
+        /// - `AttestationExpired`: If the caller's proof expired.
+        /// - `TooManySessions`: If the caller has `MaxSessions` sessions open already.
+        #[pallet::weight(T::DbWeight::get().reads_writes(7, 3))]
+        pub fn open_session(origin: OriginFor<T>, dapp: T::AccountId, scopes: Scopes) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let attestation = T::Attestations::attestation_of(&who).ok_or_else(|| Self::not_attested(&who))?;
+
+            if !Sessions::<T>::contains_key(&who, &dapp) {
+                SessionCount::<T>::try_mutate(&who, |count| {
+                    ensure!(*count < T::MaxSessions::get(), Error::<T>::TooManySessions);
+                    *count += 1;
+                    Ok::<_, Error<T>>(())
+                })?;
+                TotalSessions::<T>::mutate(|total| total.saturating_inc());
+            }
+
+            let expires_at =
+                T::UnixTime::now().as_secs().saturating_add(T::SessionLifetime::get()).min(attestation.expires_at);
+            let session = Session {
+                nullifier: attestation.nullifier,
+                scopes: scopes.clone(),
+                opened_at: frame_system::Pallet::<T>::block_number(),
+                expires_at,
+            };
+            Sessions::<T>::insert(&who, &dapp, session);
+
+            Self::deposit_event(Event::SessionOpened { who, dapp, scopes, expires_at });
+            Ok(())
+        }
+
+        /// Revoke the session opened with `dapp`.
+        ///
+        /// # Errors
+        /// - `UnknownSession`: If the caller has no session with `dapp`.
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3))]
+        pub fn revoke_session(origin: OriginFor<T>, dapp: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Sessions::<T>::contains_key(&who, &dapp), Error::<T>::UnknownSession);
+
+            Self::remove_session(&who, &dapp);
+            Self::deposit_event(Event::SessionRevoked { who, dapp });
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// The session `who` opened with `dapp`, while it is active: it has not expired, the
+        /// identity it was opened with still holds a valid proof stored by `who`, and was not
+        /// locked since.
+        pub fn active_session(who: &T::AccountId, dapp: &T::AccountId) -> Option<Session<BlockNumberFor<T>>> {
+            let session = Sessions::<T>::get(who, dapp)?;
+            Self::is_active(who, &session).then_some(session)
+        }
+
+        /// The active sessions of `who`, along with the dApp of each.
+        pub fn active_sessions(who: &T::AccountId) -> Vec<(T::AccountId, Session<BlockNumberFor<T>>)> {
+            Sessions::<T>::iter_prefix(who).filter(|(_, session)| Self::is_active(who, session)).collect()
+        }
+
+        /// The number of sessions stored, expired ones included until they are pruned.
+        pub fn total_sessions() -> u64 {
+            TotalSessions::<T>::get()
+        }
+
+        fn is_active(who: &T::AccountId, session: &Session<BlockNumberFor<T>>) -> bool {
+            T::UnixTime::now().as_secs() < session.expires_at
+                && T::Attestations::attestation_of(who)
+                    .map_or(false, |attestation| attestation.nullifier == session.nullifier)
+                && IdentityLockedAt::<T>::get(session.nullifier).map_or(true, |locked_at| session.opened_at > locked_at)
+        }
+
+        /// Why `who` holds no valid proof.
+        fn not_attested(who: &T::AccountId) -> Error<T> {
+            if T::Attestations::attestation_expired(who) {
+                Error::<T>::AttestationExpired
+            } else {
+                Error::<T>::NotAttested
+            }
+        }
+
+        fn remove_session(who: &T::AccountId, dapp: &T::AccountId) {
+            Sessions::<T>::remove(who, dapp);
+            SessionCount::<T>::mutate_exists(who, |count| {
+                *count = count.map(|count| count.saturating_sub(1)).filter(|count| *count > 0);
+            });
+            TotalSessions::<T>::mutate(|total| total.saturating_dec());
+        }
+
+        /// Prune the expired sessions among the next `MaxPrunedSessions`, within `limit`.
+        pub(crate) fn prune_sessions(limit: Weight) -> Weight {
+            let mut meter = WeightMeter::with_limit(limit);
+            // Reading and writing the cursor.
+            if meter.try_consume(T::DbWeight::get().reads_writes(1, 1)).is_err() {
+                return Weight::zero();
+            }
+
+            // Reading a session, and removing it and updating the counts if expired.
+            let per_session = T::DbWeight::get().reads_writes(3, 3);
+            let now = T::UnixTime::now().as_secs();
+            let mut sessions = match PruneCursor::<T>::take() {
+                Some(cursor) => Sessions::<T>::iter_from(cursor),
+                None => Sessions::<T>::iter(),
+            };
+            for _ in 0..T::MaxPrunedSessions::get() {
+                if meter.try_consume(per_session).is_err() {
+                    break;
+                }
+                let Some((who, dapp, session)) = sessions.next() else {
+                    return meter.consumed();
+                };
+                if session.expires_at <= now {
+                    Self::remove_session(&who, &dapp);
+                    Self::deposit_event(Event::SessionExpired { who, dapp });
+                }
+            }
+            PruneCursor::<T>::put(sessions.last_raw_key().to_vec());
+            meter.consumed()
+        }
+    }
+}
+
+impl<T: Config> OnIdentityLocked for Pallet<T> {
+    fn on_identity_locked(nullifier: &Nullifier) {
+        IdentityLockedAt::<T>::insert(nullifier, frame_system::Pallet::<T>::block_number());
+    }
+}
+
+impl<T: Config> SessionInspector<T::AccountId> for Pallet<T> {
+    fn session_expiry(who: &T::AccountId, dapp: &T::AccountId) -> Option<u64> {
+        Self::active_session(who, dapp).map(|session| session.expires_at)
+    }
+
+    fn has_scope(who: &T::AccountId, dapp: &T::AccountId, scope: &[u8]) -> bool {
+        Self::active_session(who, dapp).map_or(false, |session| session.scopes.iter().any(|s| &s[..] == scope))
+    }
+}
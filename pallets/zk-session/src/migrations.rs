@@ -0,0 +1,81 @@
+//! Storage migrations of the pallet.
+//!
+//! Migrations touching every session do not fit in a block. They are `SteppedMigration`s, run by
+//! `pallet-migrations` over as many blocks as they need.
+
+use crate::*;
+use frame_support::{
+    migrations::{MigrationId, SteppedMigration, SteppedMigrationError},
+    pallet_prelude::*,
+    sp_runtime::Saturating,
+    weights::WeightMeter,
+};
+
+/// The identifier prefix of the pallet's migrations.
+const PALLET_MIGRATIONS_ID: &[u8; 17] = b"pallet-zk-session";
+
+/// Version 1: sessions are counted.
+pub mod v1 {
+    use super::*;
+
+    /// The raw storage key of a session.
+    pub type RawKey = BoundedVec<u8, ConstU32<256>>;
+
+    /// Count the sessions opened before version 1 into `TotalSessions`, which later writes keep up
+    /// to date.
+    ///
+    /// The cursor is the raw key of the last session counted.
+    pub struct CountSessions<T>(PhantomData<T>);
+
+    impl<T: Config> CountSessions<T> {
+        /// The weight of counting one session: reading it.
+        fn step_weight() -> Weight {
+            T::DbWeight::get().reads(1)
+        }
+    }
+
+    impl<T: Config> SteppedMigration for CountSessions<T> {
+        type Cursor = RawKey;
+        type Identifier = MigrationId<17>;
+
+        fn id() -> Self::Identifier {
+            MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 0, version_to: 1 }
+        }
+
+        fn step(
+            cursor: Option<Self::Cursor>,
+            meter: &mut WeightMeter,
+        ) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+            if Pallet::<T>::on_chain_storage_version() != Self::id().version_from as u16 {
+                return Ok(None);
+            }
+
+            let required = Self::step_weight();
+            if meter.remaining().any_lt(required) {
+                return Err(SteppedMigrationError::InsufficientWeight { required });
+            }
+
+            // Counting starts over from zero, whatever was counted before the migration ran.
+            let mut total = if cursor.is_some() { TotalSessions::<T>::get() } else { 0 };
+            let mut sessions = match cursor {
+                Some(cursor) => Sessions::<T>::iter_keys_from(cursor.into_inner()),
+                None => Sessions::<T>::iter_keys(),
+            };
+            let mut complete = false;
+            while meter.try_consume(required).is_ok() {
+                if sessions.next().is_none() {
+                    complete = true;
+                    break;
+                }
+                total.saturating_inc();
+            }
+            TotalSessions::<T>::put(total);
+
+            if complete {
+                StorageVersion::new(Self::id().version_to as u16).put::<Pallet<T>>();
+                return Ok(None);
+            }
+            RawKey::try_from(sessions.last_raw_key().to_vec()).map(Some).map_err(|_| SteppedMigrationError::Failed)
+        }
+    }
+}
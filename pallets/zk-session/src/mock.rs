@@ -0,0 +1,107 @@
+use crate as pallet_zk_session;
+use frame_support::{
+    derive_impl, parameter_types,
+    traits::{ConstU16, ConstU32, ConstU64},
+};
+use pallet_zkproof::{Attestation, AttestationInspector, IssuerId, Nullifier, GOOGLE_ISSUER};
+use sp_core::H256;
+use sp_runtime::{
+    traits::{BlakeTwo256, IdentityLookup},
+    BuildStorage,
+};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test
+    {
+        System: frame_system,
+        Timestamp: pallet_timestamp,
+        ZkSession: pallet_zk_session,
+    }
+);
+
+parameter_types! {
+    pub static Attested: Vec<(u64, Nullifier, u64)> = vec![];
+    pub static MaxPrunedSessions: u32 = 100;
+}
+
+/// Attests the accounts in `Attested` to their nullifier, until their expiry.
+pub struct MockAttestations;
+
+impl AttestationInspector<u64> for MockAttestations {
+    fn attestation_of(who: &u64) -> Option<Attestation> {
+        let now = Timestamp::get() / 1_000;
+        Attested::get().into_iter().find(|(account, _, expires_at)| account == who && now < *expires_at).map(
+            |(_, nullifier, expires_at)| {
+                let provider = IssuerId::truncate_from(GOOGLE_ISSUER.to_vec());
+                Attestation {
+                    provider,
+                    commitment: nullifier,
+                    nullifier,
+                    circuit_version: 0,
+                    verified_at: 0,
+                    expires_at,
+                }
+            },
+        )
+    }
+
+    fn attestation_expired(who: &u64) -> bool {
+        let now = Timestamp::get() / 1_000;
+        Attested::get().into_iter().any(|(account, _, expires_at)| account == *who && now >= expires_at)
+    }
+}
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig as frame_system::DefaultConfig)]
+impl frame_system::Config for Test {
+    type BaseCallFilter = frame_support::traits::Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Nonce = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Block = Block;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = ();
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ConstU16<42>;
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_timestamp::Config for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = ConstU64<1>;
+    type WeightInfo = ();
+}
+
+impl pallet_zk_session::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Attestations = MockAttestations;
+    type UnixTime = Timestamp;
+    type SessionLifetime = ConstU64<600>;
+    type MaxSessions = ConstU32<2>;
+    type MaxPrunedSessions = MaxPrunedSessions;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+    let mut ext: sp_io::TestExternalities = storage.into();
+    // Go past genesis block so events get deposited.
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
@@ -0,0 +1,23 @@
+//! The runtime API of the pallet, for dApps to authenticate users by their sessions.
+
+use crate::Session;
+use alloc::vec::Vec;
+use codec::Codec;
+
+sp_api::decl_runtime_apis! {
+    /// The sessions users opened with dApps.
+    #[api_version(2)]
+    pub trait ZkSessionApi<AccountId, BlockNumber>
+    where
+        AccountId: Codec,
+        BlockNumber: Codec,
+    {
+        /// The session `who` opened with `dapp`, while it is active.
+        fn session(who: AccountId, dapp: AccountId) -> Option<Session<BlockNumber>>;
+        /// The active sessions of `who`, along with the dApp of each.
+        fn sessions(who: AccountId) -> Vec<(AccountId, Session<BlockNumber>)>;
+        /// The number of sessions stored, expired ones included until they are pruned.
+        #[api_version(2)]
+        fn total_sessions() -> u64;
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    migrations::v1::CountSessions, mock::*, Error, Event, PruneCursor, Scope, Scopes, SessionCount, SessionInspector,
+    Sessions, TotalSessions,
+};
+use frame_support::{
+    assert_noop, assert_ok,
+    migrations::SteppedMigration,
+    traits::{GetStorageVersion, Hooks, StorageVersion},
+    weights::{Weight, WeightMeter},
+};
+use pallet_zkproof::OnIdentityLocked;
+
+const DAPP: u64 = 10;
+
+fn scopes(scopes: &[&str]) -> Scopes {
+    Scopes::truncate_from(scopes.iter().map(|scope| Scope::truncate_from(scope.as_bytes().to_vec())).collect())
+}
+
+fn open(who: u64, dapp: u64) -> frame_support::dispatch::DispatchResult {
+    ZkSession::open_session(RuntimeOrigin::signed(who), dapp, scopes(&["profile"]))
+}
+
+#[test]
+fn attested_accounts_open_sessions() {
+    new_test_ext().execute_with(|| {
+        Timestamp::set_timestamp(100_000);
+        assert_noop!(open(1, DAPP), Error::<Test>::NotAttested);
+
+        Attested::set(vec![(1, [1; 32], 1_000)]);
+        assert_ok!(open(1, DAPP));
+        System::assert_last_event(
+            Event::SessionOpened { who: 1, dapp: DAPP, scopes: scopes(&["profile"]), expires_at: 700 }.into(),
+        );
+
+        let session = ZkSession::active_session(&1, &DAPP).unwrap();
+        assert_eq!((session.nullifier, session.opened_at, session.expires_at), ([1; 32], 1, 700));
+        assert_eq!(ZkSession::active_sessions(&1), vec![(DAPP, session)]);
+        assert_eq!(ZkSession::session_expiry(&1, &DAPP), Some(700));
+        assert!(ZkSession::has_scope(&1, &DAPP, b"profile"));
+        assert!(!ZkSession::has_scope(&1, &DAPP, b"payments"));
+        assert_eq!(ZkSession::session_expiry(&1, &11), None);
+    });
+}
+
+#[test]
+fn sessions_end_with_the_proof_they_were_opened_with() {
+    new_test_ext().execute_with(|| {
+        Timestamp::set_timestamp(100_000);
+        Attested::set(vec![(1, [1; 32], 300)]);
+        assert_ok!(open(1, DAPP));
+        assert_eq!(ZkSession::session_expiry(&1, &DAPP), Some(300));
+
+        Timestamp::set_timestamp(300_000);
+        assert_eq!(ZkSession::active_session(&1, &DAPP), None);
+        assert_noop!(open(1, DAPP), Error::<Test>::AttestationExpired);
+
+        // A proof of another identity does not carry the session on.
+        Timestamp::set_timestamp(100_000);
+        Attested::set(vec![(1, [2; 32], 1_000)]);
+        assert_eq!(ZkSession::active_session(&1, &DAPP), None);
+        assert!(ZkSession::active_sessions(&1).is_empty());
+    });
+}
+
+#[test]
+fn locking_the_identity_ends_its_sessions() {
+    new_test_ext().execute_with(|| {
+        Attested::set(vec![(1, [1; 32], 1_000)]);
+        assert_ok!(open(1, DAPP));
+
+        System::set_block_number(2);
+        ZkSession::on_identity_locked(&[1; 32]);
+        assert_eq!(ZkSession::active_session(&1, &DAPP), None);
+
+        // Sessions opened after the lock are active.
+        System::set_block_number(3);
+        assert_ok!(open(1, 11));
+        assert_eq!(ZkSession::active_sessions(&1).len(), 1);
+    });
+}
+
+#[test]
+fn users_revoke_their_sessions() {
+    new_test_ext().execute_with(|| {
+        Attested::set(vec![(1, [1; 32], 1_000)]);
+        assert_ok!(open(1, DAPP));
+        assert_ok!(open(1, 11));
+
+        assert_ok!(ZkSession::revoke_session(RuntimeOrigin::signed(1), DAPP));
+        System::assert_last_event(Event::SessionRevoked { who: 1, dapp: DAPP }.into());
+        assert!(!Sessions::<Test>::contains_key(1, DAPP));
+        assert!(ZkSession::active_session(&1, &11).is_some());
+        assert_eq!((SessionCount::<Test>::get(1), ZkSession::total_sessions()), (1, 1));
+
+        assert_noop!(ZkSession::revoke_session(RuntimeOrigin::signed(1), DAPP), Error::<Test>::UnknownSession);
+        assert_ok!(ZkSession::revoke_session(RuntimeOrigin::signed(1), 11));
+        assert!(!SessionCount::<Test>::contains_key(1));
+        assert_eq!(ZkSession::total_sessions(), 0);
+    });
+}
+
+#[test]
+fn accounts_open_at_most_max_sessions() {
+    new_test_ext().execute_with(|| {
+        Attested::set(vec![(1, [1; 32], 1_000)]);
+        assert_ok!(open(1, DAPP));
+        assert_ok!(open(1, 11));
+        assert_noop!(open(1, 12), Error::<Test>::TooManySessions);
+
+        // Sessions are renewed without counting twice.
+        assert_ok!(ZkSession::open_session(RuntimeOrigin::signed(1), DAPP, scopes(&["profile", "payments"])));
+        assert!(ZkSession::has_scope(&1, &DAPP, b"payments"));
+        assert_eq!(SessionCount::<Test>::get(1), 2);
+        assert_eq!(ZkSession::total_sessions(), 2);
+    });
+}
+
+#[test]
+fn expired_sessions_are_pruned() {
+    new_test_ext().execute_with(|| {
+        Attested::set(vec![(1, [1; 32], 200), (2, [2; 32], 1_000), (3, [3; 32], 200)]);
+        for who in 1..=3 {
+            assert_ok!(open(who, DAPP));
+        }
+        MaxPrunedSessions::set(2);
+        Timestamp::set_timestamp(200_000);
+
+        // Sessions are checked `MaxPrunedSessions` at a time, resuming where pruning stopped.
+        ZkSession::on_idle(1, Weight::MAX);
+        assert!(PruneCursor::<Test>::exists());
+        ZkSession::on_idle(2, Weight::MAX);
+        assert!(!PruneCursor::<Test>::exists());
+
+        let remaining: Vec<_> = Sessions::<Test>::iter().map(|(who, dapp, _)| (who, dapp)).collect();
+        assert_eq!(remaining, vec![(2, DAPP)]);
+        assert_eq!((SessionCount::<Test>::get(1), SessionCount::<Test>::get(2)), (0, 1));
+        assert_eq!(ZkSession::total_sessions(), 1);
+        System::assert_has_event(Event::SessionExpired { who: 1, dapp: DAPP }.into());
+        System::assert_has_event(Event::SessionExpired { who: 3, dapp: DAPP }.into());
+    });
+}
+
+#[test]
+fn sessions_opened_before_version_1_are_counted() {
+    new_test_ext().execute_with(|| {
+        Attested::set(vec![(1, [1; 32], 1_000), (2, [2; 32], 1_000)]);
+        assert_ok!(open(1, DAPP));
+        assert_ok!(open(1, 11));
+        assert_ok!(open(2, DAPP));
+        StorageVersion::new(0).put::<ZkSession>();
+        TotalSessions::<Test>::put(42);
+
+        assert_eq!(CountSessions::<Test>::step(None, &mut WeightMeter::new()), Ok(None));
+        assert_eq!(ZkSession::total_sessions(), 3);
+        assert_eq!(ZkSession::on_chain_storage_version(), 1);
+    });
+}
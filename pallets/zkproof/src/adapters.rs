@@ -0,0 +1,211 @@
+//! Per-provider normalization of token claims.
+//!
+//! Issuers put the stable user id, the email verification status and the tenant in different
+//! claims. A [`ProviderAdapter`] maps the claims of the issuers it handles to the canonical
+//! [`VerifiedClaims`] the pallet works with. Adapters are combined in tuples, the first one
+//! handling the token's issuer being used.
+
+use crate::{ClaimHash, IssuerId};
+use alloc::{string::String, vec::Vec};
+use codec::Encode;
+use frame_support::RuntimeDebug;
+use serde_json::Value;
+
+/// The claims of a token, independently of its issuer.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug, Encode)]
+pub struct VerifiedClaims {
+    /// The issuer of the token.
+    pub issuer: IssuerId,
+    /// The stable identifier of the user at the issuer.
+    pub subject: String,
+    /// The client the token was issued to.
+    pub audience: Option<String>,
+    /// The email of the user, if disclosed.
+    pub email: Option<String>,
+    /// Whether the issuer vouches for `email`.
+    pub email_verified: bool,
+    /// The organization the user belongs to, for multi-tenant issuers.
+    pub tenant: Option<String>,
+    /// When (unix seconds) the token was issued.
+    pub issued_at: u64,
+    /// When (unix seconds) the token expires.
+    pub expires_at: u64,
+    /// The nonce the token was requested with.
+    pub nonce: Option<String>,
+    /// The unique identifier of the token (`jti`), if the issuer gives one.
+    pub token_id: Option<String>,
+    /// The hashes of the claims the issuer requires beyond the standard ones, in the order of
+    /// `RequiredClaims`.
+    pub required_claims: Vec<ClaimHash>,
+}
+
+impl VerifiedClaims {
+    /// The hash of the claims, identifying them without disclosing them.
+    pub fn digest(&self) -> [u8; 32] {
+        sp_io::hashing::blake2_256(&self.encode())
+    }
+}
+
+/// Normalizes the claims of the tokens of some issuers.
+pub trait ProviderAdapter {
+    /// Whether this adapter handles tokens issued by `issuer`.
+    fn handles(issuer: &[u8]) -> bool;
+
+    /// Normalize the claims of a token issued by `issuer`. Returns `None` if a claim required by
+    /// the issuer is missing or malformed.
+    fn normalize(issuer: IssuerId, payload: &Value) -> Option<VerifiedClaims>;
+
+    /// The signature algorithms (the `alg` of the JWT header) accepted from `issuer`. Whatever
+    /// this returns, `none` is always rejected.
+    fn algorithms(_issuer: &[u8]) -> &'static [&'static str] {
+        &["RS256"]
+    }
+}
+
+macro_rules! impl_provider_adapter_for_tuples {
+    ($($adapter:ident),+) => {
+        impl<$($adapter: ProviderAdapter),+> ProviderAdapter for ($($adapter,)+) {
+            fn handles(issuer: &[u8]) -> bool {
+                $($adapter::handles(issuer))||+
+            }
+
+            fn normalize(issuer: IssuerId, payload: &Value) -> Option<VerifiedClaims> {
+                $(
+                    if $adapter::handles(&issuer) {
+                        return $adapter::normalize(issuer, payload);
+                    }
+                )+
+                None
+            }
+
+            fn algorithms(issuer: &[u8]) -> &'static [&'static str] {
+                $(
+                    if $adapter::handles(issuer) {
+                        return $adapter::algorithms(issuer);
+                    }
+                )+
+                &[]
+            }
+        }
+    };
+}
+
+impl_provider_adapter_for_tuples!(A);
+impl_provider_adapter_for_tuples!(A, B);
+impl_provider_adapter_for_tuples!(A, B, C);
+impl_provider_adapter_for_tuples!(A, B, C, D);
+impl_provider_adapter_for_tuples!(A, B, C, D, E);
+impl_provider_adapter_for_tuples!(A, B, C, D, E, F);
+
+/// The adapters of the providers supported out of the box, falling back to plain OIDC.
+pub type StandardAdapters = (Google, Microsoft, Apple, GenericOidc);
+
+/// Google accounts. The tenant is the Workspace domain (`hd`).
+pub struct Google;
+
+impl ProviderAdapter for Google {
+    fn handles(issuer: &[u8]) -> bool {
+        issuer == b"https://accounts.google.com" || issuer == b"accounts.google.com"
+    }
+
+    fn normalize(issuer: IssuerId, payload: &Value) -> Option<VerifiedClaims> {
+        Some(VerifiedClaims { tenant: string(payload, "hd"), ..standard(issuer, payload)? })
+    }
+}
+
+/// Microsoft Entra ID (v2.0 endpoints). The subject is the object id (`oid`), stable across
+/// applications unlike `sub`, and the tenant is the directory id (`tid`). Emails are only
+/// verified when the tenant owns their domain (`xms_edov`).
+pub struct Microsoft;
+
+impl ProviderAdapter for Microsoft {
+    fn handles(issuer: &[u8]) -> bool {
+        issuer.starts_with(b"https://login.microsoftonline.com/") && issuer.ends_with(b"/v2.0")
+    }
+
+    fn normalize(issuer: IssuerId, payload: &Value) -> Option<VerifiedClaims> {
+        let standard = standard(issuer, payload)?;
+        Some(VerifiedClaims {
+            subject: string(payload, "oid").unwrap_or(standard.subject),
+            email: standard.email.or_else(|| string(payload, "preferred_username")),
+            email_verified: flag(payload, "xms_edov"),
+            tenant: string(payload, "tid"),
+            ..standard
+        })
+    }
+}
+
+/// Sign in with Apple, which encodes booleans as strings.
+pub struct Apple;
+
+impl ProviderAdapter for Apple {
+    fn handles(issuer: &[u8]) -> bool {
+        issuer == b"https://appleid.apple.com"
+    }
+
+    fn normalize(issuer: IssuerId, payload: &Value) -> Option<VerifiedClaims> {
+        standard(issuer, payload)
+    }
+}
+
+/// Any OpenID Connect provider, going by the standard claims only.
+pub struct GenericOidc;
+
+impl ProviderAdapter for GenericOidc {
+    fn handles(_issuer: &[u8]) -> bool {
+        true
+    }
+
+    fn normalize(issuer: IssuerId, payload: &Value) -> Option<VerifiedClaims> {
+        standard(issuer, payload)
+    }
+}
+
+/// The claims as defined by OpenID Connect Core.
+fn standard(issuer: IssuerId, payload: &Value) -> Option<VerifiedClaims> {
+    Some(VerifiedClaims {
+        issuer,
+        subject: string(payload, "sub")?,
+        audience: audience(payload),
+        email: string(payload, "email"),
+        email_verified: flag(payload, "email_verified"),
+        tenant: None,
+        issued_at: payload.get("iat")?.as_u64()?,
+        expires_at: payload.get("exp")?.as_u64()?,
+        nonce: string(payload, "nonce"),
+        token_id: string(payload, "jti"),
+        required_claims: Vec::new(),
+    })
+}
+
+/// The hash of the value of a claim: of its UTF-8 bytes for strings, of its JSON otherwise.
+pub fn claim_hash(value: &Value) -> ClaimHash {
+    match value {
+        Value::String(value) => sp_io::hashing::blake2_256(value.as_bytes()),
+        value => sp_io::hashing::blake2_256(value.to_string().as_bytes()),
+    }
+}
+
+/// A string claim.
+fn string(payload: &Value, claim: &str) -> Option<String> {
+    payload.get(claim)?.as_str().map(Into::into)
+}
+
+/// The `aud` claim, either a single audience or the first of several.
+fn audience(payload: &Value) -> Option<String> {
+    let audience = match payload.get("aud")? {
+        Value::Array(audiences) => audiences.first()?,
+        audience => audience,
+    };
+    audience.as_str().map(Into::into)
+}
+
+/// A boolean claim, either as a JSON boolean or as a `"true"` / `"false"` string. Missing or
+/// malformed claims are `false`.
+fn flag(payload: &Value, claim: &str) -> bool {
+    match payload.get(claim) {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::String(value)) => value == "true",
+        _ => false,
+    }
+}
@@ -0,0 +1,193 @@
+//! Benchmarks of the parsing and verification of proof envelopes, on each curve and with each hash
+//! scheme of the circuits, of the signature check of tokens, and of the calls reading stored
+//! proofs.
+#![cfg(feature = "runtime-benchmarks")]
+use super::*;
+
+use crate::{
+    circuit::{self, CircuitVersion},
+    envelope::{Curve, G1Point, G2Coordinates, G2Point, JsonProof, ScalarField},
+};
+use alloc::borrow::Cow;
+use ark_ec::{
+    bls12::{Bls12, Bls12Config, G1Affine, G2Affine},
+    AffineRepr,
+};
+use ark_groth16::{prepare_verifying_key, VerifyingKey};
+use ark_serialize::CanonicalSerialize;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use frame_benchmarking::v2::*;
+use frame_system::RawOrigin;
+use sp_runtime::traits::Hash;
+
+/// The base64 encoding of `value`, compressed.
+fn base64_of(value: &impl CanonicalSerialize) -> Cow<'static, str> {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).expect("serializing into a vector cannot fail");
+    STANDARD.encode(bytes).into()
+}
+
+/// An envelope on `curve`, whose parameters are `P`, with valid points, a verifying key for
+/// `MaxPublicInputs` public inputs, a token of `MaxJwtLength` and those public inputs: the largest
+/// envelope parsed and verified.
+fn json_proof<T: Config, P: Bls12Config>(curve: Curve) -> JsonProof<'static> {
+    let g1 = G1Affine::<P>::generator();
+    let g2 = G2Affine::<P>::generator();
+    let inputs = T::MaxPublicInputs::get();
+    let verifying_key = VerifyingKey::<Bls12<P>> {
+        alpha_g1: g1,
+        beta_g2: g2,
+        gamma_g2: g2,
+        delta_g2: g2,
+        gamma_abc_g1: alloc::vec![g1; inputs as usize + 1],
+    };
+    let point = G1Point { x: base64_of(&g1.x), y: base64_of(&g1.y) };
+    JsonProof {
+        curve,
+        circuit_version: None,
+        a: point.clone(),
+        b: G2Point {
+            x: G2Coordinates { c0: base64_of(&g2.x.c0), c1: base64_of(&g2.x.c1) },
+            y: G2Coordinates { c0: base64_of(&g2.y.c0), c1: base64_of(&g2.y.c1) },
+        },
+        c: point,
+        public_hash: Cow::Borrowed(""),
+        verifying_key: base64_of(&prepare_verifying_key(&verifying_key)),
+        jwt_token: "a".repeat(T::MaxJwtLength::get() as usize).into(),
+        chain_id: "00".repeat(32).into(),
+        public_inputs: (0..inputs).map(|i| base64_of(&ScalarField::<P>::from(i))).collect(),
+        predicate: None,
+        token: None,
+        encoding: Default::default(),
+    }
+}
+
+/// The largest BLS12-381 envelope, serialized.
+fn envelope<T: Config>() -> Vec<u8> {
+    let envelope = json_proof::<T, ark_bls12_381::Config>(Curve::Bls12_381);
+    serde_json::to_vec(&envelope).expect("serializing into a vector cannot fail")
+}
+
+/// Claims with every claim the circuits hash, of a realistic length.
+fn claims() -> VerifiedClaims {
+    VerifiedClaims {
+        issuer: IssuerId::truncate_from(GOOGLE_ISSUER.to_vec()),
+        subject: "1".repeat(64),
+        audience: Some("a".repeat(128)),
+        email: None,
+        email_verified: false,
+        tenant: None,
+        issued_at: 0,
+        expires_at: u64::MAX,
+        nonce: Some(alloc::format!("{}-{}", u32::MAX, "00".repeat(32))),
+        token_id: None,
+        required_claims: Vec::new(),
+    }
+}
+
+#[benchmarks]
+mod benchmarks {
+    use super::*;
+
+    #[benchmark]
+    fn parse_envelope() {
+        let json = envelope::<T>();
+
+        #[block]
+        {
+            JsonProof::from_bytes(&json).expect("the envelope is well-formed");
+        }
+    }
+
+    #[benchmark]
+    fn parse_proof_points() {
+        let json = envelope::<T>();
+        let envelope = JsonProof::from_bytes(&json).expect("the envelope is well-formed");
+
+        #[block]
+        {
+            envelope.proof::<ark_bls12_381::Config>().expect("the points are valid");
+            envelope.public_inputs::<ark_bls12_381::Config>().expect("the public inputs are valid");
+        }
+    }
+
+    /// Parsing the verifying key and the points of a BLS12-381 proof, and checking the pairing.
+    #[benchmark]
+    fn verify_bls12_381() {
+        let envelope = json_proof::<T, ark_bls12_381::Config>(Curve::Bls12_381);
+
+        #[block]
+        {
+            let instance = envelope.instance().expect("the envelope is well-formed");
+            host::verify(&instance);
+        }
+    }
+
+    /// Parsing the verifying key and the points of a BLS12-377 proof, and checking the pairing.
+    #[benchmark]
+    fn verify_bls12_377() {
+        let envelope = json_proof::<T, ark_bls12_377::Config>(Curve::Bls12_377);
+
+        #[block]
+        {
+            let instance = envelope.instance().expect("the envelope is well-formed");
+            host::verify(&instance);
+        }
+    }
+
+    /// Checking the RS256 signature of the longest token against the longest modulus and exponent a
+    /// key may have.
+    #[benchmark]
+    fn verify_rs256() {
+        let message = "a".repeat(T::MaxJwtLength::get() as usize);
+        let modulus = alloc::vec![0xff; MAX_MODULUS_LENGTH as usize * 3 / 4];
+        let exponent = alloc::vec![0xff; MAX_JWK_FIELD_LENGTH as usize * 3 / 4];
+        let signature = alloc::vec![0x01; modulus.len()];
+
+        #[block]
+        {
+            rsa::verify_rs256(message.as_bytes(), &signature, &modulus, &exponent);
+        }
+    }
+
+    /// Assembling the public inputs of the SHA-256 circuit hashing the most claims.
+    #[benchmark]
+    fn public_inputs_sha256() {
+        let claims = claims();
+        let predicate = Some(([0; 32], true));
+
+        #[block]
+        {
+            circuit::public_inputs(CircuitVersion::V2, &claims, ark_bls12_381::Fr::from(1), predicate, None)
+                .expect("the claims are complete");
+        }
+    }
+
+    /// Assembling the public inputs of the Poseidon circuit, its parameters included.
+    #[benchmark]
+    fn public_inputs_poseidon() {
+        let claims = claims();
+
+        #[block]
+        {
+            circuit::public_inputs(CircuitVersion::V4, &claims, ark_bls12_381::Fr::from(1), None, None)
+                .expect("the claims are complete");
+        }
+    }
+
+    /// Retrieving `n` proofs whose payloads are `MaxJsonLength` bytes long, all read into the proof
+    /// of the block.
+    #[benchmark]
+    fn retrieve_all_zk_proofs(n: Linear<1, 64>) {
+        let payload = alloc::vec![0u8; T::MaxJsonLength::get() as usize];
+        let payload = BoundedVec::<u8, T::MaxJsonLength>::try_from(payload).expect("the payload fits");
+        for i in 0..n {
+            ZkProofData::<T>::insert(T::Hashing::hash_of(&i), payload.clone());
+        }
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(whitelisted_caller()), None, n);
+    }
+
+    impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
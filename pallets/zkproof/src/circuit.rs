@@ -0,0 +1,225 @@
+//! The public inputs of the login circuits, as the chain expects them.
+//!
+//! Provers and the chain must agree on the order of the public inputs. Both assemble them with
+//! [`public_inputs`] from the verified claims, following the layout of the circuit version the
+//! envelope declares.
+//!
+//! Circuits hash the claims among their public inputs with the [`HashScheme`] of their version,
+//! so generations of circuits hashing with SHA-256 and with Poseidon are accepted side by side.
+//!
+//! Circuits keeping the token private check its signature themselves, against the key whose
+//! modulus hash is among their public inputs; the chain only sees what the envelope discloses.
+
+use crate::{
+    envelope::{Curve, EnvelopeError, JsonProof},
+    poseidon::Poseidon,
+    PredicateId, VerifiedClaims,
+};
+use alloc::vec::Vec;
+use ark_ec::bls12::Bls12Config;
+use ark_ff::{One, PrimeField, Zero};
+use sha2::{Digest, Sha256};
+
+/// A version of the login circuit, fixing the layout of its public inputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitVersion {
+    /// Address seed, issuer hash, audience hash, nonce hash and epoch.
+    V1,
+    /// The inputs of `V1`, followed by the hash of a claim predicate and whether it holds. The
+    /// claims the predicate is about are private inputs.
+    V2,
+    /// Address seed, issuer hash, audience hash, nonce hash, epoch, the hash of the modulus of the
+    /// issuer key the token is signed with, and its issue and expiry times. The token itself is a
+    /// private input.
+    V3,
+    /// The inputs of `V1`, hashed with Poseidon.
+    V4,
+}
+
+impl TryFrom<u32> for CircuitVersion {
+    type Error = ();
+
+    fn try_from(version: u32) -> Result<Self, Self::Error> {
+        match version {
+            1 => Ok(CircuitVersion::V1),
+            2 => Ok(CircuitVersion::V2),
+            3 => Ok(CircuitVersion::V3),
+            4 => Ok(CircuitVersion::V4),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How a circuit hashes claims into the scalar field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashScheme {
+    /// SHA-256 over the bits of the claim, its digest reduced into the field, see [`hash_to_field`].
+    Sha256,
+    /// Poseidon over the field elements packing the claim, see [`Poseidon::hash`].
+    Poseidon,
+}
+
+/// A public input of the login circuits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicInput {
+    /// The identity commitment, derived by the prover from the subject, the audience and a salt
+    /// the chain never sees.
+    AddressSeed,
+    /// The hash of the `iss` claim.
+    IssuerHash,
+    /// The hash of the `aud` claim.
+    AudienceHash,
+    /// The hash of the `nonce` claim.
+    NonceHash,
+    /// The number of the block the nonce is bound to.
+    Epoch,
+    /// The hash of the id of the predicate the proof is about.
+    PredicateHash,
+    /// Whether the predicate holds, as 0 or 1.
+    PredicateResult,
+    /// The hash of the base64url-encoded modulus of the key the token is signed with.
+    ModulusHash,
+    /// When (unix seconds) the token was issued.
+    IssuedAt,
+    /// When (unix seconds) the token expires.
+    ExpiresAt,
+}
+
+impl CircuitVersion {
+    /// The public inputs of the circuit, in order.
+    pub fn layout(self) -> &'static [PublicInput] {
+        use PublicInput::*;
+        match self {
+            CircuitVersion::V1 | CircuitVersion::V4 => &[AddressSeed, IssuerHash, AudienceHash, NonceHash, Epoch],
+            CircuitVersion::V2 => {
+                &[AddressSeed, IssuerHash, AudienceHash, NonceHash, Epoch, PredicateHash, PredicateResult]
+            }
+            CircuitVersion::V3 => {
+                &[AddressSeed, IssuerHash, AudienceHash, NonceHash, Epoch, ModulusHash, IssuedAt, ExpiresAt]
+            }
+        }
+    }
+
+    /// How the circuit hashes the claims among its public inputs.
+    pub fn hash_scheme(self) -> HashScheme {
+        match self {
+            CircuitVersion::V1 | CircuitVersion::V2 | CircuitVersion::V3 => HashScheme::Sha256,
+            CircuitVersion::V4 => HashScheme::Poseidon,
+        }
+    }
+
+    /// Whether the circuit proves a claim predicate.
+    pub fn proves_predicate(self) -> bool {
+        self.layout().contains(&PublicInput::PredicateResult)
+    }
+
+    /// Whether the circuit keeps the token a private input, checking its signature itself.
+    pub fn keeps_token_private(self) -> bool {
+        self.layout().contains(&PublicInput::ModulusHash)
+    }
+}
+
+/// Assemble the public inputs of `version` from `claims`. The address seed, and the predicate with
+/// its result for predicate circuits and the modulus of the signing key for circuits keeping the
+/// token private, cannot be derived from the claims and are taken as given. Claims are hashed with
+/// the scheme of `version`. Returns `None` if a claim, the predicate or the modulus the layout
+/// needs is missing.
+pub fn public_inputs<F: PrimeField>(
+    version: CircuitVersion,
+    claims: &VerifiedClaims,
+    address_seed: F,
+    predicate: Option<(PredicateId, bool)>,
+    modulus: Option<&[u8]>,
+) -> Option<Vec<F>> {
+    let poseidon = (version.hash_scheme() == HashScheme::Poseidon).then(Poseidon::<F>::new);
+    let hash = |bytes: &[u8]| match &poseidon {
+        Some(poseidon) => poseidon.hash(bytes),
+        None => hash_to_field(bytes),
+    };
+    version
+        .layout()
+        .iter()
+        .map(|input| {
+            Some(match input {
+                PublicInput::AddressSeed => address_seed,
+                PublicInput::IssuerHash => hash(&claims.issuer),
+                PublicInput::AudienceHash => hash(claims.audience.as_ref()?.as_bytes()),
+                PublicInput::NonceHash => hash(claims.nonce.as_ref()?.as_bytes()),
+                PublicInput::Epoch => F::from(nonce_epoch(claims.nonce.as_ref()?)?),
+                PublicInput::PredicateHash => hash(&predicate?.0),
+                PublicInput::PredicateResult => F::from(predicate?.1),
+                PublicInput::ModulusHash => hash(modulus?),
+                PublicInput::IssuedAt => F::from(claims.issued_at),
+                PublicInput::ExpiresAt => F::from(claims.expires_at),
+            })
+        })
+        .collect()
+}
+
+/// Whether the public inputs of `envelope` are the ones `version` lays out for `claims` and, for
+/// predicate circuits, for `predicate` and its result and, for circuits keeping the token private,
+/// for the `modulus` of the key the token is signed with.
+pub fn matches_layout(
+    envelope: &JsonProof,
+    version: CircuitVersion,
+    claims: &VerifiedClaims,
+    predicate: Option<(PredicateId, bool)>,
+    modulus: Option<&[u8]>,
+) -> Result<bool, EnvelopeError> {
+    match envelope.curve {
+        Curve::Bls12_381 => matches_layout_on::<ark_bls12_381::Config>(envelope, version, claims, predicate, modulus),
+        Curve::Bls12_377 => matches_layout_on::<ark_bls12_377::Config>(envelope, version, claims, predicate, modulus),
+    }
+}
+
+/// [`matches_layout`] on the curve `P`.
+fn matches_layout_on<P: Bls12Config>(
+    envelope: &JsonProof,
+    version: CircuitVersion,
+    claims: &VerifiedClaims,
+    predicate: Option<(PredicateId, bool)>,
+    modulus: Option<&[u8]>,
+) -> Result<bool, EnvelopeError> {
+    let inputs = envelope.public_inputs::<P>()?;
+    let address_seed = input_at(version, &inputs, PublicInput::AddressSeed).unwrap_or_default();
+    Ok(public_inputs(version, claims, address_seed, predicate, modulus).as_ref() == Some(&inputs))
+}
+
+/// Whether the predicate a proof of `version` is about holds, going by the proof's public inputs.
+/// Returns `None` if `version` proves no predicate, or if the result is neither 0 nor 1.
+pub fn predicate_result(envelope: &JsonProof, version: CircuitVersion) -> Result<Option<bool>, EnvelopeError> {
+    match envelope.curve {
+        Curve::Bls12_381 => predicate_result_on::<ark_bls12_381::Config>(envelope, version),
+        Curve::Bls12_377 => predicate_result_on::<ark_bls12_377::Config>(envelope, version),
+    }
+}
+
+/// [`predicate_result`] on the curve `P`.
+fn predicate_result_on<P: Bls12Config>(
+    envelope: &JsonProof,
+    version: CircuitVersion,
+) -> Result<Option<bool>, EnvelopeError> {
+    let inputs = envelope.public_inputs::<P>()?;
+    Ok(match input_at(version, &inputs, PublicInput::PredicateResult) {
+        Some(result) if result.is_zero() => Some(false),
+        Some(result) if result.is_one() => Some(true),
+        _ => None,
+    })
+}
+
+/// The public input `input` among the `inputs` of a proof of `version`, if the layout has it.
+fn input_at<F: Copy>(version: CircuitVersion, inputs: &[F], input: PublicInput) -> Option<F> {
+    let position = version.layout().iter().position(|i| *i == input)?;
+    inputs.get(position).copied()
+}
+
+/// Hash arbitrary bytes into the scalar field: the SHA-256 digest, read as a big-endian integer
+/// and reduced modulo the field order.
+pub fn hash_to_field<F: PrimeField>(bytes: &[u8]) -> F {
+    F::from_be_bytes_mod_order(&Sha256::digest(bytes))
+}
+
+/// The number of the block a nonce is bound to, i.e. its leading `<block number>-` part.
+pub fn nonce_epoch(nonce: &str) -> Option<u64> {
+    nonce.split('-').next()?.parse().ok()
+}
@@ -0,0 +1,177 @@
+//! Base64 decoding of proof envelopes and tokens.
+//!
+//! Prover tooling encodes the same fields with different base64 variants: snarkjs wrappers emit
+//! standard, padded base64, while web tooling tends to emit URL-safe base64, with or without
+//! padding. Rather than guessing, each provider declares the variants its tooling emits, see
+//! [`crate::ProviderEncoding`], and [`decode`] accepts exactly those.
+//!
+//! Decoding takes the same time whatever the characters decoded: the alphabet is looked up with
+//! arithmetic instead of tables or branches, and invalid characters are only reported once the
+//! whole input is decoded. Only the length of the input and its padding, which the length of the
+//! output discloses anyway, shape the work done.
+
+use alloc::{vec, vec::Vec};
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::RuntimeDebug;
+use scale_info::TypeInfo;
+
+/// The characters encoding the values 62 and 63.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum Base64Alphabet {
+    /// `+` and `/` (RFC 4648, section 4).
+    Standard,
+    /// `-` and `_` (RFC 4648, section 5), as in JWTs.
+    UrlSafe,
+    /// Either pair.
+    Any,
+}
+
+/// Whether encoded data is padded with `=` to a multiple of four characters.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum Base64Padding {
+    /// Padding is required.
+    Required,
+    /// Padding is rejected.
+    Forbidden,
+    /// Padding may be left out, but must be complete if present.
+    Optional,
+}
+
+/// A variant of base64.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Base64Variant {
+    /// The alphabet of the encoded data.
+    pub alphabet: Base64Alphabet,
+    /// The padding of the encoded data.
+    pub padding: Base64Padding,
+}
+
+impl Base64Variant {
+    /// Standard, padded base64, in which envelopes encode their proof fields by default.
+    pub const STANDARD: Self = Self { alphabet: Base64Alphabet::Standard, padding: Base64Padding::Required };
+    /// URL-safe base64, padded or not, in which JWTs encode their segments.
+    pub const URL_SAFE: Self = Self { alphabet: Base64Alphabet::UrlSafe, padding: Base64Padding::Optional };
+    /// Any alphabet, padded or not.
+    pub const ANY: Self = Self { alphabet: Base64Alphabet::Any, padding: Base64Padding::Optional };
+}
+
+impl Default for Base64Variant {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// Why data is not valid base64 of a variant.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub enum Base64Error {
+    /// The data, padding aside, has a length no encoding has.
+    InvalidLength,
+    /// A character is not in the alphabet.
+    InvalidCharacter,
+    /// The padding is missing, incomplete or not allowed.
+    InvalidPadding,
+    /// The bits left over by the last character are not zero.
+    NonCanonical,
+    /// The output buffer is too small for the decoded data.
+    BufferTooSmall,
+}
+
+impl core::fmt::Display for Base64Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let reason = match self {
+            Base64Error::InvalidLength => "invalid base64 length",
+            Base64Error::InvalidCharacter => "invalid base64 character",
+            Base64Error::InvalidPadding => "invalid base64 padding",
+            Base64Error::NonCanonical => "non-canonical base64",
+            Base64Error::BufferTooSmall => "base64 output buffer too small",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Decode `input`, encoded with `variant`.
+pub fn decode(input: &[u8], variant: Base64Variant) -> Result<Vec<u8>, Base64Error> {
+    let data = strip_padding(input, variant.padding)?;
+    let mut output = vec![0; decoded_len(data)?];
+    decode_data(data, variant.alphabet, &mut output)?;
+    Ok(output)
+}
+
+/// Decode `input`, encoded with `variant`, into `output`, returning the length of the decoded data.
+pub fn decode_slice(input: &[u8], variant: Base64Variant, output: &mut [u8]) -> Result<usize, Base64Error> {
+    let data = strip_padding(input, variant.padding)?;
+    let len = decoded_len(data)?;
+    let output = output.get_mut(..len).ok_or(Base64Error::BufferTooSmall)?;
+    decode_data(data, variant.alphabet, output)?;
+    Ok(len)
+}
+
+/// `input` without its padding, once checked against `padding`.
+fn strip_padding(input: &[u8], padding: Base64Padding) -> Result<&[u8], Base64Error> {
+    let pads = input.iter().rev().take(2).take_while(|c| **c == b'=').count();
+    let padded = input.len() % 4 == 0;
+    let valid = match padding {
+        Base64Padding::Required => padded,
+        Base64Padding::Forbidden => pads == 0,
+        Base64Padding::Optional => pads == 0 || padded,
+    };
+    let data = &input[..input.len() - pads];
+    if data.len() % 4 == 1 {
+        return Err(Base64Error::InvalidLength);
+    }
+    valid.then_some(data).ok_or(Base64Error::InvalidPadding)
+}
+
+/// The length of the data `data`, without padding, decodes into.
+fn decoded_len(data: &[u8]) -> Result<usize, Base64Error> {
+    match data.len() % 4 {
+        1 => Err(Base64Error::InvalidLength),
+        rest => Ok(data.len() / 4 * 3 + rest.saturating_sub(1)),
+    }
+}
+
+/// Decode `data`, without padding, into `output`, of its decoded length.
+fn decode_data(data: &[u8], alphabet: Base64Alphabet, output: &mut [u8]) -> Result<(), Base64Error> {
+    let mut invalid = 0u8;
+    let mut leftover = 0u32;
+    for (chunk, out) in data.chunks(4).zip(output.chunks_mut(3)) {
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = decode_char(*c, alphabet);
+            invalid |= (value >> 8) as u8;
+            bits |= ((value & 0x3f) as u32) << (18 - 6 * i);
+        }
+        out.copy_from_slice(&bits.to_be_bytes()[1..=out.len()]);
+        // The bits past the last decoded byte must be zero, for each output to have one encoding.
+        let (total, used) = (6 * chunk.len() as u32, 8 * out.len() as u32);
+        leftover |= bits & (((1 << (total - used)) - 1) << (24 - total));
+    }
+    if invalid != 0 {
+        return Err(Base64Error::InvalidCharacter);
+    }
+    if leftover != 0 {
+        return Err(Base64Error::NonCanonical);
+    }
+    Ok(())
+}
+
+/// The value of the character `c` in `alphabet`, or -1 if it is not in it, computed without
+/// branching on `c`. Each term adds the offset of `c` within a range of characters when `c` is
+/// in the range: `(low - 1 - c) & (c - high - 1)` is negative exactly then.
+fn decode_char(c: u8, alphabet: Base64Alphabet) -> i16 {
+    let c = c as i16;
+    let within = |low: i16, high: i16| ((low - 1 - c) & (c - high - 1)) >> 8;
+    let mut value = -1;
+    value += within(b'A' as i16, b'Z' as i16) & (c - b'A' as i16 + 1);
+    value += within(b'a' as i16, b'z' as i16) & (c - b'a' as i16 + 27);
+    value += within(b'0' as i16, b'9' as i16) & (c - b'0' as i16 + 53);
+    if alphabet != Base64Alphabet::UrlSafe {
+        value += within(b'+' as i16, b'+' as i16) & 63;
+        value += within(b'/' as i16, b'/' as i16) & 64;
+    }
+    if alphabet != Base64Alphabet::Standard {
+        value += within(b'-' as i16, b'-' as i16) & 63;
+        value += within(b'_' as i16, b'_' as i16) & 64;
+    }
+    value
+}
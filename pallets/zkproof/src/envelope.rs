@@ -0,0 +1,761 @@
+//! The proof envelope: the JSON submitted to `store_zk_proof`, and its parsing.
+//!
+//! Envelopes may be prefixed with an [`EnvelopeFormat`] byte to be submitted in another format,
+//! which [`decode_envelope`] turns into the JSON envelope before parsing. Envelopes without one
+//! are JSON, as submitted before formats were introduced.
+//!
+//! Off-chain services can call [`validate_envelope`] to pre-validate user submissions with the same
+//! code the runtime runs, before paying for a transaction.
+
+use crate::{
+    encoding::{self, Base64Error, Base64Variant},
+    host, Commitment, G1Bytes, G2Bytes, PredicateId, ProofSubmission,
+};
+use alloc::{borrow::Cow, string::String, vec, vec::Vec};
+use ark_ec::{
+    bls12::{Bls12, Bls12Config, G1Affine, G2Affine},
+    pairing::Pairing,
+};
+use ark_ff::{Field, Fp2, PrimeField};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use codec::{Decode, DecodeAll, Encode};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::from_slice;
+
+/// Why an envelope is malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// The envelope is not valid UTF-8.
+    InvalidUtf8,
+    /// The envelope is not valid JSON, or misses fields.
+    MalformedJson,
+    /// A field that should be base64 of the envelope's variant is not.
+    InvalidBase64(Base64Error),
+    /// A proof point is not a valid point of the curve's prime-order subgroup.
+    InvalidPoint,
+    /// The verifying key could not be deserialized.
+    InvalidVerifyingKey,
+    /// The JWT is not a well-formed `header.payload.signature` token.
+    MalformedJwt,
+    /// The chain id is not a hex-encoded 32 bytes hash.
+    InvalidChainId,
+    /// The envelope starts with a reserved format byte that is no known [`EnvelopeFormat`].
+    UnknownFormat,
+    /// The envelope is not a valid SCALE or CBOR encoding.
+    MalformedEncoding,
+}
+
+impl core::fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let reason = match self {
+            EnvelopeError::InvalidUtf8 => "envelope is not valid UTF-8",
+            EnvelopeError::MalformedJson => "envelope is not a valid proof JSON",
+            EnvelopeError::InvalidBase64(_) => "invalid base64 field",
+            EnvelopeError::InvalidPoint => "proof point is not a valid curve point",
+            EnvelopeError::InvalidVerifyingKey => "verifying key could not be deserialized",
+            EnvelopeError::MalformedJwt => "malformed JWT",
+            EnvelopeError::InvalidChainId => "chain id is not a hex-encoded genesis hash",
+            EnvelopeError::UnknownFormat => "unknown envelope format",
+            EnvelopeError::MalformedEncoding => "envelope is not a valid encoding of its format",
+        };
+        f.write_str(reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EnvelopeError {}
+
+/// The scalar field of a BLS12 curve, public inputs are elements of.
+pub type ScalarField<P> = <Bls12<P> as Pairing>::ScalarField;
+
+/// The curve a proof is made on.
+#[derive(Serialize, Deserialize, Encode, Decode, TypeInfo, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Curve {
+    /// BLS12-381.
+    #[default]
+    #[serde(rename = "bls12-381")]
+    Bls12_381,
+    /// BLS12-377, whose proofs can be verified recursively in BW6-761 circuits.
+    #[serde(rename = "bls12-377")]
+    Bls12_377,
+}
+
+/// A lower bound on the length of an envelope besides its token: the base64 coordinates of the
+/// points of a BLS12-381 proof alone take this many bytes.
+pub const MIN_ENVELOPE_OVERHEAD: u32 = 8 * 64;
+
+/// Struct representing a JSON proof.
+///
+/// The string fields borrow from the JSON they are parsed from whenever they contain no escapes,
+/// which holds for base64, hex and JWTs, so parsing an envelope does not copy them.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone)]
+pub struct JsonProof<'a> {
+    /// The curve the proof is made on; BLS12-381 if unset.
+    #[serde(default)]
+    pub curve: Curve,
+    /// The version of the circuit the proof is made with, fixing the layout of the public inputs.
+    /// Unset for legacy proofs, whose public inputs are not checked against the claims.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_version: Option<u32>,
+    #[serde(borrow)]
+    pub a: G1Point<'a>,
+    #[serde(borrow)]
+    pub b: G2Point<'a>,
+    #[serde(borrow)]
+    pub c: G1Point<'a>,
+    /// The base64-encoded public input of single-input circuits.
+    #[serde(borrow)]
+    pub public_hash: Cow<'a, str>,
+    /// The base64-encoded compressed verifying key.
+    #[serde(borrow)]
+    pub verifying_key: Cow<'a, str>,
+    /// The ID token the proof was made for. Empty for proofs keeping it private, see `token`.
+    #[serde(default, borrow)]
+    pub jwt_token: Cow<'a, str>,
+    /// The hex-encoded genesis hash of the chain the proof is meant for, so that it cannot be
+    /// replayed on another chain.
+    #[serde(borrow)]
+    pub chain_id: Cow<'a, str>,
+    /// The base64-encoded public inputs of multi-input circuits. When empty, `public_hash` is the
+    /// single public input.
+    #[serde(default, borrow)]
+    pub public_inputs: Vec<Cow<'a, str>>,
+    /// The hex-encoded id of the claim predicate the proof attests, for predicate circuits.
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<Cow<'a, str>>,
+    /// What the proof discloses of its token, for circuits keeping the token a private witness.
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub token: Option<DisclosedToken<'a>>,
+    /// The base64 variant of the proof points, verifying key and public inputs. Not part of the
+    /// envelope: it is the variant the tooling of the token's provider emits, standard by default.
+    #[serde(skip)]
+    #[codec(skip)]
+    pub encoding: Base64Variant,
+}
+
+/// What a proof keeping its token private discloses of it: enough to look up the key the circuit
+/// checks its signature against, bind it to a recent block, let it expire, and hold it to
+/// `MaxTokenAge` and to the policies of the dApp it was issued to. The subject and every other
+/// claim stay private.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone)]
+pub struct DisclosedToken<'a> {
+    /// The issuer of the token.
+    #[serde(borrow)]
+    pub iss: Cow<'a, str>,
+    /// The id of the key of the issuer the token is signed with.
+    #[serde(borrow)]
+    pub kid: Cow<'a, str>,
+    /// The client the token was issued to.
+    #[serde(borrow)]
+    pub aud: Cow<'a, str>,
+    /// The nonce the token was requested with.
+    #[serde(borrow)]
+    pub nonce: Cow<'a, str>,
+    /// When (unix seconds) the token was issued.
+    pub iat: u64,
+    /// When (unix seconds) the token expires.
+    pub exp: u64,
+}
+
+/// Struct representing a G1 point.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone)]
+pub struct G1Point<'a> {
+    #[serde(borrow)]
+    pub x: Cow<'a, str>,
+    #[serde(borrow)]
+    pub y: Cow<'a, str>,
+}
+
+/// Struct representing a G2 point.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone)]
+pub struct G2Point<'a> {
+    #[serde(borrow)]
+    pub x: G2Coordinates<'a>,
+    #[serde(borrow)]
+    pub y: G2Coordinates<'a>,
+}
+
+/// Struct representing the coordinates of a G2 point.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone)]
+pub struct G2Coordinates<'a> {
+    #[serde(borrow)]
+    pub c0: Cow<'a, str>,
+    #[serde(borrow)]
+    pub c1: Cow<'a, str>,
+}
+
+/// Struct representing claims in a JWT.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Claims {
+    // L'émetteur du token
+    pub iss: String,
+    // L'ID client autorisé
+    #[serde(default)]
+    pub azp: String,
+    // Le destinataire du token, doit correspondre à l'ID client
+    pub aud: String,
+    // L'identifiant unique de l'utilisateur
+    pub sub: String,
+    // Une chaîne utilisée pour associer une session client à un ID Token
+    #[serde(default)]
+    pub nonce: String,
+    // La date/heure avant laquelle le token n'est pas accepté (Not Before)
+    #[serde(default)]
+    pub nbf: i64,
+    // L'heure d'émission du token (Issued At)
+    pub iat: i64,
+    // L'heure d'expiration du token (Expire)
+    pub exp: i64,
+    // Un identifiant unique pour le token (JWT ID)
+    #[serde(default)]
+    pub jti: String,
+    #[serde(default)]
+    pub email: String,
+}
+
+/// A Groth16 proof on the BLS12 curve `P`, along with what it is verified against.
+pub struct Groth16Instance<P: Bls12Config> {
+    /// The Groth16 proof.
+    pub proof: Proof<Bls12<P>>,
+    /// The verifying key embedded in the envelope.
+    pub verifying_key: PreparedVerifyingKey<Bls12<P>>,
+    /// The public inputs, in circuit order.
+    pub public_inputs: Vec<ScalarField<P>>,
+}
+
+impl<P: Bls12Config> Groth16Instance<P> {
+    /// Check the proof against the verifying key.
+    pub fn verify(&self) -> bool {
+        verify_groth16(&self.verifying_key, &self.proof, &self.public_inputs)
+    }
+
+    /// The verifying key, proof and public inputs, serialized compressed.
+    pub fn to_compressed(&self) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+        let (mut verifying_key, mut proof, mut public_inputs) = (Vec::new(), Vec::new(), Vec::new());
+        self.verifying_key.vk.serialize_compressed(&mut verifying_key).ok()?;
+        self.proof.serialize_compressed(&mut proof).ok()?;
+        self.public_inputs.serialize_compressed(&mut public_inputs).ok()?;
+        Some((verifying_key, proof, public_inputs))
+    }
+}
+
+/// A Groth16 proof on any of the supported curves.
+pub enum CurveInstance {
+    /// A proof on BLS12-381.
+    Bls12_381(Groth16Instance<ark_bls12_381::Config>),
+    /// A proof on BLS12-377.
+    Bls12_377(Groth16Instance<ark_bls12_377::Config>),
+}
+
+impl CurveInstance {
+    /// Check the proof against the verifying key.
+    pub fn verify(&self) -> bool {
+        match self {
+            CurveInstance::Bls12_381(instance) => instance.verify(),
+            CurveInstance::Bls12_377(instance) => instance.verify(),
+        }
+    }
+
+    /// The curve the proof is on.
+    pub fn curve(&self) -> Curve {
+        match self {
+            CurveInstance::Bls12_381(_) => Curve::Bls12_381,
+            CurveInstance::Bls12_377(_) => Curve::Bls12_377,
+        }
+    }
+
+    /// The verifying key, proof and public inputs, serialized compressed.
+    pub fn to_compressed(&self) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+        match self {
+            CurveInstance::Bls12_381(instance) => instance.to_compressed(),
+            CurveInstance::Bls12_377(instance) => instance.to_compressed(),
+        }
+    }
+
+    /// The commitment the proof is bound to.
+    pub fn commitment(&self) -> Commitment {
+        match self {
+            CurveInstance::Bls12_381(instance) => first_commitment(&instance.public_inputs),
+            CurveInstance::Bls12_377(instance) => first_commitment(&instance.public_inputs),
+        }
+    }
+}
+
+/// A fully parsed envelope.
+pub struct ParsedProof {
+    /// The proof, verifying key and public inputs.
+    pub instance: CurveInstance,
+    /// The key id of the JWT header.
+    pub kid: String,
+    /// The claims of the JWT.
+    pub claims: Claims,
+    /// The genesis hash of the chain the proof is meant for.
+    pub chain_id: [u8; 32],
+}
+
+impl ParsedProof {
+    /// Check the Groth16 proof against the embedded verifying key.
+    pub fn verify(&self) -> bool {
+        self.instance.verify()
+    }
+
+    /// The commitment the proof is bound to.
+    pub fn commitment(&self) -> Commitment {
+        self.instance.commitment()
+    }
+}
+
+/// Parse and structurally validate an envelope, in any format: JSON layout, curve points,
+/// verifying key, public inputs and JWT claims. This does not verify the proof itself, see
+/// [`ParsedProof::verify`].
+pub fn validate_envelope(bytes: &[u8]) -> Result<ParsedProof, EnvelopeError> {
+    JsonProof::from_bytes(&decode_envelope(bytes)?)?.parse()
+}
+
+/// The format of an envelope, given by its first byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EnvelopeFormat {
+    /// The JSON envelope.
+    JsonV1 = 0x01,
+    /// A SCALE-encoded [`ProofSubmission`].
+    ScaleV2 = 0x02,
+    /// The fields of the JSON envelope, encoded in CBOR.
+    Cbor = 0x03,
+    /// A Groth16 proof on BLS12-381 as output by snarkjs, see [`SnarkjsEnvelope`].
+    Snarkjs = 0x04,
+}
+
+impl TryFrom<u8> for EnvelopeFormat {
+    type Error = ();
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0x01 => Ok(EnvelopeFormat::JsonV1),
+            0x02 => Ok(EnvelopeFormat::ScaleV2),
+            0x03 => Ok(EnvelopeFormat::Cbor),
+            0x04 => Ok(EnvelopeFormat::Snarkjs),
+            _ => Err(()),
+        }
+    }
+}
+
+impl EnvelopeFormat {
+    /// The first bytes reserved for formats. No JSON text starts with any of them, so envelopes
+    /// without a format byte are still told apart.
+    const RESERVED: core::ops::Range<u8> = 0x00..0x09;
+
+    /// `envelope`, prefixed with the format byte.
+    pub fn prefix(self, envelope: &[u8]) -> Vec<u8> {
+        [&[self as u8][..], envelope].concat()
+    }
+
+    /// The format of the envelope `bytes`, and the envelope without its format byte.
+    pub fn of(bytes: &[u8]) -> Result<(Self, &[u8]), EnvelopeError> {
+        Ok(match bytes.split_first() {
+            Some((&byte, envelope)) if EnvelopeFormat::RESERVED.contains(&byte) => {
+                (EnvelopeFormat::try_from(byte).map_err(|_| EnvelopeError::UnknownFormat)?, envelope)
+            }
+            _ => (EnvelopeFormat::JsonV1, bytes),
+        })
+    }
+
+    /// Whether the base64 fields of the JSON envelope are written by the client, in the variant of
+    /// its tooling, rather than by [`decode_envelope`] in standard base64.
+    pub fn client_base64(self) -> bool {
+        matches!(self, EnvelopeFormat::JsonV1 | EnvelopeFormat::Cbor)
+    }
+}
+
+/// Decode an envelope in any format into the JSON envelope. JSON envelopes are borrowed.
+pub fn decode_envelope(bytes: &[u8]) -> Result<Cow<'_, [u8]>, EnvelopeError> {
+    let (format, envelope) = EnvelopeFormat::of(bytes)?;
+
+    Ok(match format {
+        EnvelopeFormat::JsonV1 => Cow::Borrowed(envelope),
+        EnvelopeFormat::ScaleV2 => {
+            let submission =
+                ProofSubmission::decode_all(&mut &envelope[..]).map_err(|_| EnvelopeError::MalformedEncoding)?;
+            Cow::Owned(submission.to_envelope())
+        }
+        EnvelopeFormat::Cbor => {
+            let fields: serde_json::Value =
+                ciborium::from_reader(envelope).map_err(|_| EnvelopeError::MalformedEncoding)?;
+            Cow::Owned(serde_json::to_vec(&fields).map_err(|_| EnvelopeError::MalformedEncoding)?)
+        }
+        EnvelopeFormat::Snarkjs => Cow::Owned(SnarkjsEnvelope::from_bytes(envelope)?.to_envelope()?),
+    })
+}
+
+impl<'a> JsonProof<'a> {
+    /// Decode the JSON of an envelope, without parsing its fields.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, EnvelopeError> {
+        core::str::from_utf8(bytes).map_err(|_| EnvelopeError::InvalidUtf8)?;
+        host::parse_envelope(bytes).ok_or(EnvelopeError::MalformedJson)
+    }
+
+    /// Parse every field of the envelope. The claims of a private token are the ones it discloses,
+    /// the others being empty.
+    pub fn parse(&self) -> Result<ParsedProof, EnvelopeError> {
+        let instance = self.instance()?;
+        let chain_id = self.chain_id()?;
+
+        let (kid, claims) = match &self.token {
+            Some(token) => (token.kid.clone().into_owned(), token.claims()),
+            None => {
+                let (header, payload) = jwt_segments(&self.jwt_token).ok_or(EnvelopeError::MalformedJwt)?;
+                let kid = header.get("kid").and_then(|k| k.as_str()).ok_or(EnvelopeError::MalformedJwt)?.into();
+                (kid, serde_json::from_value(payload).map_err(|_| EnvelopeError::MalformedJwt)?)
+            }
+        };
+
+        Ok(ParsedProof { instance, kid, claims, chain_id })
+    }
+
+    /// Parse the proof, verifying key and public inputs on the envelope's curve.
+    pub fn instance(&self) -> Result<CurveInstance, EnvelopeError> {
+        Ok(match self.curve {
+            Curve::Bls12_381 => CurveInstance::Bls12_381(self.instance_on()?),
+            Curve::Bls12_377 => CurveInstance::Bls12_377(self.instance_on()?),
+        })
+    }
+
+    /// Parse the proof, verifying key and public inputs on the curve `P`.
+    pub fn instance_on<P: Bls12Config>(&self) -> Result<Groth16Instance<P>, EnvelopeError> {
+        let verifying_key = self.verifying_key::<P>()?;
+        let proof = self.proof::<P>()?;
+        let public_inputs = self.public_inputs::<P>()?;
+        Ok(Groth16Instance { proof, verifying_key, public_inputs })
+    }
+
+    /// The commitment the proof is bound to, parsing only the public inputs.
+    pub fn commitment(&self) -> Result<Commitment, EnvelopeError> {
+        Ok(match self.curve {
+            Curve::Bls12_381 => first_commitment(&self.public_inputs::<ark_bls12_381::Config>()?),
+            Curve::Bls12_377 => first_commitment(&self.public_inputs::<ark_bls12_377::Config>()?),
+        })
+    }
+
+    /// Decode the genesis hash of the chain the proof is meant for.
+    pub fn chain_id(&self) -> Result<[u8; 32], EnvelopeError> {
+        let hex_id = self.chain_id.strip_prefix("0x").unwrap_or(&self.chain_id);
+        let mut chain_id = [0u8; 32];
+        hex::decode_to_slice(hex_id, &mut chain_id).map_err(|_| EnvelopeError::InvalidChainId)?;
+        Ok(chain_id)
+    }
+
+    /// Decode the id of the claim predicate the proof attests, if any.
+    pub fn predicate_id(&self) -> Option<PredicateId> {
+        let mut id = PredicateId::default();
+        hex::decode_to_slice(self.predicate.as_deref()?, &mut id).ok()?;
+        Some(id)
+    }
+
+    /// The number of public inputs the proof is verified against.
+    pub fn public_input_count(&self) -> usize {
+        self.public_inputs.len().max(1)
+    }
+
+    /// Parse the embedded verifying key as a key on the curve `P`.
+    pub fn verifying_key<P: Bls12Config>(&self) -> Result<PreparedVerifyingKey<Bls12<P>>, EnvelopeError> {
+        parse_verifying_key(&self.verifying_key, self.encoding)
+    }
+
+    /// Parse the proof points as points of the curve `P`.
+    pub fn proof<P: Bls12Config>(&self) -> Result<Proof<Bls12<P>>, EnvelopeError> {
+        let a = parse_g1_point::<P>(&self.a, self.encoding)?;
+        let b = parse_g2_point::<P>(&self.b, self.encoding)?;
+        let c = parse_g1_point::<P>(&self.c, self.encoding)?;
+        Ok(Proof { a, b, c })
+    }
+
+    /// Parse all the public inputs of the proof, in order, as scalars of the curve `P`.
+    pub fn public_inputs<P: Bls12Config>(&self) -> Result<Vec<ScalarField<P>>, EnvelopeError> {
+        if self.public_inputs.is_empty() {
+            return Ok(vec![parse_public_input(&self.public_hash, self.encoding)?]);
+        }
+        self.public_inputs.iter().map(|input| parse_public_input(input, self.encoding)).collect()
+    }
+}
+
+impl DisclosedToken<'_> {
+    /// The algorithm the tokens circuits keep private are signed with.
+    pub const ALGORITHM: &'static str = "RS256";
+
+    /// The header and payload of the token, as far as it discloses them.
+    pub fn segments(&self) -> (serde_json::Value, serde_json::Value) {
+        (
+            serde_json::json!({ "alg": Self::ALGORITHM, "kid": self.kid }),
+            serde_json::json!({
+                "iss": self.iss, "aud": self.aud, "nonce": self.nonce, "iat": self.iat, "exp": self.exp
+            }),
+        )
+    }
+
+    /// The claims the token discloses, the others being empty.
+    pub fn claims(&self) -> Claims {
+        Claims {
+            iss: self.iss.clone().into_owned(),
+            azp: String::new(),
+            aud: self.aud.clone().into_owned(),
+            sub: String::new(),
+            nonce: self.nonce.clone().into_owned(),
+            nbf: 0,
+            iat: self.iat as i64,
+            exp: self.exp as i64,
+            jti: String::new(),
+            email: String::new(),
+        }
+    }
+}
+
+/// Verify a Groth16 proof. Verification errors (e.g. a public input count not matching the key)
+/// reject the proof.
+pub fn verify_groth16<P: Bls12Config>(
+    vk: &PreparedVerifyingKey<Bls12<P>>,
+    proof: &Proof<Bls12<P>>,
+    public_inputs: &[ScalarField<P>],
+) -> bool {
+    Groth16::<Bls12<P>>::verify_proof(vk, proof, public_inputs).unwrap_or(false)
+}
+
+/// Serialize a public input into the commitment it stands for.
+pub fn commitment_of<F: CanonicalSerialize>(input: &F) -> Commitment {
+    let mut commitment = Commitment::default();
+    if let Err(e) = input.serialize_compressed(&mut commitment[..]) {
+        log::error!("commitment serialization error: {:?}", e);
+    }
+    commitment
+}
+
+/// The commitment the first of `public_inputs` stands for.
+fn first_commitment<F: CanonicalSerialize>(public_inputs: &[F]) -> Commitment {
+    public_inputs.first().map(commitment_of).unwrap_or_default()
+}
+
+/// The size of the buffer field elements are decoded into, enough for the base field of any
+/// supported curve.
+const FIELD_ELEMENT_BUFFER: usize = 64;
+
+/// Decode a field element, base64 of `variant`, into `buffer`, returning its bytes. Elements larger
+/// than the buffer are rejected.
+fn decode_field_element<'b>(
+    data: &str,
+    variant: Base64Variant,
+    buffer: &'b mut [u8; FIELD_ELEMENT_BUFFER],
+) -> Result<&'b [u8], EnvelopeError> {
+    let len = encoding::decode_slice(data.as_bytes(), variant, buffer).map_err(EnvelopeError::InvalidBase64)?;
+    Ok(&buffer[..len])
+}
+
+/// Parse a base field element from its base64 representation.
+fn parse_fq<P: Bls12Config>(data: &str, variant: Base64Variant) -> Result<P::Fp, EnvelopeError> {
+    let mut buffer = [0u8; FIELD_ELEMENT_BUFFER];
+    let bytes = decode_field_element(data, variant, &mut buffer)?;
+    P::Fp::deserialize_compressed_unchecked(bytes).map_err(|_| EnvelopeError::InvalidPoint)
+}
+
+/// Parse a G1 point from its JSON representation.
+fn parse_g1_point<P: Bls12Config>(point: &G1Point, variant: Base64Variant) -> Result<G1Affine<P>, EnvelopeError> {
+    let point = G1Affine::<P>::new_unchecked(parse_fq::<P>(&point.x, variant)?, parse_fq::<P>(&point.y, variant)?);
+    let valid = point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve();
+    valid.then_some(point).ok_or(EnvelopeError::InvalidPoint)
+}
+
+/// Parse a G2 point from its JSON representation.
+fn parse_g2_point<P: Bls12Config>(point: &G2Point, variant: Base64Variant) -> Result<G2Affine<P>, EnvelopeError> {
+    let fq = |data: &str| parse_fq::<P>(data, variant);
+    let x_fq2 = Fp2::<P::Fp2Config>::new(fq(&point.x.c0)?, fq(&point.x.c1)?);
+    let y_fq2 = Fp2::<P::Fp2Config>::new(fq(&point.y.c0)?, fq(&point.y.c1)?);
+
+    let point = G2Affine::<P>::new_unchecked(x_fq2, y_fq2);
+    let valid = point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve();
+    valid.then_some(point).ok_or(EnvelopeError::InvalidPoint)
+}
+
+/// Parse a verifying key from its JSON representation.
+fn parse_verifying_key<P: Bls12Config>(
+    json_vk: &str,
+    variant: Base64Variant,
+) -> Result<PreparedVerifyingKey<Bls12<P>>, EnvelopeError> {
+    // Verifying keys grow with the number of public inputs, so are decoded onto the heap.
+    let vk_bytes = host::decode_base64(json_vk.as_bytes(), variant).ok_or(EnvelopeError::InvalidVerifyingKey)?;
+    PreparedVerifyingKey::<Bls12<P>>::deserialize_compressed_unchecked(&*vk_bytes).map_err(|e| {
+        log::error!("vk error prepare: {:?}", e);
+        EnvelopeError::InvalidVerifyingKey
+    })
+}
+
+/// Parse a public input from a base64-encoded string.
+fn parse_public_input<F: Field>(public_hash: &str, variant: Base64Variant) -> Result<F, EnvelopeError> {
+    let mut buffer = [0u8; FIELD_ELEMENT_BUFFER];
+    let bytes = decode_field_element(public_hash, variant, &mut buffer)?;
+    Ok(F::from_random_bytes(bytes).unwrap_or_default())
+}
+
+/// Decode a JWT segment (header or payload), base64 of `variant`, into a JSON value.
+fn decode_jwt_segment(segment: &str, variant: Base64Variant) -> Option<serde_json::Value> {
+    let bytes = host::decode_base64(segment.as_bytes(), variant)?;
+    from_slice(&bytes).ok()
+}
+
+/// Split a JWT and decode its header and payload.
+pub(crate) fn jwt_segments(token: &str) -> Option<(serde_json::Value, serde_json::Value)> {
+    jwt_segments_as(token, Base64Variant::URL_SAFE)
+}
+
+/// The issuer of a JWT, decoding its payload in any variant of base64, to learn the variant its
+/// provider's tokens are to be decoded with.
+pub(crate) fn jwt_issuer(token: &str) -> Option<String> {
+    let mut parts = token.split('.');
+    let (_header, payload, _signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let payload = decode_jwt_segment(payload, Base64Variant::ANY)?;
+    Some(payload.get("iss")?.as_str()?.into())
+}
+
+/// Split a JWT whose segments are base64 of `variant` and decode its header and payload.
+pub(crate) fn jwt_segments_as(token: &str, variant: Base64Variant) -> Option<(serde_json::Value, serde_json::Value)> {
+    let mut parts = token.split('.');
+    let (header, payload, _signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((decode_jwt_segment(header, variant)?, decode_jwt_segment(payload, variant)?))
+}
+
+/// A proof as snarkjs writes it in `proof.json`, with decimal coordinates.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnarkjsProof {
+    /// The `A` point, in projective coordinates.
+    pub pi_a: Vec<String>,
+    /// The `B` point, in projective coordinates.
+    pub pi_b: Vec<Vec<String>>,
+    /// The `C` point, in projective coordinates.
+    pub pi_c: Vec<String>,
+    /// The proving system, only `groth16` being supported.
+    pub protocol: String,
+    /// The curve, only `bls12381` being supported.
+    pub curve: String,
+}
+
+/// A snarkjs proof along with its public signals, as written in `public.json`, and the fields of
+/// the JSON envelope snarkjs knows nothing of.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnarkjsEnvelope<'a> {
+    /// The proof.
+    pub proof: SnarkjsProof,
+    /// The decimal public inputs, in the order of the circuit.
+    pub public_signals: Vec<String>,
+    /// The version of the circuit the proof is made with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_version: Option<u32>,
+    /// The base64-encoded compressed verifying key, as approved on-chain.
+    #[serde(borrow)]
+    pub verifying_key: Cow<'a, str>,
+    /// The ID token the proof was made for. Empty for proofs keeping it private.
+    #[serde(default, borrow)]
+    pub jwt_token: Cow<'a, str>,
+    /// The hex-encoded genesis hash of the chain the proof is meant for.
+    #[serde(borrow)]
+    pub chain_id: Cow<'a, str>,
+    /// The hex-encoded id of the claim predicate the proof attests, for predicate circuits.
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<Cow<'a, str>>,
+    /// What the proof discloses of its token, for circuits keeping the token private.
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub token: Option<DisclosedToken<'a>>,
+}
+
+impl<'a> SnarkjsEnvelope<'a> {
+    /// Decode the JSON of a snarkjs envelope.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, EnvelopeError> {
+        from_slice(bytes).map_err(|_| EnvelopeError::MalformedJson)
+    }
+
+    /// The JSON envelope carrying the same proof.
+    pub fn to_envelope(&self) -> Result<Vec<u8>, EnvelopeError> {
+        let proof = &self.proof;
+        if proof.protocol != "groth16" || proof.curve != "bls12381" {
+            return Err(EnvelopeError::MalformedJson);
+        }
+        type P = ark_bls12_381::Config;
+        let fq = |coordinate: Option<&String>| {
+            coordinate.and_then(|decimal| decimal_to_base64::<<P as Bls12Config>::Fp>(decimal))
+        };
+        let g1 = |point: &[String]| Some(G1Point { x: fq(point.first())?, y: fq(point.get(1))? });
+        let fq2 = |coordinate: Option<&Vec<String>>| {
+            coordinate.and_then(|c| Some(G2Coordinates { c0: fq(c.first())?, c1: fq(c.get(1))? }))
+        };
+        let b = fq2(proof.pi_b.first()).zip(fq2(proof.pi_b.get(1))).map(|(x, y)| G2Point { x, y });
+
+        let envelope = JsonProof {
+            curve: Curve::Bls12_381,
+            circuit_version: self.circuit_version,
+            a: g1(&proof.pi_a).ok_or(EnvelopeError::InvalidPoint)?,
+            b: b.ok_or(EnvelopeError::InvalidPoint)?,
+            c: g1(&proof.pi_c).ok_or(EnvelopeError::InvalidPoint)?,
+            public_hash: Cow::Borrowed(""),
+            verifying_key: Cow::Borrowed(&self.verifying_key),
+            jwt_token: Cow::Borrowed(&self.jwt_token),
+            chain_id: Cow::Borrowed(&self.chain_id),
+            predicate: self.predicate.as_deref().map(Cow::Borrowed),
+            token: self.token.clone(),
+            encoding: Base64Variant::STANDARD,
+            public_inputs: self
+                .public_signals
+                .iter()
+                .map(|signal| decimal_to_base64::<ScalarField<P>>(signal))
+                .collect::<Option<_>>()
+                .ok_or(EnvelopeError::MalformedJson)?,
+        };
+        serde_json::to_vec(&envelope).map_err(|_| EnvelopeError::MalformedJson)
+    }
+}
+
+/// The base64 encoding of the compressed field element written in decimal as `decimal`.
+fn decimal_to_base64<F: PrimeField>(decimal: &str) -> Option<Cow<'static, str>> {
+    let element = F::from_str(decimal).ok()?;
+    let mut bytes = Vec::new();
+    element.serialize_compressed(&mut bytes).ok()?;
+    Some(Cow::Owned(STANDARD.encode(bytes)))
+}
+
+impl ProofSubmission {
+    /// The JSON envelope carrying the same proof, as `store_zk_proof` takes it.
+    pub fn to_envelope(&self) -> Vec<u8> {
+        let base64 = |bytes: &[u8]| Cow::Owned(STANDARD.encode(bytes));
+        let g1 = |point: &G1Bytes| G1Point { x: base64(&point.x), y: base64(&point.y) };
+        let g2 = |point: &G2Bytes| G2Point {
+            x: G2Coordinates { c0: base64(&point.x.c0), c1: base64(&point.x.c1) },
+            y: G2Coordinates { c0: base64(&point.y.c0), c1: base64(&point.y.c1) },
+        };
+        let hex = |bytes: &[u8; 32]| {
+            let mut digits = [0u8; 64];
+            hex::encode_to_slice(bytes, &mut digits).expect("64 hex digits encode 32 bytes; qed");
+            Cow::Owned(String::from_utf8_lossy(&digits).into_owned())
+        };
+
+        let envelope = JsonProof {
+            curve: self.curve,
+            circuit_version: self.circuit_version,
+            a: g1(&self.a),
+            b: g2(&self.b),
+            c: g1(&self.c),
+            public_hash: Cow::Borrowed(""),
+            verifying_key: base64(&self.verifying_key),
+            jwt_token: Cow::Borrowed(&self.jwt_token),
+            chain_id: hex(&self.chain_id),
+            public_inputs: self.public_inputs.iter().map(|input| base64(input)).collect(),
+            predicate: self.predicate.as_ref().map(hex),
+            token: None,
+            encoding: Base64Variant::STANDARD,
+        };
+        serde_json::to_vec(&envelope).expect("envelopes serialize to JSON; qed")
+    }
+}
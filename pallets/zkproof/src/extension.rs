@@ -0,0 +1,297 @@
+//! Admission checks of proof submissions.
+//!
+//! Verifying a proof takes pairings, so a malformed proof is only rejected once it has been
+//! included, and paid for, in a block. [`CheckProofStructure`] runs the cheap checks of
+//! [`Pallet::check_structure`] when transactions enter the pool instead, so that such proofs never
+//! take up block space. It also caps the verifications a block includes, and their share of the
+//! block weight, to the limits set with `set_config`.
+//! Once a submission is dispatched, it records its failure in
+//! [`RecentFailures`](crate::RecentFailures) and [`FailureStats`](crate::FailureStats), and counts
+//! verification failures towards locking out its submitter and identity after
+//! `Config::MaxFailedAttempts` of them.
+//!
+//! [`CheckSpendingLimit`] enforces the spending limits of device keys on the transactions they
+//! sign, freezes the accounts of locked identities, reserves the calls gated by
+//! `Config::AttestationGate` to accounts holding a valid attestation, and the calls of
+//! `Config::StepUpCalls` to identities which logged in recently.
+
+use crate::{Admission, Call, Config, Error, Nullifier, Pallet, Spent, SubmissionContext, ZkLoginAuth};
+use alloc::borrow::Cow;
+use codec::{Decode, Encode};
+use core::{fmt, marker::PhantomData};
+use frame_support::traits::IsSubType;
+use frame_system::pallet_prelude::BlockNumberFor;
+use scale_info::TypeInfo;
+use sp_runtime::{
+    traits::{DispatchInfoOf, PostDispatchInfoOf, SignedExtension},
+    transaction_validity::{InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction},
+    DispatchError, DispatchResult, ModuleError,
+};
+
+/// Rejects proof submissions that are oversized, malformed, or whose token is not from a trusted
+/// issuer signed with a known key, before they enter the transaction pool.
+///
+/// Rejections are `InvalidTransaction::Custom`, carrying the index of the pallet error the call
+/// would have failed with. Submissions beyond `max_verifications_per_block`, or whose weight does not
+/// fit the share of the block left to proof calls, are rejected from the block being built with
+/// `InvalidTransaction::ExhaustsResources`, and stay in the pool for the next blocks. Submissions
+/// of a submitter, or about an identity, locked out after failing too often are rejected with
+/// `TooManyFailedAttempts`.
+///
+/// Logins are prioritized by the freshness of their token, and provide a tag derived from the
+/// nullifier of their identity, so that the pool only keeps one of competing logins of the same
+/// identity.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckProofStructure<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckProofStructure<T> {
+    /// Create a new extension.
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Config + Send + Sync> Default for CheckProofStructure<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckProofStructure<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CheckProofStructure")
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckProofStructure<T>
+where
+    T::RuntimeCall: IsSubType<Call<T>>,
+{
+    const IDENTIFIER: &'static str = "CheckProofStructure";
+    type AccountId = T::AccountId;
+    type Call = T::RuntimeCall;
+    type AdditionalSigned = ();
+    /// The submitter, context and identity nullifier of the proof the call submits, if any.
+    type Pre = Option<(T::AccountId, SubmissionContext, Option<Nullifier>)>;
+
+    fn additional_signed(&self) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> TransactionValidity {
+        let Some(proof) = submitted_proof::<T>(call) else {
+            return Ok(ValidTransaction::default());
+        };
+        let admission = check::<T>(&proof)?;
+        Pallet::<T>::ensure_not_locked_out(who, admission.nullifier.as_ref()).map_err(invalid)?;
+        let mut valid = ValidTransaction::with_tag_prefix("DotLogin").priority(admission.priority);
+        if let (true, Some(nullifier)) = (is_login::<T>(call), admission.nullifier) {
+            valid = valid.and_provides(nullifier);
+        }
+        valid.build()
+    }
+
+    fn pre_dispatch(
+        self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        let Some(proof) = submitted_proof::<T>(call) else {
+            return Ok(None);
+        };
+        let admission = check::<T>(&proof)?;
+        Pallet::<T>::ensure_not_locked_out(who, admission.nullifier.as_ref()).map_err(invalid)?;
+        if !Pallet::<T>::reserve_verification() || !Pallet::<T>::reserve_proof_weight(info.weight) {
+            return Err(InvalidTransaction::ExhaustsResources.into());
+        }
+        Ok(Some((who.clone(), admission.context, admission.nullifier)))
+    }
+
+    fn post_dispatch(
+        pre: Option<Self::Pre>,
+        _info: &DispatchInfoOf<Self::Call>,
+        _post_info: &PostDispatchInfoOf<Self::Call>,
+        _len: usize,
+        result: &DispatchResult,
+    ) -> Result<(), TransactionValidityError> {
+        match (pre, result) {
+            (Some(Some((submitter, context, nullifier))), Err(error)) => {
+                Pallet::<T>::note_failure(submitter, context, nullifier, error)
+            }
+            (Some(Some((submitter, _, nullifier))), Ok(())) => Pallet::<T>::note_success(&submitter, nullifier),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Rejects the transactions of device keys that their spending limit does not allow: calls outside
+/// of its allowlist, and calls that would take the key over its spending for the period, as
+/// measured by `Config::CallSpending`. Accounts whose latest proof attests to an identity locked
+/// with `emergency_lock` are frozen: they may only lock or unlock the identity. Calls are rejected
+/// if an account `Config::AttestationGate` requires to be verified holds no valid attestation, or
+/// if `Config::StepUpCalls` requires a fresher proof of the identity of the signer.
+///
+/// Rejections are `InvalidTransaction::Custom`, carrying the index of the pallet error, as with
+/// [`CheckProofStructure`]. The spending is recorded when the transaction is dispatched, whether
+/// the call succeeds or not.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckSpendingLimit<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckSpendingLimit<T> {
+    /// Create a new extension.
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Config + Send + Sync> Default for CheckSpendingLimit<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckSpendingLimit<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CheckSpendingLimit")
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckSpendingLimit<T>
+where
+    T::RuntimeCall: IsSubType<Call<T>>,
+{
+    const IDENTIFIER: &'static str = "CheckSpendingLimit";
+    type AccountId = T::AccountId;
+    type Call = T::RuntimeCall;
+    type AdditionalSigned = ();
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> TransactionValidity {
+        check_spending::<T>(who, call)?;
+        Ok(ValidTransaction::default())
+    }
+
+    fn pre_dispatch(
+        self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        if let Some(spent) = check_spending::<T>(who, call)? {
+            Spent::<T>::insert(who, spent);
+        }
+        Ok(())
+    }
+}
+
+/// Check `call` against the block and the lock of the identity of `who`, the attestations and the freshness of
+/// proof it requires and the spending limit of `who`. Returns what `who` will have spent in the period, if it has a limit.
+fn check_spending<T: Config>(
+    who: &T::AccountId,
+    call: &T::RuntimeCall,
+) -> Result<Option<(BlockNumberFor<T>, u128)>, TransactionValidityError>
+where
+    T::RuntimeCall: IsSubType<Call<T>>,
+{
+    if Pallet::<T>::is_blocked_account(who) {
+        return Err(invalid(Error::<T>::IdentityBlocked));
+    }
+    let locking = matches!(call.is_sub_type(), Some(Call::emergency_lock { .. } | Call::unlock_identity { .. }));
+    if !locking && Pallet::<T>::is_frozen(who) {
+        return Err(invalid(Error::<T>::AccountFrozen));
+    }
+    for account in T::AttestationGate::gated_accounts(who, call) {
+        Pallet::<T>::valid_attestation(&account).map_err(invalid)?;
+    }
+    if let Some(max_age) = T::StepUpCalls::max_proof_age(call) {
+        if !Pallet::<T>::verified_within(who, max_age) {
+            return Err(invalid(Error::<T>::StepUpRequired));
+        }
+    }
+    Pallet::<T>::check_spending(who, call).map_err(invalid)
+}
+
+/// The JSON envelope of the proof `call` submits, if any.
+fn submitted_proof<T: Config>(call: &T::RuntimeCall) -> Option<Cow<'_, [u8]>>
+where
+    T::RuntimeCall: IsSubType<Call<T>>,
+{
+    match call.is_sub_type()? {
+        Call::store_zk_proof { json, .. } | Call::reveal_login { json } => Some(Cow::Borrowed(&json[..])),
+        Call::erase_identity_data { proof }
+        | Call::register_key { proof, .. }
+        | Call::emergency_lock { proof }
+        | Call::unlock_identity { proof }
+        | Call::renew_attestation { proof }
+        | Call::zklogin_execute { auth: ZkLoginAuth::Proof(proof), .. } => Some(Cow::Borrowed(&proof[..])),
+        Call::submit_proof { submission } => Some(Cow::Owned(submission.to_envelope())),
+        _ => None,
+    }
+}
+
+/// Whether `call` logs an identity in, as opposed to erasing it.
+fn is_login<T: Config>(call: &T::RuntimeCall) -> bool
+where
+    T::RuntimeCall: IsSubType<Call<T>>,
+{
+    matches!(
+        call.is_sub_type(),
+        Some(
+            Call::store_zk_proof { .. }
+                | Call::reveal_login { .. }
+                | Call::submit_proof { .. }
+                | Call::register_key { .. }
+        )
+    )
+}
+
+/// Run the admission checks of `proof`.
+fn check<T: Config>(proof: &[u8]) -> Result<Admission, TransactionValidityError> {
+    Pallet::<T>::admit(proof).map_err(|e| {
+        log::debug!("Rejected malformed proof submission: {:?}", e);
+        invalid(e)
+    })
+}
+
+/// The transaction validity error reporting `error`.
+fn invalid<T: Config>(error: Error<T>) -> TransactionValidityError {
+    let index = match DispatchError::from(error) {
+        DispatchError::Module(ModuleError { error, .. }) => error[0],
+        _ => 0,
+    };
+    InvalidTransaction::Custom(index).into()
+}
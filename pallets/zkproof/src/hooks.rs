@@ -0,0 +1,168 @@
+//! Hooks through which runtimes react to verified proofs, and other pallets read them.
+//!
+//! Runtimes plug side effects in, such as minting a badge or crediting a referral, by setting
+//! `Config::OnProofVerified`. Hooks are combined in tuples, each of them being called in order.
+//! Pallets building on logins, such as sessions, read the attestations through
+//! [`AttestationInspector`], which the pallet implements. Runtimes tell how much value their calls
+//! move through [`CallSpending`], against which the spending limits of device keys are enforced.
+//! Pallets end what they granted an identity once it is locked through [`OnIdentityLocked`].
+//! Runtimes reserve calls to verified users, such as minting an asset, through
+//! [`AttestationGate`], and other pallets require verified origins through [`EnsureVerified`].
+//! Runtimes require a fresh proof before high-value calls, such as sweeping an account, through
+//! [`StepUpCalls`].
+
+use crate::{Attestation, Config, Error, Nullifier, Pallet};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use frame_support::traits::{ConstU64, Contains, EnsureOrigin, Get, UnixTime};
+use frame_system::RawOrigin;
+
+/// Called once a proof is verified and stored.
+pub trait OnProofVerified<AccountId, Commitment> {
+    /// `who` stored a proof bound to `commitment`, made for a token whose normalized claims hash to
+    /// `claims_digest` (see `VerifiedClaims::digest`). Its weight must fit in the weight of the
+    /// proof call.
+    fn on_proof_verified(who: &AccountId, commitment: &Commitment, claims_digest: &[u8; 32]);
+}
+
+impl<AccountId, Commitment> OnProofVerified<AccountId, Commitment> for () {
+    fn on_proof_verified(_who: &AccountId, _commitment: &Commitment, _claims_digest: &[u8; 32]) {}
+}
+
+macro_rules! impl_on_proof_verified_for_tuples {
+    ($($hook:ident),+) => {
+        impl<AccountId, Commitment, $($hook: OnProofVerified<AccountId, Commitment>),+>
+            OnProofVerified<AccountId, Commitment> for ($($hook,)+)
+        {
+            fn on_proof_verified(who: &AccountId, commitment: &Commitment, claims_digest: &[u8; 32]) {
+                $($hook::on_proof_verified(who, commitment, claims_digest);)+
+            }
+        }
+    };
+}
+
+impl_on_proof_verified_for_tuples!(A);
+impl_on_proof_verified_for_tuples!(A, B);
+impl_on_proof_verified_for_tuples!(A, B, C);
+impl_on_proof_verified_for_tuples!(A, B, C, D);
+
+/// Called once an identity is locked with `emergency_lock`.
+pub trait OnIdentityLocked {
+    /// The identity with `nullifier` was locked. Its weight must fit in the weight of the lock
+    /// call.
+    fn on_identity_locked(nullifier: &Nullifier);
+}
+
+impl OnIdentityLocked for () {
+    fn on_identity_locked(_nullifier: &Nullifier) {}
+}
+
+/// How much value calls spend, for the spending limits of device keys.
+pub trait CallSpending<Call> {
+    /// The amount `call` moves out of the account dispatching it, in the smallest unit of the
+    /// currency.
+    fn spending(call: &Call) -> u128;
+
+    /// The call `call` dispatches for the account dispatching it, if it wraps one, so that the
+    /// call allowlists of device keys hold for wrapped calls too.
+    fn wrapped(_call: &Call) -> Option<&Call> {
+        None
+    }
+}
+
+impl<Call> CallSpending<Call> for () {
+    fn spending(_call: &Call) -> u128 {
+        0
+    }
+}
+
+/// The accounts calls require to hold a valid attestation, for "KYC-lite" operations reserved to
+/// verified users.
+pub trait AttestationGate<AccountId, Call> {
+    /// The accounts that must hold a valid attestation for `who` to dispatch `call`: `who` itself,
+    /// or the recipient of what `call` moves, or none.
+    fn gated_accounts(who: &AccountId, call: &Call) -> Vec<AccountId>;
+}
+
+impl<AccountId, Call> AttestationGate<AccountId, Call> for () {
+    fn gated_accounts(_who: &AccountId, _call: &Call) -> Vec<AccountId> {
+        Vec::new()
+    }
+}
+
+/// The calls requiring the identity of their signer to have logged in recently, as web apps prompt
+/// for re-authentication before high-value actions, even if a session or a device key is active.
+pub trait StepUpCalls<Call, BlockNumber> {
+    /// How many blocks ago, at most, the identity of the signer of `call` must have stored a
+    /// proof, if `call` requires a fresh one.
+    fn max_proof_age(call: &Call) -> Option<BlockNumber>;
+}
+
+impl<Call, BlockNumber> StepUpCalls<Call, BlockNumber> for () {
+    fn max_proof_age(_call: &Call) -> Option<BlockNumber> {
+        None
+    }
+}
+
+/// Read access to the attestations of the stored proofs.
+pub trait AttestationInspector<AccountId> {
+    /// The attestation of the latest proof stored by `who`, while it is valid.
+    fn attestation_of(who: &AccountId) -> Option<Attestation>;
+
+    /// Whether `who` holds no valid attestation because it expired, rather than because it
+    /// never held one, for pallets to point their users at renewing it.
+    fn attestation_expired(_who: &AccountId) -> bool {
+        false
+    }
+}
+
+impl<T: Config> AttestationInspector<T::AccountId> for Pallet<T> {
+    fn attestation_of(who: &T::AccountId) -> Option<Attestation> {
+        Pallet::<T>::attestation_of(who)
+    }
+
+    fn attestation_expired(who: &T::AccountId) -> bool {
+        matches!(Pallet::<T>::valid_attestation(who), Err(Error::<T>::AttestationExpired))
+    }
+}
+
+/// Lets through the accounts holding an attestation valid for at least `MinValidity` more seconds,
+/// as an origin and as a filter. Pallets reserving calls to verified users, such as contributing
+/// to a crowdloan or borrowing from a lending market, plug it in as their origin, with
+/// `MinValidity` keeping out attestations which expire before the position settles.
+pub struct EnsureVerified<T, MinValidity = ConstU64<0>>(PhantomData<(T, MinValidity)>);
+
+impl<T: Config, MinValidity: Get<u64>> EnsureVerified<T, MinValidity> {
+    /// Whether `who` holds an attestation valid for at least `MinValidity` more seconds.
+    pub fn is_verified(who: &T::AccountId) -> bool {
+        Pallet::<T>::attestation_of(who).map_or(false, |attestation| {
+            T::UnixTime::now().as_secs().saturating_add(MinValidity::get()) <= attestation.expires_at
+        })
+    }
+}
+
+impl<T: Config, MinValidity: Get<u64>, O> EnsureOrigin<O> for EnsureVerified<T, MinValidity>
+where
+    O: Into<Result<RawOrigin<T::AccountId>, O>> + From<RawOrigin<T::AccountId>>,
+{
+    type Success = T::AccountId;
+
+    fn try_origin(o: O) -> Result<T::AccountId, O> {
+        o.into().and_then(|o| match o {
+            RawOrigin::Signed(who) if Self::is_verified(&who) => Ok(who),
+            o => Err(O::from(o)),
+        })
+    }
+
+    /// No origin is verified without a stored proof.
+    #[cfg(feature = "runtime-benchmarks")]
+    fn try_successful_origin() -> Result<O, ()> {
+        Err(())
+    }
+}
+
+impl<T: Config, MinValidity: Get<u64>> Contains<T::AccountId> for EnsureVerified<T, MinValidity> {
+    fn contains(who: &T::AccountId) -> bool {
+        Self::is_verified(who)
+    }
+}
@@ -0,0 +1,238 @@
+//! Host functions for the decoding and verification done on every proof.
+//!
+//! Decoding base64, parsing JSON and checking pairings dominate the execution of proof calls in
+//! Wasm. With the `host-functions` feature, the pallet runs them natively through [`dot_login`]
+//! instead; nodes must then register [`HostFunctions`] with their executor, next to
+//! `sp_io::SubstrateHostFunctions`. Without it, as in browsers and parachains, whose blocks
+//! relay chain validators execute with the standard host functions only, the same code runs in
+//! Wasm.
+//!
+//! Natively verified proofs are remembered by the node, see [`cache`], so that a proof verified
+//! when its transaction entered the pool is not verified again in the block storing it.
+
+#[cfg(feature = "std")]
+use crate::envelope::{verify_groth16, Curve, ScalarField};
+use crate::{
+    encoding::Base64Variant,
+    envelope::{CurveInstance, JsonProof},
+};
+use alloc::vec::Vec;
+use sp_runtime_interface::runtime_interface;
+
+/// Natively implemented decoding and verification.
+#[runtime_interface]
+pub trait DotLogin {
+    /// Decode standard, padded base64. Returns `None` if `input` is malformed.
+    fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+        decode::base64(input)
+    }
+
+    /// Decode URL-safe base64, padded or not. Returns `None` if `input` is malformed.
+    fn base64url_decode(input: &[u8]) -> Option<Vec<u8>> {
+        decode::base64url(input)
+    }
+
+    /// Parse the JSON of a proof envelope, returning the SCALE-encoded [`JsonProof`] as it was
+    /// before envelopes could disclose their token instead of carrying it. Returns `None` if
+    /// `json` is not a valid envelope, or discloses its token.
+    fn parse_envelope(json: &[u8]) -> Option<Vec<u8>> {
+        use codec::Encode;
+        decode::envelope(json).filter(|envelope| envelope.token.is_none()).map(|envelope| {
+            let mut encoded = envelope.encode();
+            // The disclosed token is the last field, encoded as a single `None` byte.
+            encoded.pop();
+            encoded
+        })
+    }
+
+    /// Parse the JSON of a proof envelope, returning the SCALE-encoded [`JsonProof`]. Returns
+    /// `None` if `json` is not a valid envelope.
+    #[version(2)]
+    fn parse_envelope(json: &[u8]) -> Option<Vec<u8>> {
+        use codec::Encode;
+        decode::envelope(json).map(|envelope| envelope.encode())
+    }
+
+    /// Check a Groth16 proof on the curve of SCALE index `curve`, given its compressed verifying
+    /// key, points and public inputs. Returns `false` if the proof is invalid or any of them
+    /// malformed. Proofs verified before by the node are not verified again, see [`cache`].
+    fn verify_groth16(curve: u8, verifying_key: &[u8], proof: &[u8], public_inputs: &[u8]) -> bool {
+        let key = cache::key(curve, verifying_key, proof, public_inputs);
+        if cache::contains(&key) {
+            return true;
+        }
+        let valid = match codec::Decode::decode(&mut &[curve][..]) {
+            Ok(Curve::Bls12_381) => verify_compressed::<ark_bls12_381::Config>(verifying_key, proof, public_inputs),
+            Ok(Curve::Bls12_377) => verify_compressed::<ark_bls12_377::Config>(verifying_key, proof, public_inputs),
+            Err(_) => false,
+        };
+        if valid {
+            cache::insert(key);
+        }
+        valid
+    }
+}
+
+/// Decode standard, padded base64. Returns `None` if `input` is malformed.
+pub fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+    #[cfg(feature = "host-functions")]
+    return dot_login::base64_decode(input);
+    #[cfg(not(feature = "host-functions"))]
+    return decode::base64(input);
+}
+
+/// Decode URL-safe base64, padded or not. Returns `None` if `input` is malformed.
+pub fn base64url_decode(input: &[u8]) -> Option<Vec<u8>> {
+    #[cfg(feature = "host-functions")]
+    return dot_login::base64url_decode(input);
+    #[cfg(not(feature = "host-functions"))]
+    return decode::base64url(input);
+}
+
+/// Decode base64 of `variant`. Standard and URL-safe base64 are decoded natively, other variants
+/// in the runtime. Returns `None` if `input` is malformed.
+pub fn decode_base64(input: &[u8], variant: Base64Variant) -> Option<Vec<u8>> {
+    match variant {
+        Base64Variant::STANDARD => base64_decode(input),
+        Base64Variant::URL_SAFE => base64url_decode(input),
+        variant => crate::encoding::decode(input, variant).ok(),
+    }
+}
+
+/// Check the Groth16 proof of `instance` against its verifying key. Natively, the pairings are
+/// skipped for the proofs the node verified before.
+pub fn verify(instance: &CurveInstance) -> bool {
+    #[cfg(feature = "host-functions")]
+    return instance.to_compressed().is_some_and(|(verifying_key, proof, public_inputs)| {
+        dot_login::verify_groth16(instance.curve() as u8, &verifying_key, &proof, &public_inputs)
+    });
+    #[cfg(not(feature = "host-functions"))]
+    return instance.verify();
+}
+
+/// Parse the JSON of a proof envelope. Returns `None` if `json` is not a valid envelope.
+pub fn parse_envelope(json: &[u8]) -> Option<JsonProof<'_>> {
+    #[cfg(feature = "host-functions")]
+    return dot_login::parse_envelope(json).and_then(|encoded| codec::Decode::decode(&mut &encoded[..]).ok());
+    #[cfg(not(feature = "host-functions"))]
+    return decode::envelope(json);
+}
+
+/// The decoding itself, wherever it runs.
+#[cfg_attr(all(feature = "host-functions", not(feature = "std")), allow(dead_code))]
+mod decode {
+    use super::*;
+    use crate::encoding;
+
+    pub fn base64(input: &[u8]) -> Option<Vec<u8>> {
+        encoding::decode(input, Base64Variant::STANDARD).ok()
+    }
+
+    pub fn base64url(input: &[u8]) -> Option<Vec<u8>> {
+        encoding::decode(input, Base64Variant::URL_SAFE).ok()
+    }
+
+    pub fn envelope(json: &[u8]) -> Option<JsonProof<'_>> {
+        serde_json::from_slice(json).ok()
+    }
+}
+
+/// Check a Groth16 proof on the curve `P` from its compressed verifying key, points and public
+/// inputs.
+#[cfg(feature = "std")]
+fn verify_compressed<P: ark_ec::bls12::Bls12Config>(verifying_key: &[u8], proof: &[u8], public_inputs: &[u8]) -> bool {
+    use ark_ec::bls12::Bls12;
+    use ark_groth16::{prepare_verifying_key, Proof, VerifyingKey};
+    use ark_serialize::CanonicalDeserialize;
+
+    let verifying_key = VerifyingKey::<Bls12<P>>::deserialize_compressed(verifying_key);
+    let proof = Proof::<Bls12<P>>::deserialize_compressed(proof);
+    let public_inputs = Vec::<ScalarField<P>>::deserialize_compressed(public_inputs);
+    match (verifying_key, proof, public_inputs) {
+        (Ok(verifying_key), Ok(proof), Ok(public_inputs)) => {
+            verify_groth16(&prepare_verifying_key(&verifying_key), &proof, &public_inputs)
+        }
+        _ => false,
+    }
+}
+
+/// The proofs the node verified natively.
+///
+/// The pool validating a transaction, the author building a block and the node importing it all
+/// call the runtime in the node's process, and share this cache: a proof is verified once, and
+/// found here the next times. Proofs are keyed by their hash along with the verifying key and
+/// public inputs they were checked against, so that a proof found answers as the pairings would.
+/// Only valid proofs are remembered, the oldest being forgotten first.
+///
+/// The cache is disabled until the node [`enable`]s it, so that benchmarks measure the pairings.
+#[cfg(feature = "std")]
+pub mod cache {
+    use std::{
+        collections::{BTreeSet, VecDeque},
+        sync::{Mutex, MutexGuard, PoisonError},
+    };
+
+    /// The key of a verified proof.
+    pub type Key = [u8; 32];
+
+    /// The proofs remembered, and how many may be.
+    struct Verified {
+        /// The most proofs remembered at once.
+        capacity: usize,
+        /// The keys of the proofs remembered.
+        keys: BTreeSet<Key>,
+        /// The same keys, oldest first.
+        order: VecDeque<Key>,
+    }
+
+    impl Verified {
+        /// Forget the oldest proofs beyond the capacity.
+        fn shrink(&mut self) {
+            while self.order.len() > self.capacity {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.keys.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    static VERIFIED: Mutex<Verified> =
+        Mutex::new(Verified { capacity: 0, keys: BTreeSet::new(), order: VecDeque::new() });
+
+    fn verified() -> MutexGuard<'static, Verified> {
+        VERIFIED.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Remember up to `capacity` verified proofs; 0 disables the cache, forgetting them all.
+    pub fn enable(capacity: usize) {
+        let mut verified = verified();
+        verified.capacity = capacity;
+        verified.shrink();
+    }
+
+    /// The key of the proof `proof` on the curve of SCALE index `curve`, checked against
+    /// `verifying_key` and `public_inputs`.
+    pub fn key(curve: u8, verifying_key: &[u8], proof: &[u8], public_inputs: &[u8]) -> Key {
+        use codec::Encode;
+        sp_core::hashing::blake2_256(&(curve, verifying_key, proof, public_inputs).encode())
+    }
+
+    /// Whether the proof of `key` was verified.
+    pub fn contains(key: &Key) -> bool {
+        verified().keys.contains(key)
+    }
+
+    /// Remember that the proof of `key` was verified.
+    pub fn insert(key: Key) {
+        let mut verified = verified();
+        if verified.capacity > 0 && verified.keys.insert(key) {
+            verified.order.push_back(key);
+            verified.shrink();
+        }
+    }
+
+    /// The number of proofs remembered.
+    pub fn len() -> usize {
+        verified().order.len()
+    }
+}
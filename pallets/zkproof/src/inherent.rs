@@ -0,0 +1,67 @@
+//! JWKS updates supplied by block authors as inherents.
+//!
+//! As an alternative to the oracles' offchain workers, nodes may fetch the providers' JWKS
+//! themselves and supply them as inherent data of the blocks they author, through
+//! [`InherentDataProvider`]. The pallet then applies the keysets that changed in a
+//! `set_authored_jwks` inherent, provided the runtime trusts block authors with the keys of the
+//! providers (`Config::AuthoredJwks`). Importing nodes cannot fetch the same keys at the same time,
+//! so they do not check them against their own.
+
+use crate::JwksUpdate;
+use alloc::vec::Vec;
+use codec::{Decode, Encode};
+use sp_inherents::{InherentIdentifier, IsFatalError};
+
+/// The identifier of the JWKS inherent data.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"dljwks00";
+
+/// The JWKS inherent data: the keys of the providers, as last fetched by the block author.
+pub type InherentType = Vec<JwksUpdate>;
+
+/// Why a block's JWKS inherent was rejected.
+#[derive(Encode, Decode, sp_runtime::RuntimeDebug)]
+pub enum InherentError {
+    /// The runtime does not trust block authors with the keys of the providers.
+    AuthoredJwksDisabled,
+}
+
+impl IsFatalError for InherentError {
+    fn is_fatal_error(&self) -> bool {
+        true
+    }
+}
+
+/// Supplies the keys fetched by the node to the blocks it authors.
+#[cfg(feature = "std")]
+pub struct InherentDataProvider(InherentType);
+
+#[cfg(feature = "std")]
+impl InherentDataProvider {
+    /// Supply `updates`, which need not be limited to the keysets that changed.
+    pub fn new(updates: InherentType) -> Self {
+        Self(updates)
+    }
+}
+
+#[cfg(feature = "std")]
+#[async_trait::async_trait]
+impl sp_inherents::InherentDataProvider for InherentDataProvider {
+    async fn provide_inherent_data(
+        &self,
+        inherent_data: &mut sp_inherents::InherentData,
+    ) -> Result<(), sp_inherents::Error> {
+        inherent_data.put_data(INHERENT_IDENTIFIER, &self.0)
+    }
+
+    async fn try_handle_error(
+        &self,
+        identifier: &InherentIdentifier,
+        mut error: &[u8],
+    ) -> Option<Result<(), sp_inherents::Error>> {
+        if *identifier != INHERENT_IDENTIFIER {
+            return None;
+        }
+        let error = InherentError::decode(&mut error).ok()?;
+        Some(Err(sp_inherents::Error::Application(Box::from(format!("{:?}", error)))))
+    }
+}
@@ -5,16 +5,113 @@ use frame_support::BoundedVec;
 pub use pallet::*;
 pub use scale_info::prelude::vec::Vec;
 
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod prover;
+
+#[cfg(test)]
+mod test;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+pub mod adapters;
+pub mod circuit;
+pub mod encoding;
+pub mod envelope;
+pub mod extension;
+pub mod hooks;
+pub mod host;
+pub mod inherent;
+pub mod migrations;
+mod offchain;
+pub mod poseidon;
+pub mod rsa;
+pub mod runtime_api;
+pub mod types;
+pub mod weights;
+pub use adapters::{claim_hash, ProviderAdapter, VerifiedClaims};
+pub use encoding::{Base64Alphabet, Base64Error, Base64Padding, Base64Variant};
+#[cfg(feature = "std")]
+pub use envelope::{decode_envelope, validate_envelope, EnvelopeError, EnvelopeFormat, ParsedProof};
+pub use extension::{CheckProofStructure, CheckSpendingLimit};
+pub use hooks::{
+    AttestationGate, AttestationInspector, CallSpending, EnsureVerified, OnIdentityLocked, OnProofVerified, StepUpCalls,
+};
+pub use offchain::{cache_expiry, parse_jwks};
+pub use types::*;
+pub use weights::WeightInfo;
+
+use sp_core::crypto::KeyTypeId;
+
+/// Key type of the oracle keys used by the offchain worker to sign JWKS submissions.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"zkjw");
+
+/// The engine id of the header digest carrying the identity roots, so light clients and bridges
+/// following the headers learn them without reading the state.
+pub const IDENTITY_ROOT_ENGINE_ID: sp_runtime::ConsensusEngineId = *b"DLIR";
+
+/// Application crypto of the oracle keys.
+///
+/// Oracle nodes insert an sr25519 key of this type into their keystore; the offchain worker
+/// signs `submit_jwks` with the account derived from it.
+pub mod crypto {
+    use super::KEY_TYPE;
+    use sp_core::sr25519::Signature as Sr25519Signature;
+    use sp_runtime::{
+        app_crypto::{app_crypto, sr25519},
+        traits::Verify,
+        MultiSignature, MultiSigner,
+    };
+    app_crypto!(sr25519, KEY_TYPE);
+
+    /// The identifier used by the offchain worker to look up oracle keys.
+    pub struct OracleAuthId;
+
+    impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for OracleAuthId {
+        type RuntimeAppPublic = Public;
+        type GenericSignature = sp_core::sr25519::Signature;
+        type GenericPublic = sp_core::sr25519::Public;
+    }
+
+    impl frame_system::offchain::AppCrypto<<Sr25519Signature as Verify>::Signer, Sr25519Signature>
+        for OracleAuthId
+    {
+        type RuntimeAppPublic = Public;
+        type GenericSignature = sp_core::sr25519::Signature;
+        type GenericPublic = sp_core::sr25519::Public;
+    }
+}
+
 // All pallet logic is defined in its own module and must be annotated by the `pallet` attribute.
 #[frame_support::pallet(dev_mode)]
 pub mod pallet {
     // Import various useful types required by all FRAME pallets.
     use super::*;
-    use frame_support::pallet_prelude::*;
-    use frame_system::pallet_prelude::*;
+    use alloc::boxed::Box;
+    use frame_support::{
+        dispatch::{extract_actual_weight, GetDispatchInfo, PostDispatchInfo},
+        pallet_prelude::*,
+        sp_runtime::{
+            traits::{Dispatchable, Keccak256, One, SaturatedConversion, Saturating, TrailingZeroInput, Zero},
+            DigestItem, ModuleError, Perbill,
+        },
+        traits::{Contains, OriginTrait, UnixTime},
+    };
+    use frame_system::{
+        offchain::{AppCrypto, CreateSignedTransaction},
+        pallet_prelude::*,
+    };
+    use sp_core::H256;
+
+    /// The in-code storage version.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
 
     // The main struct for the pallet.
     #[pallet::pallet]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     /// The pallet's configuration trait.
@@ -23,29 +120,609 @@ pub mod pallet {
     /// These types are defined generically and made concrete when the pallet is declared in the
     /// `runtime/src/lib.rs` file of your chain.
     #[pallet::config]
-    pub trait Config: frame_system::Config {
+    pub trait Config: CreateSignedTransaction<Call<Self>> + frame_system::Config {
         /// The overarching event type.
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+        /// The origin allowed to manage the pallet (oracle set, keys).
+        type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+        /// The origin allowed to make the changes the security of logins rests on: trusting a new
+        /// provider or provider key, and approving the verifying key of a circuit. Runtimes set it
+        /// to a stricter body than `AdminOrigin`, which keeps routine operations and removals.
+        type OnboardingOrigin: EnsureOrigin<Self::RuntimeOrigin>;
         /// The maximum length of the JSON data.
         type MaxJsonLength: Get<u32>;
+        /// The maximum length of the JWT embedded in the proof JSON.
+        type MaxJwtLength: Get<u32>;
+        /// The maximum number of public inputs a proof may carry.
+        type MaxPublicInputs: Get<u32>;
+        /// The maximum number of signing keys kept per provider.
+        type MaxJwksKeys: Get<u32>;
+        /// The maximum number of oracles that can back a single keyset.
+        type MaxOracles: Get<u32>;
+        /// The number of matching oracle submissions required before a keyset takes effect.
+        type JwksQuorum: Get<u32>;
+        /// The number of blocks a proposed keyset may wait for quorum before it is discarded.
+        type JwksSubmissionWindow: Get<BlockNumberFor<Self>>;
+        /// Source of the current wall-clock time.
+        type UnixTime: UnixTime;
+        /// How long (in seconds) a keyset is still accepted after its advertised expiry.
+        type JwksGracePeriod: Get<u64>;
+        /// The offchain worker refreshes the providers' JWKS every this many blocks.
+        type JwksFetchInterval: Get<BlockNumberFor<Self>>;
+        /// The oracle key used by the offchain worker to sign JWKS submissions.
+        type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+        /// The issuers whose tokens are accepted; any other `iss` claim is rejected.
+        type AllowedIssuers: Get<Vec<IssuerId>>;
+        /// Normalizes the claims of the tokens of each issuer, e.g. `adapters::StandardAdapters`.
+        type ProviderAdapters: ProviderAdapter;
+        /// Whether the original submitter may store an already existing proof again. When
+        /// `false`, duplicates are always rejected.
+        type AllowProofOverwrite: Get<bool>;
+        /// INSECURE: skip the cryptographic verification of proofs, keeping only the JSON and
+        /// claims checks. Meant for local development chains only; must be `false` anywhere else.
+        type InsecureSkipVerification: Get<bool>;
+        /// The share of the maximum block weight proof calls may use together, so that a burst
+        /// of logins cannot crowd out the rest of the block. Enforced by `CheckProofStructure`.
+        type ProofWeightShare: Get<Perbill>;
+        /// Whether proof events also carry the full proof JSON. Off, events only identify the proof.
+        type EmitProofPayloads: Get<bool>;
+        /// The default of how long (in seconds) a stored proof stays valid.
+        type ProofLifetime: Get<u64>;
+        /// The default of how far (in seconds) the chain clock may lag behind the token issuer's
+        /// when checking the `exp` claim.
+        type ClockSkewTolerance: Get<u64>;
+        /// How many blocks old the block a token's nonce is bound to may be. Must not exceed
+        /// `frame_system::Config::BlockHashCount`, past which block hashes are pruned.
+        type NonceBlockWindow: Get<BlockNumberFor<Self>>;
+        /// How old (in seconds) a token may be, going by its `iat` claim, regardless of `exp`.
+        type MaxTokenAge: Get<u64>;
+        /// How many blocks after `commit_login` the committed proof may be revealed.
+        type RevealWindow: Get<BlockNumberFor<Self>>;
+        /// Whether raw proofs are discarded once verified. When set, neither `ZkProofData` nor
+        /// events carry the JWT or its claims (such as the email): only attestations, nullifiers
+        /// and expiries are kept.
+        type PrivacyMode: Get<bool>;
+        /// The maximum number of proofs a single `erase_identity_data` call erases.
+        type MaxErasedProofs: Get<u32>;
+        /// The maximum number of activations that may be scheduled for the same block.
+        type MaxScheduledActivations: Get<u32>;
+        /// How many of the latest failed submissions `RecentFailures` keeps.
+        type MaxRecentFailures: Get<u32>;
+        /// Called once a proof is verified and stored, for runtimes to plug in side effects.
+        type OnProofVerified: OnProofVerified<Self::AccountId, Commitment>;
+        /// The maximum number of proof verifications a block may include, whatever their weight.
+        /// Further submissions are retried in the next blocks. `set_config` may lower it.
+        type MaxVerificationsPerBlock: Get<u32>;
+        /// Whether block authors may replace the keys of the providers with the JWKS inherent, next
+        /// to the oracles. Authors are then trusted to fetch the keys from the providers.
+        type AuthoredJwks: Get<bool>;
+        /// Every this many blocks, the Merkle root of the identities holding a valid proof is
+        /// computed at the end of the block, for light clients to check memberships against. Zero
+        /// disables it.
+        type IdentityRootPeriod: Get<BlockNumberFor<Self>>;
+        /// The maximum number of stored proofs the identity root covers, bounding the work of the
+        /// block computing it.
+        type MaxIdentityRootLeaves: Get<u32>;
+        /// How many device keys an identity may have registered at once.
+        type MaxDeviceKeys: Get<u32>;
+        /// How much the calls of the runtime spend, which the spending limits of device keys
+        /// are enforced against by `CheckSpendingLimit`.
+        type CallSpending: CallSpending<<Self as frame_system::Config>::RuntimeCall>;
+        /// How many blocks after a fresh proof requested it a locked identity unlocks.
+        type UnlockDelay: Get<BlockNumberFor<Self>>;
+        /// Called once an identity is locked, for pallets to end what they granted it.
+        type OnIdentityLocked: OnIdentityLocked;
+        /// The accounts the calls of the runtime require to hold a valid attestation, which
+        /// `CheckSpendingLimit` enforces.
+        type AttestationGate: AttestationGate<Self::AccountId, <Self as frame_system::Config>::RuntimeCall>;
+        /// The maximum length of the application metadata stored alongside a proof.
+        type MaxMetadataLength: Get<u32>;
+        /// How often the circuit versions due to be retired are announced with
+        /// `CircuitSunsetApproaching`, in blocks. Zero disables the announcements.
+        type CircuitWarningPeriod: Get<BlockNumberFor<Self>>;
+        /// The calls requiring a fresh proof of the identity of their signer, which
+        /// `CheckSpendingLimit` enforces.
+        type StepUpCalls: StepUpCalls<<Self as frame_system::Config>::RuntimeCall, BlockNumberFor<Self>>;
+        /// How many proof submissions of a submitter, or about an identity, may fail verification
+        /// in a row before they are locked out. Zero disables lockouts.
+        type MaxFailedAttempts: Get<u32>;
+        /// How many blocks the first lockout lasts. Each further `MaxFailedAttempts` failures
+        /// double it.
+        type LockoutPeriod: Get<BlockNumberFor<Self>>;
+        /// The calls the zkLogin accounts of identities dispatch with `zklogin_execute`.
+        type ZkLoginCall: Parameter
+            + Dispatchable<RuntimeOrigin = Self::RuntimeOrigin, PostInfo = PostDispatchInfo>
+            + GetDispatchInfo;
+        /// Which of those calls `zklogin_execute` dispatches.
+        type ZkLoginCalls: Contains<Self::ZkLoginCall>;
+        /// How long (seconds) before an attestation expires its holder is reminded to renew it
+        /// with `AttestationExpiring`. Zero disables the reminders.
+        type RenewalNotice: Get<u64>;
+        /// The most renewal reminders emitted in a block; those left over are emitted in the next
+        /// blocks.
+        type MaxRenewalReminders: Get<u32>;
+        /// The weights of the pallet's benchmarks, see `weights.rs`.
+        type WeightInfo: WeightInfo;
     }
 
+    /// The proof size of the trie nodes above a storage entry, as the benchmarking CLI estimates
+    /// them for maps of unbounded size: five levels of fifteen 33-byte siblings.
+    pub const STORAGE_READ_PROOF_OVERHEAD: u64 = 5 * 15 * 33;
+
+    /// The most blocks a single `proofs_in_range` query covers.
+    pub const MAX_PROOF_RANGE: u32 = 1_000;
+
+    /// The most proofs a single `proofs_with_tag` or `namespace_proofs` query returns.
+    pub const MAX_PROOF_PAGE: u32 = 1_000;
+
+    /// How many times a lockout doubles at most, past `LockoutPeriod`.
+    pub const MAX_LOCKOUT_DOUBLINGS: u32 = 10;
+
     /// Storage map to hold the ZK proof data.
     #[pallet::storage]
     pub type ZkProofData<T: Config> = StorageMap<_, Twox64Concat, T::Hash, BoundedVec<u8, T::MaxJsonLength>, OptionQuery>;
 
+    /// The limits set from the `Config` defaults.
+    #[pallet::type_value]
+    pub fn DefaultParameters<T: Config>() -> ProofParameters {
+        ProofParameters {
+            max_json_length: T::MaxJsonLength::get(),
+            proof_lifetime: T::ProofLifetime::get(),
+            clock_skew_tolerance: T::ClockSkewTolerance::get(),
+            proof_weight_share: T::ProofWeightShare::get(),
+            max_verifications_per_block: T::MaxVerificationsPerBlock::get(),
+        }
+    }
+
+    /// The limits currently in force, as last set by `set_config`.
+    #[pallet::storage]
+    pub type Parameters<T: Config> = StorageValue<_, ProofParameters, ValueQuery, DefaultParameters<T>>;
+
+    /// When (unix seconds) each stored ZK proof stops being valid.
+    #[pallet::storage]
+    pub type ZkProofExpiry<T: Config> = StorageMap<_, Twox64Concat, T::Hash, u64, OptionQuery>;
+
+    /// The proofs whose submitters are to be reminded to renew their attestation, by when (unix
+    /// seconds, big-endian so that they iterate in time order) they are.
+    #[pallet::storage]
+    pub type RenewalReminders<T: Config> =
+        StorageDoubleMap<_, Identity, [u8; 8], Twox64Concat, T::Hash, (), OptionQuery>;
+
+    /// The weight reserved by the proof calls included in the current block, whether they succeed
+    /// or not.
+    #[pallet::storage]
+    pub type ProofWeightUsed<T: Config> = StorageValue<_, Weight, ValueQuery>;
+
+    /// The number of proof verifications included in the current block, counted by
+    /// `CheckProofStructure`.
+    #[pallet::storage]
+    pub type VerificationsInBlock<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// Whether the current block includes the JWKS inherent.
+    #[pallet::storage]
+    pub type AuthoredJwksSet<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// The attestation of each stored ZK proof.
+    #[pallet::storage]
+    pub type Attestations<T: Config> = StorageMap<_, Twox64Concat, T::Hash, Attestation, OptionQuery>;
+
+    /// The latest proof stored for each identity, keyed by nullifier.
+    #[pallet::storage]
+    pub type Nullifiers<T: Config> = StorageMap<_, Identity, Nullifier, T::Hash, OptionQuery>;
+
+    /// The block the latest proof of each identity was stored in, keyed by nullifier.
+    #[pallet::storage]
+    pub type IdentityVerifiedAt<T: Config> = StorageMap<_, Identity, Nullifier, BlockNumberFor<T>, OptionQuery>;
+
+    /// The tokens spent by a proof, keyed by the block their nonce embeds and the hash of their
+    /// identity and nonce, and again of their identity and id (`jti`) if they have one, along with
+    /// the hash of the proof. Pruned once the nonces of a block are stale, see `NonceBlockWindow`.
+    #[pallet::storage]
+    pub type ConsumedNonces<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, BlockNumberFor<T>, Identity, [u8; 32], T::Hash, OptionQuery>;
+
+    /// The proofs stored for each identity, keyed by nullifier and proof hash.
+    #[pallet::storage]
+    pub type IdentityProofs<T: Config> =
+        StorageDoubleMap<_, Identity, Nullifier, Twox64Concat, T::Hash, (), OptionQuery>;
+
+    /// The account that stored each ZK proof.
+    #[pallet::storage]
+    pub type ZkProofSubmitter<T: Config> = StorageMap<_, Twox64Concat, T::Hash, T::AccountId, OptionQuery>;
+
+    /// The application metadata stored alongside each ZK proof, such as a dApp-specific user id.
+    #[pallet::storage]
+    pub type ProofMetadata<T: Config> =
+        StorageMap<_, Twox64Concat, T::Hash, BoundedVec<u8, T::MaxMetadataLength>, OptionQuery>;
+
+    /// The tags of each ZK proof, given by its submitter.
+    #[pallet::storage]
+    pub type ProofTagsOf<T: Config> = StorageMap<_, Twox64Concat, T::Hash, ProofTags, OptionQuery>;
+
+    /// The proofs carrying each tag, keyed by tag and proof hash.
+    #[pallet::storage]
+    pub type TaggedProofs<T: Config> =
+        StorageDoubleMap<_, Blake2_128Concat, ProofTag, Identity, T::Hash, (), OptionQuery>;
+
+    /// The dApps registered as namespaces, keyed by client id.
+    #[pallet::storage]
+    pub type Namespaces<T: Config> = StorageMap<_, Blake2_128Concat, ClientId, Namespace<T::AccountId>, OptionQuery>;
+
+    /// The namespace each ZK proof was stored in, for the proofs made for tokens issued to a
+    /// registered dApp.
+    #[pallet::storage]
+    pub type ProofNamespaces<T: Config> = StorageMap<_, Twox64Concat, T::Hash, ClientId, OptionQuery>;
+
+    /// The quota of each namespace, if limited.
+    #[pallet::storage]
+    pub type NamespaceQuotas<T: Config> =
+        StorageMap<_, Blake2_128Concat, ClientId, NamespaceQuota<BlockNumberFor<T>>, OptionQuery>;
+
+    /// How much of its quota each namespace used.
+    #[pallet::storage]
+    pub type NamespaceUsages<T: Config> =
+        StorageMap<_, Blake2_128Concat, ClientId, NamespaceUsage<BlockNumberFor<T>>, ValueQuery>;
+
+    /// The proofs stored in each namespace, keyed by client id and proof hash.
+    #[pallet::storage]
+    pub type NamespaceProofs<T: Config> =
+        StorageDoubleMap<_, Blake2_128Concat, ClientId, Identity, T::Hash, (), OptionQuery>;
+
+    /// The proofs stored in each block, keyed by block number and proof hash, for indexers to
+    /// backfill the blocks they missed. Proofs erased since are kept in the index.
+    #[pallet::storage]
+    pub type ProofsByBlock<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, BlockNumberFor<T>, Identity, T::Hash, (), OptionQuery>;
+
+    /// The latest proof stored by each account.
+    #[pallet::storage]
+    pub type AccountProofs<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, T::Hash, OptionQuery>;
+
+    /// The device keys registered to each identity, keyed by nullifier and key.
+    #[pallet::storage]
+    pub type DeviceKeys<T: Config> = StorageDoubleMap<
+        _,
+        Identity,
+        Nullifier,
+        Blake2_128Concat,
+        T::AccountId,
+        DeviceKey<BlockNumberFor<T>>,
+        OptionQuery,
+    >;
+
+    /// The identity each device key is registered to.
+    #[pallet::storage]
+    pub type KeyIdentities<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, Nullifier, OptionQuery>;
+
+    /// The spending limit of each device key that has one.
+    #[pallet::storage]
+    pub type SpendingLimits<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, SpendingLimit<BlockNumberFor<T>>, OptionQuery>;
+
+    /// The identities locked with `emergency_lock`, keyed by nullifier. An identity stays locked
+    /// until its `unlocks_at` block.
+    #[pallet::storage]
+    pub type IdentityLocks<T: Config> =
+        StorageMap<_, Identity, Nullifier, IdentityLock<BlockNumberFor<T>>, OptionQuery>;
+
+    /// What each limited device key spent in its current period, along with the first block of
+    /// the period.
+    #[pallet::storage]
+    pub type Spent<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, (BlockNumberFor<T>, u128), OptionQuery>;
+
+    /// Proofs committed with `commit_login` and not revealed yet, keyed by proof hash, along with
+    /// the committer and the block of the commitment.
+    #[pallet::storage]
+    pub type LoginCommitments<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, BlockNumberFor<T>), OptionQuery>;
+
+    /// The verifying keys proofs may embed, by hash.
+    #[pallet::storage]
+    pub type ApprovedVerifyingKeys<T: Config> = StorageMap<_, Identity, VkHash, (), OptionQuery>;
+
+    /// The block from which the proofs of a deprecated circuit version are rejected, keyed by
+    /// version. Version 0 stands for the proofs declaring no version.
+    #[pallet::storage]
+    pub type CircuitSunsets<T: Config> = StorageMap<_, Twox64Concat, u32, BlockNumberFor<T>, OptionQuery>;
+
+    /// Verifying key approvals and provider registrations scheduled for a later block, keyed by
+    /// the block they take effect in.
+    #[pallet::storage]
+    pub type ScheduledActivations<T: Config> =
+        StorageMap<_, Twox64Concat, BlockNumberFor<T>, BoundedVec<Activation, T::MaxScheduledActivations>, ValueQuery>;
+
+    /// The number of proof submissions included in a block and rejected, per error. Only
+    /// submissions passing the admission checks of `CheckProofStructure` are counted.
+    #[pallet::storage]
+    pub type FailureStats<T: Config> = StorageMap<_, Twox64Concat, Error<T>, u64, ValueQuery>;
+
+    /// The latest proof submissions included in a block and rejected, oldest first. Read through
+    /// `ZkProofApi::recent_failures` to reproduce failed logins.
+    #[pallet::storage]
+    pub type RecentFailures<T: Config> =
+        StorageValue<_, BoundedVec<FailureRecord<T::AccountId, BlockNumberFor<T>>, T::MaxRecentFailures>, ValueQuery>;
+
+    /// The proof submissions of each submitter failing verification since its latest successful
+    /// one. Cleared once a submission succeeds.
+    #[pallet::storage]
+    pub type SubmitterFailures<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, FailedAttempts<BlockNumberFor<T>>, ValueQuery>;
+
+    /// The proof submissions about each identity failing verification past the pairing since its
+    /// latest successful one, keyed by nullifier. Only then are the public inputs the nullifier
+    /// derives from known to be bound to the proof.
+    #[pallet::storage]
+    pub type IdentityFailures<T: Config> =
+        StorageMap<_, Identity, Nullifier, FailedAttempts<BlockNumberFor<T>>, ValueQuery>;
+
+    /// How much the logins of each provider are used, keyed by issuer. Read through
+    /// `ZkProofApi::provider_stats`.
+    #[pallet::storage]
+    pub type ProviderUsage<T: Config> =
+        StorageMap<_, Blake2_128Concat, IssuerId, ProviderStats<BlockNumberFor<T>>, ValueQuery>;
+
+    /// The storage footprint of the pallet, counted as proofs, attestations, device keys and
+    /// keysets are written and removed. Read through `ZkProofApi::storage_stats`.
+    #[pallet::storage]
+    pub type StorageUsage<T: Config> = StorageValue<_, StorageStats, ValueQuery>;
+
+    /// The latest Merkle root of the identities holding a valid proof, see `IdentityRootPeriod`.
+    #[pallet::storage]
+    pub type LatestIdentityRoot<T: Config> = StorageValue<_, IdentityRoot<BlockNumberFor<T>>, OptionQuery>;
+
+    /// The set of accounts allowed to submit JWKS updates.
+    #[pallet::storage]
+    pub type Oracles<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+    /// The registered OIDC providers, keyed by issuer.
+    #[pallet::storage]
+    pub type Providers<T: Config> = StorageMap<_, Blake2_128Concat, IssuerId, ProviderInfo, OptionQuery>;
+
+    /// The registered providers whose tokens are not accepted for the time being, e.g. during a
+    /// security incident at the provider.
+    #[pallet::storage]
+    pub type DisabledProviders<T: Config> = StorageMap<_, Blake2_128Concat, IssuerId, (), OptionQuery>;
+
+    /// The claims the tokens of each provider must carry beyond the standard ones, keyed by issuer.
+    #[pallet::storage]
+    pub type RequiredClaims<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        IssuerId,
+        BoundedVec<ClaimRequirement, ConstU32<MAX_REQUIRED_CLAIMS>>,
+        ValueQuery,
+    >;
+
+    /// The claims the tokens issued to each dApp registered as a namespace must carry, keyed by
+    /// client id.
+    #[pallet::storage]
+    pub type NamespaceClaims<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        ClientId,
+        BoundedVec<ClaimRequirement, ConstU32<MAX_REQUIRED_CLAIMS>>,
+        ValueQuery,
+    >;
+
+    /// The tenants the logins into each namespace are restricted to, such as the Workspace domains
+    /// of a company, keyed by client id. Any tenant, or none, if empty.
+    #[pallet::storage]
+    pub type NamespaceTenants<T: Config> =
+        StorageMap<_, Blake2_128Concat, ClientId, BoundedVec<Tenant, ConstU32<MAX_NAMESPACE_TENANTS>>, ValueQuery>;
+
+    /// How the identities of each namespace are derived, keyed by client id.
+    #[pallet::storage]
+    pub type NamespaceAddressSchemes<T: Config> = StorageMap<_, Blake2_128Concat, ClientId, AddressScheme, ValueQuery>;
+
+    /// The identities whose proofs are rejected and whose attestations are ignored, e.g. because
+    /// their accounts at the provider were compromised.
+    #[pallet::storage]
+    pub type BlockedIdentities<T: Config> = StorageMap<_, Blake2_128Concat, BlockedIdentity, (), OptionQuery>;
+
+    /// The base64 variants each provider's tooling emits, standard for proofs and URL-safe for
+    /// tokens unless set.
+    #[pallet::storage]
+    pub type ProviderEncodings<T: Config> = StorageMap<_, Blake2_128Concat, IssuerId, ProviderEncoding, ValueQuery>;
+
+    /// The claim predicates proofs may attest to, by id.
+    #[pallet::storage]
+    pub type Predicates<T: Config> = StorageMap<_, Blake2_128Concat, PredicateId, ClaimPredicate, OptionQuery>;
+
+    /// The predicate each stored predicate proof attests to, and whether it holds.
+    #[pallet::storage]
+    pub type PredicateAttestations<T: Config> = StorageMap<_, Twox64Concat, T::Hash, PredicateAttestation, OptionQuery>;
+
+    /// The signing keys currently accepted for each provider, keyed by issuer.
+    #[pallet::storage]
+    pub type Jwks<T: Config> = StorageMap<_, Blake2_128Concat, IssuerId, BoundedVec<JwkRecord, T::MaxJwksKeys>, OptionQuery>;
+
+    /// When (unix seconds) the current keyset of a provider expires, as advertised by the
+    /// provider's HTTP cache headers. Keysets without an entry never expire.
+    #[pallet::storage]
+    pub type JwksExpiry<T: Config> = StorageMap<_, Blake2_128Concat, IssuerId, u64, OptionQuery>;
+
+    /// A keyset proposed by oracles that has not reached quorum yet.
+    #[derive(CloneNoBound, Encode, Decode, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound, TypeInfo)]
+    #[scale_info(skip_type_params(T))]
+    pub struct PendingKeyset<T: Config> {
+        /// The proposed keys.
+        pub keys: BoundedVec<JwkRecord, T::MaxJwksKeys>,
+        /// The block in which the keyset was first proposed.
+        pub first_seen: BlockNumberFor<T>,
+        /// The earliest expiry reported by the approving oracles.
+        pub expires_at: Option<u64>,
+        /// The oracles that submitted this exact keyset.
+        pub approvals: BoundedVec<T::AccountId, T::MaxOracles>,
+    }
+
+    /// Keysets awaiting quorum, keyed by issuer and keyset hash.
+    #[pallet::storage]
+    pub type PendingJwks<T: Config> =
+        StorageDoubleMap<_, Blake2_128Concat, IssuerId, Identity, T::Hash, PendingKeyset<T>, OptionQuery>;
+
+    /// Genesis configuration: the initial oracle set, providers and signing keys.
+    #[pallet::genesis_config]
+    pub struct GenesisConfig<T: Config> {
+        /// Accounts allowed to submit JWKS updates.
+        pub oracles: Vec<T::AccountId>,
+        /// Registered providers as `(issuer, jwks_uri)` pairs.
+        pub providers: Vec<(Vec<u8>, Vec<u8>)>,
+        /// Signing keys per issuer.
+        pub jwks: Vec<(Vec<u8>, Vec<JwkRecord>)>,
+        /// Hashes of the verifying keys proofs may embed.
+        pub approved_verifying_keys: Vec<VkHash>,
+    }
+
+    impl<T: Config> Default for GenesisConfig<T> {
+        fn default() -> Self {
+            Self {
+                oracles: Vec::new(),
+                providers: Vec::from([(GOOGLE_ISSUER.to_vec(), GOOGLE_JWKS_URI.to_vec())]),
+                jwks: Vec::from([(GOOGLE_ISSUER.to_vec(), google_jwks())]),
+                approved_verifying_keys: Vec::new(),
+            }
+        }
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+        fn build(&self) {
+            for oracle in &self.oracles {
+                Oracles::<T>::insert(oracle, ());
+            }
+            for (issuer, jwks_uri) in &self.providers {
+                let issuer: IssuerId = issuer.clone().try_into().expect("genesis issuer is too long");
+                let jwks_uri = jwks_uri.clone().try_into().expect("genesis JWKS URI is too long");
+                Providers::<T>::insert(issuer, ProviderInfo { jwks_uri });
+            }
+            for (issuer, keys) in &self.jwks {
+                let issuer: IssuerId = issuer.clone().try_into().expect("genesis issuer is too long");
+                let keys: BoundedVec<JwkRecord, T::MaxJwksKeys> =
+                    keys.clone().try_into().expect("too many genesis keys for one issuer");
+                Pallet::<T>::write_jwks(&issuer, Some(keys));
+            }
+            for hash in &self.approved_verifying_keys {
+                ApprovedVerifyingKeys::<T>::insert(hash, ());
+            }
+        }
+    }
+
     /// Events emitted by the pallet.
     #[pallet::event]
     #[pallet::generate_deposit(pub (super) fn deposit_event)]
     pub enum Event<T: Config> {
-        /// Event emitted when ZK proof data is stored.
+        /// Event emitted when ZK proof data is stored. `json` is only set if `EmitProofPayloads` is
+        /// and `PrivacyMode` is not.
         ZkProofStored {
-            json: BoundedVec<u8, T::MaxJsonLength>,
-            who: T::AccountId,
             hash: T::Hash,
+            who: T::AccountId,
+            provider: IssuerId,
+            commitment: Commitment,
+            nullifier: Nullifier,
+            json: Option<BoundedVec<u8, T::MaxJsonLength>>,
         },
-        /// Event emitted when ZK proof data is retrieved.
-        ZkProofRetrieved(T::Hash, Vec<u8>),
+        /// Event emitted when ZK proof data is retrieved. `json` is only set if `EmitProofPayloads` is.
+        ZkProofRetrieved { hash: T::Hash, who: Option<T::AccountId>, json: Option<Vec<u8>> },
+        /// An account was added to the oracle set.
+        OracleAdded { oracle: T::AccountId },
+        /// An account was removed from the oracle set.
+        OracleRemoved { oracle: T::AccountId },
+        /// An oracle submitted a keyset that has not reached quorum yet.
+        JwksSubmitted { provider: IssuerId, keyset_hash: T::Hash, who: T::AccountId, approvals: u32 },
+        /// A keyset reached quorum and replaced the signing keys of a provider.
+        JwksUpdated { provider: IssuerId, keyset_hash: T::Hash, expires_at: Option<u64> },
+        /// A provider was registered.
+        ProviderAdded { provider: IssuerId },
+        /// A provider and its keys were removed.
+        ProviderRemoved { provider: IssuerId },
+        /// A key was inserted or replaced by `OnboardingOrigin`, bypassing the oracles.
+        JwkForceSet { provider: IssuerId, kid: BoundedVec<u8, ConstU32<MAX_KID_LENGTH>> },
+        /// A key was removed by `AdminOrigin`, bypassing the oracles.
+        JwkForceRemoved { provider: IssuerId, kid: BoundedVec<u8, ConstU32<MAX_KID_LENGTH>> },
+        /// A page of proofs was retrieved. `next` is the cursor of the following page, if any.
+        ZkProofsRetrieved { next: Option<T::Hash> },
+        /// The tunable limits were updated.
+        ParametersUpdated { parameters: ProofParameters },
+        /// A proof hash was committed, to be revealed with `reveal_login`.
+        LoginCommitted { hash: T::Hash, who: T::AccountId },
+        /// `erased` proofs of an identity were erased at the request of its owner. `complete` is
+        /// `false` if proofs remain, to be erased by another call.
+        IdentityErased { nullifier: Nullifier, who: T::AccountId, erased: u32, complete: bool },
+        /// Proofs may now embed the verifying key with this hash.
+        VerifyingKeyApproved { hash: VkHash },
+        /// Proofs may no longer embed the verifying key with this hash.
+        VerifyingKeyRevoked { hash: VkHash },
+        /// `activation` will take effect at the start of block `at`.
+        ActivationScheduled { activation: Activation, at: BlockNumberFor<T> },
+        /// A step of a storage migration migrated `migrated` entries. `complete` is `true` once
+        /// the migration is over.
+        MigrationProgressed { migrated: u32, complete: bool },
+        /// The claims the tokens of a provider must carry were replaced.
+        RequiredClaimsSet { provider: IssuerId },
+        /// Proofs may now attest to `predicate`, under `id`.
+        PredicateRegistered { id: PredicateId, predicate: ClaimPredicate },
+        /// Proofs may no longer attest to the predicate with this id.
+        PredicateRemoved { id: PredicateId },
+        /// The proof stored under `hash` attests whether `predicate` holds for its token's claims.
+        PredicateAttested { hash: T::Hash, predicate: PredicateId, holds: bool },
+        /// The Merkle root of the identities holding a valid proof was computed, covering `leaves`
+        /// commitments. `complete` is `false` if proofs were left out past `MaxIdentityRootLeaves`.
+        IdentityRootComputed { root: H256, leaves: u32, complete: bool },
+        /// `key` was registered to an identity as a device key, until `expires_at`.
+        DeviceKeyRegistered { nullifier: Nullifier, key: T::AccountId, label: DeviceLabel, expires_at: u64 },
+        /// `key` was revoked by a device key of the same identity.
+        DeviceKeyRevoked { nullifier: Nullifier, key: T::AccountId, by: T::AccountId },
+        /// The `revoked` device keys of an identity were revoked at once.
+        AllDeviceKeysRevoked { nullifier: Nullifier, by: T::AccountId, revoked: u32 },
+        /// The spending limit of `key` was set by a device key of the same identity, or lifted if
+        /// `None`.
+        SpendingLimitSet { key: T::AccountId, by: T::AccountId, limit: Option<SpendingLimit<BlockNumberFor<T>>> },
+        /// An identity was locked by `who`, revoking its `revoked` device keys.
+        IdentityLocked { nullifier: Nullifier, who: T::AccountId, revoked: u32 },
+        /// A locked identity will unlock at block `unlocks_at`, unless it is locked again before.
+        UnlockRequested { nullifier: Nullifier, who: T::AccountId, unlocks_at: BlockNumberFor<T> },
+        /// The tags of the proof stored under `hash` were replaced.
+        ProofTagged { hash: T::Hash, tags: ProofTags },
+        /// The dApp `client_id` was registered as a namespace, administered by `owner`.
+        NamespaceRegistered { client_id: ClientId, owner: T::AccountId },
+        /// The namespace of the dApp `client_id` is now administered by `owner`.
+        NamespaceOwnerSet { client_id: ClientId, owner: T::AccountId },
+        /// Logins into the namespace of the dApp `client_id` were suspended, or resumed.
+        NamespaceStatusSet { client_id: ClientId, paused: bool },
+        /// The quota of the namespace of the dApp `client_id` was set, or lifted if `None`.
+        NamespaceQuotaSet { client_id: ClientId, quota: Option<NamespaceQuota<BlockNumberFor<T>>> },
+        /// The tokens of `provider` are accepted again, or no longer are, for the time being.
+        ProviderStatusSet { provider: IssuerId, enabled: bool },
+        /// The proofs of circuit `version` will be rejected from block `sunset_at`.
+        CircuitDeprecated { version: u32, sunset_at: BlockNumberFor<T> },
+        /// The deprecation of circuit `version` was cancelled.
+        CircuitDeprecationCancelled { version: u32 },
+        /// The proofs of circuit `version` will be rejected in `blocks_left` blocks, from block
+        /// `sunset_at`. Emitted every `CircuitWarningPeriod` until then.
+        CircuitSunsetApproaching { version: u32, sunset_at: BlockNumberFor<T>, blocks_left: BlockNumberFor<T> },
+        /// The claims the tokens issued to the dApp `client_id` must carry were set.
+        NamespaceClaimsSet { client_id: ClientId },
+        /// The tenants the logins into the namespace of the dApp `client_id` are restricted to
+        /// were set.
+        NamespaceTenantsSet { client_id: ClientId },
+        /// The raw proof stored under `hash` was pruned; its attestation remains until it expires.
+        ProofDataPruned { hash: T::Hash },
+        /// The zkLogin account of an identity dispatched a call on behalf of `who`.
+        ZkLoginExecuted { nullifier: Nullifier, account: T::AccountId, who: T::AccountId, result: DispatchResult },
+        /// The identities of the namespace of the dApp `client_id` are derived with `scheme`.
+        NamespaceAddressSchemeSet { client_id: ClientId, scheme: AddressScheme },
+        /// The proofs of `identity` are rejected and its attestations ignored from now on.
+        IdentityBlocked { identity: BlockedIdentity },
+        /// `identity` is no longer blocked.
+        IdentityUnblocked { identity: BlockedIdentity },
+        /// The envelopes and tokens of `provider` are decoded with `encoding`.
+        ProviderEncodingSet { provider: IssuerId, encoding: ProviderEncoding },
+        /// `who` renewed the attestation of its proof `hash` with a fresh proof of the identity
+        /// `nullifier`, until `expires_at`.
+        AttestationRenewed { hash: T::Hash, who: T::AccountId, nullifier: Nullifier, expires_at: u64 },
+        /// The attestation of the proof `hash` that `who` logged in with expires at `expires_at`,
+        /// within `RenewalNotice`; `renew_attestation` extends it.
+        AttestationExpiring { hash: T::Hash, who: T::AccountId, nullifier: Nullifier, expires_at: u64 },
     }
 
     /// Errors that can occur in the pallet.
@@ -55,6 +732,169 @@ pub mod pallet {
         ZkProofTooLarge,
         /// The ZK proof is invalid.
         InvalidProof,
+        /// The account is already a registered oracle.
+        OracleAlreadyRegistered,
+        /// The account is not a registered oracle.
+        UnknownOracle,
+        /// Only registered oracles may submit JWKS updates.
+        NotOracle,
+        /// The oracle already submitted this keyset.
+        DuplicateSubmission,
+        /// The keyset has more approvals than `MaxOracles` allows.
+        TooManyApprovals,
+        /// The provider is already registered.
+        ProviderAlreadyRegistered,
+        /// The provider is not registered.
+        UnknownProvider,
+        /// The provider already has `MaxJwksKeys` keys.
+        TooManyKeys,
+        /// The provider has no key with this id.
+        UnknownKid,
+        /// The token's `iss` claim is not one of the `AllowedIssuers`.
+        UntrustedIssuer,
+        /// The JWT embedded in the proof exceeds `MaxJwtLength`.
+        JwtTooLarge,
+        /// The proof carries more than `MaxPublicInputs` public inputs.
+        TooManyPublicInputs,
+        /// The verifying key embedded in the proof could not be deserialized.
+        VkDeserializationFailed,
+        /// A proof with the same hash is already stored.
+        ProofAlreadyExists,
+        /// Proof calls used up their share of this block's weight. No longer returned:
+        /// `CheckProofStructure` keeps such calls out of the block instead.
+        ProofWeightExhausted,
+        /// The parameters exceed the bounds fixed by the runtime.
+        InvalidParameters,
+        /// The token's `exp` claim is in the past.
+        TokenExpired,
+        /// The proof is meant for another chain.
+        WrongChain,
+        /// The token was issued more than `MaxTokenAge` ago, or in the future.
+        StaleToken,
+        /// The token's `nonce` claim is missing or not of the form `<block number>-<block hash>`.
+        InvalidNonce,
+        /// The token's nonce is not bound to one of the last `NonceBlockWindow` blocks.
+        StaleNonce,
+        /// A live commitment to this proof already exists.
+        CommitmentAlreadyExists,
+        /// The proof was not committed with `commit_login`.
+        UnknownCommitment,
+        /// The proof was committed by another account.
+        NotCommitter,
+        /// The proof must be revealed in a later block than its commitment.
+        RevealTooEarly,
+        /// The commitment is older than `RevealWindow`.
+        CommitmentExpired,
+        /// The verifying key embedded in the proof is not approved.
+        UnapprovedVerifyingKey,
+        /// The verifying key is already approved.
+        VerifyingKeyAlreadyApproved,
+        /// The verifying key is not approved.
+        UnknownVerifyingKey,
+        /// The proof declares a circuit version the chain does not know.
+        UnknownCircuitVersion,
+        /// The public inputs are not the ones the circuit version lays out for the token's claims.
+        PublicInputMismatch,
+        /// Activations can only be scheduled for a future block.
+        ActivationNotInFuture,
+        /// `MaxScheduledActivations` activations are already scheduled for that block.
+        TooManyScheduledActivations,
+        /// The proof starts with a format byte that is no known `EnvelopeFormat`.
+        UnknownEnvelopeFormat,
+        /// The token is signed with an algorithm its provider is not trusted with, or with none.
+        UnsupportedAlgorithm,
+        /// The path of a required claim is not a JSON pointer.
+        InvalidClaimPath,
+        /// No longer returned, see `ClaimPolicyViolation`.
+        MissingRequiredClaim,
+        /// No longer returned, see `ClaimPolicyViolation`.
+        ClaimNotAllowed,
+        /// The proof attests to a predicate that is not registered, or to none.
+        UnknownPredicate,
+        /// The runtime does not trust block authors with the keys of the providers.
+        AuthoredJwksDisabled,
+        /// The block already includes the JWKS inherent.
+        AuthoredJwksAlreadySet,
+        /// The token's nonce does not end with the hex-encoded key being registered.
+        KeyNotBound,
+        /// The key is registered to another identity.
+        KeyRegisteredElsewhere,
+        /// The identity has `MaxDeviceKeys` unexpired device keys already.
+        TooManyDeviceKeys,
+        /// The account is not a device key of the identity.
+        UnknownDeviceKey,
+        /// The device key of the caller expired.
+        DeviceKeyExpired,
+        /// Device keys with a spending limit may not set spending limits.
+        SpendingLimited,
+        /// The period of a spending limit must be at least one block.
+        InvalidSpendingLimit,
+        /// The spending limit of the signer does not allow the call.
+        CallNotAllowed,
+        /// The call would take the signer over its spending limit for the period.
+        SpendingLimitExceeded,
+        /// The identity is not locked.
+        IdentityNotLocked,
+        /// The identity is unlocking already.
+        UnlockAlreadyRequested,
+        /// The signer logged in with a locked identity, and may only lock or unlock it.
+        AccountFrozen,
+        /// The call requires an account to hold a valid attestation, which it does not.
+        AttestationRequired,
+        /// No proof is stored under the given hash.
+        UnknownProof,
+        /// The proof was stored by another account.
+        NotSubmitter,
+        /// The dApp is registered as a namespace already.
+        NamespaceAlreadyRegistered,
+        /// The dApp is not registered as a namespace.
+        UnknownNamespace,
+        /// The caller does not administer the namespace.
+        NotNamespaceOwner,
+        /// Logins into the namespace of the token's audience are suspended.
+        NamespacePaused,
+        /// The period of a quota must be at least one block.
+        InvalidQuota,
+        /// The login would take the namespace of the token's audience over its quota.
+        QuotaExceeded,
+        /// The tokens of the provider are not accepted for the time being.
+        ProviderDisabled,
+        /// The proof is made with a circuit version past its sunset.
+        CircuitSunset,
+        /// A sunset can only be set for a future block.
+        SunsetNotInFuture,
+        /// The call requires the identity of the signer to have stored a proof more recently.
+        StepUpRequired,
+        /// The token fails the requirement at `index` in the claim policy `policy`.
+        ClaimPolicyViolation { policy: ClaimPolicy, index: u8, violation: ClaimViolation },
+        /// The token was issued to a user outside of the tenants its dApp is restricted to.
+        TenantNotAllowed,
+        /// Another proof was verified for the same token.
+        NonceAlreadyUsed,
+        /// Too many proof submissions of the signer, or about the identity, failed recently.
+        TooManyFailedAttempts,
+        /// `ZkLoginCalls` does not allow zkLogin accounts to dispatch the call.
+        CallFiltered,
+        /// The envelope discloses its token instead of carrying it, but its circuit does not keep
+        /// the token private, or the other way round.
+        PrivateTokenMismatch,
+        /// The issuer of a token kept private, or the namespace of its audience, requires claims or
+        /// tenants the chain cannot check.
+        PrivateTokenUnsupported,
+        /// The identity is blocked.
+        IdentityBlocked,
+        /// The identity is blocked already.
+        AlreadyBlocked,
+        /// The identity is not blocked.
+        NotBlocked,
+        /// A token or envelope field is not base64 of the variant its provider's tooling emits.
+        InvalidEncoding,
+        /// The account's attestation expired; `renew_attestation` extends it.
+        AttestationExpired,
+        /// The proof is of another identity than the attestation it would renew.
+        IdentityMismatch,
+        /// The signature of the token does not check out against the key it names.
+        InvalidSignature,
     }
 
     /// Dispatchable functions of the pallet.
@@ -64,316 +904,2980 @@ pub mod pallet {
         ///
         /// # Parameters
         /// - `origin`: The origin of the call (must be signed).
-        /// - `json`: The ZK proof data to store.
+        /// - `json`: The ZK proof data to store: the JSON envelope, or an envelope in any format
+        ///   prefixed with its `EnvelopeFormat` byte.
+        /// - `metadata`: Application metadata to store alongside the proof, such as a dApp-specific
+        ///   user id, replacing any stored with it before.
         ///
         /// # Errors
         /// - `ZkProofTooLarge`: If the provided JSON data is too large.
+        /// - `UnknownEnvelopeFormat`: If the data starts with an unknown format byte.
+        /// - `ProofAlreadyExists`: If the proof is already stored, unless `AllowProofOverwrite` is
+        ///   set and the caller is the original submitter.
+        /// - `JwtTooLarge`: If the embedded JWT is too large.
+        /// - `TooManyPublicInputs`: If the proof carries more than `MaxPublicInputs` public inputs.
+        /// - `UnknownCircuitVersion`: If the proof declares an unknown circuit version.
+        /// - `CircuitSunset`: If the circuit version of the proof is past its sunset.
+        /// - `WrongChain`: If the proof's chain id is not this chain's genesis hash.
+        /// - `UnapprovedVerifyingKey`: If the embedded verifying key is not approved.
+        /// - `VkDeserializationFailed`: If the embedded verifying key is malformed.
         /// - `InvalidProof`: If the ZK proof is invalid.
-        #[pallet::weight({10_000})]
-        pub fn store_zk_proof(origin: OriginFor<T>, json: Vec<u8>) -> DispatchResult {
+        /// - `UntrustedIssuer`: If the token was issued by an issuer outside `AllowedIssuers`.
+        /// - `ProviderDisabled`: If the tokens of the issuer are not accepted for the time being.
+        /// - `UnsupportedAlgorithm`: If the token is unsigned, or signed with an algorithm its
+        ///   provider's adapter does not accept.
+        /// - `ClaimPolicyViolation`: If the token fails a claim requirement of its provider, or of
+        ///   the dApp it is issued to.
+        /// - `TenantNotAllowed`: If the dApp the token is issued to is restricted to other tenants.
+        /// - `NonceAlreadyUsed`: If another proof was verified for the same token.
+        /// - `TokenExpired`: If the token expired, beyond the clock skew tolerance.
+        /// - `StaleToken`: If the token was issued more than `MaxTokenAge` ago.
+        /// - `InvalidNonce`: If the token's nonce does not embed a block hash.
+        /// - `StaleNonce`: If the token's nonce embeds the hash of a block that is not recent.
+        /// - `UnknownPredicate`: If a predicate proof attests to an unregistered predicate.
+        /// - `PublicInputMismatch`: If the public inputs do not match the token's claims.
+        /// - `NamespacePaused`: If logins into the namespace of the token's audience are suspended.
+        /// - `QuotaExceeded`: If the login takes the namespace of the token's audience over its
+        ///   quota.
+        #[pallet::weight(Pallet::<T>::store_weight())]
+        pub fn store_zk_proof(
+            origin: OriginFor<T>,
+            json: Vec<u8>,
+            metadata: Option<BoundedVec<u8, T::MaxMetadataLength>>,
+        ) -> DispatchResult {
             // Ensure the origin of the call is signed.
             let who = ensure_signed(origin)?;
 
-            // Calculate the hash of the provided JSON data.
             use frame_support::sp_runtime::traits::Hash;
             let proof_hash = T::Hashing::hash(&json);
-
-            // Ensure the provided JSON data is a valid ZK proof.
-            ensure!(pallet_verify_proof(&json), Error::<T>::InvalidProof);
-
-            // Convert the JSON data into a bounded vector.
-            let bounded_json = BoundedVec::try_from(json).map_err(|_| Error::<T>::ZkProofTooLarge)?;
-
-            // Store the ZK proof data in the storage map.
-            ZkProofData::<T>::insert(proof_hash, bounded_json.clone());
-
-            // Emit an event indicating the ZK proof data has been stored.
-            Self::deposit_event(Event::ZkProofStored { json: bounded_json, who, hash: proof_hash });
-
+            let verified = Self::do_store_zk_proof(who, json)?;
+            Self::charge_namespace_login(&verified)?;
+            ProofMetadata::<T>::set(proof_hash, metadata);
             Ok(())
         }
 
-        /// Retrieve all stored ZK proofs.
+        /// Commit to a proof without disclosing it, to be revealed with `reveal_login` in a later
+        /// block. Unlike `store_zk_proof`, a proof seen in the transaction pool cannot then be
+        /// claimed by another account before inclusion.
         ///
         /// # Parameters
         /// - `origin`: The origin of the call (must be signed).
+        /// - `proof_hash`: The hash of the proof JSON.
+        ///
+        /// # Errors
+        /// - `CommitmentAlreadyExists`: If the proof was already committed within `RevealWindow`.
         #[pallet::weight({10_000})]
-        pub fn retrieve_all_zk_proofs(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
-            // Ensure the origin of the call is signed.
-            let _who = ensure_signed(origin)?;
+        pub fn commit_login(origin: OriginFor<T>, proof_hash: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
 
-            // Iterate over all stored ZK proofs and emit an event for each.
-            for (proof_hash, zk_proof) in ZkProofData::<T>::iter() {
-                Self::deposit_event(Event::ZkProofRetrieved(proof_hash, zk_proof.to_vec()));
+            // Expired commitments may be taken over.
+            if let Some((_, committed_at)) = LoginCommitments::<T>::get(proof_hash) {
+                ensure!(Self::commitment_expired(committed_at), Error::<T>::CommitmentAlreadyExists);
             }
 
-            Ok(().into())
-        }
-    }
-}
+            let now = frame_system::Pallet::<T>::block_number();
+            LoginCommitments::<T>::insert(proof_hash, (&who, now));
+            Self::deposit_indexed_event(&[proof_hash], Event::LoginCommitted { hash: proof_hash, who });
 
-extern crate alloc;
+            Ok(())
+        }
 
-use alloc::string::String;
-use serde::{Deserialize, Serialize};
-use serde_json::{self, from_str};
+        /// Reveal and store a proof committed with `commit_login`.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the committer).
+        /// - `json`: The ZK proof data to store.
+        ///
+        /// # Errors
+        /// - `UnknownCommitment`: If the proof was not committed.
+        /// - `NotCommitter`: If the proof was committed by another account.
+        /// - `RevealTooEarly`: If the proof was committed in the current block.
+        /// - `CommitmentExpired`: If the proof was committed more than `RevealWindow` blocks ago.
+        /// - Any error of `store_zk_proof`.
+        #[pallet::weight(Pallet::<T>::store_weight())]
+        pub fn reveal_login(origin: OriginFor<T>, json: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
 
-use ark_bls12_381::{Bls12_381, Fq, Fq2, Fr, FrConfig, G1Affine, G2Affine};
-use ark_ff::{Field, Fp256, MontBackend};
-use ark_groth16::{Groth16, Proof, PreparedVerifyingKey};
-use ark_serialize::CanonicalDeserialize;
-#[allow(deprecated)]
-use base64::decode;
-use log::error;
+            use frame_support::sp_runtime::traits::Hash;
+            let proof_hash = T::Hashing::hash(&json);
+            let (committer, committed_at) =
+                LoginCommitments::<T>::get(proof_hash).ok_or(Error::<T>::UnknownCommitment)?;
+            ensure!(committer == who, Error::<T>::NotCommitter);
+            ensure!(committed_at < frame_system::Pallet::<T>::block_number(), Error::<T>::RevealTooEarly);
+            ensure!(!Self::commitment_expired(committed_at), Error::<T>::CommitmentExpired);
 
-/// Decode a base64 string into a vector of bytes.
-fn decode_base64(data: String) -> Vec<u8> {
-    #[allow(deprecated)]
-    return decode(data).unwrap();
-}
+            LoginCommitments::<T>::remove(proof_hash);
+            let verified = Self::do_store_zk_proof(who, json)?;
+            Self::charge_namespace_login(&verified)?;
+            Ok(())
+        }
 
-/// Parse a G1 point from its JSON representation.
-fn parse_g1_point(point: G1Point) -> G1Affine {
-    let x_bytes = decode_base64(point.x);
-    let y_bytes = decode_base64(point.y);
+        /// Erase the stored proofs of an identity, along with their attestations, submitters and
+        /// expiries. Only the nullifier of the identity is kept, so that it cannot be claimed
+        /// again as new.
+        ///
+        /// At most `MaxErasedProofs` proofs are erased per call; the `IdentityErased` event tells
+        /// whether another call is needed.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be signed).
+        /// - `proof`: A fresh proof of the identity, which is verified but not stored.
+        ///
+        /// # Errors
+        /// - `ProofAlreadyExists`: If `proof` is already stored; a dedicated proof is required.
+        /// - Any error of `store_zk_proof` about verifying `proof`.
+        #[pallet::weight(Pallet::<T>::erase_weight(T::MaxErasedProofs::get()))]
+        pub fn erase_identity_data(origin: OriginFor<T>, proof: Vec<u8>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
 
-    let x_fq = Fq::deserialize_compressed_unchecked(&*x_bytes).unwrap();
-    let y_fq = Fq::deserialize_compressed_unchecked(&*y_bytes).unwrap();
+            ensure!(proof.len() <= Parameters::<T>::get().max_json_length as usize, Error::<T>::ZkProofTooLarge);
 
-    return G1Affine::new(x_fq, y_fq);
-}
+            use frame_support::sp_runtime::traits::Hash;
+            let proof_hash = T::Hashing::hash(&proof);
+            ensure!(!ZkProofSubmitter::<T>::contains_key(proof_hash), Error::<T>::ProofAlreadyExists);
 
-/// Parse a G2 point from its JSON representation.
-fn parse_g2_point(point: G2Point) -> G2Affine {
-    let x_c0_bytes = decode_base64(point.x.c0);
-    let x_c1_bytes = decode_base64(point.x.c1);
-    let y_c0_bytes = decode_base64(point.y.c0);
-    let y_c1_bytes = decode_base64(point.y.c1);
+            let verified = Self::verify_zk_proof(&proof)?;
+            Self::consume_nonce(&verified, proof_hash);
+            let nullifier = Self::identity_nullifier(&verified);
 
-    let x_c0_fq = Fq::deserialize_compressed_unchecked(&*x_c0_bytes).unwrap();
-    let x_c1_fq = Fq::deserialize_compressed_unchecked(&*x_c1_bytes).unwrap();
-    let y_c0_fq = Fq::deserialize_compressed_unchecked(&*y_c0_bytes).unwrap();
-    let y_c1_fq = Fq::deserialize_compressed_unchecked(&*y_c1_bytes).unwrap();
+            let mut erased = 0;
+            for (hash, ()) in IdentityProofs::<T>::drain_prefix(nullifier).take(T::MaxErasedProofs::get() as usize) {
+                Self::remove_proof_data(hash);
+                ProofMetadata::<T>::remove(hash);
+                Self::untag_proof(hash);
+                if let Some(client_id) = ProofNamespaces::<T>::take(hash) {
+                    NamespaceProofs::<T>::remove(&client_id, hash);
+                    NamespaceUsages::<T>::mutate(&client_id, |usage| usage.stored_proofs.saturating_dec());
+                }
+                Self::remove_attestation(hash);
+                PredicateAttestations::<T>::remove(hash);
+                if let Some(submitter) = ZkProofSubmitter::<T>::take(hash) {
+                    AccountProofs::<T>::mutate_exists(submitter, |latest| {
+                        if *latest == Some(hash) {
+                            *latest = None;
+                        }
+                    });
+                }
+                ZkProofExpiry::<T>::remove(hash);
+                erased += 1;
+            }
+            let complete = IdentityProofs::<T>::iter_prefix(nullifier).next().is_none();
 
-    let x_fq2 = Fq2::new(x_c0_fq, x_c1_fq);
-    let y_fq2 = Fq2::new(y_c0_fq, y_c1_fq);
+            Self::deposit_event(Event::IdentityErased { nullifier, who, erased, complete });
 
-    return G2Affine::new(x_fq2, y_fq2);
-}
+            Ok(Some(Self::erase_weight(erased)).into())
+        }
 
-/// Parse a verifying key from its JSON representation.
-fn parse_verifying_key(json_vk: String) -> PreparedVerifyingKey<Bls12_381> {
-    let vk_bytes = decode_base64(json_vk);
-    PreparedVerifyingKey::<Bls12_381>::deserialize_compressed_unchecked(&*vk_bytes).unwrap_or_else(|e| {
-        log::error!("vk error prepare: {:?}", e);
-        PreparedVerifyingKey::<Bls12_381>::default()
-    })
-}
+        /// Retrieve a page of the stored ZK proofs.
+        ///
+        /// Emits a `ZkProofRetrieved` event per proof, followed by `ZkProofsRetrieved` carrying the
+        /// cursor to pass as `start_key` to fetch the next page.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be signed).
+        /// - `start_key`: Resume after this proof; `None` starts from the beginning.
+        /// - `limit`: The maximum number of proofs to retrieve.
+        #[pallet::weight(Pallet::<T>::retrieve_weight(*limit))]
+        pub fn retrieve_all_zk_proofs(
+            origin: OriginFor<T>,
+            start_key: Option<T::Hash>,
+            limit: u32,
+        ) -> DispatchResultWithPostInfo {
+            // Ensure the origin of the call is signed.
+            let _who = ensure_signed(origin)?;
 
-/// Parse a proof from its JSON representation.
-fn parse_proof(proof: JsonProof) -> Proof<Bls12_381> {
-    let a = parse_g1_point(proof.a);
-    let b = parse_g2_point(proof.b);
-    let c = parse_g1_point(proof.c);
-    return Proof { a, b, c };
-}
+            let mut proofs = match start_key {
+                Some(key) => ZkProofData::<T>::iter_from(ZkProofData::<T>::hashed_key_for(key)),
+                None => ZkProofData::<T>::iter(),
+            };
 
-/// Parse public inputs from a base64-encoded string.
-fn parse_public_inputs(public_hash: String) -> Fp256<MontBackend<FrConfig, 4>> {
-    let public_hash_bytes = decode_base64(public_hash);
-    return Fr::from_random_bytes(public_hash_bytes.as_slice()).unwrap_or_default();
-}
+            // Emit an event for each stored ZK proof of the page.
+            let emit_payloads = T::EmitProofPayloads::get();
+            let mut retrieved = 0;
+            let mut last = start_key;
+            for (proof_hash, zk_proof) in proofs.by_ref().take(limit as usize) {
+                Self::deposit_indexed_event(
+                    &[proof_hash],
+                    Event::ZkProofRetrieved {
+                        hash: proof_hash,
+                        who: ZkProofSubmitter::<T>::get(proof_hash),
+                        json: emit_payloads.then(|| zk_proof.into_inner()),
+                    },
+                );
+                retrieved += 1;
+                last = Some(proof_hash);
+            }
 
-/// Verify a proof.
-fn verify_proof(json_proof: JsonProof, public_inputs: &[Fr]) -> bool {
-    let vk = parse_verifying_key(json_proof.verifying_key.clone());
-    let proof = parse_proof(json_proof);
-    Groth16::<Bls12_381>::verify_proof(&vk, &proof, public_inputs).unwrap_or(true)
-}
+            let next = if proofs.next().is_some() { last } else { None };
+            Self::deposit_event(Event::ZkProofsRetrieved { next });
 
-/// Verify a ZK proof from its raw data.
-fn pallet_verify_proof(proof_data: &[u8]) -> bool {
-    return match core::str::from_utf8(proof_data) {
-        Ok(proof_str) => {
-            let json_proof: JsonProof = from_str(proof_str).unwrap();
-            let public_inputs = parse_public_inputs(json_proof.public_hash.clone());
-
-            let jwt_token = json_proof.jwt_token.clone();
-            return if verify_proof(json_proof, &[public_inputs]) {
-                return if validate_jwt(jwt_token) {
-                    true
-                } else {
-                    error!("FAIL VERIFICATION TOKEN JWT");
-                    false
-                }
-            } else {
-                error!("FAIL VERIFICATION ZK PROOF");
-                false
-            }
-        }
-        Err(e) => {
-            log::error!("Invalid UTF-8 in zk proof data: {:?}", e);
-            false
+            Ok(Some(Self::retrieve_weight(retrieved)).into())
         }
-    };
-}
-
-/// Struct representing a JSON proof.
-#[derive(Serialize, Deserialize, Debug)]
-struct JsonProof {
-    a: G1Point,
-    b: G2Point,
-    c: G1Point,
-    public_hash: String,
-    verifying_key: String,
-    jwt_token: String,
-}
 
-/// Struct representing a G1 point.
-#[derive(Serialize, Deserialize, Debug)]
-struct G1Point {
-    x: String,
-    y: String,
-}
+        /// Add an account to the oracle set.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `oracle`: The account allowed to submit JWKS updates.
+        ///
+        /// # Errors
+        /// - `OracleAlreadyRegistered`: If the account is already an oracle.
+        #[pallet::weight({10_000})]
+        pub fn add_oracle(origin: OriginFor<T>, oracle: T::AccountId) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
 
-/// Struct representing a G2 point.
-#[derive(Serialize, Deserialize, Debug)]
-struct G2Point {
-    x: G2Coordinates,
-    y: G2Coordinates,
-}
+            ensure!(!Oracles::<T>::contains_key(&oracle), Error::<T>::OracleAlreadyRegistered);
+            Oracles::<T>::insert(&oracle, ());
 
-/// Struct representing the coordinates of a G2 point.
-#[derive(Serialize, Deserialize, Debug)]
-struct G2Coordinates {
-    c0: String,
-    c1: String,
-}
+            Self::deposit_event(Event::OracleAdded { oracle });
 
-/// Struct representing claims in a JWT.
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-    iss: String,
-    // L'émetteur du token
-    azp: String,
-    // L'ID client autorisé
-    aud: String,
-    // Le destinataire du token, doit correspondre à l'ID client
-    sub: String,
-    // L'identifiant unique de l'utilisateur
-    nonce: String,
-    // Une chaîne utilisée pour associer une session client à un ID Token
-    nbf: i64,
-    // La date/heure avant laquelle le token n'est pas accepté (Not Before)
-    iat: i64,
-    // L'heure d'émission du token (Issued At)
-    exp: i64,
-    // L'heure d'expiration du token (Expire)
-    jti: String,
-    // Un identifiant unique pour le token (JWT ID)
-    email: String,
-}
+            Ok(())
+        }
 
-/// Struct representing Google's JSON Web Key Set (JWKS).
-#[derive(Debug, Deserialize)]
-struct GoogleJwks {
-    keys: Vec<Jwk>,
-}
+        /// Remove an account from the oracle set.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `oracle`: The account to remove.
+        ///
+        /// # Errors
+        /// - `UnknownOracle`: If the account is not an oracle.
+        #[pallet::weight({10_000})]
+        pub fn remove_oracle(origin: OriginFor<T>, oracle: T::AccountId) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
 
-/// Struct representing a JSON Web Key (JWK).
-#[derive(Debug, Deserialize)]
-struct Jwk {
-    #[allow(dead_code)]
-    n: String,
-    #[serde(rename = "use")]
-    #[allow(dead_code)]
-    k_use: String,
-    #[allow(dead_code)]
-    kid: String,
-    #[allow(dead_code)]
-    alg: String,
-    #[allow(dead_code)]
-    kty: String,
-    #[allow(dead_code)]
-    e: String,
-}
+            ensure!(Oracles::<T>::contains_key(&oracle), Error::<T>::UnknownOracle);
+            Oracles::<T>::remove(&oracle);
 
-/// Decode a base64 URL string into a vector of bytes.
-fn base64_url_decode(input: &str) -> Result<Vec<u8>, base64::DecodeError> {
-    let mut input = input.replace('-', "+").replace('_', "/");
-    while input.len() % 4 != 0 {
-        input.push('=');
-    }
+            Self::deposit_event(Event::OracleRemoved { oracle });
 
-    #[allow(deprecated)]
-    base64::decode(&input)
-}
+            Ok(())
+        }
 
-/// Validate a JWT.
-fn validate_jwt(token: String) -> bool {
-    let jwks: GoogleJwks = get_google_jwks();
+        /// Vote for the signing keys of a provider.
+        ///
+        /// The keys replace the provider's JWKS once `JwksQuorum` distinct oracles submitted the
+        /// same keyset within `JwksSubmissionWindow` blocks of its first submission. The keyset
+        /// then expires at the earliest `expires_at` reported by the approving oracles.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be a registered oracle).
+        /// - `provider`: The issuer the keys belong to.
+        /// - `keys`: The provider's current JWKS.
+        /// - `expires_at`: When (unix seconds) the provider's cache headers say the keys expire.
+        ///
+        /// # Errors
+        /// - `NotOracle`: If the signer is not a registered oracle.
+        /// - `UnknownProvider`: If the provider is not registered.
+        /// - `DuplicateSubmission`: If the oracle already voted for this keyset.
+        #[pallet::weight({10_000})]
+        pub fn submit_jwks(
+            origin: OriginFor<T>,
+            provider: IssuerId,
+            keys: BoundedVec<JwkRecord, T::MaxJwksKeys>,
+            expires_at: Option<u64>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Oracles::<T>::contains_key(&who), Error::<T>::NotOracle);
+            ensure!(Providers::<T>::contains_key(&provider), Error::<T>::UnknownProvider);
 
-    let parts: Vec<&str> = token.split('.').collect();
-    if parts.len() != 3 {
-        return false;
-    }
+            use frame_support::sp_runtime::traits::Hash;
+            let keyset_hash = T::Hashing::hash_of(&keys);
+            let now = frame_system::Pallet::<T>::block_number();
 
-    let header_part = parts[0];
-    let _payload_part = parts[1];
-    let _signature_part = parts[2];
+            // Start a fresh candidate if none exists or the previous one ran out of time.
+            let mut pending = PendingJwks::<T>::get(&provider, keyset_hash)
+                .filter(|p| now.saturating_sub(p.first_seen) <= T::JwksSubmissionWindow::get())
+                .unwrap_or_else(|| PendingKeyset {
+                    keys,
+                    first_seen: now,
+                    expires_at: None,
+                    approvals: Default::default(),
+                });
 
-    let header_bytes = match base64_url_decode(header_part) {
-        Ok(bytes) => bytes,
-        Err(_) => return false,
-    };
+            ensure!(!pending.approvals.contains(&who), Error::<T>::DuplicateSubmission);
+            pending.expires_at = match (pending.expires_at, expires_at) {
+                (Some(current), Some(reported)) => Some(current.min(reported)),
+                (current, reported) => current.or(reported),
+            };
+            // Votes of oracles removed in the meantime no longer count.
+            pending.approvals.retain(|oracle| Oracles::<T>::contains_key(oracle));
+            pending.approvals.try_push(who.clone()).map_err(|_| Error::<T>::TooManyApprovals)?;
 
-    let header_str = match core::str::from_utf8(&header_bytes) {
-        Ok(s) => s,
-        Err(_) => return false,
-    };
+            let approvals = pending.approvals.len() as u32;
+            if approvals >= T::JwksQuorum::get() {
+                Self::set_jwks(provider, pending.keys, pending.expires_at);
+            } else {
+                PendingJwks::<T>::insert(&provider, keyset_hash, pending);
 
-    let header: serde_json::Value = match from_str(header_str) {
-        Ok(h) => h,
-        Err(_) => return false,
-    };
+                Self::deposit_event(Event::JwksSubmitted { provider, keyset_hash, who, approvals });
+            }
 
-    let kid = match header.get("kid") {
-        Some(k) => k.as_str().unwrap_or(""),
-        None => return false,
-    };
+            Ok(())
+        }
 
-    let _jwk = match jwks.keys.iter().find(|k| k.kid == kid) {
-        Some(jwk) => jwk,
-        None => return false,
-    };
+        /// Register an OIDC provider whose keys the oracles should track.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `OnboardingOrigin`.
+        /// - `provider`: The issuer, as it appears in the `iss` claim.
+        /// - `jwks_uri`: The URL the provider serves its JWKS from.
+        /// - `activate_at`: The block to register the provider in, to announce it ahead of time.
+        ///   Registered immediately if `None`.
+        ///
+        /// # Errors
+        /// - `ProviderAlreadyRegistered`: If the provider is already registered.
+        /// - `ActivationNotInFuture`: If `activate_at` is not a future block.
+        /// - `TooManyScheduledActivations`: If `activate_at` has no room for another activation.
+        #[pallet::weight({10_000})]
+        pub fn add_provider(
+            origin: OriginFor<T>,
+            provider: IssuerId,
+            jwks_uri: BoundedVec<u8, ConstU32<MAX_URI_LENGTH>>,
+            activate_at: Option<BlockNumberFor<T>>,
+        ) -> DispatchResult {
+            T::OnboardingOrigin::ensure_origin(origin)?;
+
+            ensure!(!Providers::<T>::contains_key(&provider), Error::<T>::ProviderAlreadyRegistered);
+
+            Self::activate_at(Activation::Provider { provider, info: ProviderInfo { jwks_uri } }, activate_at)
+        }
+
+        /// Remove a provider along with its keys, so its tokens are no longer accepted.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `provider`: The issuer to remove.
+        ///
+        /// # Errors
+        /// - `UnknownProvider`: If the provider is not registered.
+        #[pallet::weight({10_000})]
+        pub fn remove_provider(origin: OriginFor<T>, provider: IssuerId) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Providers::<T>::contains_key(&provider), Error::<T>::UnknownProvider);
+            Providers::<T>::remove(&provider);
+            DisabledProviders::<T>::remove(&provider);
+            RequiredClaims::<T>::remove(&provider);
+            Self::write_jwks(&provider, None);
+            JwksExpiry::<T>::remove(&provider);
+            let _ = PendingJwks::<T>::clear_prefix(&provider, u32::MAX, None);
+
+            Self::deposit_event(Event::ProviderRemoved { provider });
+
+            Ok(())
+        }
+
+        /// Insert a key for a provider, replacing any key with the same id.
+        ///
+        /// Break-glass mechanism for emergency key rotations: the key takes effect immediately,
+        /// without waiting for the oracles. The keyset no longer expires until the oracles replace
+        /// it again.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `OnboardingOrigin`.
+        /// - `provider`: The issuer the key belongs to.
+        /// - `jwk`: The key to insert.
+        ///
+        /// # Errors
+        /// - `UnknownProvider`: If the provider is not registered.
+        /// - `TooManyKeys`: If the provider already has `MaxJwksKeys` other keys.
+        #[pallet::weight({10_000})]
+        pub fn force_set_jwk(origin: OriginFor<T>, provider: IssuerId, jwk: JwkRecord) -> DispatchResult {
+            T::OnboardingOrigin::ensure_origin(origin)?;
+            ensure!(Providers::<T>::contains_key(&provider), Error::<T>::UnknownProvider);
+
+            let kid = jwk.kid.clone();
+            let mut keys = Jwks::<T>::get(&provider).unwrap_or_default();
+            keys.retain(|key| key.kid != jwk.kid);
+            keys.try_push(jwk).map_err(|_| Error::<T>::TooManyKeys)?;
+            Self::write_jwks(&provider, Some(keys));
+            JwksExpiry::<T>::remove(&provider);
+
+            Self::deposit_event(Event::JwkForceSet { provider, kid });
+
+            Ok(())
+        }
+
+        /// Remove a key of a provider, bypassing the oracles.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `provider`: The issuer the key belongs to.
+        /// - `kid`: The id of the key to remove.
+        ///
+        /// # Errors
+        /// - `UnknownKid`: If the provider has no key with this id.
+        #[pallet::weight({10_000})]
+        pub fn force_remove_jwk(
+            origin: OriginFor<T>,
+            provider: IssuerId,
+            kid: BoundedVec<u8, ConstU32<MAX_KID_LENGTH>>,
+        ) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            let mut keys = Jwks::<T>::get(&provider).ok_or(Error::<T>::UnknownKid)?;
+            let before = keys.len();
+            keys.retain(|key| key.kid != kid);
+            ensure!(keys.len() < before, Error::<T>::UnknownKid);
+            Self::write_jwks(&provider, Some(keys).filter(|keys| !keys.is_empty()));
+
+            Self::deposit_event(Event::JwkForceRemoved { provider, kid });
+
+            Ok(())
+        }
+
+        /// Approve a verifying key, so that proofs embedding it are verified.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `OnboardingOrigin`.
+        /// - `hash`: The blake2-256 hash of the base64 verifying key, as embedded in envelopes.
+        /// - `activate_at`: The block to approve the key in, so that circuit rollouts can be
+        ///   announced ahead of time. Approved immediately if `None`.
+        ///
+        /// # Errors
+        /// - `VerifyingKeyAlreadyApproved`: If the key is already approved.
+        /// - `ActivationNotInFuture`: If `activate_at` is not a future block.
+        /// - `TooManyScheduledActivations`: If `activate_at` has no room for another activation.
+        #[pallet::weight({10_000})]
+        pub fn approve_verifying_key(
+            origin: OriginFor<T>,
+            hash: VkHash,
+            activate_at: Option<BlockNumberFor<T>>,
+        ) -> DispatchResult {
+            T::OnboardingOrigin::ensure_origin(origin)?;
+
+            ensure!(!ApprovedVerifyingKeys::<T>::contains_key(hash), Error::<T>::VerifyingKeyAlreadyApproved);
+
+            Self::activate_at(Activation::VerifyingKey(hash), activate_at)
+        }
+
+        /// Revoke a verifying key, so that proofs embedding it are rejected.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `hash`: The hash of the key, as passed to `approve_verifying_key`.
+        ///
+        /// # Errors
+        /// - `UnknownVerifyingKey`: If the key is not approved.
+        #[pallet::weight({10_000})]
+        pub fn revoke_verifying_key(origin: OriginFor<T>, hash: VkHash) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(ApprovedVerifyingKeys::<T>::contains_key(hash), Error::<T>::UnknownVerifyingKey);
+            ApprovedVerifyingKeys::<T>::remove(hash);
+
+            Self::deposit_event(Event::VerifyingKeyRevoked { hash });
+
+            Ok(())
+        }
+
+        /// Update the tunable limits.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `parameters`: The new limits.
+        ///
+        /// # Errors
+        /// - `InvalidParameters`: If `max_json_length` exceeds `Config::MaxJsonLength`, or
+        ///   `max_verifications_per_block` exceeds `Config::MaxVerificationsPerBlock`.
+        #[pallet::weight({10_000})]
+        pub fn set_config(origin: OriginFor<T>, parameters: ProofParameters) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+            ensure!(parameters.max_json_length <= T::MaxJsonLength::get(), Error::<T>::InvalidParameters);
+            ensure!(
+                parameters.max_verifications_per_block <= T::MaxVerificationsPerBlock::get(),
+                Error::<T>::InvalidParameters
+            );
+
+            Parameters::<T>::put(&parameters);
+            Self::deposit_event(Event::ParametersUpdated { parameters });
+
+            Ok(())
+        }
+
+        /// Store ZK proof data given as a typed submission rather than JSON, so that its fields
+        /// are described in the metadata. It is stored as the equivalent JSON envelope, under the
+        /// hash of that envelope.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be signed).
+        /// - `submission`: The proof and the token it was made for.
+        ///
+        /// # Errors
+        /// - Any error of `store_zk_proof`.
+        #[pallet::weight(Pallet::<T>::store_weight())]
+        pub fn submit_proof(origin: OriginFor<T>, submission: ProofSubmission) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let verified = Self::do_store_zk_proof(who, submission.to_envelope())?;
+            Self::charge_namespace_login(&verified)?;
+            Ok(())
+        }
+
+        /// Set the claims the tokens of a provider must carry beyond the standard ones, possibly
+        /// nested, such as a tenant id or roles. The hashes of their values are part of the claims
+        /// digest handed to `OnProofVerified`, for runtimes to gate features on them.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `provider`: The issuer whose tokens must carry the claims.
+        /// - `requirements`: The required claims, replacing the current ones. None if empty.
+        ///
+        /// # Errors
+        /// - `UnknownProvider`: If the provider is not registered.
+        /// - `InvalidClaimPath`: If a path is not a JSON pointer to a claim.
+        #[pallet::weight({10_000})]
+        pub fn set_required_claims(
+            origin: OriginFor<T>,
+            provider: IssuerId,
+            requirements: BoundedVec<ClaimRequirement, ConstU32<MAX_REQUIRED_CLAIMS>>,
+        ) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+            ensure!(Providers::<T>::contains_key(&provider), Error::<T>::UnknownProvider);
+
+            ensure!(requirements.iter().all(|r| Self::is_claim_path(&r.path)), Error::<T>::InvalidClaimPath);
+            if requirements.is_empty() {
+                RequiredClaims::<T>::remove(&provider);
+            } else {
+                RequiredClaims::<T>::insert(&provider, requirements);
+            }
+
+            Self::deposit_event(Event::RequiredClaimsSet { provider });
+
+            Ok(())
+        }
+
+        /// Register a predicate over claims, such as the domain of the email, that proofs of a
+        /// predicate circuit version may attest to without disclosing the claims.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `predicate`: The predicate, registered under `ClaimPredicate::id`.
+        ///
+        /// # Errors
+        /// - `InvalidClaimPath`: If the predicate compares a claim whose path is not a JSON pointer.
+        #[pallet::weight({10_000})]
+        pub fn register_predicate(origin: OriginFor<T>, predicate: ClaimPredicate) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+            if let ClaimPredicate::ClaimEquals { path, .. } = &predicate {
+                ensure!(
+                    path.first() == Some(&b'/') && core::str::from_utf8(path).is_ok(),
+                    Error::<T>::InvalidClaimPath
+                );
+            }
+
+            let id = predicate.id();
+            Predicates::<T>::insert(id, &predicate);
+
+            Self::deposit_event(Event::PredicateRegistered { id, predicate });
+
+            Ok(())
+        }
+
+        /// Remove a predicate, so that proofs attesting to it are rejected. Attestations already
+        /// stored are kept.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `id`: The id of the predicate.
+        ///
+        /// # Errors
+        /// - `UnknownPredicate`: If the predicate is not registered.
+        #[pallet::weight({10_000})]
+        pub fn remove_predicate(origin: OriginFor<T>, id: PredicateId) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Predicates::<T>::contains_key(id), Error::<T>::UnknownPredicate);
+            Predicates::<T>::remove(id);
+
+            Self::deposit_event(Event::PredicateRemoved { id });
+
+            Ok(())
+        }
+
+        /// Replace the keys of providers with the ones the block author fetched, as the JWKS
+        /// inherent. Included by `ProvideInherent::create_inherent` if a keyset changed.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `None`.
+        /// - `updates`: The new keysets.
+        ///
+        /// # Errors
+        /// - `AuthoredJwksDisabled`: If `AuthoredJwks` is not set.
+        /// - `AuthoredJwksAlreadySet`: If the block already includes the inherent.
+        /// - `UnknownProvider`: If a provider is not registered.
+        /// - `TooManyKeys`: If a keyset has more than `MaxJwksKeys` keys.
+        #[pallet::weight((Weight::from_parts(10_000, 0), DispatchClass::Mandatory))]
+        pub fn set_authored_jwks(origin: OriginFor<T>, updates: Vec<JwksUpdate>) -> DispatchResult {
+            ensure_none(origin)?;
+            ensure!(T::AuthoredJwks::get(), Error::<T>::AuthoredJwksDisabled);
+            ensure!(!AuthoredJwksSet::<T>::get(), Error::<T>::AuthoredJwksAlreadySet);
+            AuthoredJwksSet::<T>::put(true);
+
+            for JwksUpdate { provider, keys, expires_at } in updates {
+                ensure!(Providers::<T>::contains_key(&provider), Error::<T>::UnknownProvider);
+                let keys = BoundedVec::try_from(keys).map_err(|_| Error::<T>::TooManyKeys)?;
+                Self::set_jwks(provider, keys, expires_at);
+            }
+
+            Ok(())
+        }
+
+        /// Register the caller as a device key of the identity a proof attests to, such as the
+        /// ephemeral key of a phone or a laptop. The proof is stored like with `store_zk_proof`, and
+        /// the key expires along with it; the other keys of the identity are kept, so that each
+        /// device logs in on its own.
+        ///
+        /// # Parameters
+        /// - `origin`: The key to register (must be signed).
+        /// - `proof`: A proof whose token's nonce ends with the hex-encoded key, as in
+        ///   `<block number>-<block hash>-<key>`.
+        /// - `label`: The label of the device.
+        ///
+        /// # Errors
+        /// - `KeyNotBound`: If the token's nonce does not end with the caller.
+        /// - `KeyRegisteredElsewhere`: If the caller is a device key of another identity.
+        /// - `TooManyDeviceKeys`: If the identity has `MaxDeviceKeys` unexpired keys already.
+        /// - Any error of `store_zk_proof`.
+        #[pallet::weight(Pallet::<T>::store_weight() + Pallet::<T>::device_keys_weight(T::MaxDeviceKeys::get()))]
+        pub fn register_key(origin: OriginFor<T>, proof: Vec<u8>, label: DeviceLabel) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let verified = Self::do_store_zk_proof(who.clone(), proof)?;
+            Self::charge_namespace_login(&verified)?;
+            let bound = verified.claims.nonce.as_deref().map_or(false, |nonce| Self::nonce_binds(nonce, &who));
+            ensure!(bound, Error::<T>::KeyNotBound);
+
+            let nullifier = Self::identity_nullifier(&verified);
+            if let Some(registered) = KeyIdentities::<T>::get(&who) {
+                ensure!(registered == nullifier, Error::<T>::KeyRegisteredElsewhere);
+            }
+
+            // Expired keys give their slot up.
+            let now = T::UnixTime::now().as_secs();
+            let mut active = 0;
+            for (key, device) in DeviceKeys::<T>::iter_prefix(nullifier).collect::<Vec<_>>() {
+                if device.expires_at <= now {
+                    Self::remove_device_key(nullifier, &key);
+                } else if key != who {
+                    active += 1;
+                }
+            }
+            ensure!(active < T::MaxDeviceKeys::get(), Error::<T>::TooManyDeviceKeys);
+
+            let expires_at = now.saturating_add(Parameters::<T>::get().proof_lifetime);
+            let device = DeviceKey {
+                label: label.clone(),
+                registered_at: frame_system::Pallet::<T>::block_number(),
+                expires_at,
+            };
+            if !DeviceKeys::<T>::contains_key(nullifier, &who) {
+                StorageUsage::<T>::mutate(|usage| usage.device_keys.saturating_inc());
+            }
+            DeviceKeys::<T>::insert(nullifier, &who, device);
+            KeyIdentities::<T>::insert(&who, nullifier);
+
+            Self::deposit_event(Event::DeviceKeyRegistered { nullifier, key: who, label, expires_at });
+
+            Ok(())
+        }
+
+        /// Revoke a device key of the caller's identity, e.g. of a lost device. The proofs stored
+        /// by the key no longer attest to it.
+        ///
+        /// # Parameters
+        /// - `origin`: An unexpired device key of the identity (must be signed).
+        /// - `key`: The key to revoke, possibly the caller.
+        ///
+        /// # Errors
+        /// - `UnknownDeviceKey`: If the caller or `key` is not a device key of the identity.
+        /// - `DeviceKeyExpired`: If the device key of the caller expired.
+        #[pallet::weight(Pallet::<T>::device_keys_weight(1))]
+        pub fn revoke_key(origin: OriginFor<T>, key: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let nullifier = Self::active_identity(&who)?;
+            ensure!(DeviceKeys::<T>::contains_key(nullifier, &key), Error::<T>::UnknownDeviceKey);
+
+            Self::remove_device_key(nullifier, &key);
+            Self::deposit_event(Event::DeviceKeyRevoked { nullifier, key, by: who });
+
+            Ok(())
+        }
+
+        /// Revoke every device key of the caller's identity, the caller included, e.g. after the
+        /// account at the provider was compromised.
+        ///
+        /// # Parameters
+        /// - `origin`: An unexpired device key of the identity (must be signed).
+        ///
+        /// # Errors
+        /// - `UnknownDeviceKey`: If the caller is not a device key.
+        /// - `DeviceKeyExpired`: If the device key of the caller expired.
+        #[pallet::weight(Pallet::<T>::device_keys_weight(T::MaxDeviceKeys::get()))]
+        pub fn revoke_all(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let nullifier = Self::active_identity(&who)?;
+
+            let keys: Vec<_> = DeviceKeys::<T>::iter_key_prefix(nullifier).collect();
+            for key in &keys {
+                Self::remove_device_key(nullifier, key);
+            }
+            Self::deposit_event(Event::AllDeviceKeysRevoked { nullifier, by: who, revoked: keys.len() as u32 });
+
+            Ok(())
+        }
+
+        /// Set the spending limit of a device key of the caller's identity, e.g. of a browser
+        /// session, or lift it with `None`. Transactions signed by the key are then checked
+        /// against the limit by `CheckSpendingLimit`. Keys with a limit may not set limits, so a
+        /// compromised key cannot lift its own.
+        ///
+        /// # Parameters
+        /// - `origin`: An unexpired device key of the identity without a limit (must be signed).
+        /// - `key`: The key to limit, possibly the caller.
+        /// - `limit`: The limit, replacing the current one.
+        ///
+        /// # Errors
+        /// - `UnknownDeviceKey`: If the caller or `key` is not a device key of the identity.
+        /// - `DeviceKeyExpired`: If the device key of the caller expired.
+        /// - `SpendingLimited`: If the caller has a spending limit.
+        /// - `InvalidSpendingLimit`: If the period of the limit is zero.
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 2))]
+        pub fn set_spending_limit(
+            origin: OriginFor<T>,
+            key: T::AccountId,
+            limit: Option<SpendingLimit<BlockNumberFor<T>>>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let nullifier = Self::active_identity(&who)?;
+            ensure!(!SpendingLimits::<T>::contains_key(&who), Error::<T>::SpendingLimited);
+            ensure!(DeviceKeys::<T>::contains_key(nullifier, &key), Error::<T>::UnknownDeviceKey);
+
+            match &limit {
+                Some(limit) => {
+                    ensure!(!limit.period.is_zero(), Error::<T>::InvalidSpendingLimit);
+                    SpendingLimits::<T>::insert(&key, limit);
+                }
+                None => SpendingLimits::<T>::remove(&key),
+            }
+            Spent::<T>::remove(&key);
+
+            Self::deposit_event(Event::SpendingLimitSet { key, by: who, limit });
+
+            Ok(())
+        }
+
+        /// Lock an identity at once, e.g. when a device or the account at the provider is
+        /// compromised: its device keys are revoked, the sessions opened with it end, its proofs
+        /// no longer attest to it, and the accounts that logged in with it are frozen by
+        /// `CheckSpendingLimit`. Locking an unlocking identity cancels the unlock.
+        ///
+        /// # Parameters
+        /// - `origin`: Any account (must be signed), e.g. of a fresh device.
+        /// - `proof`: A fresh proof of the identity, stored like with `store_zk_proof`.
+        ///
+        /// # Errors
+        /// - Any error of `store_zk_proof`.
+        #[pallet::weight(Pallet::<T>::store_weight() + Pallet::<T>::device_keys_weight(T::MaxDeviceKeys::get()))]
+        pub fn emergency_lock(origin: OriginFor<T>, proof: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let verified = Self::do_store_zk_proof(who.clone(), proof)?;
+            let nullifier = Self::identity_nullifier(&verified);
+
+            // The accounts of the keys keep their proofs, so that they stay frozen.
+            let mut revoked = 0;
+            for key in DeviceKeys::<T>::drain_prefix(nullifier).map(|(key, _)| key) {
+                Self::forget_device_key(&key);
+                revoked += 1;
+            }
+            StorageUsage::<T>::mutate(|usage| usage.device_keys.saturating_reduce(revoked.into()));
+            let lock = IdentityLock { locked_at: frame_system::Pallet::<T>::block_number(), unlocks_at: None };
+            IdentityLocks::<T>::insert(nullifier, lock);
+            T::OnIdentityLocked::on_identity_locked(&nullifier);
+
+            Self::deposit_event(Event::IdentityLocked { nullifier, who, revoked });
+
+            Ok(())
+        }
+
+        /// Request the unlock of a locked identity, which takes effect `UnlockDelay` blocks later
+        /// unless the identity is locked again in the meantime.
+        ///
+        /// # Parameters
+        /// - `origin`: Any account (must be signed).
+        /// - `proof`: A fresh proof of the identity, stored like with `store_zk_proof`.
+        ///
+        /// # Errors
+        /// - `IdentityNotLocked`: If the identity is not locked.
+        /// - `UnlockAlreadyRequested`: If the identity is unlocking already.
+        /// - Any error of `store_zk_proof`.
+        #[pallet::weight(Pallet::<T>::store_weight() + T::DbWeight::get().reads_writes(1, 1))]
+        pub fn unlock_identity(origin: OriginFor<T>, proof: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let verified = Self::do_store_zk_proof(who.clone(), proof)?;
+            let nullifier = Self::identity_nullifier(&verified);
+
+            ensure!(Self::is_locked(&nullifier), Error::<T>::IdentityNotLocked);
+            let mut lock = IdentityLocks::<T>::get(nullifier).ok_or(Error::<T>::IdentityNotLocked)?;
+            ensure!(lock.unlocks_at.is_none(), Error::<T>::UnlockAlreadyRequested);
+            let unlocks_at = frame_system::Pallet::<T>::block_number().saturating_add(T::UnlockDelay::get());
+            lock.unlocks_at = Some(unlocks_at);
+            IdentityLocks::<T>::insert(nullifier, lock);
+
+            Self::deposit_event(Event::UnlockRequested { nullifier, who, unlocks_at });
+
+            Ok(())
+        }
+
+        /// Replace the tags of a stored proof, telling what it was submitted for, e.g. `login`,
+        /// `recovery` or `kyc`, for dApps to list the proofs of a purpose with `proofs_with_tag`.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the submitter of the proof).
+        /// - `proof_hash`: The hash of the proof.
+        /// - `tags`: The tags of the proof; none removes them.
+        ///
+        /// # Errors
+        /// - `UnknownProof`: If no proof is stored under `proof_hash`.
+        /// - `NotSubmitter`: If the proof was stored by another account.
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 1 + 2 * MAX_PROOF_TAGS as u64))]
+        pub fn tag_proof(origin: OriginFor<T>, proof_hash: T::Hash, tags: ProofTags) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let submitter = ZkProofSubmitter::<T>::get(proof_hash).ok_or(Error::<T>::UnknownProof)?;
+            ensure!(submitter == who, Error::<T>::NotSubmitter);
+
+            Self::untag_proof(proof_hash);
+            for tag in tags.iter() {
+                TaggedProofs::<T>::insert(tag, proof_hash, ());
+            }
+            if !tags.is_empty() {
+                ProofTagsOf::<T>::insert(proof_hash, &tags);
+            }
+
+            Self::deposit_indexed_event(&[proof_hash], Event::ProofTagged { hash: proof_hash, tags });
+
+            Ok(())
+        }
+
+        /// Register a dApp as a namespace. The identities logging in with tokens issued to the
+        /// dApp, whose `aud` claim is `client_id`, get nullifiers of their own in it, so that they
+        /// neither collide with nor can be linked to their identities in other dApps.
+        ///
+        /// Identities which logged into the dApp before are seen as new ones once it is registered.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `client_id`: The OAuth client id of the dApp.
+        /// - `owner`: The account administering the namespace on behalf of the dApp.
+        ///
+        /// # Errors
+        /// - `NamespaceAlreadyRegistered`: If the dApp is registered already.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn register_namespace(origin: OriginFor<T>, client_id: ClientId, owner: T::AccountId) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(!Namespaces::<T>::contains_key(&client_id), Error::<T>::NamespaceAlreadyRegistered);
+            Namespaces::<T>::insert(&client_id, Namespace { owner: owner.clone(), paused: false });
+
+            Self::deposit_event(Event::NamespaceRegistered { client_id, owner });
+
+            Ok(())
+        }
+
+        /// Hand the administration of a namespace over to another account.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the owner of the namespace).
+        /// - `client_id`: The client id of the dApp.
+        /// - `owner`: The new owner of the namespace.
+        ///
+        /// # Errors
+        /// - `UnknownNamespace`: If the dApp is not registered.
+        /// - `NotNamespaceOwner`: If the caller does not own the namespace.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_namespace_owner(origin: OriginFor<T>, client_id: ClientId, owner: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Self::mutate_namespace(&who, &client_id, |namespace| namespace.owner = owner.clone())?;
+
+            Self::deposit_event(Event::NamespaceOwnerSet { client_id, owner });
+
+            Ok(())
+        }
+
+        /// Suspend or resume logins into a namespace, e.g. while the dApp is under attack. Proofs
+        /// may still be stored to lock, unlock or erase an identity of the namespace.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the owner of the namespace).
+        /// - `client_id`: The client id of the dApp.
+        /// - `paused`: Whether logins are suspended.
+        ///
+        /// # Errors
+        /// - `UnknownNamespace`: If the dApp is not registered.
+        /// - `NotNamespaceOwner`: If the caller does not own the namespace.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_namespace_paused(origin: OriginFor<T>, client_id: ClientId, paused: bool) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Self::mutate_namespace(&who, &client_id, |namespace| namespace.paused = paused)?;
+
+            Self::deposit_event(Event::NamespaceStatusSet { client_id, paused });
+
+            Ok(())
+        }
+
+        /// Limit the logins into a namespace, per block and per period, and the proofs stored in
+        /// it, or lift the limits. Logins are counted from the block, and the period, they are
+        /// made in, starting over in the next one.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `client_id`: The client id of the dApp.
+        /// - `quota`: The limits of the namespace, or `None` to lift them.
+        ///
+        /// # Errors
+        /// - `UnknownNamespace`: If the dApp is not registered.
+        /// - `InvalidQuota`: If the period is zero blocks.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_namespace_quota(
+            origin: OriginFor<T>,
+            client_id: ClientId,
+            quota: Option<NamespaceQuota<BlockNumberFor<T>>>,
+        ) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Namespaces::<T>::contains_key(&client_id), Error::<T>::UnknownNamespace);
+            ensure!(quota.as_ref().map_or(true, |quota| !quota.period.is_zero()), Error::<T>::InvalidQuota);
+            NamespaceQuotas::<T>::set(&client_id, quota.clone());
+
+            Self::deposit_event(Event::NamespaceQuotaSet { client_id, quota });
+
+            Ok(())
+        }
+
+        /// Stop accepting the tokens of a provider at once, e.g. during a security incident at the
+        /// provider, or accept them again. The proofs stored with its tokens, and what they attest,
+        /// are left intact.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `provider`: The issuer to disable or enable.
+        /// - `enabled`: Whether the tokens of the provider are accepted.
+        ///
+        /// # Errors
+        /// - `UnknownProvider`: If the provider is not registered.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_provider_status(origin: OriginFor<T>, provider: IssuerId, enabled: bool) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Providers::<T>::contains_key(&provider), Error::<T>::UnknownProvider);
+            if enabled {
+                DisabledProviders::<T>::remove(&provider);
+            } else {
+                DisabledProviders::<T>::insert(&provider, ());
+            }
+
+            Self::deposit_event(Event::ProviderStatusSet { provider, enabled });
+
+            Ok(())
+        }
+
+        /// Deprecate a circuit version: its proofs are rejected from block `sunset_at` on, and
+        /// the sunset is announced every `CircuitWarningPeriod` until then, for provers to upgrade
+        /// in time. The proofs stored before are left intact.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `version`: The circuit version to deprecate, or 0 for the proofs declaring none.
+        /// - `sunset_at`: The block from which its proofs are rejected, or `None` to cancel the
+        ///   deprecation.
+        ///
+        /// # Errors
+        /// - `UnknownCircuitVersion`: If the circuit version is unknown.
+        /// - `SunsetNotInFuture`: If `sunset_at` is not a future block.
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn deprecate_circuit(
+            origin: OriginFor<T>,
+            version: u32,
+            sunset_at: Option<BlockNumberFor<T>>,
+        ) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(version == 0 || CircuitVersion::try_from(version).is_ok(), Error::<T>::UnknownCircuitVersion);
+            match sunset_at {
+                Some(sunset_at) => {
+                    ensure!(sunset_at > frame_system::Pallet::<T>::block_number(), Error::<T>::SunsetNotInFuture);
+                    CircuitSunsets::<T>::insert(version, sunset_at);
+                    Self::deposit_event(Event::CircuitDeprecated { version, sunset_at });
+                }
+                None => {
+                    CircuitSunsets::<T>::remove(version);
+                    Self::deposit_event(Event::CircuitDeprecationCancelled { version });
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Set the claims the tokens issued to a dApp must carry, such as `email_verified` being
+        /// `true` or the presence of a `nonce`, on top of those its providers require. Tokens
+        /// failing one are rejected with `ClaimPolicyViolation`, telling which.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the owner of the namespace).
+        /// - `client_id`: The client id of the dApp.
+        /// - `requirements`: The required claims, replacing the current ones. None if empty.
+        ///
+        /// # Errors
+        /// - `UnknownNamespace`: If the dApp is not registered.
+        /// - `NotNamespaceOwner`: If the caller does not own the namespace.
+        /// - `InvalidClaimPath`: If a path is not a JSON pointer to a claim.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_namespace_claims(
+            origin: OriginFor<T>,
+            client_id: ClientId,
+            requirements: BoundedVec<ClaimRequirement, ConstU32<MAX_REQUIRED_CLAIMS>>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let namespace = Namespaces::<T>::get(&client_id).ok_or(Error::<T>::UnknownNamespace)?;
+            ensure!(namespace.owner == who, Error::<T>::NotNamespaceOwner);
+            ensure!(requirements.iter().all(|r| Self::is_claim_path(&r.path)), Error::<T>::InvalidClaimPath);
+            if requirements.is_empty() {
+                NamespaceClaims::<T>::remove(&client_id);
+            } else {
+                NamespaceClaims::<T>::insert(&client_id, requirements);
+            }
+
+            Self::deposit_event(Event::NamespaceClaimsSet { client_id });
+
+            Ok(())
+        }
+
+        /// Restrict the logins into a namespace to users of the given tenants, such as the
+        /// Workspace domains (`hd`) of a company for Google accounts, or lift the restriction.
+        /// Domains are compared case-insensitively.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the owner of the namespace).
+        /// - `client_id`: The client id of the dApp.
+        /// - `tenants`: The tenants allowed, replacing the current ones. Any tenant if empty.
+        ///
+        /// # Errors
+        /// - `UnknownNamespace`: If the dApp is not registered.
+        /// - `NotNamespaceOwner`: If the caller does not own the namespace.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_namespace_tenants(
+            origin: OriginFor<T>,
+            client_id: ClientId,
+            tenants: BoundedVec<Tenant, ConstU32<MAX_NAMESPACE_TENANTS>>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let namespace = Namespaces::<T>::get(&client_id).ok_or(Error::<T>::UnknownNamespace)?;
+            ensure!(namespace.owner == who, Error::<T>::NotNamespaceOwner);
+            if tenants.is_empty() {
+                NamespaceTenants::<T>::remove(&client_id);
+            } else {
+                NamespaceTenants::<T>::insert(&client_id, tenants);
+            }
+
+            Self::deposit_event(Event::NamespaceTenantsSet { client_id });
+
+            Ok(())
+        }
+
+        /// Prune the raw payload of a stored proof, keeping its attestation until it expires. Most
+        /// consumers only need the attestation, and the payload carries the claims of the token.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the submitter of the proof).
+        /// - `proof_hash`: The hash of the proof.
+        ///
+        /// # Errors
+        /// - `UnknownProof`: If no proof is stored under `proof_hash`.
+        /// - `NotSubmitter`: If the proof was stored by another account.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn prune_proof_data(origin: OriginFor<T>, proof_hash: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let submitter = ZkProofSubmitter::<T>::get(proof_hash).ok_or(Error::<T>::UnknownProof)?;
+            ensure!(submitter == who, Error::<T>::NotSubmitter);
+            Self::remove_proof_data(proof_hash);
+
+            Self::deposit_indexed_event(&[proof_hash], Event::ProofDataPruned { hash: proof_hash });
+
+            Ok(())
+        }
+
+        /// Dispatch `call` with the zkLogin account of an identity as the signed origin, once
+        /// `auth` authenticated the identity. The zkLogin account of an identity is derived from
+        /// its nullifier, so that the identity controls it from any device, without holding a key
+        /// of its own.
+        ///
+        /// The call's own result is reported by the `ZkLoginExecuted` event.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be signed).
+        /// - `auth`: The device key session of the signer, or a fresh proof of the identity.
+        /// - `call`: The call to dispatch.
+        ///
+        /// # Errors
+        /// - `CallFiltered`: If `ZkLoginCalls` does not allow `call`.
+        /// - `UnknownDeviceKey`, `DeviceKeyExpired`: If the signer is not an active device key, for
+        ///   `ZkLoginAuth::Session`.
+        /// - Any error of `store_zk_proof`, for `ZkLoginAuth::Proof`.
+        /// - `AccountFrozen`: If the identity is locked.
+        #[pallet::weight({
+            let info = call.get_dispatch_info();
+            // The call comes from a user, whatever class it would have dispatched on its own.
+            (Pallet::<T>::zklogin_auth_weight(auth).saturating_add(info.weight), DispatchClass::Normal)
+        })]
+        pub fn zklogin_execute(
+            origin: OriginFor<T>,
+            auth: ZkLoginAuth,
+            call: Box<T::ZkLoginCall>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin.clone())?;
+            ensure!(T::ZkLoginCalls::contains(&call), Error::<T>::CallFiltered);
+
+            let auth_weight = Self::zklogin_auth_weight(&auth);
+            let nullifier = match auth {
+                ZkLoginAuth::Session => Self::active_identity(&who)?,
+                ZkLoginAuth::Proof(proof) => {
+                    let verified = Self::do_store_zk_proof(who.clone(), proof)?;
+                    Self::charge_namespace_login(&verified)?;
+                    Self::identity_nullifier(&verified)
+                }
+            };
+            ensure!(!Self::is_locked(&nullifier), Error::<T>::AccountFrozen);
+
+            let account = Self::zklogin_account(&nullifier);
+            let info = call.get_dispatch_info();
+            let mut origin = origin;
+            origin.set_caller_from(frame_system::RawOrigin::Signed(account.clone()));
+            let result = call.dispatch(origin);
+
+            let weight = auth_weight.saturating_add(extract_actual_weight(&result, &info));
+            let result = result.map(|_| ()).map_err(|e| e.error);
+            Self::deposit_event(Event::ZkLoginExecuted { nullifier, account, who, result });
+            Ok(Some(weight).into())
+        }
+
+        /// Choose how the identities of a namespace are derived from the address seeds of their
+        /// proofs. With `AddressScheme::Sui`, the nullifier of an identity is its Sui zkLogin
+        /// address, so that provers deriving address seeds as Sui does can be reused as they are,
+        /// and users keep their address across both chains.
+        ///
+        /// Identities which logged into the dApp before are seen as new ones once the scheme
+        /// changes.
+        ///
+        /// # Parameters
+        /// - `origin`: The origin of the call (must be the owner of the namespace).
+        /// - `client_id`: The client id of the dApp.
+        /// - `scheme`: How identities are derived.
+        ///
+        /// # Errors
+        /// - `UnknownNamespace`: If the dApp is not registered.
+        /// - `NotNamespaceOwner`: If the caller does not own the namespace.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_namespace_address_scheme(
+            origin: OriginFor<T>,
+            client_id: ClientId,
+            scheme: AddressScheme,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let namespace = Namespaces::<T>::get(&client_id).ok_or(Error::<T>::UnknownNamespace)?;
+            ensure!(namespace.owner == who, Error::<T>::NotNamespaceOwner);
+            NamespaceAddressSchemes::<T>::insert(&client_id, scheme);
+
+            Self::deposit_event(Event::NamespaceAddressSchemeSet { client_id, scheme });
+
+            Ok(())
+        }
+
+        /// Block an identity, e.g. when its account at the provider is compromised or abused: its
+        /// new proofs are rejected, and its attestations ignored, so that the accounts logged in
+        /// with it can neither transact nor open sessions. Blocking a commitment blocks the
+        /// identity at every provider and in every namespace.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `identity`: The commitment or nullifier of the identity.
+        ///
+        /// # Errors
+        /// - `AlreadyBlocked`: If the identity is blocked already.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn block_identity(origin: OriginFor<T>, identity: BlockedIdentity) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(!BlockedIdentities::<T>::contains_key(identity), Error::<T>::AlreadyBlocked);
+            BlockedIdentities::<T>::insert(identity, ());
+
+            Self::deposit_event(Event::IdentityBlocked { identity });
+
+            Ok(())
+        }
+
+        /// Lift the block of an identity. The attestations it had, if still valid, count again.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `identity`: The commitment or nullifier of the identity.
+        ///
+        /// # Errors
+        /// - `NotBlocked`: If the identity is not blocked.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn unblock_identity(origin: OriginFor<T>, identity: BlockedIdentity) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            ensure!(BlockedIdentities::<T>::take(identity).is_some(), Error::<T>::NotBlocked);
+
+            Self::deposit_event(Event::IdentityUnblocked { identity });
+
+            Ok(())
+        }
+
+        /// Set the base64 variants the tooling of a provider's users emits. Envelopes written by
+        /// clients, in JSON or CBOR, and the provider's tokens must then be encoded with them;
+        /// anything else is rejected rather than guessed at.
+        ///
+        /// # Parameters
+        /// - `origin`: Must be `AdminOrigin`.
+        /// - `provider`: The issuer whose encoding is set.
+        /// - `encoding`: The variants of proof fields and of token segments.
+        ///
+        /// # Errors
+        /// - `UnknownProvider`: If the provider is not registered.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_provider_encoding(
+            origin: OriginFor<T>,
+            provider: IssuerId,
+            encoding: ProviderEncoding,
+        ) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+            ensure!(Providers::<T>::contains_key(&provider), Error::<T>::UnknownProvider);
+
+            ProviderEncodings::<T>::insert(&provider, encoding);
+
+            Self::deposit_event(Event::ProviderEncodingSet { provider, encoding });
+
+            Ok(())
+        }
+
+        /// Renew the attestation of the caller's latest proof with a fresh proof of the same
+        /// identity, extending its expiry by `ProofLifetime` from now, expired or not. Unlike
+        /// `store_zk_proof`, the proof is verified but not stored: the attestation keeps the
+        /// account and the nullifier it binds, and the proofs indexed under them.
+        ///
+        /// # Parameters
+        /// - `origin`: The account that stored the proof (must be signed).
+        /// - `proof`: A fresh proof of the identity, which is verified but not stored.
+        ///
+        /// # Errors
+        /// - `AttestationRequired`: If the caller stored no proof, or its attestation was erased.
+        /// - `ProofAlreadyExists`: If `proof` is already stored; a dedicated proof is required.
+        /// - `IdentityMismatch`: If `proof` is of another identity.
+        /// - Any error of `store_zk_proof` about verifying `proof`.
+        #[pallet::weight(Pallet::<T>::renew_weight())]
+        pub fn renew_attestation(origin: OriginFor<T>, proof: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let parameters = Parameters::<T>::get();
+            ensure!(proof.len() <= parameters.max_json_length as usize, Error::<T>::ZkProofTooLarge);
+            let hash = AccountProofs::<T>::get(&who).ok_or(Error::<T>::AttestationRequired)?;
+            let mut attestation = Attestations::<T>::get(hash).ok_or(Error::<T>::AttestationRequired)?;
+
+            use frame_support::sp_runtime::traits::Hash;
+            let proof_hash = T::Hashing::hash(&proof);
+            ensure!(!ZkProofSubmitter::<T>::contains_key(proof_hash), Error::<T>::ProofAlreadyExists);
+
+            let verified = Self::verify_zk_proof(&proof)?;
+            let nullifier = Self::identity_nullifier(&verified);
+            ensure!(nullifier == attestation.nullifier, Error::<T>::IdentityMismatch);
+            Self::consume_nonce(&verified, proof_hash);
+
+            let now = T::UnixTime::now().as_secs();
+            if let Some(remind_at) = Self::reminder_time(attestation.expires_at) {
+                RenewalReminders::<T>::remove(remind_at, hash);
+            }
+            attestation.verified_at = now;
+            attestation.expires_at = now.saturating_add(parameters.proof_lifetime);
+            let expires_at = attestation.expires_at;
+            Self::insert_attestation(hash, attestation);
+            ZkProofExpiry::<T>::insert(hash, expires_at);
+            IdentityVerifiedAt::<T>::insert(nullifier, frame_system::Pallet::<T>::block_number());
+            Self::schedule_reminder(hash, expires_at);
+
+            Self::deposit_indexed_event(&[hash], Event::AttestationRenewed { hash, who, nullifier, expires_at });
+
+            Ok(())
+        }
+    }
+
+    #[pallet::inherent]
+    impl<T: Config> ProvideInherent for Pallet<T> {
+        type Call = Call<T>;
+        type Error = inherent::InherentError;
+        const INHERENT_IDENTIFIER: InherentIdentifier = inherent::INHERENT_IDENTIFIER;
+
+        fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+            if !T::AuthoredJwks::get() {
+                return None;
+            }
+            let updates: inherent::InherentType = data.get_data(&inherent::INHERENT_IDENTIFIER).ok()??;
+            let updates: Vec<_> = updates.into_iter().filter(Self::is_applicable).collect();
+            (!updates.is_empty()).then_some(Call::set_authored_jwks { updates })
+        }
+
+        fn check_inherent(_call: &Self::Call, _data: &InherentData) -> Result<(), Self::Error> {
+            if !T::AuthoredJwks::get() {
+                return Err(inherent::InherentError::AuthoredJwksDisabled);
+            }
+            Ok(())
+        }
+
+        fn is_inherent(call: &Self::Call) -> bool {
+            matches!(call, Call::set_authored_jwks { .. })
+        }
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(block_number: BlockNumberFor<T>) -> Weight {
+            ProofWeightUsed::<T>::kill();
+            VerificationsInBlock::<T>::kill();
+            AuthoredJwksSet::<T>::kill();
+
+            let activations = ScheduledActivations::<T>::take(block_number);
+            let count = activations.len() as u64;
+            for activation in activations {
+                Self::activate(activation);
+            }
+
+            let sunsets = Self::announce_circuit_sunsets(block_number);
+            let pruned = Self::prune_consumed_nonces(block_number);
+            let reminded = Self::remind_renewals();
+
+            let identity_root_weight =
+                if Self::computes_identity_root(block_number) { Self::identity_root_weight() } else { Weight::zero() };
+            T::DbWeight::get()
+                .reads_writes(2 + count + sunsets + 3 * reminded, 4 + count + pruned + reminded)
+                .saturating_add(identity_root_weight)
+        }
+
+        fn on_finalize(block_number: BlockNumberFor<T>) {
+            if Self::computes_identity_root(block_number) {
+                Self::update_identity_root(block_number);
+            }
+        }
+
+        fn offchain_worker(block_number: BlockNumberFor<T>) {
+            let interval = T::JwksFetchInterval::get();
+            if interval.is_zero() || !(block_number % interval).is_zero() {
+                return;
+            }
+            Self::refresh_jwks();
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn try_state(_block_number: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+            Self::do_try_state()
+        }
+
+        fn integrity_test() {
+            assert!(
+                T::MaxJsonLength::get() >= T::MaxJwtLength::get().saturating_add(envelope::MIN_ENVELOPE_OVERHEAD),
+                "`MaxJsonLength` must fit an envelope around a token of `MaxJwtLength`"
+            );
+            assert!(
+                T::MaxPublicInputs::get() as usize >= CircuitVersion::V1.layout().len(),
+                "`MaxPublicInputs` must fit the public inputs of the login circuit"
+            );
+            assert!(T::ProofLifetime::get() > 0, "`ProofLifetime` must not be zero");
+            assert!(
+                T::RenewalNotice::get() < T::ProofLifetime::get(),
+                "`RenewalNotice` must be shorter than `ProofLifetime`"
+            );
+            assert!(T::MaxTokenAge::get() > 0, "`MaxTokenAge` must not be zero");
+            assert!(!T::NonceBlockWindow::get().is_zero(), "`NonceBlockWindow` must not be zero");
+            assert!(
+                T::NonceBlockWindow::get() <= T::BlockHashCount::get(),
+                "`NonceBlockWindow` must not exceed `BlockHashCount`, past which block hashes are pruned"
+            );
+            assert!(!T::RevealWindow::get().is_zero(), "`RevealWindow` must not be zero");
+            assert!(
+                T::JwksQuorum::get() > 0 && T::JwksQuorum::get() <= T::MaxOracles::get(),
+                "`JwksQuorum` must be reachable by `MaxOracles` oracles"
+            );
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// The proof size of reading a storage entry with a key of type `K` and a value of type
+        /// `V`, at their largest.
+        fn read_proof_size<K: MaxEncodedLen, V: MaxEncodedLen>() -> u64 {
+            // Keys are hashed into at most 16 bytes besides themselves.
+            (16 + K::max_encoded_len() + V::max_encoded_len()) as u64 + STORAGE_READ_PROOF_OVERHEAD
+        }
+
+        /// The computation weight of verifying a proof, as benchmarked: parsing the envelope and
+        /// its points, verifying it on the costlier curve, checking the signature of its token and
+        /// assembling its public inputs with the costlier hash scheme, since neither is known before
+        /// the envelope is parsed.
+        pub fn verification_weight() -> Weight {
+            T::WeightInfo::parse_envelope()
+                .saturating_add(T::WeightInfo::parse_proof_points())
+                .saturating_add(T::WeightInfo::verify_rs256())
+                .saturating_add(T::WeightInfo::verify_bls12_381().max(T::WeightInfo::verify_bls12_377()))
+                .saturating_add(T::WeightInfo::public_inputs_sha256().max(T::WeightInfo::public_inputs_poseidon()))
+        }
+
+        /// The weight of verifying a proof: `verification_weight`, and the reads of the limits, of
+        /// the genesis and nonce block hashes, of the approval of the verifying key, of the status,
+        /// keys, keyset expiry and required claims of the issuer, of the predicate attested to, of
+        /// the namespace of the token's audience and the claims and tenants it requires, of the
+        /// sunset of the circuit and of the proof the token was consumed by, if any.
+        fn verify_weight() -> Weight {
+            let proof_size = Self::read_proof_size::<(), ProofParameters>()
+                + 2 * Self::read_proof_size::<BlockNumberFor<T>, T::Hash>()
+                + Self::read_proof_size::<VkHash, ()>()
+                + Self::read_proof_size::<IssuerId, BoundedVec<JwkRecord, T::MaxJwksKeys>>()
+                + Self::read_proof_size::<IssuerId, u64>()
+                + Self::read_proof_size::<IssuerId, BoundedVec<ClaimRequirement, ConstU32<MAX_REQUIRED_CLAIMS>>>()
+                + Self::read_proof_size::<PredicateId, ClaimPredicate>()
+                + Self::read_proof_size::<ClientId, Namespace<T::AccountId>>()
+                + Self::read_proof_size::<ClientId, BoundedVec<ClaimRequirement, ConstU32<MAX_REQUIRED_CLAIMS>>>()
+                + Self::read_proof_size::<ClientId, BoundedVec<Tenant, ConstU32<MAX_NAMESPACE_TENANTS>>>()
+                + Self::read_proof_size::<IssuerId, ()>()
+                + Self::read_proof_size::<u32, BlockNumberFor<T>>()
+                + Self::read_proof_size::<(BlockNumberFor<T>, [u8; 32]), T::Hash>();
+            Self::verification_weight()
+                .saturating_add(T::DbWeight::get().reads(14))
+                .saturating_add(Weight::from_parts(0, proof_size))
+        }
+
+        /// The weight of verifying and storing a proof: `verify_weight`, the reads of the proof
+        /// calls' block budget, of the submitter of the proof, of the latest proof of the identity,
+        /// of the usage of the provider and of the namespace index, quota and usage of the dApp, and
+        /// the writes indexing the proof.
+        ///
+        /// The payload itself travels in the extrinsic, whose length counts towards the proof size
+        /// of the block already.
+        fn store_weight() -> Weight {
+            let proof_size = Self::read_proof_size::<(), Weight>()
+                + Self::read_proof_size::<T::Hash, T::AccountId>()
+                + Self::read_proof_size::<Nullifier, T::Hash>()
+                + Self::read_proof_size::<IssuerId, ProviderStats<BlockNumberFor<T>>>()
+                + Self::read_proof_size::<(ClientId, T::Hash), ()>()
+                + Self::read_proof_size::<ClientId, NamespaceQuota<BlockNumberFor<T>>>()
+                + Self::read_proof_size::<ClientId, NamespaceUsage<BlockNumberFor<T>>>();
+            Self::verify_weight()
+                .saturating_add(T::DbWeight::get().reads_writes(7, 17))
+                .saturating_add(Weight::from_parts(0, proof_size))
+        }
+
+        /// The weight of renewing an attestation: `verify_weight`, the reads of the proof calls'
+        /// block budget, of the latest proof of the caller, of its attestation and of the
+        /// submitter of the renewing proof, and the writes of the attestation, its expiry, its
+        /// reminders, the verification block of the identity and the nonce consumed.
+        fn renew_weight() -> Weight {
+            let proof_size = Self::read_proof_size::<(), Weight>()
+                + Self::read_proof_size::<T::AccountId, T::Hash>()
+                + Self::read_proof_size::<T::Hash, Attestation>()
+                + Self::read_proof_size::<T::Hash, T::AccountId>();
+            Self::verify_weight()
+                .saturating_add(T::DbWeight::get().reads_writes(4, 7))
+                .saturating_add(Weight::from_parts(0, proof_size))
+        }
+
+        /// The weight of retrieving `count` proofs: two reads and an event per proof, plus the
+        /// lookahead read deciding whether there is a next page, as benchmarked. Each read payload
+        /// may be up to `MaxJsonLength` bytes, all of which go into the proof of the block; the
+        /// proof size follows the configured bound rather than the benchmarked one.
+        fn retrieve_weight(count: u32) -> Weight {
+            let proof_size = Self::read_proof_size::<T::Hash, BoundedVec<u8, T::MaxJsonLength>>()
+                + Self::read_proof_size::<T::Hash, T::AccountId>();
+            let ref_time = T::WeightInfo::retrieve_all_zk_proofs(count).ref_time();
+            Weight::from_parts(ref_time, proof_size.saturating_mul(count.into()))
+                .saturating_add(Weight::from_parts(0, Self::read_proof_size::<T::Hash, ()>()))
+        }
+
+        /// The weight of erasing `count` proofs of an identity: verifying the identity proof, then
+        /// five reads and ten writes per proof, plus the removal of its tags, the consumption of
+        /// the token and the lookahead read deciding whether proofs remain. The payloads are
+        /// removed without being read.
+        fn erase_weight(count: u32) -> Weight {
+            let index_proof_size = Self::read_proof_size::<(Nullifier, T::Hash), ()>();
+            let submitter_proof_size = Self::read_proof_size::<T::Hash, T::AccountId>();
+            let per_proof = index_proof_size
+                + submitter_proof_size
+                + Self::read_proof_size::<T::AccountId, T::Hash>()
+                + Self::read_proof_size::<T::Hash, ProofTags>()
+                + Self::read_proof_size::<T::Hash, ClientId>()
+                + Self::read_proof_size::<ClientId, NamespaceUsage<BlockNumberFor<T>>>();
+            let per_proof_writes = 10 + MAX_PROOF_TAGS as u64;
+            Self::verify_weight()
+                .saturating_add(Weight::from_parts(0, per_proof).saturating_mul(count.into()))
+                .saturating_add(
+                    T::DbWeight::get().reads_writes(5 * count as u64 + 2, per_proof_writes * count as u64 + 1),
+                )
+                .saturating_add(Weight::from_parts(0, index_proof_size + submitter_proof_size))
+        }
+
+        /// The weight of looking up the identity and the device key of the caller, then going
+        /// through `count` device keys of the identity: three reads per key, and the removal of the
+        /// key, of its identity and of its latest proof.
+        fn device_keys_weight(count: u32) -> Weight {
+            T::DbWeight::get().reads_writes(3 * count as u64 + 2, 3 * count as u64)
+        }
+
+        /// The weight of authenticating the identity `zklogin_execute` dispatches a call for:
+        /// looking up the device key of the caller, or storing a fresh proof, then whether the
+        /// identity is locked.
+        fn zklogin_auth_weight(auth: &ZkLoginAuth) -> Weight {
+            let authentication = match auth {
+                ZkLoginAuth::Session => T::DbWeight::get().reads(2),
+                ZkLoginAuth::Proof(_) => Self::store_weight(),
+            };
+            authentication.saturating_add(T::DbWeight::get().reads(1))
+        }
+
+        /// Check the invariants tying the proof storage together, as `try-runtime` does after
+        /// upgrades: predicate attestations belong to stored proofs, once migrated to version 1,
+        /// every stored proof is indexed by the nullifier of its attestation and only by it and,
+        /// once migrated to version 2, attestations expire with their proof.
+        #[cfg(any(feature = "try-runtime", test))]
+        pub fn do_try_state() -> Result<(), sp_runtime::TryRuntimeError> {
+            for hash in PredicateAttestations::<T>::iter_keys() {
+                ensure!(Attestations::<T>::contains_key(hash), "predicate attestation without a proof");
+            }
+
+            if Self::on_chain_storage_version() < 1 {
+                return Ok(());
+            }
+            // Until `ExtendAttestations` runs, the attestations are in the layout of version 1.
+            let attested: alloc::collections::BTreeMap<T::Hash, Nullifier> = if Self::on_chain_storage_version() < 2 {
+                migrations::v2::Attestations::<T>::iter()
+                    .map(|(hash, attestation)| (hash, attestation.nullifier))
+                    .collect()
+            } else {
+                Attestations::<T>::iter().map(|(hash, attestation)| (hash, attestation.nullifier)).collect()
+            };
+            for (hash, nullifier) in &attested {
+                ensure!(IdentityProofs::<T>::contains_key(nullifier, hash), "proof not indexed by its identity");
+            }
+            for (nullifier, hash, ()) in IdentityProofs::<T>::iter() {
+                let attested = attested.get(&hash).ok_or("identity index of a missing proof")?;
+                ensure!(*attested == nullifier, "proof indexed by another identity");
+            }
+
+            if Self::on_chain_storage_version() < 2 {
+                return Ok(());
+            }
+            for (hash, attestation) in Attestations::<T>::iter() {
+                ensure!(
+                    ZkProofExpiry::<T>::get(hash) == Some(attestation.expires_at),
+                    "attestation expiring apart from its proof"
+                );
+            }
+
+            Ok(())
+        }
+
+        /// Apply `activation` at the start of block `at`, or immediately if `None`.
+        fn activate_at(activation: Activation, at: Option<BlockNumberFor<T>>) -> DispatchResult {
+            let Some(at) = at else {
+                Self::activate(activation);
+                return Ok(());
+            };
+
+            ensure!(at > frame_system::Pallet::<T>::block_number(), Error::<T>::ActivationNotInFuture);
+            ScheduledActivations::<T>::try_append(at, activation.clone())
+                .map_err(|_| Error::<T>::TooManyScheduledActivations)?;
+            Self::deposit_event(Event::ActivationScheduled { activation, at });
+
+            Ok(())
+        }
+
+        /// Apply `activation`, unless the key was approved or the provider registered since it was
+        /// scheduled.
+        fn activate(activation: Activation) {
+            match activation {
+                Activation::VerifyingKey(hash) => {
+                    if !ApprovedVerifyingKeys::<T>::contains_key(hash) {
+                        ApprovedVerifyingKeys::<T>::insert(hash, ());
+                        Self::deposit_event(Event::VerifyingKeyApproved { hash });
+                    }
+                }
+                Activation::Provider { provider, info } => {
+                    if !Providers::<T>::contains_key(&provider) {
+                        Providers::<T>::insert(&provider, info);
+                        Self::deposit_event(Event::ProviderAdded { provider });
+                    }
+                }
+            }
+        }
+
+        /// Record a rejected proof submission of `submitter` in `RecentFailures`, count it in the
+        /// `ProviderUsage` of its token's issuer, and in `FailureStats` if it failed with an error of
+        /// this pallet. Verification failures also count in the failed attempts of `submitter`, and
+        /// of the identity of `nullifier` once past the pairing.
+        ///
+        /// Called once the submission is dispatched, as the storage changes of a failed call are
+        /// discarded.
+        pub(crate) fn note_failure(
+            submitter: T::AccountId,
+            context: SubmissionContext,
+            nullifier: Option<Nullifier>,
+            error: &DispatchError,
+        ) {
+            let block = frame_system::Pallet::<T>::block_number();
+            let kind = Self::pallet_error(error);
+            if kind.as_ref().is_some_and(Self::fails_verification) {
+                SubmitterFailures::<T>::mutate(&submitter, |attempts| Self::count_failure(attempts, block));
+            }
+            if let (true, Some(nullifier)) = (kind.as_ref().is_some_and(Self::fails_after_pairing), nullifier) {
+                IdentityFailures::<T>::mutate(nullifier, |attempts| Self::count_failure(attempts, block));
+            }
+            if let Some(provider) = &context.provider {
+                ProviderUsage::<T>::mutate(provider, |stats| {
+                    stats.failures.saturating_inc();
+                    stats.last_active = block;
+                });
+            }
+            let record = FailureRecord { block, submitter, error: *error, context };
+            RecentFailures::<T>::mutate(|failures| {
+                if failures.is_full() {
+                    failures.remove(0);
+                }
+                let _ = failures.try_push(record);
+            });
+
+            if let Some(kind) = kind {
+                FailureStats::<T>::mutate(kind, |count| count.saturating_inc());
+            }
+        }
+
+        /// The error of this pallet `error` is, if any.
+        fn pallet_error(error: &DispatchError) -> Option<Error<T>> {
+            let DispatchError::Module(ModuleError { index, error, .. }) = error else {
+                return None;
+            };
+            if *index as usize != <Self as PalletInfoAccess>::index() {
+                return None;
+            }
+            Error::<T>::decode(&mut &error[..]).ok()
+        }
+
+        /// Whether `error` rejects the proof itself. Calls failing because of the state of the
+        /// chain, such as `ProofAlreadyExists` or `QuotaExceeded`, say nothing of their submitter.
+        fn fails_verification(error: &Error<T>) -> bool {
+            Self::fails_after_pairing(error)
+                || matches!(
+                    error,
+                    Error::<T>::ZkProofTooLarge
+                        | Error::<T>::InvalidProof
+                        | Error::<T>::UnknownEnvelopeFormat
+                        | Error::<T>::InvalidEncoding
+                        | Error::<T>::JwtTooLarge
+                        | Error::<T>::TooManyPublicInputs
+                        | Error::<T>::UnknownCircuitVersion
+                        | Error::<T>::WrongChain
+                        | Error::<T>::UnapprovedVerifyingKey
+                        | Error::<T>::VkDeserializationFailed
+                        | Error::<T>::InvalidSignature
+                )
+        }
+
+        /// Whether `error` rejects a proof whose pairing checked out, i.e. about the identity its
+        /// public inputs commit to. Failing proofs carrying anyone's commitment are not charged to
+        /// the identity before, or anyone could lock it out.
+        fn fails_after_pairing(error: &Error<T>) -> bool {
+            matches!(
+                error,
+                Error::<T>::PrivateTokenMismatch
+                    | Error::<T>::UnsupportedAlgorithm
+                    | Error::<T>::TokenExpired
+                    | Error::<T>::StaleToken
+                    | Error::<T>::InvalidNonce
+                    | Error::<T>::StaleNonce
+                    | Error::<T>::NonceAlreadyUsed
+                    | Error::<T>::TenantNotAllowed
+                    | Error::<T>::ClaimPolicyViolation { .. }
+                    | Error::<T>::PublicInputMismatch
+            )
+        }
+
+        /// Count a failed submission in `attempts`. Every `MaxFailedAttempts` failures in a row lock
+        /// them out, for `LockoutPeriod` the first time and twice as long each time after.
+        fn count_failure(attempts: &mut FailedAttempts<BlockNumberFor<T>>, block: BlockNumberFor<T>) {
+            attempts.count.saturating_inc();
+            let threshold = T::MaxFailedAttempts::get();
+            if threshold == 0 || attempts.count % threshold != 0 {
+                return;
+            }
+            let doublings = (attempts.count / threshold - 1).min(MAX_LOCKOUT_DOUBLINGS);
+            let period = T::LockoutPeriod::get().saturating_mul((1u32 << doublings).into());
+            attempts.locked_until = block.saturating_add(period);
+        }
+
+        /// Clear the failed attempts of `submitter` and of the identity of `nullifier`, once a
+        /// proof submission of theirs succeeded.
+        pub(crate) fn note_success(submitter: &T::AccountId, nullifier: Option<Nullifier>) {
+            SubmitterFailures::<T>::remove(submitter);
+            if let Some(nullifier) = nullifier {
+                IdentityFailures::<T>::remove(nullifier);
+            }
+        }
+
+        /// Reject the proof submissions of `submitter`, or about the identity of `nullifier`, while
+        /// they are locked out after failing too often.
+        pub(crate) fn ensure_not_locked_out(
+            submitter: &T::AccountId,
+            nullifier: Option<&Nullifier>,
+        ) -> Result<(), Error<T>> {
+            let block = frame_system::Pallet::<T>::block_number();
+            let locked = SubmitterFailures::<T>::get(submitter).locked_until > block
+                || nullifier.map_or(false, |nullifier| IdentityFailures::<T>::get(nullifier).locked_until > block);
+            ensure!(!locked, Error::<T>::TooManyFailedAttempts);
+            Ok(())
+        }
+
+        /// The latest rejected proof submissions, oldest first.
+        pub fn recent_failures() -> Vec<FailureRecord<T::AccountId, BlockNumberFor<T>>> {
+            RecentFailures::<T>::get().into_inner()
+        }
+
+        /// Replace the keys of `provider`, expiring at `expires_at`.
+        fn set_jwks(provider: IssuerId, keys: BoundedVec<JwkRecord, T::MaxJwksKeys>, expires_at: Option<u64>) {
+            use frame_support::sp_runtime::traits::Hash;
+            let keyset_hash = T::Hashing::hash_of(&keys);
+            Self::write_jwks(&provider, Some(keys));
+            JwksExpiry::<T>::set(&provider, expires_at);
+            // Competing candidates for this provider are obsolete now.
+            let _ = PendingJwks::<T>::clear_prefix(&provider, u32::MAX, None);
+
+            Self::deposit_event(Event::JwksUpdated { provider, keyset_hash, expires_at });
+        }
+
+        /// Replace the keys of `provider` with `keys`, or remove them, keeping the storage usage
+        /// up to date.
+        fn write_jwks(provider: &IssuerId, keys: Option<BoundedVec<JwkRecord, T::MaxJwksKeys>>) {
+            let replaced = Jwks::<T>::take(provider);
+            StorageUsage::<T>::mutate(|usage| {
+                if let Some(replaced) = &replaced {
+                    usage.jwks_keys.saturating_reduce(replaced.len() as u64);
+                    usage.jwks_bytes.saturating_reduce(replaced.encoded_size() as u64);
+                }
+                if let Some(keys) = &keys {
+                    usage.jwks_keys.saturating_accrue(keys.len() as u64);
+                    usage.jwks_bytes.saturating_accrue(keys.encoded_size() as u64);
+                }
+            });
+            if let Some(keys) = keys {
+                Jwks::<T>::insert(provider, keys);
+            }
+        }
+
+        /// Store the raw proof `data` under `hash`, keeping the storage usage up to date.
+        fn insert_proof_data(hash: T::Hash, data: BoundedVec<u8, T::MaxJsonLength>) {
+            let replaced = ZkProofData::<T>::decode_len(hash);
+            StorageUsage::<T>::mutate(|usage| {
+                match replaced {
+                    Some(len) => usage.proof_bytes.saturating_reduce(len as u64),
+                    None => usage.proofs.saturating_inc(),
+                }
+                usage.proof_bytes.saturating_accrue(data.len() as u64);
+            });
+            ZkProofData::<T>::insert(hash, data);
+        }
+
+        /// Remove the raw proof stored under `hash`, if any, keeping the storage usage up to date.
+        fn remove_proof_data(hash: T::Hash) {
+            if let Some(len) = ZkProofData::<T>::decode_len(hash) {
+                ZkProofData::<T>::remove(hash);
+                StorageUsage::<T>::mutate(|usage| {
+                    usage.proofs.saturating_dec();
+                    usage.proof_bytes.saturating_reduce(len as u64);
+                });
+            }
+        }
+
+        /// Store the attestation of the proof `hash`, keeping the storage usage up to date.
+        fn insert_attestation(hash: T::Hash, attestation: Attestation) {
+            if !Attestations::<T>::contains_key(hash) {
+                StorageUsage::<T>::mutate(|usage| usage.attestations.saturating_inc());
+            }
+            Attestations::<T>::insert(hash, attestation);
+        }
 
-    //TODO @Ahmed verify the last signature part with RSA
+        /// Remove the attestation of the proof `hash`, if any, keeping the storage usage up to date.
+        fn remove_attestation(hash: T::Hash) {
+            if Attestations::<T>::take(hash).is_some() {
+                StorageUsage::<T>::mutate(|usage| usage.attestations.saturating_dec());
+            }
+        }
+
+        /// Whether the JWKS inherent would change the keys of a registered provider with `update`.
+        fn is_applicable(update: &JwksUpdate) -> bool {
+            let fits = !update.keys.is_empty() && update.keys.len() <= T::MaxJwksKeys::get() as usize;
+            let keys_changed = Jwks::<T>::get(&update.provider).map_or(true, |keys| keys[..] != update.keys[..]);
+            let expiry_changed = JwksExpiry::<T>::get(&update.provider) != update.expires_at;
+            fits && (keys_changed || expiry_changed) && Providers::<T>::contains_key(&update.provider)
+        }
+
+        /// The registered providers.
+        pub fn providers() -> Vec<(IssuerId, ProviderInfo)> {
+            Providers::<T>::iter().collect()
+        }
+
+        /// Verify `proof` against the current state as `store_zk_proof` does, without storing
+        /// anything.
+        pub fn check_proof(proof: &[u8]) -> Result<VerificationReport, DispatchError> {
+            Self::check_structure(proof)?;
+            let verified = Self::verify_zk_proof(proof)?;
+
+            use frame_support::sp_runtime::traits::Hash;
+            let nullifier = Self::identity_nullifier(&verified);
+            let now = T::UnixTime::now().as_secs();
+            Ok(VerificationReport {
+                attestation: Attestation {
+                    provider: verified.claims.issuer,
+                    commitment: verified.commitment,
+                    nullifier,
+                    circuit_version: verified.circuit_version,
+                    verified_at: now,
+                    expires_at: now.saturating_add(Parameters::<T>::get().proof_lifetime),
+                },
+                predicate: verified.predicate,
+                expires_at: verified.claims.expires_at,
+                already_stored: ZkProofSubmitter::<T>::contains_key(T::Hashing::hash(proof)),
+            })
+        }
+
+        /// The attestation of the latest proof stored by `who`, while it is valid. The raw proof
+        /// may have been pruned.
+        pub fn attestation_of(who: &T::AccountId) -> Option<Attestation> {
+            Self::valid_attestation(who).ok()
+        }
+
+        /// The attestation of the latest proof stored by `who`, or why it is not valid:
+        /// `AttestationExpired` once it expired, `AttestationRequired` if there is none, or if it is
+        /// of a locked or blocked identity.
+        pub fn valid_attestation(who: &T::AccountId) -> Result<Attestation, Error<T>> {
+            let attestation = AccountProofs::<T>::get(who)
+                .and_then(|hash| Attestations::<T>::get(hash))
+                .ok_or(Error::<T>::AttestationRequired)?;
+            ensure!(
+                !Self::is_locked(&attestation.nullifier)
+                    && !Self::is_blocked(&attestation.nullifier, &attestation.commitment),
+                Error::<T>::AttestationRequired
+            );
+            ensure!(attestation.is_valid_at(T::UnixTime::now().as_secs()), Error::<T>::AttestationExpired);
+            Ok(attestation)
+        }
+
+        /// Whether the identity with `nullifier` and `commitment` is blocked.
+        pub fn is_blocked(nullifier: &Nullifier, commitment: &Commitment) -> bool {
+            BlockedIdentities::<T>::contains_key(BlockedIdentity::Nullifier(*nullifier))
+                || BlockedIdentities::<T>::contains_key(BlockedIdentity::Commitment(*commitment))
+        }
+
+        /// Whether `who` logged in with a blocked identity, going by its latest proof.
+        pub fn is_blocked_account(who: &T::AccountId) -> bool {
+            AccountProofs::<T>::get(who)
+                .and_then(|hash| Attestations::<T>::get(hash))
+                .map_or(false, |attestation| Self::is_blocked(&attestation.nullifier, &attestation.commitment))
+        }
+
+        /// Whether the identity with `nullifier` is locked.
+        pub fn is_locked(nullifier: &Nullifier) -> bool {
+            IdentityLocks::<T>::get(nullifier).map_or(false, |lock| {
+                lock.unlocks_at.map_or(true, |unlocks_at| frame_system::Pallet::<T>::block_number() < unlocks_at)
+            })
+        }
+
+        /// Whether `who` logged in with a locked identity, going by its latest proof.
+        pub fn is_frozen(who: &T::AccountId) -> bool {
+            AccountProofs::<T>::get(who)
+                .and_then(|hash| Attestations::<T>::get(hash))
+                .map_or(false, |attestation| Self::is_locked(&attestation.nullifier))
+        }
+
+        /// Whether the identity `who` acts for stored a proof at most `max_age` blocks ago: the
+        /// identity of its device key, or else of its latest proof.
+        pub fn verified_within(who: &T::AccountId, max_age: BlockNumberFor<T>) -> bool {
+            let nullifier = Self::active_identity(who).ok().or_else(|| {
+                AccountProofs::<T>::get(who)
+                    .and_then(|hash| Attestations::<T>::get(hash))
+                    .map(|attestation| attestation.nullifier)
+            });
+            let now = frame_system::Pallet::<T>::block_number();
+            nullifier
+                .and_then(|nullifier| IdentityVerifiedAt::<T>::get(nullifier))
+                .map_or(false, |verified_at| now.saturating_sub(verified_at) <= max_age)
+        }
+
+        /// The device keys registered to the identity `account` is a device key of, expired ones
+        /// included until they are pruned.
+        pub fn device_keys(account: &T::AccountId) -> Vec<(T::AccountId, DeviceKey<BlockNumberFor<T>>)> {
+            KeyIdentities::<T>::get(account)
+                .map(|nullifier| DeviceKeys::<T>::iter_prefix(nullifier).collect())
+                .unwrap_or_default()
+        }
+
+        /// The proofs stored from block `from` to block `to` included, in block order. At most
+        /// `MAX_PROOF_RANGE` blocks are covered, from `from` on.
+        pub fn proofs_in_range(from: BlockNumberFor<T>, to: BlockNumberFor<T>) -> Vec<(BlockNumberFor<T>, T::Hash)> {
+            let now = frame_system::Pallet::<T>::block_number();
+            let to = to.min(from.saturating_add((MAX_PROOF_RANGE - 1).into())).min(now);
+            let mut proofs = Vec::new();
+            let mut block = from;
+            while block <= to {
+                proofs.extend(ProofsByBlock::<T>::iter_key_prefix(block).map(|hash| (block, hash)));
+                block.saturating_inc();
+            }
+            proofs
+        }
+
+        /// Up to `limit` proofs stored in the namespace of `client_id`, in storage order, resuming
+        /// after `start_key`. At most `MAX_PROOF_PAGE` proofs are returned.
+        pub fn namespace_proofs(client_id: &ClientId, start_key: Option<T::Hash>, limit: u32) -> Vec<T::Hash> {
+            let proofs = match start_key {
+                Some(key) => NamespaceProofs::<T>::iter_key_prefix_from(
+                    client_id,
+                    NamespaceProofs::<T>::hashed_key_for(client_id, key),
+                ),
+                None => NamespaceProofs::<T>::iter_key_prefix(client_id),
+            };
+            proofs.take(limit.min(MAX_PROOF_PAGE) as usize).collect()
+        }
+
+        /// Up to `limit` proofs tagged with `tag`, in storage order, resuming after `start_key`.
+        /// At most `MAX_PROOF_PAGE` proofs are returned.
+        pub fn proofs_with_tag(tag: &ProofTag, start_key: Option<T::Hash>, limit: u32) -> Vec<T::Hash> {
+            let proofs = match start_key {
+                Some(key) => TaggedProofs::<T>::iter_key_prefix_from(tag, TaggedProofs::<T>::hashed_key_for(tag, key)),
+                None => TaggedProofs::<T>::iter_key_prefix(tag),
+            };
+            proofs.take(limit.min(MAX_PROOF_PAGE) as usize).collect()
+        }
+
+        /// The keys tokens of `provider` are currently accepted with: none if the issuer is not
+        /// allowed or its keyset expired.
+        pub fn accepted_jwks(provider: &IssuerId) -> Vec<JwkRecord> {
+            if !T::AllowedIssuers::get().contains(provider) || Self::keyset_expired(provider) {
+                return Vec::new();
+            }
+            Jwks::<T>::get(provider).map(BoundedVec::into_inner).unwrap_or_default()
+        }
+
+        /// The usage of every provider whose tokens were ever submitted.
+        pub fn provider_stats() -> Vec<(IssuerId, ProviderStats<BlockNumberFor<T>>)> {
+            ProviderUsage::<T>::iter().collect()
+        }
+
+        /// The storage footprint of the pallet.
+        pub fn storage_stats() -> StorageStats {
+            StorageUsage::<T>::get()
+        }
+
+        /// The circuit versions deprecated, with the block from which their proofs are rejected.
+        pub fn circuit_sunsets() -> Vec<(u32, BlockNumberFor<T>)> {
+            CircuitSunsets::<T>::iter().collect()
+        }
+
+        /// Announce the sunsets still ahead if `block_number` starts a `CircuitWarningPeriod`.
+        /// Returns the number of sunsets read, at most one per known circuit version and one for
+        /// the proofs declaring none.
+        fn announce_circuit_sunsets(block_number: BlockNumberFor<T>) -> u64 {
+            let period = T::CircuitWarningPeriod::get();
+            if period.is_zero() || !(block_number % period).is_zero() {
+                return 0;
+            }
+            let mut read = 0;
+            for (version, sunset_at) in CircuitSunsets::<T>::iter() {
+                read += 1;
+                if sunset_at > block_number {
+                    let blocks_left = sunset_at - block_number;
+                    Self::deposit_event(Event::CircuitSunsetApproaching { version, sunset_at, blocks_left });
+                }
+            }
+            read
+        }
+
+        /// Forget the tokens consumed with nonces bound to the block that went stale at
+        /// `block_number`, as they can no longer be replayed. Returns the number of entries removed.
+        fn prune_consumed_nonces(block_number: BlockNumberFor<T>) -> u64 {
+            let window = T::NonceBlockWindow::get();
+            if block_number <= window {
+                return 0;
+            }
+            let stale = block_number - window - One::one();
+            // Each verification consumes a nonce and an id at most.
+            let limit =
+                T::MaxVerificationsPerBlock::get().saturating_mul(window.saturated_into::<u32>()).saturating_mul(2);
+            ConsumedNonces::<T>::clear_prefix(stale, limit, None).backend as u64
+        }
+
+        /// The key under which the submitter of a proof whose attestation expires at `expires_at`
+        /// is reminded to renew it, if reminders are enabled.
+        fn reminder_time(expires_at: u64) -> Option<[u8; 8]> {
+            let notice = T::RenewalNotice::get();
+            (notice > 0).then(|| expires_at.saturating_sub(notice).to_be_bytes())
+        }
+
+        /// Remind the submitter of the proof `hash` to renew its attestation `RenewalNotice` before
+        /// `expires_at`.
+        fn schedule_reminder(hash: T::Hash, expires_at: u64) {
+            if let Some(remind_at) = Self::reminder_time(expires_at) {
+                RenewalReminders::<T>::insert(remind_at, hash, ());
+            }
+        }
+
+        /// Emit the renewal reminders due, up to `MaxRenewalReminders`. Reminders of attestations
+        /// renewed, erased or superseded by a later proof of their account since are dropped.
+        /// Returns the number of reminders handled.
+        fn remind_renewals() -> u64 {
+            let now = T::UnixTime::now().as_secs();
+            let due: Vec<_> = RenewalReminders::<T>::iter_keys()
+                .take_while(|(remind_at, _)| u64::from_be_bytes(*remind_at) <= now)
+                .take(T::MaxRenewalReminders::get() as usize)
+                .collect();
+            for (remind_at, hash) in &due {
+                RenewalReminders::<T>::remove(remind_at, hash);
+                let Some(attestation) = Attestations::<T>::get(hash) else { continue };
+                let Some(who) = ZkProofSubmitter::<T>::get(hash) else { continue };
+                let current = Self::reminder_time(attestation.expires_at) == Some(*remind_at)
+                    && attestation.is_valid_at(now)
+                    && AccountProofs::<T>::get(&who) == Some(*hash);
+                if current {
+                    Self::deposit_indexed_event(
+                        &[*hash],
+                        Event::AttestationExpiring {
+                            hash: *hash,
+                            who,
+                            nullifier: attestation.nullifier,
+                            expires_at: attestation.expires_at,
+                        },
+                    );
+                }
+            }
+            due.len() as u64
+        }
+
+        /// Whether the identity root is computed at the end of block `block_number`.
+        fn computes_identity_root(block_number: BlockNumberFor<T>) -> bool {
+            let period = T::IdentityRootPeriod::get();
+            !period.is_zero() && (block_number % period).is_zero()
+        }
+
+        /// The weight of computing the identity root: a read per proof covered, the lookahead read
+        /// deciding whether proofs were left out and the writes of the root and its digest.
+        fn identity_root_weight() -> Weight {
+            T::DbWeight::get().reads_writes(T::MaxIdentityRootLeaves::get() as u64 + 1, 2)
+        }
+
+        /// The sorted, distinct commitments of the proofs still valid among the first
+        /// `MaxIdentityRootLeaves` stored, and whether no stored proof was left out.
+        fn identity_leaves() -> (Vec<Commitment>, bool) {
+            let now = T::UnixTime::now().as_secs();
+            let mut attestations = Attestations::<T>::iter();
+            let mut leaves: Vec<Commitment> = attestations
+                .by_ref()
+                .take(T::MaxIdentityRootLeaves::get() as usize)
+                .filter(|(_, attestation)| attestation.is_valid_at(now))
+                .map(|(_, attestation)| attestation.commitment)
+                .collect();
+            let complete = attestations.next().is_none();
+            leaves.sort_unstable();
+            leaves.dedup();
+            (leaves, complete)
+        }
+
+        /// Compute the identity root as of the end of block `block_number`.
+        fn update_identity_root(block_number: BlockNumberFor<T>) {
+            let (leaves, complete) = Self::identity_leaves();
+            let root = binary_merkle_tree::merkle_root::<Keccak256, _>(&leaves);
+            let leaves = leaves.len() as u32;
+            LatestIdentityRoot::<T>::put(IdentityRoot { block: block_number, root, leaves, complete });
+            frame_system::Pallet::<T>::deposit_log(DigestItem::Consensus(IDENTITY_ROOT_ENGINE_ID, root.encode()));
+            Self::deposit_event(Event::IdentityRootComputed { root, leaves, complete });
+        }
+
+        /// The latest identity root.
+        pub fn identity_root() -> Option<IdentityRoot<BlockNumberFor<T>>> {
+            LatestIdentityRoot::<T>::get()
+        }
+
+        /// A proof that `commitment` is covered by the identity root computed at the end of the
+        /// current block. Only meaningful in the state of a block the root was computed in.
+        pub fn identity_proof(commitment: &Commitment) -> Option<IdentityProof> {
+            let (leaves, _) = Self::identity_leaves();
+            let leaf_index = leaves.binary_search(commitment).ok()?;
+            let proof = binary_merkle_tree::merkle_proof::<Keccak256, _, _>(leaves, leaf_index);
+            Some(IdentityProof {
+                root: proof.root,
+                proof: proof.proof,
+                leaves: proof.number_of_leaves as u32,
+                leaf_index: leaf_index as u32,
+            })
+        }
+
+        /// Count a proof verification against the `max_verifications_per_block` parameter. Returns
+        /// `false`, counting nothing, if the current block already includes as many.
+        pub(crate) fn reserve_verification() -> bool {
+            let limit = Parameters::<T>::get().max_verifications_per_block;
+            VerificationsInBlock::<T>::mutate(|count| {
+                let reserved = *count < limit;
+                if reserved {
+                    *count += 1;
+                }
+                reserved
+            })
+        }
+
+        /// Account `weight` against the proof calls' share of the current block. Returns `false`,
+        /// accounting nothing, if the share cannot fit it.
+        pub(crate) fn reserve_proof_weight(weight: Weight) -> bool {
+            let limit = Parameters::<T>::get().proof_weight_share * T::BlockWeights::get().max_block;
+            ProofWeightUsed::<T>::mutate(|used| {
+                let reserved = used.saturating_add(weight).all_lte(limit);
+                if reserved {
+                    *used = used.saturating_add(weight);
+                }
+                reserved
+            })
+        }
+
+        /// Verify and store a ZK proof on behalf of `who`. Returns what the proof attests to.
+        fn do_store_zk_proof(who: T::AccountId, json: Vec<u8>) -> Result<VerifiedProof, DispatchError> {
+            // Reject oversized payloads before doing any parsing work.
+            let parameters = Parameters::<T>::get();
+            ensure!(json.len() <= parameters.max_json_length as usize, Error::<T>::ZkProofTooLarge);
+
+            // Calculate the hash of the provided JSON data.
+            use frame_support::sp_runtime::traits::Hash;
+            let proof_hash = T::Hashing::hash(&json);
+
+            // Only the original submitter may store the same proof again, and only if allowed.
+            if let Some(submitter) = ZkProofSubmitter::<T>::get(proof_hash) {
+                ensure!(T::AllowProofOverwrite::get() && submitter == who, Error::<T>::ProofAlreadyExists);
+            }
+
+            // Ensure the provided JSON data is a valid ZK proof.
+            let verified = Self::verify_zk_proof(&json)?;
+            Self::consume_nonce(&verified, proof_hash);
+
+            // Convert the JSON data into a bounded vector.
+            let bounded_json = BoundedVec::try_from(json).map_err(|_| Error::<T>::ZkProofTooLarge)?;
+
+            // Store the ZK proof data in the storage map, unless raw proofs must be discarded. The
+            // claims behind a predicate proof are never kept, only whether the predicate holds.
+            let keep_payload = !T::PrivacyMode::get() && verified.predicate.is_none();
+            if keep_payload {
+                Self::insert_proof_data(proof_hash, bounded_json.clone());
+            }
+            let nullifier = Self::identity_nullifier(&verified);
+            let now = T::UnixTime::now().as_secs();
+            let expires_at = now.saturating_add(parameters.proof_lifetime);
+            Self::insert_attestation(
+                proof_hash,
+                Attestation {
+                    provider: verified.claims.issuer.clone(),
+                    commitment: verified.commitment,
+                    nullifier,
+                    circuit_version: verified.circuit_version,
+                    verified_at: now,
+                    expires_at,
+                },
+            );
+            let first_proof = !Nullifiers::<T>::contains_key(nullifier);
+            Nullifiers::<T>::insert(nullifier, proof_hash);
+            IdentityVerifiedAt::<T>::insert(nullifier, frame_system::Pallet::<T>::block_number());
+            ProviderUsage::<T>::mutate(&verified.claims.issuer, |stats| {
+                stats.logins.saturating_inc();
+                if first_proof {
+                    stats.accounts.saturating_inc();
+                }
+                stats.last_active = frame_system::Pallet::<T>::block_number();
+            });
+            IdentityProofs::<T>::insert(nullifier, proof_hash, ());
+            ProofsByBlock::<T>::insert(frame_system::Pallet::<T>::block_number(), proof_hash, ());
+            ZkProofSubmitter::<T>::insert(proof_hash, &who);
+            AccountProofs::<T>::insert(&who, proof_hash);
+            if let Some(client_id) = &verified.namespace {
+                if !NamespaceProofs::<T>::contains_key(client_id, proof_hash) {
+                    NamespaceUsages::<T>::mutate(client_id, |usage| usage.stored_proofs.saturating_inc());
+                }
+                ProofNamespaces::<T>::insert(proof_hash, client_id);
+                NamespaceProofs::<T>::insert(client_id, proof_hash, ());
+            }
+            ZkProofExpiry::<T>::insert(proof_hash, expires_at);
+            Self::schedule_reminder(proof_hash, expires_at);
+            let topics = [proof_hash, Self::commitment_topic(&verified.commitment)];
+            if let Some(attestation) = verified.predicate {
+                PredicateAttestations::<T>::insert(proof_hash, attestation);
+                Self::deposit_indexed_event(
+                    &topics,
+                    Event::PredicateAttested {
+                        hash: proof_hash,
+                        predicate: attestation.predicate,
+                        holds: attestation.holds,
+                    },
+                );
+            }
+            T::OnProofVerified::on_proof_verified(&who, &verified.commitment, &verified.claims.digest());
+
+            // Emit an event indicating the ZK proof data has been stored.
+            Self::deposit_indexed_event(
+                &topics,
+                Event::ZkProofStored {
+                    hash: proof_hash,
+                    who,
+                    provider: verified.claims.issuer.clone(),
+                    commitment: verified.commitment,
+                    nullifier,
+                    json: (keep_payload && T::EmitProofPayloads::get()).then_some(bounded_json),
+                },
+            );
+
+            Ok(verified)
+        }
+
+        /// Deposit `event` under `topics`, for indexers to look the events of a proof or an
+        /// identity up without decoding every event.
+        fn deposit_indexed_event(topics: &[T::Hash], event: Event<T>) {
+            let event = <T as Config>::RuntimeEvent::from(event);
+            frame_system::Pallet::<T>::deposit_event_indexed(topics, event.into());
+        }
+
+        /// The topic of the events of the proofs bound to `commitment`: the commitment itself.
+        pub fn commitment_topic(commitment: &Commitment) -> T::Hash {
+            Decode::decode(&mut TrailingZeroInput::new(commitment))
+                .expect("infinite length input; no invalid inputs for type; qed")
+        }
+
+        /// The hash under which a base64 verifying key is approved.
+        pub fn verifying_key_hash(verifying_key: &str) -> VkHash {
+            sp_io::hashing::blake2_256(verifying_key.as_bytes())
+        }
+
+        /// The zkLogin account of the identity with `nullifier`, which `zklogin_execute` dispatches
+        /// calls as.
+        pub fn zklogin_account(nullifier: &Nullifier) -> T::AccountId {
+            let entropy = sp_io::hashing::blake2_256(&(ZKLOGIN_ACCOUNT_CONTEXT, nullifier).encode());
+            Decode::decode(&mut TrailingZeroInput::new(&entropy))
+                .expect("infinite length input; no invalid inputs for type; qed")
+        }
+
+        /// The nullifier of the identity with `commitment` at `provider`.
+        pub fn nullifier(provider: &IssuerId, commitment: &Commitment) -> Nullifier {
+            sp_io::hashing::blake2_256(&(NULLIFIER_CONTEXT, provider, commitment).encode())
+        }
+
+        /// The nullifier of the identity with `commitment` at `provider`, in the namespace of the
+        /// dApp `client_id`.
+        pub fn namespace_nullifier(provider: &IssuerId, client_id: &ClientId, commitment: &Commitment) -> Nullifier {
+            sp_io::hashing::blake2_256(&(NULLIFIER_CONTEXT, provider, client_id, commitment).encode())
+        }
+
+        /// The Sui zkLogin address of the identity whose address seed is `commitment` at
+        /// `provider`: the BLAKE2b-256 hash of the zkLogin signature flag, the length-prefixed
+        /// issuer and the big-endian address seed.
+        pub fn sui_address(provider: &IssuerId, commitment: &Commitment) -> Nullifier {
+            let mut address_seed = *commitment;
+            address_seed.reverse();
+            let mut preimage = Vec::with_capacity(2 + provider.len() + address_seed.len());
+            preimage.push(SUI_ZKLOGIN_FLAG);
+            preimage.push(provider.len() as u8);
+            preimage.extend_from_slice(provider);
+            preimage.extend_from_slice(&address_seed);
+            sp_io::hashing::blake2_256(&preimage)
+        }
+
+        /// The nullifier of the identity `verified` attests to, in its namespace if any.
+        fn identity_nullifier(verified: &VerifiedProof) -> Nullifier {
+            Self::nullifier_in(&verified.claims.issuer, verified.namespace.as_ref(), &verified.commitment)
+        }
+
+        /// The nullifier of the identity bound to `commitment` at `provider`, in `namespace` if any,
+        /// derived with the address scheme of the namespace.
+        fn nullifier_in(provider: &IssuerId, namespace: Option<&ClientId>, commitment: &Commitment) -> Nullifier {
+            match namespace {
+                Some(client_id) => match NamespaceAddressSchemes::<T>::get(client_id) {
+                    AddressScheme::Native => Self::namespace_nullifier(provider, client_id, commitment),
+                    AddressScheme::Sui => Self::sui_address(provider, commitment),
+                },
+                None => Self::nullifier(provider, commitment),
+            }
+        }
+
+        /// The namespace of the dApp a token was issued to, if registered.
+        fn namespace_of(audience: Option<&str>) -> Option<ClientId> {
+            audience
+                .and_then(|audience| ClientId::try_from(audience.as_bytes().to_vec()).ok())
+                .filter(Namespaces::<T>::contains_key)
+        }
+
+        /// Count the login the proof `verified` makes into its namespace, if any. Fails if the
+        /// logins into the namespace are suspended, or if the login takes it over its quota.
+        fn charge_namespace_login(verified: &VerifiedProof) -> DispatchResult {
+            let Some(client_id) = &verified.namespace else {
+                return Ok(());
+            };
+            let paused = Namespaces::<T>::get(client_id).map_or(false, |namespace| namespace.paused);
+            ensure!(!paused, Error::<T>::NamespacePaused);
+
+            let now = frame_system::Pallet::<T>::block_number();
+            let quota = NamespaceQuotas::<T>::get(client_id);
+            NamespaceUsages::<T>::try_mutate(client_id, |usage| {
+                if usage.block != now {
+                    usage.block = now;
+                    usage.block_logins = 0;
+                }
+                usage.block_logins.saturating_inc();
+
+                let Some(quota) = quota else {
+                    return Ok(());
+                };
+                let period_start = now - now % quota.period;
+                if usage.period_start != period_start {
+                    usage.period_start = period_start;
+                    usage.period_logins = 0;
+                }
+                usage.period_logins.saturating_inc();
+
+                let within = |used: u32, limit: u32| limit == 0 || used <= limit;
+                ensure!(
+                    within(usage.block_logins, quota.logins_per_block)
+                        && within(usage.period_logins, quota.logins_per_period)
+                        && within(usage.stored_proofs, quota.stored_proofs),
+                    Error::<T>::QuotaExceeded
+                );
+                Ok(())
+            })
+        }
+
+        /// Apply `f` to the namespace of `client_id`, on behalf of its owner `who`.
+        fn mutate_namespace(
+            who: &T::AccountId,
+            client_id: &ClientId,
+            f: impl FnOnce(&mut Namespace<T::AccountId>),
+        ) -> DispatchResult {
+            Namespaces::<T>::try_mutate(client_id, |namespace| {
+                let namespace = namespace.as_mut().ok_or(Error::<T>::UnknownNamespace)?;
+                ensure!(namespace.owner == *who, Error::<T>::NotNamespaceOwner);
+                f(namespace);
+                Ok(())
+            })
+        }
+
+        /// Whether the third part of `nonce`, after the block number and hash, is `key`
+        /// hex-encoded (optionally `0x` prefixed).
+        fn nonce_binds(nonce: &str, key: &T::AccountId) -> bool {
+            let Some(bound) = nonce.splitn(3, '-').nth(2) else {
+                return false;
+            };
+            let key = key.encode();
+            let mut decoded = key.clone();
+            hex::decode_to_slice(bound.strip_prefix("0x").unwrap_or(bound), &mut decoded).is_ok() && decoded == key
+        }
+
+        /// The identity `who` is an unexpired device key of.
+        fn active_identity(who: &T::AccountId) -> Result<Nullifier, Error<T>> {
+            let nullifier = KeyIdentities::<T>::get(who).ok_or(Error::<T>::UnknownDeviceKey)?;
+            let device = DeviceKeys::<T>::get(nullifier, who).ok_or(Error::<T>::UnknownDeviceKey)?;
+            ensure!(T::UnixTime::now().as_secs() < device.expires_at, Error::<T>::DeviceKeyExpired);
+            Ok(nullifier)
+        }
+
+        /// Remove the device key `key` of the identity `nullifier`, and its latest proof if it
+        /// attests to that identity.
+        fn remove_device_key(nullifier: Nullifier, key: &T::AccountId) {
+            if DeviceKeys::<T>::take(nullifier, key).is_some() {
+                StorageUsage::<T>::mutate(|usage| usage.device_keys.saturating_dec());
+            }
+            Self::forget_device_key(key);
+            AccountProofs::<T>::mutate_exists(key, |latest| {
+                let attestation = latest.and_then(|hash| Attestations::<T>::get(hash));
+                if attestation.map(|attestation| attestation.nullifier) == Some(nullifier) {
+                    *latest = None;
+                }
+            });
+        }
+
+        /// Remove the tags of the proof stored under `proof_hash`, and its entries in the tag index.
+        fn untag_proof(proof_hash: T::Hash) {
+            for tag in ProofTagsOf::<T>::take(proof_hash).unwrap_or_default() {
+                TaggedProofs::<T>::remove(tag, proof_hash);
+            }
+        }
+
+        /// Remove what is kept about the device key `key` besides its entry in `DeviceKeys`.
+        fn forget_device_key(key: &T::AccountId) {
+            KeyIdentities::<T>::remove(key);
+            SpendingLimits::<T>::remove(key);
+            Spent::<T>::remove(key);
+        }
+
+        /// Check `call` against the spending limit of `who`, if it has one. Returns the first
+        /// block of the current period and what `who` will have spent in it once `call` is
+        /// dispatched.
+        pub(crate) fn check_spending(
+            who: &T::AccountId,
+            call: &<T as frame_system::Config>::RuntimeCall,
+        ) -> Result<Option<(BlockNumberFor<T>, u128)>, Error<T>> {
+            let Some(limit) = SpendingLimits::<T>::get(who) else {
+                return Ok(None);
+            };
+            if let Some(calls) = &limit.calls {
+                // Calls wrapped in others, e.g. in `zklogin_execute`, must be allowed as well.
+                let mut next = Some(call);
+                while let Some(call) = next {
+                    let index = call.using_encoded(|call| call.get(..2).map(|index| (index[0], index[1])));
+                    ensure!(index.map_or(false, |index| calls.contains(&index)), Error::<T>::CallNotAllowed);
+                    next = T::CallSpending::wrapped(call);
+                }
+            }
+
+            let now = frame_system::Pallet::<T>::block_number();
+            let period_start = now - now % limit.period;
+            let spent = Spent::<T>::get(who).filter(|(start, _)| *start == period_start).map_or(0, |(_, spent)| spent);
+            let spent = spent.saturating_add(T::CallSpending::spending(call));
+            ensure!(spent <= limit.amount, Error::<T>::SpendingLimitExceeded);
+
+            Ok(Some((period_start, spent)))
+        }
+
+        /// Whether a commitment made at `committed_at` is older than `RevealWindow`.
+        fn commitment_expired(committed_at: BlockNumberFor<T>) -> bool {
+            frame_system::Pallet::<T>::block_number().saturating_sub(committed_at) > T::RevealWindow::get()
+        }
+
+        /// Whether the current keyset of `issuer` is past its expiry plus the grace period.
+        pub(crate) fn keyset_expired(issuer: &IssuerId) -> bool {
+            match JwksExpiry::<T>::get(issuer) {
+                Some(expires_at) => {
+                    T::UnixTime::now().as_secs() > expires_at.saturating_add(T::JwksGracePeriod::get())
+                }
+                None => false,
+            }
+        }
+
+        /// Decode a proof envelope in any format into the JSON envelope, learning its format.
+        fn decode_proof(proof_data: &[u8]) -> Result<(envelope::EnvelopeFormat, Cow<'_, [u8]>), Error<T>> {
+            let map_err = |e| {
+                log::error!("Malformed zk proof: {:?}", e);
+                match e {
+                    envelope::EnvelopeError::UnknownFormat => Error::<T>::UnknownEnvelopeFormat,
+                    _ => Error::<T>::InvalidProof,
+                }
+            };
+            let (format, _) = envelope::EnvelopeFormat::of(proof_data).map_err(map_err)?;
+            Ok((format, envelope::decode_envelope(proof_data).map_err(map_err)?))
+        }
+
+        /// Parse a JSON proof envelope decoded from `format`, bounding the parts that are
+        /// expensive to decode.
+        fn parse_proof(proof_data: &[u8], format: envelope::EnvelopeFormat) -> Result<JsonProof<'_>, Error<T>> {
+            let mut json_proof = JsonProof::from_bytes(proof_data).map_err(|e| {
+                log::error!("Malformed zk proof: {:?}", e);
+                Error::<T>::InvalidProof
+            })?;
+            // Bound the token before decoding any of its segments.
+            ensure!(json_proof.jwt_token.len() <= T::MaxJwtLength::get() as usize, Error::<T>::JwtTooLarge);
+            // Bound the public inputs before deserializing any field element.
+            ensure!(
+                json_proof.public_input_count() <= T::MaxPublicInputs::get() as usize,
+                Error::<T>::TooManyPublicInputs
+            );
+            // Clients write the base64 fields in the variant of their provider's tooling, the
+            // runtime in the standard one when converting from other formats. Tokens whose issuer
+            // cannot be read are rejected once validated.
+            if format.client_base64() {
+                let issuer = match &json_proof.token {
+                    Some(token) => Some(token.iss.clone().into_owned()),
+                    None => envelope::jwt_issuer(&json_proof.jwt_token),
+                };
+                if let Some(issuer) = issuer.and_then(|issuer| IssuerId::try_from(issuer.into_bytes()).ok()) {
+                    json_proof.encoding = ProviderEncodings::<T>::get(&issuer).proof;
+                }
+            }
+            Ok(json_proof)
+        }
+
+        /// Split a token and decode its header and payload in the variant of its provider,
+        /// returning its issuer too. The issuer is read first, decoding the payload in any variant.
+        fn token_segments(token: &str) -> Result<(IssuerId, serde_json::Value, serde_json::Value), Error<T>> {
+            let issuer = envelope::jwt_issuer(token).ok_or(Error::<T>::InvalidProof)?;
+            let issuer = IssuerId::try_from(issuer.into_bytes()).map_err(|_| Error::<T>::InvalidProof)?;
+            let variant = ProviderEncodings::<T>::get(&issuer).jwt;
+            let (header, payload) = jwt_segments_as(token, variant).ok_or(Error::<T>::InvalidEncoding)?;
+            Ok((issuer, header, payload))
+        }
+
+        /// The checks of a proof that need neither pairings nor the block context: its size, its
+        /// structure, and whether its token is from a trusted issuer and signed with a known key.
+        /// Run by `CheckProofStructure` when transactions enter the pool.
+        pub fn check_structure(proof_data: &[u8]) -> Result<SubmissionContext, Error<T>> {
+            Self::admit(proof_data).map(|admission| admission.context)
+        }
+
+        /// Run the checks of `check_structure`, learning what prioritizes the proof in the pool.
+        pub(crate) fn admit(proof_data: &[u8]) -> Result<Admission, Error<T>> {
+            ensure!(proof_data.len() <= Parameters::<T>::get().max_json_length as usize, Error::<T>::ZkProofTooLarge);
+            let (format, envelope) = Self::decode_proof(proof_data)?;
+            let json_proof = Self::parse_proof(&envelope, format)?;
+
+            let (issuer, header, payload) = match &json_proof.token {
+                Some(token) => {
+                    let (header, payload) = token.segments();
+                    let issuer: IssuerId = payload
+                        .get("iss")
+                        .and_then(|i| i.as_str())
+                        .and_then(|iss| iss.as_bytes().to_vec().try_into().ok())
+                        .ok_or(Error::<T>::InvalidProof)?;
+                    (issuer, header, payload)
+                }
+                None => Self::token_segments(&json_proof.jwt_token)?,
+            };
+            ensure!(T::AllowedIssuers::get().contains(&issuer), Error::<T>::UntrustedIssuer);
+            ensure!(!DisabledProviders::<T>::contains_key(&issuer), Error::<T>::ProviderDisabled);
+            Self::check_algorithm(&issuer, &header)?;
+
+            let kid = header.get("kid").and_then(|k| k.as_str()).ok_or(Error::<T>::InvalidProof)?;
+            let jwks = Jwks::<T>::get(&issuer).ok_or(Error::<T>::InvalidProof)?;
+            ensure!(jwks.iter().any(|k| k.kid.as_slice() == kid.as_bytes()), Error::<T>::InvalidProof);
+
+            // The identity is the one the proof is stored under once verified, in the namespace of
+            // the token's audience. Malformed public inputs are only rejected once verified.
+            let namespace = match &json_proof.token {
+                Some(token) => Self::namespace_of(Some(token.aud.as_ref())),
+                None => T::ProviderAdapters::normalize(issuer.clone(), &payload)
+                    .and_then(|claims| Self::namespace_of(claims.audience.as_deref())),
+            };
+            let nullifier = json_proof
+                .commitment()
+                .ok()
+                .map(|commitment| Self::nullifier_in(&issuer, namespace.as_ref(), &commitment));
+            // Fresher tokens go first.
+            let age = payload
+                .get("iat")
+                .and_then(|iat| iat.as_u64())
+                .map_or(u64::MAX, |iat| T::UnixTime::now().as_secs().saturating_sub(iat));
+            let priority = T::MaxTokenAge::get().saturating_sub(age);
+
+            Ok(Admission {
+                context: SubmissionContext {
+                    kid: Some(KeyId::truncate_from(kid.as_bytes().to_vec())),
+                    circuit_version: json_proof.circuit_version,
+                    provider: Some(issuer),
+                },
+                nullifier,
+                priority,
+            })
+        }
+
+        /// Verify a ZK proof from its raw data.
+        fn verify_zk_proof(proof_data: &[u8]) -> Result<VerifiedProof, Error<T>> {
+            let (format, envelope) = Self::decode_proof(proof_data)?;
+            let json_proof = Self::parse_proof(&envelope, format)?;
+            let circuit_version = json_proof
+                .circuit_version
+                .map(CircuitVersion::try_from)
+                .transpose()
+                .map_err(|_| Error::<T>::UnknownCircuitVersion)?;
+            // Deprecated circuits are rejected from their sunset on.
+            let sunset = CircuitSunsets::<T>::get(json_proof.circuit_version.unwrap_or(0));
+            ensure!(
+                sunset.map_or(true, |sunset_at| frame_system::Pallet::<T>::block_number() < sunset_at),
+                Error::<T>::CircuitSunset
+            );
+            // Proofs are bound to a chain, so they cannot be replayed across chains.
+            let chain_id = json_proof.chain_id().map_err(|_| Error::<T>::WrongChain)?;
+            let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+            ensure!(genesis_hash.as_ref() == chain_id.as_slice(), Error::<T>::WrongChain);
+
+            let commitment = json_proof.commitment().map_err(|e| {
+                log::error!("Invalid public inputs: {:?}", e);
+                Error::<T>::InvalidProof
+            })?;
+            if T::InsecureSkipVerification::get() {
+                log::warn!("INSECURE: skipping zk proof verification");
+            } else {
+                // Only keys approved by governance may be embedded, until keys are registered
+                // on-chain rather than brought along with each proof.
+                let vk_hash = Self::verifying_key_hash(&json_proof.verifying_key);
+                ensure!(ApprovedVerifyingKeys::<T>::contains_key(vk_hash), Error::<T>::UnapprovedVerifyingKey);
+
+                let instance = json_proof.instance().map_err(|e| match e {
+                    envelope::EnvelopeError::InvalidVerifyingKey => Error::<T>::VkDeserializationFailed,
+                    e => {
+                        log::error!("Invalid proof points: {:?}", e);
+                        Error::<T>::InvalidProof
+                    }
+                })?;
+
+                if !host::verify(&instance) {
+                    error!("FAIL VERIFICATION ZK PROOF");
+                    return Err(Error::<T>::InvalidProof);
+                }
+            }
+
+            // Circuits keeping the token private check its signature, and the chain what it discloses.
+            let keeps_token_private = circuit_version.is_some_and(CircuitVersion::keeps_token_private);
+            ensure!(keeps_token_private == json_proof.token.is_some(), Error::<T>::PrivateTokenMismatch);
+            let (claims, modulus) = match &json_proof.token {
+                Some(token) => {
+                    let (claims, key) = Self::validate_private_token(token)?;
+                    (claims, Some(key.n.into_inner()))
+                }
+                None => {
+                    let claims = Self::validate_jwt(&json_proof.jwt_token).map_err(|e| {
+                        error!("FAIL VERIFICATION TOKEN JWT");
+                        e
+                    })?;
+                    (claims, None)
+                }
+            };
+
+            // Predicate circuits attest whether a registered predicate holds for the claims.
+            let predicate = match circuit_version {
+                Some(version) if version.proves_predicate() => {
+                    let predicate = json_proof.predicate_id().ok_or(Error::<T>::UnknownPredicate)?;
+                    ensure!(Predicates::<T>::contains_key(predicate), Error::<T>::UnknownPredicate);
+                    let holds = circuit::predicate_result(&json_proof, version)
+                        .map_err(|_| Error::<T>::InvalidProof)?
+                        .ok_or(Error::<T>::PublicInputMismatch)?;
+                    Some(PredicateAttestation { predicate, holds })
+                }
+                _ => None,
+            };
+
+            // The public inputs must be derived from the claims the way the circuit lays them out.
+            if let Some(version) = circuit_version {
+                let predicate = predicate.map(|p| (p.predicate, p.holds));
+                let matches = circuit::matches_layout(&json_proof, version, &claims, predicate, modulus.as_deref())
+                    .map_err(|_| Error::<T>::InvalidProof)?;
+                ensure!(matches, Error::<T>::PublicInputMismatch);
+            }
+
+            // Tokens issued to a registered dApp log into its namespace.
+            let namespace = Self::namespace_of(claims.audience.as_deref());
+
+            let verified = VerifiedProof {
+                claims,
+                commitment,
+                predicate,
+                namespace,
+                circuit_version: json_proof.circuit_version.unwrap_or(0),
+            };
+            ensure!(
+                !Self::is_blocked(&Self::identity_nullifier(&verified), &verified.commitment),
+                Error::<T>::IdentityBlocked
+            );
+            // A token may only be spent by one proof while its nonce is fresh.
+            use frame_support::sp_runtime::traits::Hash;
+            if let Some((block, tokens)) = Self::consumed_nonce_keys(&verified) {
+                let proof_hash = T::Hashing::hash(proof_data);
+                for token in tokens {
+                    let consumed_by = ConsumedNonces::<T>::get(block, token);
+                    ensure!(consumed_by.map_or(true, |hash| hash == proof_hash), Error::<T>::NonceAlreadyUsed);
+                }
+            }
+
+            Ok(verified)
+        }
+
+        /// The block number `nonce` is bound to, if it has the form of a nonce.
+        fn nonce_block(nonce: &str) -> Option<BlockNumberFor<T>> {
+            nonce.split('-').next().and_then(|n| n.parse::<u64>().ok()).and_then(|n| n.try_into().ok())
+        }
+
+        /// The keys under which the token of `verified` is consumed: the block its nonce is bound
+        /// to, and the hashes of its identity with its nonce and with its id, if it has one. A
+        /// nonce is spent once even by tokens of different ids, and an id even under other nonces.
+        fn consumed_nonce_keys(verified: &VerifiedProof) -> Option<(BlockNumberFor<T>, Vec<[u8; 32]>)> {
+            let nonce = verified.claims.nonce.as_deref()?;
+            let block = Self::nonce_block(nonce)?;
+            let nullifier = Self::identity_nullifier(verified);
+            let tokens = core::iter::once(nonce)
+                .chain(verified.claims.token_id.as_deref())
+                .map(|token| sp_io::hashing::blake2_256(&(nullifier, token).encode()))
+                .collect();
+            Some((block, tokens))
+        }
+
+        /// Mark the token of `verified` as spent by the proof `proof_hash`.
+        fn consume_nonce(verified: &VerifiedProof, proof_hash: T::Hash) {
+            if let Some((block, tokens)) = Self::consumed_nonce_keys(verified) {
+                for token in tokens {
+                    ConsumedNonces::<T>::insert(block, token, proof_hash);
+                }
+            }
+        }
+
+        /// Check that `nonce` is bound to a recent block, bounding the replay window of a token.
+        ///
+        /// The nonce must be of the form `<block number>-<block hash>[-<anything>]`, the hash being
+        /// hex-encoded (optionally `0x` prefixed).
+        fn check_nonce(nonce: &str) -> Result<(), Error<T>> {
+            let number = Self::nonce_block(nonce).ok_or(Error::<T>::InvalidNonce)?;
+            let hash_hex = nonce.splitn(3, '-').nth(1).ok_or(Error::<T>::InvalidNonce)?;
+            let hash_hex = hash_hex.strip_prefix("0x").unwrap_or(hash_hex);
+
+            let now = frame_system::Pallet::<T>::block_number();
+            // The hash of the current block is not known yet.
+            ensure!(number < now && now - number <= T::NonceBlockWindow::get(), Error::<T>::StaleNonce);
+
+            let expected = frame_system::Pallet::<T>::block_hash(number);
+            let mut hash = T::Hash::default();
+            hex::decode_to_slice(hash_hex, hash.as_mut()).map_err(|_| Error::<T>::InvalidNonce)?;
+            ensure!(hash == expected, Error::<T>::StaleNonce);
+
+            Ok(())
+        }
+
+        /// Check the `alg` of a JWT header against the algorithms the adapter of `issuer` accepts,
+        /// before any signature work, so that tokens cannot be downgraded to a weaker algorithm or
+        /// to none. Returns the algorithm.
+        fn check_algorithm<'h>(issuer: &IssuerId, header: &'h serde_json::Value) -> Result<&'h str, Error<T>> {
+            let alg = header.get("alg").and_then(|a| a.as_str()).ok_or(Error::<T>::UnsupportedAlgorithm)?;
+            ensure!(!alg.eq_ignore_ascii_case("none"), Error::<T>::UnsupportedAlgorithm);
+            ensure!(T::ProviderAdapters::algorithms(issuer).contains(&alg), Error::<T>::UnsupportedAlgorithm);
+            Ok(alg)
+        }
+
+        /// Whether `path` is a JSON pointer to a claim.
+        fn is_claim_path(path: &[u8]) -> bool {
+            path.first() == Some(&b'/') && core::str::from_utf8(path).is_ok()
+        }
+
+        /// Check the claims of `payload` against the requirements of the claim policy `policy`,
+        /// returning the hashes of their values.
+        fn check_claim_policy(
+            policy: ClaimPolicy,
+            requirements: &[ClaimRequirement],
+            payload: &serde_json::Value,
+        ) -> Result<Vec<ClaimHash>, Error<T>> {
+            requirements
+                .iter()
+                .enumerate()
+                .map(|(index, requirement)| {
+                    let violation =
+                        |violation| Error::<T>::ClaimPolicyViolation { policy, index: index as u8, violation };
+                    let path = core::str::from_utf8(&requirement.path).map_err(|_| Error::<T>::InvalidClaimPath)?;
+                    let value = payload.pointer(path).ok_or(violation(ClaimViolation::Missing))?;
+                    let allowed = |value: &serde_json::Value| requirement.allowed.contains(&claim_hash(value));
+                    let permitted = requirement.allowed.is_empty()
+                        || allowed(value)
+                        || value.as_array().is_some_and(|values| values.iter().any(allowed));
+                    ensure!(permitted, violation(ClaimViolation::NotAllowed));
+                    Ok(claim_hash(value))
+                })
+                .collect()
+        }
+
+        /// Validate a JWT against the keys registered on-chain for its issuer, returning its
+        /// normalized claims.
+        pub(crate) fn validate_jwt(token: &str) -> Result<VerifiedClaims, Error<T>> {
+            let (issuer, header, payload) = Self::token_segments(token)?;
+            let kid = header.get("kid").ok_or(Error::<T>::InvalidProof)?.as_str().unwrap_or("");
+
+            ensure!(T::AllowedIssuers::get().contains(&issuer), Error::<T>::UntrustedIssuer);
+            ensure!(!DisabledProviders::<T>::contains_key(&issuer), Error::<T>::ProviderDisabled);
+            let alg = Self::check_algorithm(&issuer, &header)?;
+
+            let mut claims =
+                T::ProviderAdapters::normalize(issuer.clone(), &payload).ok_or(Error::<T>::InvalidProof)?;
+            Self::check_token_times(claims.issued_at, claims.expires_at)?;
+
+            let nonce = claims.nonce.as_deref().ok_or(Error::<T>::InvalidNonce)?;
+            Self::check_nonce(nonce)?;
+
+            let key = Self::signing_key(&issuer, kid, alg)?;
+            Self::check_signature(&issuer, token, &key)?;
+
+            // The claims of a valid token are held to the policies of its provider and of its dApp.
+            claims.required_claims =
+                Self::check_claim_policy(ClaimPolicy::Provider, &RequiredClaims::<T>::get(&issuer), &payload)?;
+            if let Some(client_id) =
+                claims.audience.as_ref().and_then(|audience| ClientId::try_from(audience.as_bytes().to_vec()).ok())
+            {
+                Self::check_claim_policy(ClaimPolicy::Namespace, &NamespaceClaims::<T>::get(&client_id), &payload)?;
+                let tenants = NamespaceTenants::<T>::get(&client_id);
+                let allowed = claims.tenant.as_ref().is_some_and(|tenant| {
+                    tenants.iter().any(|allowed| allowed.eq_ignore_ascii_case(tenant.as_bytes()))
+                });
+                ensure!(tenants.is_empty() || allowed, Error::<T>::TenantNotAllowed);
+            }
+
+            Ok(claims)
+        }
+
+        /// Validate what a proof keeping its token private discloses of it, returning the claims it
+        /// discloses and the key the circuit checks the signature of the token against.
+        pub(crate) fn validate_private_token(token: &DisclosedToken) -> Result<(VerifiedClaims, JwkRecord), Error<T>> {
+            let (header, _) = token.segments();
+            let issuer: IssuerId = token.iss.as_bytes().to_vec().try_into().map_err(|_| Error::<T>::InvalidProof)?;
 
-    return true
+            ensure!(T::AllowedIssuers::get().contains(&issuer), Error::<T>::UntrustedIssuer);
+            ensure!(!DisabledProviders::<T>::contains_key(&issuer), Error::<T>::ProviderDisabled);
+            let alg = Self::check_algorithm(&issuer, &header)?;
+            // The claims the provider, or the namespace of the token's audience, requires are hidden
+            // along with the token. Its audience and issue time are disclosed, and bound by the
+            // circuit, so the login is held to `MaxTokenAge` and to the quota of its namespace.
+            ensure!(RequiredClaims::<T>::get(&issuer).is_empty(), Error::<T>::PrivateTokenUnsupported);
+            if let Some(client_id) = Self::namespace_of(Some(token.aud.as_ref())) {
+                ensure!(
+                    NamespaceClaims::<T>::get(&client_id).is_empty()
+                        && NamespaceTenants::<T>::get(&client_id).is_empty(),
+                    Error::<T>::PrivateTokenUnsupported
+                );
+            }
+
+            Self::check_token_times(token.iat, token.exp)?;
+            Self::check_nonce(&token.nonce)?;
+
+            let key = Self::signing_key(&issuer, &token.kid, alg)?;
+            let claims = VerifiedClaims {
+                issuer,
+                subject: Default::default(),
+                audience: Some(token.aud.clone().into_owned()),
+                email: None,
+                email_verified: false,
+                tenant: None,
+                issued_at: token.iat,
+                expires_at: token.exp,
+                nonce: Some(token.nonce.clone().into_owned()),
+                token_id: None,
+                required_claims: Vec::new(),
+            };
+            Ok((claims, key))
+        }
+
+        /// Check that a token issued at `issued_at` and expiring at `expires_at` (unix seconds) has
+        /// not expired and is at most `MaxTokenAge` old, within the clock skew tolerance.
+        fn check_token_times(issued_at: u64, expires_at: u64) -> Result<(), Error<T>> {
+            let tolerance = Parameters::<T>::get().clock_skew_tolerance;
+            let now = T::UnixTime::now().as_secs();
+            ensure!(now <= expires_at.saturating_add(tolerance), Error::<T>::TokenExpired);
+            ensure!(
+                issued_at <= now.saturating_add(tolerance) && now.saturating_sub(issued_at) <= T::MaxTokenAge::get(),
+                Error::<T>::StaleToken
+            );
+            Ok(())
+        }
+
+        /// Check the RS256 signature of `token`, a JWT of `issuer`, against `key`.
+        fn check_signature(issuer: &IssuerId, token: &str, key: &JwkRecord) -> Result<(), Error<T>> {
+            ensure!(key.kty.as_slice() == b"RSA", Error::<T>::UnsupportedAlgorithm);
+            let (signing_input, signature) = token.rsplit_once('.').ok_or(Error::<T>::InvalidProof)?;
+            let signature = encoding::decode(signature.as_bytes(), ProviderEncodings::<T>::get(issuer).jwt)
+                .map_err(|_| Error::<T>::InvalidEncoding)?;
+            // Keys are published in base64url, whatever the provider's tokens are encoded in.
+            let modulus =
+                encoding::decode(&key.n, Base64Variant::URL_SAFE).map_err(|_| Error::<T>::InvalidSignature)?;
+            let exponent =
+                encoding::decode(&key.e, Base64Variant::URL_SAFE).map_err(|_| Error::<T>::InvalidSignature)?;
+            ensure!(
+                rsa::verify_rs256(signing_input.as_bytes(), &signature, &modulus, &exponent),
+                Error::<T>::InvalidSignature
+            );
+            Ok(())
+        }
+
+        /// The key `kid` of `issuer`, which tokens signed with `alg` may be checked against.
+        fn signing_key(issuer: &IssuerId, kid: &str, alg: &str) -> Result<JwkRecord, Error<T>> {
+            let jwks = Jwks::<T>::get(issuer).ok_or(Error::<T>::InvalidProof)?;
+
+            if Self::keyset_expired(issuer) {
+                error!("JWKS of the token issuer expired");
+                return Err(Error::<T>::InvalidProof);
+            }
+
+            let jwk = jwks.into_iter().find(|k| k.kid.as_slice() == kid.as_bytes()).ok_or(Error::<T>::InvalidProof)?;
+            // Keys may leave their algorithm unspecified, but must not be used for another one.
+            ensure!(jwk.alg.is_empty() || jwk.alg.as_slice() == alg.as_bytes(), Error::<T>::UnsupportedAlgorithm);
+            Ok(jwk)
+        }
+    }
 }
 
-//TODO @Ahmed to be retrieved from on off chain worker as JWK may be rotated.
-/// Retrieve Google's JSON Web Key Set (JWKS).
-fn get_google_jwks() -> GoogleJwks {
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use circuit::CircuitVersion;
+use envelope::{jwt_segments_as, DisclosedToken, JsonProof};
+use log::error;
+use sp_runtime::transaction_validity::TransactionPriority;
+
+/// What a successfully verified proof attests to.
+struct VerifiedProof {
+    /// The normalized claims of the embedded token.
+    claims: VerifiedClaims,
+    /// The commitment the proof is bound to.
+    commitment: Commitment,
+    /// The predicate a predicate proof attests to, and whether it holds.
+    predicate: Option<PredicateAttestation>,
+    /// The namespace of the dApp the token was issued to, if registered.
+    namespace: Option<ClientId>,
+    /// The version of the circuit the proof was made with, 0 if it declared none.
+    circuit_version: u32,
+}
+
+/// What the admission checks learn of a proof submission.
+pub(crate) struct Admission {
+    /// What is kept to diagnose the failure of the submission.
+    pub context: SubmissionContext,
+    /// The nullifier of the identity the proof is stored under, in the namespace of its token's
+    /// audience if any, if its public inputs are well-formed.
+    pub nullifier: Option<Nullifier>,
+    /// The priority of the submission in the pool: how much younger its token is than
+    /// `MaxTokenAge`.
+    pub priority: TransactionPriority,
+}
+
+/// Domain separator of nullifiers.
+const NULLIFIER_CONTEXT: &[u8] = b"dot-login/nullifier";
+
+/// The signature scheme flag Sui prefixes zkLogin addresses with.
+const SUI_ZKLOGIN_FLAG: u8 = 0x05;
+
+/// Domain separator of zkLogin accounts.
+const ZKLOGIN_ACCOUNT_CONTEXT: &[u8] = b"dot-login/zklogin-account";
+
+/// The issuer of Google ID tokens.
+pub const GOOGLE_ISSUER: &[u8] = b"https://accounts.google.com";
+
+/// The URL Google serves its signing keys from.
+pub const GOOGLE_JWKS_URI: &[u8] = b"https://www.googleapis.com/oauth2/v3/certs";
+
+/// Google's signing keys, used to seed the default genesis configuration.
+pub fn google_jwks() -> Vec<JwkRecord> {
     let mut keys = Vec::new();
 
-    keys.push(Jwk {
-        alg: String::from("RS256"),
-        n: String::from("rv95jmy91hibD7cb_BCA25jv5HrX7WoqHv-fh8wrOR5aYcM8Kvsc3mbzs2w1vCUlMRv7NdEGVBEnOZ6tHvUzGLon4ythd5XsX-wTvAtIHPkyHdo5zGpTgATO9CEn78Y-f1E8By63ttv14kXe_RMjt5aKttK4yqqUyzWUexSs7pET2zWiigd0_bGhJGYYEJlEk_JsOBFvloIBaycMfDjK--kgqnlRA8SWUkP3pEJIAo9oHzmvX6uXZTEJK10a1YNj0JVR4wZY3k60NaUX-KCroreU85iYgnecyxSdL-trpKdkg0-2OYks-_2Isymu7jPX-uKVyi-zKyaok3N64mERRQ"),
-        e: String::from("AQAB"),
-        kty: String::from("RSA"),
-        k_use: String::from("sig"),
-        kid: String::from("0e345fd7e4a97271dffa991f5a893cd16b8e0827"),
-    });
-
-    keys.push(Jwk {
-        alg: String::from("RS256"),
-        n: String::from("zaUomGGU1qSBxBHOQRk5fF7rOVVzG5syHhJYociRyyvvMOM6Yx_n7QFrwKxW1Gv-YKPDsvs-ksSN5YsozOTb9Y2HlPsOXrnZHQTQIdjWcfUz-TLDknAdJsK3A0xZvq5ud7ElIrXPFS9UvUrXDbIv5ruv0w4pvkDrp_Xdhw32wakR5z0zmjilOHeEJ73JFoChOaVxoRfpXkFGON5ZTfiCoO9o0piPROLBKUtIg_uzMGzB6znWU8Yfv3UlGjS-ixApSltsXZHLZfat1sUvKmgT03eXV8EmNuMccrhLl5AvqKT6E5UsTheSB0veepQgX8XCEex-P3LCklisnen3UKOtLw"),
-        e: String::from("AQAB"),
-        kty: String::from("RSA"),
-        k_use: String::from("sig"),
-        kid: String::from("f2e11986282de93f27b264fd2a4de192993dcb8c"),
-    });
-
-    GoogleJwks { keys }
-}
\ No newline at end of file
+    keys.extend(JwkRecord::new(
+        "0e345fd7e4a97271dffa991f5a893cd16b8e0827",
+        "RS256",
+        "RSA",
+        "rv95jmy91hibD7cb_BCA25jv5HrX7WoqHv-fh8wrOR5aYcM8Kvsc3mbzs2w1vCUlMRv7NdEGVBEnOZ6tHvUzGLon4ythd5XsX-wTvAtIHPkyHdo5zGpTgATO9CEn78Y-f1E8By63ttv14kXe_RMjt5aKttK4yqqUyzWUexSs7pET2zWiigd0_bGhJGYYEJlEk_JsOBFvloIBaycMfDjK--kgqnlRA8SWUkP3pEJIAo9oHzmvX6uXZTEJK10a1YNj0JVR4wZY3k60NaUX-KCroreU85iYgnecyxSdL-trpKdkg0-2OYks-_2Isymu7jPX-uKVyi-zKyaok3N64mERRQ",
+        "AQAB",
+    ));
+
+    keys.extend(JwkRecord::new(
+        "f2e11986282de93f27b264fd2a4de192993dcb8c",
+        "RS256",
+        "RSA",
+        "zaUomGGU1qSBxBHOQRk5fF7rOVVzG5syHhJYociRyyvvMOM6Yx_n7QFrwKxW1Gv-YKPDsvs-ksSN5YsozOTb9Y2HlPsOXrnZHQTQIdjWcfUz-TLDknAdJsK3A0xZvq5ud7ElIrXPFS9UvUrXDbIv5ruv0w4pvkDrp_Xdhw32wakR5z0zmjilOHeEJ73JFoChOaVxoRfpXkFGON5ZTfiCoO9o0piPROLBKUtIg_uzMGzB6znWU8Yfv3UlGjS-ixApSltsXZHLZfat1sUvKmgT03eXV8EmNuMccrhLl5AvqKT6E5UsTheSB0veepQgX8XCEex-P3LCklisnen3UKOtLw",
+        "AQAB",
+    ));
+
+    keys
+}
@@ -0,0 +1,316 @@
+//! Storage migrations of the pallet.
+//!
+//! Migrations touching every stored proof do not fit in a block. They are `SteppedMigration`s, run
+//! by `pallet-migrations` over as many blocks as they need, and report their progress with the
+//! pallet's `MigrationProgressed` event.
+
+use crate::*;
+use alloc::vec::Vec;
+use frame_support::{
+    migrations::{MigrationId, SteppedMigration, SteppedMigrationError},
+    pallet_prelude::*,
+    sp_runtime::Saturating,
+    weights::WeightMeter,
+};
+
+/// The identifier prefix of the pallet's migrations.
+const PALLET_MIGRATIONS_ID: &[u8; 14] = b"pallet-zkproof";
+
+/// Version 1: proofs are indexed by identity.
+pub mod v1 {
+    use super::*;
+
+    /// Index the proofs stored before `IdentityProofs` existed by the nullifier of their
+    /// attestation, so that `erase_identity_data` finds them.
+    ///
+    /// The cursor is the last attestation indexed.
+    pub struct IndexIdentityProofs<T>(PhantomData<T>);
+
+    impl<T: Config> IndexIdentityProofs<T> {
+        /// The weight of indexing one proof: reading its attestation and writing its index entry.
+        fn step_weight() -> Weight {
+            T::DbWeight::get().reads_writes(1, 1)
+        }
+    }
+
+    impl<T: Config> SteppedMigration for IndexIdentityProofs<T> {
+        type Cursor = T::Hash;
+        type Identifier = MigrationId<14>;
+
+        fn id() -> Self::Identifier {
+            MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 0, version_to: 1 }
+        }
+
+        fn step(
+            mut cursor: Option<Self::Cursor>,
+            meter: &mut WeightMeter,
+        ) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+            if Pallet::<T>::on_chain_storage_version() != Self::id().version_from as u16 {
+                return Ok(None);
+            }
+
+            let required = Self::step_weight();
+            if meter.remaining().any_lt(required) {
+                return Err(SteppedMigrationError::InsufficientWeight { required });
+            }
+
+            let mut indexed = 0u32;
+            while meter.try_consume(required).is_ok() {
+                // The attestations are still in the layout of version 1.
+                let mut attestations = match cursor {
+                    Some(last) => v2::Attestations::<T>::iter_from(v2::Attestations::<T>::hashed_key_for(last)),
+                    None => v2::Attestations::<T>::iter(),
+                };
+                match attestations.next() {
+                    Some((hash, attestation)) => {
+                        IdentityProofs::<T>::insert(attestation.nullifier, hash, ());
+                        cursor = Some(hash);
+                        indexed += 1;
+                    }
+                    None => {
+                        cursor = None;
+                        break;
+                    }
+                }
+            }
+
+            let complete = cursor.is_none();
+            if complete {
+                StorageVersion::new(Self::id().version_to as u16).put::<Pallet<T>>();
+            }
+            Pallet::<T>::deposit_event(Event::MigrationProgressed { migrated: indexed, complete });
+
+            Ok(cursor)
+        }
+    }
+}
+
+/// Version 2: attestations carry the circuit, verification time and expiry of their proof, so that
+/// they can be read without the raw proof.
+pub mod v2 {
+    use super::*;
+
+    /// An attestation as stored up to version 1.
+    #[derive(Encode, Decode, MaxEncodedLen, TypeInfo)]
+    pub struct AttestationV1 {
+        /// The issuer of the token the proof was made for.
+        pub provider: IssuerId,
+        /// The identity commitment the proof is bound to.
+        pub commitment: Commitment,
+        /// The nullifier of the identity.
+        pub nullifier: Nullifier,
+    }
+
+    /// The attestations as stored up to version 1.
+    #[frame_support::storage_alias]
+    pub type Attestations<T: Config> =
+        StorageMap<Pallet<T>, Twox64Concat, <T as frame_system::Config>::Hash, AttestationV1, OptionQuery>;
+
+    /// Extend the attestations stored before version 2 with the expiry of their proof. Their
+    /// circuit version and verification time were not recorded, and are left at 0.
+    ///
+    /// The cursor is the last attestation extended.
+    pub struct ExtendAttestations<T>(PhantomData<T>);
+
+    impl<T: Config> ExtendAttestations<T> {
+        /// The weight of extending one attestation: reading it and the expiry of its proof, and
+        /// writing it back.
+        fn step_weight() -> Weight {
+            T::DbWeight::get().reads_writes(2, 1)
+        }
+    }
+
+    impl<T: Config> SteppedMigration for ExtendAttestations<T> {
+        type Cursor = T::Hash;
+        type Identifier = MigrationId<14>;
+
+        fn id() -> Self::Identifier {
+            MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 1, version_to: 2 }
+        }
+
+        fn step(
+            mut cursor: Option<Self::Cursor>,
+            meter: &mut WeightMeter,
+        ) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+            if Pallet::<T>::on_chain_storage_version() != Self::id().version_from as u16 {
+                return Ok(None);
+            }
+
+            let required = Self::step_weight();
+            if meter.remaining().any_lt(required) {
+                return Err(SteppedMigrationError::InsufficientWeight { required });
+            }
+
+            let mut extended = 0u32;
+            while meter.try_consume(required).is_ok() {
+                let mut attestations = match cursor {
+                    Some(last) => Attestations::<T>::iter_from(Attestations::<T>::hashed_key_for(last)),
+                    None => Attestations::<T>::iter(),
+                };
+                match attestations.next() {
+                    Some((hash, old)) => {
+                        crate::Attestations::<T>::insert(
+                            hash,
+                            Attestation {
+                                provider: old.provider,
+                                commitment: old.commitment,
+                                nullifier: old.nullifier,
+                                circuit_version: 0,
+                                verified_at: 0,
+                                expires_at: ZkProofExpiry::<T>::get(hash).unwrap_or_default(),
+                            },
+                        );
+                        cursor = Some(hash);
+                        extended += 1;
+                    }
+                    None => {
+                        cursor = None;
+                        break;
+                    }
+                }
+            }
+
+            let complete = cursor.is_none();
+            if complete {
+                StorageVersion::new(Self::id().version_to as u16).put::<Pallet<T>>();
+            }
+            Pallet::<T>::deposit_event(Event::MigrationProgressed { migrated: extended, complete });
+
+            Ok(cursor)
+        }
+    }
+}
+
+/// Version 3: the pallet counts its storage footprint in `StorageUsage`.
+pub mod v3 {
+    use super::*;
+
+    /// The maps counted by [`CountStorage`], in order.
+    #[derive(Clone, Copy, Encode, Decode, MaxEncodedLen, TypeInfo, PartialEq, Eq, RuntimeDebug)]
+    pub enum CountedMap {
+        /// `ZkProofData`.
+        ProofData,
+        /// `Attestations`.
+        Attestations,
+        /// `DeviceKeys`.
+        DeviceKeys,
+        /// `Jwks`.
+        Jwks,
+    }
+
+    impl CountedMap {
+        /// The map counted after this one, if any.
+        fn next(self) -> Option<Self> {
+            match self {
+                CountedMap::ProofData => Some(CountedMap::Attestations),
+                CountedMap::Attestations => Some(CountedMap::DeviceKeys),
+                CountedMap::DeviceKeys => Some(CountedMap::Jwks),
+                CountedMap::Jwks => None,
+            }
+        }
+    }
+
+    /// The raw storage key of an entry of a counted map.
+    pub type RawKey = BoundedVec<u8, ConstU32<256>>;
+
+    /// Count the proofs, attestations, device keys and keysets stored before version 3 into
+    /// `StorageUsage`, which later writes keep up to date.
+    ///
+    /// The cursor is the map being counted, and the raw key of the last entry counted in it.
+    pub struct CountStorage<T>(PhantomData<T>);
+
+    impl<T: Config> CountStorage<T> {
+        /// The weight of counting one entry: reading it.
+        fn step_weight() -> Weight {
+            T::DbWeight::get().reads(1)
+        }
+
+        /// Count the entry of `map` after `last`, or its first entry, into `usage`. Returns the raw
+        /// key of the entry counted, or `None` once the map is counted.
+        fn count_next(map: CountedMap, last: Option<Vec<u8>>, usage: &mut StorageStats) -> Option<Vec<u8>> {
+            match map {
+                CountedMap::ProofData => {
+                    let (hash, data) = match last {
+                        Some(last) => ZkProofData::<T>::iter_from(last).next(),
+                        None => ZkProofData::<T>::iter().next(),
+                    }?;
+                    usage.proofs.saturating_inc();
+                    usage.proof_bytes.saturating_accrue(data.len() as u64);
+                    Some(ZkProofData::<T>::hashed_key_for(hash))
+                }
+                CountedMap::Attestations => {
+                    let hash = match last {
+                        Some(last) => Attestations::<T>::iter_keys_from(last).next(),
+                        None => Attestations::<T>::iter_keys().next(),
+                    }?;
+                    usage.attestations.saturating_inc();
+                    Some(Attestations::<T>::hashed_key_for(hash))
+                }
+                CountedMap::DeviceKeys => {
+                    let (nullifier, key) = match last {
+                        Some(last) => DeviceKeys::<T>::iter_keys_from(last).next(),
+                        None => DeviceKeys::<T>::iter_keys().next(),
+                    }?;
+                    usage.device_keys.saturating_inc();
+                    Some(DeviceKeys::<T>::hashed_key_for(nullifier, key))
+                }
+                CountedMap::Jwks => {
+                    let (provider, keys) = match last {
+                        Some(last) => Jwks::<T>::iter_from(last).next(),
+                        None => Jwks::<T>::iter().next(),
+                    }?;
+                    usage.jwks_keys.saturating_accrue(keys.len() as u64);
+                    usage.jwks_bytes.saturating_accrue(keys.encoded_size() as u64);
+                    Some(Jwks::<T>::hashed_key_for(provider))
+                }
+            }
+        }
+    }
+
+    impl<T: Config> SteppedMigration for CountStorage<T> {
+        type Cursor = (CountedMap, Option<RawKey>);
+        type Identifier = MigrationId<14>;
+
+        fn id() -> Self::Identifier {
+            MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 2, version_to: 3 }
+        }
+
+        fn step(
+            cursor: Option<Self::Cursor>,
+            meter: &mut WeightMeter,
+        ) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+            if Pallet::<T>::on_chain_storage_version() != Self::id().version_from as u16 {
+                return Ok(None);
+            }
+
+            let required = Self::step_weight();
+            if meter.remaining().any_lt(required) {
+                return Err(SteppedMigrationError::InsufficientWeight { required });
+            }
+
+            // Counting starts over from zero, whatever was counted before the migration ran.
+            let mut usage = if cursor.is_some() { StorageUsage::<T>::get() } else { StorageStats::default() };
+            let mut cursor = Some(cursor.unwrap_or((CountedMap::ProofData, None)));
+            let mut counted = 0u32;
+            while meter.try_consume(required).is_ok() {
+                let Some((map, last)) = cursor.take() else { break };
+                cursor = match Self::count_next(map, last.map(BoundedVec::into_inner), &mut usage) {
+                    Some(key) => {
+                        counted += 1;
+                        Some((map, Some(RawKey::try_from(key).map_err(|_| SteppedMigrationError::Failed)?)))
+                    }
+                    None => map.next().map(|next| (next, None)),
+                };
+            }
+            StorageUsage::<T>::put(usage);
+
+            let complete = cursor.is_none();
+            if complete {
+                StorageVersion::new(Self::id().version_to as u16).put::<Pallet<T>>();
+            }
+            Pallet::<T>::deposit_event(Event::MigrationProgressed { migrated: counted, complete });
+
+            Ok(cursor)
+        }
+    }
+}
@@ -1,44 +1,90 @@
 use crate as pallet_zk_proof;
-use frame_support::{construct_runtime, parameter_types, traits::{ConstU16, ConstU64}};
+use crate::{
+    adapters::StandardAdapters, google_jwks, AttestationGate, CallSpending, Commitment, IssuerId, JwkRecord,
+    OnProofVerified, StepUpCalls, GOOGLE_ISSUER,
+};
+use frame_support::{
+    derive_impl, ord_parameter_types, parameter_types,
+    traits::{ConstU16, ConstU32, ConstU64, Contains, EitherOfDiverse},
+    BoundedVec,
+};
+use frame_system::{offchain::AppCrypto, EnsureRoot, EnsureSignedBy};
 use sp_core::H256;
-use sp_runtime::{testing::Header, traits::{BlakeTwo256, IdentityLookup}};
+use sp_runtime::{
+    testing::{TestSignature, TestXt, UintAuthorityId},
+    traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentityLookup},
+    BuildStorage, Perbill,
+};
 
-type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
+type Extrinsic = TestXt<RuntimeCall, ()>;
+
+/// The accounts registered as oracles at genesis.
+pub const ORACLE: u64 = 100;
+pub const ORACLE_2: u64 = 101;
+
+ord_parameter_types! {
+    /// The account onboarding providers and circuits besides root.
+    pub const SecurityCouncil: u64 = 42;
+}
 
 // Configure a mock runtime to test the pallet.
-construct_runtime!(
-    pub enum Test where
-        Block = Block,
-        NodeBlock = Block,
-        UncheckedExtrinsic = UncheckedExtrinsic,
+frame_support::construct_runtime!(
+    pub enum Test
     {
-        System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-        ZkProofModule: pallet_zk_proof::{Pallet, Call, Storage, Event<T>},
+        System: frame_system,
+        Timestamp: pallet_timestamp,
+        ZkProofModule: pallet_zk_proof,
     }
 );
 
 parameter_types! {
-    pub const BlockHashCount: u64 = 250;
-    pub const MaxJsonLength: u32 = 1024;
+    pub static MaxJsonLength: u32 = 2048;
+    pub static MaxPublicInputs: u32 = 5;
+    pub static MaxVerificationsPerBlock: u32 = 100;
+    pub static AuthoredJwks: bool = false;
+    pub static IdentityRootPeriod: u64 = 0;
+    pub static CircuitWarningPeriod: u64 = 0;
+    pub static RenewalNotice: u64 = 0;
+    pub const MaxJwtLength: u32 = 1024;
+    pub const JwksSubmissionWindow: u64 = 10;
+    pub const JwksGracePeriod: u64 = 60;
+    pub const NonceBlockWindow: u64 = 5;
+    pub const MaxTokenAge: u64 = 3_600;
+    pub AllowedIssuers: Vec<IssuerId> = vec![IssuerId::truncate_from(GOOGLE_ISSUER.to_vec())];
+    pub static AllowProofOverwrite: bool = false;
+    pub static InsecureSkipVerification: bool = false;
+    pub static ProofWeightShare: Perbill = Perbill::from_percent(10);
+    pub static EmitProofPayloads: bool = false;
+    pub static PrivacyMode: bool = false;
+    pub static VerifiedProofs: Vec<(u64, Commitment, [u8; 32])> = vec![];
+}
+
+/// Records the proofs `OnProofVerified` is called for in `VerifiedProofs`.
+pub struct RecordVerifiedProofs;
+
+impl OnProofVerified<u64, Commitment> for RecordVerifiedProofs {
+    fn on_proof_verified(who: &u64, commitment: &Commitment, claims_digest: &[u8; 32]) {
+        VerifiedProofs::mutate(|proofs| proofs.push((*who, *commitment, *claims_digest)));
+    }
 }
 
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig as frame_system::DefaultConfig)]
 impl frame_system::Config for Test {
     type BaseCallFilter = frame_support::traits::Everything;
     type BlockWeights = ();
     type BlockLength = ();
     type DbWeight = ();
-    type Origin = Origin;
-    type Call = Call;
-    type Index = u64;
-    type BlockNumber = u64;
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Nonce = u64;
     type Hash = H256;
     type Hashing = BlakeTwo256;
     type AccountId = u64;
     type Lookup = IdentityLookup<Self::AccountId>;
-    type Header = Header;
-    type Event = Event;
-    type BlockHashCount = BlockHashCount;
+    type Block = Block;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
     type Version = ();
     type PalletInfo = PalletInfo;
     type AccountData = ();
@@ -47,14 +93,194 @@ impl frame_system::Config for Test {
     type SystemWeightInfo = ();
     type SS58Prefix = ConstU16<42>;
     type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_timestamp::Config for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = ConstU64<1>;
+    type WeightInfo = ();
+}
+
+impl frame_system::offchain::SigningTypes for Test {
+    type Public = UintAuthorityId;
+    type Signature = TestSignature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+    RuntimeCall: From<LocalCall>,
+{
+    type OverarchingCall = RuntimeCall;
+    type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+    RuntimeCall: From<LocalCall>,
+{
+    fn create_transaction<C: AppCrypto<Self::Public, Self::Signature>>(
+        call: RuntimeCall,
+        _public: UintAuthorityId,
+        _account: u64,
+        nonce: u64,
+    ) -> Option<(RuntimeCall, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+        Some((call, (nonce, ())))
+    }
+}
+
+/// Remarks spend their length.
+pub struct RemarkSpending;
+
+impl CallSpending<RuntimeCall> for RemarkSpending {
+    fn spending(call: &RuntimeCall) -> u128 {
+        match call {
+            RuntimeCall::System(frame_system::Call::remark { remark }) => remark.len() as u128,
+            RuntimeCall::ZkProofModule(crate::Call::zklogin_execute { call, .. }) => Self::spending(call),
+            _ => 0,
+        }
+    }
+
+    fn wrapped(call: &RuntimeCall) -> Option<&RuntimeCall> {
+        match call {
+            RuntimeCall::ZkProofModule(crate::Call::zklogin_execute { call, .. }) => Some(call),
+            _ => None,
+        }
+    }
+}
+
+/// Remarks with an event are reserved to verified users.
+pub struct RemarkWithEventGate;
+
+impl AttestationGate<u64, RuntimeCall> for RemarkWithEventGate {
+    fn gated_accounts(who: &u64, call: &RuntimeCall) -> Vec<u64> {
+        match call {
+            RuntimeCall::System(frame_system::Call::remark_with_event { .. }) => vec![*who],
+            _ => vec![],
+        }
+    }
+}
+
+/// Setting the heap pages requires a proof stored at most two blocks ago.
+pub struct HeapPagesStepUp;
+
+impl StepUpCalls<RuntimeCall, u64> for HeapPagesStepUp {
+    fn max_proof_age(call: &RuntimeCall) -> Option<u64> {
+        match call {
+            RuntimeCall::System(frame_system::Call::set_heap_pages { .. }) => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// zkLogin accounts may only dispatch the calls of `frame_system`.
+pub struct SystemCalls;
+
+impl Contains<RuntimeCall> for SystemCalls {
+    fn contains(call: &RuntimeCall) -> bool {
+        matches!(call, RuntimeCall::System(_))
+    }
+}
+
+/// Oracle key crypto backed by the test authority ids.
+pub struct TestAuthId;
+
+impl AppCrypto<UintAuthorityId, TestSignature> for TestAuthId {
+    type RuntimeAppPublic = UintAuthorityId;
+    type GenericPublic = UintAuthorityId;
+    type GenericSignature = TestSignature;
 }
 
 impl pallet_zk_proof::Config for Test {
-    type RuntimeEvent = Event;
+    type RuntimeEvent = RuntimeEvent;
+    type AdminOrigin = EnsureRoot<u64>;
+    type OnboardingOrigin = EitherOfDiverse<EnsureRoot<u64>, EnsureSignedBy<SecurityCouncil, u64>>;
     type MaxJsonLength = MaxJsonLength;
+    type MaxJwtLength = MaxJwtLength;
+    type MaxPublicInputs = MaxPublicInputs;
+    type MaxJwksKeys = ConstU32<4>;
+    type MaxOracles = ConstU32<4>;
+    type JwksQuorum = ConstU32<2>;
+    type JwksSubmissionWindow = JwksSubmissionWindow;
+    type UnixTime = Timestamp;
+    type JwksGracePeriod = JwksGracePeriod;
+    type JwksFetchInterval = ConstU64<10>;
+    type AuthorityId = TestAuthId;
+    type AllowedIssuers = AllowedIssuers;
+    type ProviderAdapters = StandardAdapters;
+    type AllowProofOverwrite = AllowProofOverwrite;
+    type InsecureSkipVerification = InsecureSkipVerification;
+    type ProofWeightShare = ProofWeightShare;
+    type EmitProofPayloads = EmitProofPayloads;
+    type ProofLifetime = ConstU64<3_600>;
+    type ClockSkewTolerance = ConstU64<30>;
+    type NonceBlockWindow = NonceBlockWindow;
+    type MaxTokenAge = MaxTokenAge;
+    type RevealWindow = ConstU64<3>;
+    type PrivacyMode = PrivacyMode;
+    type MaxErasedProofs = ConstU32<2>;
+    type MaxScheduledActivations = ConstU32<2>;
+    type MaxRecentFailures = ConstU32<2>;
+    type OnProofVerified = RecordVerifiedProofs;
+    type MaxVerificationsPerBlock = MaxVerificationsPerBlock;
+    type AuthoredJwks = AuthoredJwks;
+    type IdentityRootPeriod = IdentityRootPeriod;
+    type MaxIdentityRootLeaves = ConstU32<4>;
+    type MaxDeviceKeys = ConstU32<2>;
+    type CallSpending = RemarkSpending;
+    type UnlockDelay = ConstU64<10>;
+    type OnIdentityLocked = ();
+    type AttestationGate = RemarkWithEventGate;
+    type MaxMetadataLength = ConstU32<16>;
+    type CircuitWarningPeriod = CircuitWarningPeriod;
+    type StepUpCalls = HeapPagesStepUp;
+    type MaxFailedAttempts = ConstU32<3>;
+    type LockoutPeriod = ConstU64<10>;
+    type ZkLoginCall = RuntimeCall;
+    type ZkLoginCalls = SystemCalls;
+    type RenewalNotice = RenewalNotice;
+    type MaxRenewalReminders = ConstU32<2>;
+    type WeightInfo = ();
+}
+
+/// The base64url modulus of the 2048-bit RSA key tests sign tokens with. Its exponent is `AQAB`.
+pub const TEST_MODULUS: &str = concat!(
+    "34aAxP4JlIr03cnm482apzlQ95rkxPaD7XsRmAlxRrwmrwp3G-_r1xdjbd1f2TK4jEd-MqffyjsokUJp4czRLtXNkUQ3TZG3",
+    "t9VXD5VW-hkOXpxJ4tvZ_WrsksfMKdhKhxBZPUGxnrLfSwr4kv6KxgE2F2TIC8hDh8GGyMzaV5rs-SSxXjnxDW6bSuWX0ogu",
+    "DFuihcZ8Eq2aUKinfi2tW1ttTrlSU48LCzc-iINHk6o57I9cxzRTh3zZ1_WU9GvdCkAwqYbOAyuJjP4bIL3JtgUr58RH5UvQ",
+    "FhztP0a943cU62BVWmYYwirvoD9AGF3TjoLLEh5O5BcFFkmr-eCFoQ",
+);
+
+/// The base64url private exponent of the test key.
+pub const TEST_PRIVATE_EXPONENT: &str = concat!(
+    "RBq3IU5FnPy5ntnlf4Mlm83a3ZZUHa7-g1z7_1bhLKwnHS4THG_9a4u0v8Szb3oEwLkHumdryDI-KgQSPropAhwihiBCuV-s",
+    "mlZmMFlQJuN7fVghKv3RbWUmr5Iwoo9EJeLVTdS2SaQ2ZKbzFdZ4cdvsnzQVv4FFEhfk-UlxPT9lLvekUSzTEXtndnSKvViU",
+    "INxLSDIxc15NxNECGQSp9j5jpWGAuR61BRK9aUugpB-iL6WStJIC-R8R8mVRVbE9sQ-9333EYEq5Vnb5KFcYXqIk_gt_C4is",
+    "MC610r5J3klUKSfjJsMDnMGK9hAd1v3ZLROGwi11ui45XCOWgMsZ",
+);
+
+/// Google's genesis keys, with the modulus of the test key in place of theirs.
+pub fn test_jwks() -> Vec<JwkRecord> {
+    let modulus: BoundedVec<u8, _> = TEST_MODULUS.as_bytes().to_vec().try_into().unwrap();
+    google_jwks().into_iter().map(|key| JwkRecord { n: modulus.clone(), ..key }).collect()
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-    frame_system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+    let storage = RuntimeGenesisConfig {
+        system: Default::default(),
+        zk_proof_module: pallet_zk_proof::GenesisConfig {
+            oracles: vec![ORACLE, ORACLE_2],
+            jwks: vec![(GOOGLE_ISSUER.to_vec(), test_jwks())],
+            ..Default::default()
+        },
+    }
+    .build_storage()
+    .unwrap();
+
+    let mut ext: sp_io::TestExternalities = storage.into();
+    // Go past genesis block so events get deposited.
+    ext.execute_with(|| System::set_block_number(1));
+    ext
 }
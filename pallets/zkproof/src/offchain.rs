@@ -0,0 +1,160 @@
+//! Offchain worker fetching the providers' JWKS and submitting them on behalf of the local oracle.
+
+use crate::*;
+use alloc::string::String;
+use frame_system::offchain::{SendSignedTransaction, Signer};
+use serde::Deserialize;
+use sp_runtime::offchain::{http, Duration};
+
+/// How long to wait for a JWKS endpoint to answer.
+const FETCH_TIMEOUT_MS: u64 = 5_000;
+
+/// A JWKS document as served by an OIDC provider.
+#[derive(Deserialize)]
+struct JwksDocument {
+    keys: Vec<Jwk>,
+}
+
+/// A JSON Web Key as served by an OIDC provider. Unknown fields (`use`, `x5c`, ...) are ignored.
+#[derive(Deserialize)]
+struct Jwk {
+    kid: String,
+    alg: String,
+    kty: String,
+    n: String,
+    e: String,
+}
+
+/// A freshly fetched keyset along with the expiry advertised by the provider.
+struct FetchedJwks {
+    keys: Vec<JwkRecord>,
+    expires_at: Option<u64>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Fetch the JWKS of every registered provider and vote for it with the local oracle key.
+    pub(crate) fn refresh_jwks() {
+        let signer = Signer::<T, T::AuthorityId>::any_account();
+        if !signer.can_sign() {
+            // Not an oracle node.
+            return;
+        }
+
+        for (provider, info) in Providers::<T>::iter() {
+            let fetched = match fetch_jwks(&info.jwks_uri) {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    log::warn!("Failed to fetch JWKS of {:?}: {:?}", provider, e);
+                    continue;
+                }
+            };
+
+            let keys: BoundedVec<JwkRecord, T::MaxJwksKeys> = match fetched.keys.try_into() {
+                Ok(keys) => keys,
+                Err(_) => {
+                    log::warn!("JWKS of {:?} has more keys than MaxJwksKeys", provider);
+                    continue;
+                }
+            };
+
+            let result = signer.send_signed_transaction(|_| Call::submit_jwks {
+                provider: provider.clone(),
+                keys: keys.clone(),
+                expires_at: fetched.expires_at,
+            });
+            if let Some((_, Err(()))) = result {
+                log::warn!("Failed to submit JWKS of {:?}", provider);
+            }
+        }
+    }
+}
+
+/// Fetch and parse a JWKS document, capturing its cache expiry.
+fn fetch_jwks(uri: &[u8]) -> Result<FetchedJwks, http::Error> {
+    let uri = core::str::from_utf8(uri).map_err(|_| http::Error::Unknown)?;
+    let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(FETCH_TIMEOUT_MS));
+
+    let pending = http::Request::get(uri).deadline(deadline).send().map_err(|_| http::Error::IoError)?;
+    let response = pending.try_wait(deadline).map_err(|_| http::Error::DeadlineReached)??;
+    if response.code != 200 {
+        log::warn!("Unexpected status code fetching {}: {}", uri, response.code);
+        return Err(http::Error::Unknown);
+    }
+
+    let now = sp_io::offchain::timestamp().unix_millis() / 1000;
+    let headers = response.headers();
+    let expires_at = cache_expiry(headers.find("cache-control"), headers.find("expires"), now);
+
+    let body = response.body().collect::<Vec<u8>>();
+    let keys = parse_jwks(&body).ok_or(http::Error::Unknown)?;
+
+    Ok(FetchedJwks { keys, expires_at })
+}
+
+/// Parse a JWKS document, skipping the keys that exceed the on-chain bounds. Returns `None` if
+/// `body` is not a JWKS document.
+pub fn parse_jwks(body: &[u8]) -> Option<Vec<JwkRecord>> {
+    let document: JwksDocument = serde_json::from_slice(body).ok()?;
+    Some(document.keys.iter().filter_map(|k| JwkRecord::new(&k.kid, &k.alg, &k.kty, &k.n, &k.e)).collect())
+}
+
+/// Derive the expiry (unix seconds) of a response from its `Cache-Control` and `Expires` headers.
+///
+/// As in HTTP caching, `max-age` takes precedence over `Expires`.
+pub fn cache_expiry(cache_control: Option<&str>, expires: Option<&str>, now: u64) -> Option<u64> {
+    if let Some(max_age) = cache_control.and_then(parse_max_age) {
+        return Some(now.saturating_add(max_age));
+    }
+    expires.and_then(parse_http_date)
+}
+
+/// Extract the `max-age` directive of a `Cache-Control` header.
+fn parse_max_age(cache_control: &str) -> Option<u64> {
+    cache_control
+        .split(',')
+        .find_map(|directive| directive.trim().strip_prefix("max-age="))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Parse an IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) into unix seconds.
+pub(crate) fn parse_http_date(date: &str) -> Option<u64> {
+    let mut parts = date.split_whitespace();
+    let _weekday = parts.next()?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month: u64 = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':');
+    let hours: u64 = time.next()?.parse().ok()?;
+    let minutes: u64 = time.next()?.parse().ok()?;
+    let seconds: u64 = time.next()?.parse().ok()?;
+    if parts.next()? != "GMT" || year < 1970 || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+/// Days since the unix epoch of a (post-1970) calendar date.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Shift the year to start in March so the leap day is the last day of the year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,92 @@
+//! The Poseidon hash of the circuits hashing their public inputs natively in the scalar field.
+//!
+//! Provers must hash with the same parameters: a width of 3 (rate 2, capacity 1), 8 full and 57
+//! partial rounds, the smallest S-box exponent coprime to `p - 1`, and the round constants and MDS
+//! matrix of the Grain LFSR of the reference implementation, for the bit size of the field.
+
+use alloc::vec::Vec;
+use ark_crypto_primitives::sponge::poseidon::find_poseidon_ark_and_mds;
+use ark_ff::PrimeField;
+
+/// The number of field elements absorbed per permutation.
+const RATE: usize = 2;
+/// The size of the state: the rate, and one element of capacity.
+const WIDTH: usize = RATE + 1;
+/// The number of rounds with an S-box on every element, half before the partial rounds.
+const FULL_ROUNDS: usize = 8;
+/// The number of rounds with an S-box on the first element only.
+const PARTIAL_ROUNDS: usize = 57;
+/// The number of bytes packed into each absorbed element, so that any chunk fits the field.
+const BYTES_PER_ELEMENT: usize = 31;
+
+/// The Poseidon permutation and sponge over the field `F`.
+pub struct Poseidon<F> {
+    /// The round constants, per round.
+    ark: Vec<Vec<F>>,
+    /// The MDS matrix.
+    mds: Vec<Vec<F>>,
+    /// The S-box exponent.
+    alpha: u64,
+}
+
+impl<F: PrimeField> Poseidon<F> {
+    /// Derive the parameters for `F`. Deriving them takes far longer than hashing, so one instance
+    /// should hash all the inputs of a proof.
+    pub fn new() -> Self {
+        let bits = F::MODULUS_BIT_SIZE as u64;
+        let (ark, mds) = find_poseidon_ark_and_mds::<F>(bits, RATE, FULL_ROUNDS as u64, PARTIAL_ROUNDS as u64, 0);
+        Self { ark, mds, alpha: sbox_exponent::<F>() }
+    }
+
+    /// Hash `bytes`: their length, then their 31 bytes chunks read as big-endian integers, are
+    /// absorbed two elements per permutation, and the first element of the rate squeezed.
+    pub fn hash(&self, bytes: &[u8]) -> F {
+        let elements: Vec<F> = core::iter::once(F::from(bytes.len() as u64))
+            .chain(bytes.chunks(BYTES_PER_ELEMENT).map(F::from_be_bytes_mod_order))
+            .collect();
+        let mut state = [F::zero(); WIDTH];
+        for block in elements.chunks(RATE) {
+            for (element, input) in state[1..].iter_mut().zip(block) {
+                *element += input;
+            }
+            self.permute(&mut state);
+        }
+        state[1]
+    }
+
+    /// Apply the permutation to `state`.
+    fn permute(&self, state: &mut [F; WIDTH]) {
+        let partial_rounds = FULL_ROUNDS / 2..FULL_ROUNDS / 2 + PARTIAL_ROUNDS;
+        for (round, constants) in self.ark.iter().enumerate() {
+            for (element, constant) in state.iter_mut().zip(constants) {
+                *element += constant;
+            }
+            let sboxes = if partial_rounds.contains(&round) { 1 } else { WIDTH };
+            for element in state.iter_mut().take(sboxes) {
+                *element = element.pow([self.alpha]);
+            }
+            let mixed: [F; WIDTH] =
+                core::array::from_fn(|i| self.mds[i].iter().zip(state.iter()).map(|(m, element)| *m * element).sum());
+            *state = mixed;
+        }
+    }
+}
+
+impl<F: PrimeField> Default for Poseidon<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The smallest exponent `alpha` for which `x^alpha` is a permutation of `F`, i.e. the smallest
+/// prime not dividing `p - 1`.
+fn sbox_exponent<F: PrimeField>() -> u64 {
+    // `alpha` divides `p - 1` if `p`, folded limb by limb from the most significant, is 1 modulo it.
+    let divides_order = |alpha: u128| {
+        F::MODULUS.as_ref().iter().rev().fold(0u128, |rem, limb| ((rem << 64) | *limb as u128) % alpha) == 1
+    };
+    [3, 5, 7, 11, 13, 17, 19, 23]
+        .into_iter()
+        .find(|alpha| !divides_order(*alpha as u128))
+        .expect("the order of the multiplicative group of a pairing field has few small factors; qed")
+}
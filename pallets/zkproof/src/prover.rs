@@ -0,0 +1,173 @@
+//! Genuine Groth16 proofs of a minimal login circuit, for tests.
+//!
+//! [`LoginCircuit`] lays out its public inputs as [`CircuitVersion::V1`] does and proves the
+//! knowledge of the subject and salt behind the address seed. A [`Prover`] sets it up once and
+//! wraps its proofs in envelopes that `store_zk_proof` accepts once the verifying key is approved.
+
+use crate::{
+    circuit::{hash_to_field, public_inputs, CircuitVersion, PublicInput},
+    envelope::{
+        Curve, EnvelopeFormat, G1Point, G2Coordinates, G2Point, JsonProof, ScalarField, SnarkjsEnvelope, SnarkjsProof,
+    },
+    VerifiedClaims,
+};
+use ark_ec::bls12::{Bls12, Bls12Config};
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, Groth16, Proof, ProvingKey};
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_serialize::CanonicalSerialize;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// The base64 encoding of a compressed field element, point or key.
+pub fn base64_of(value: &impl CanonicalSerialize) -> String {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    STANDARD.encode(bytes)
+}
+
+/// The address seed of `subject` at `audience`, blinded by `salt`.
+pub fn address_seed<F: PrimeField>(subject: &str, audience: &str, salt: F) -> F {
+    (hash_to_field::<F>(subject.as_bytes()) + salt) * hash_to_field::<F>(audience.as_bytes())
+}
+
+/// A login circuit with the public inputs of `CircuitVersion::V1`, proving that the address seed
+/// is [`address_seed`] of the audience and of a private subject and salt. Unassigned, it only
+/// serves the setup.
+#[derive(Default)]
+pub struct LoginCircuit<F: PrimeField> {
+    /// The public inputs, in the order of `CircuitVersion::V1`.
+    pub public_inputs: Option<Vec<F>>,
+    /// The hash of the `sub` claim.
+    pub subject_hash: Option<F>,
+    /// The salt blinding the subject.
+    pub salt: Option<F>,
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for LoginCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let layout = CircuitVersion::V1.layout();
+        let inputs = (0..layout.len())
+            .map(|i| {
+                let value = self.public_inputs.as_ref().map(|inputs| inputs[i]);
+                cs.new_input_variable(|| value.ok_or(SynthesisError::AssignmentMissing))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let input = |input| inputs[layout.iter().position(|i| *i == input).expect("V1 lays out every input")];
+
+        let subject = cs.new_witness_variable(|| self.subject_hash.ok_or(SynthesisError::AssignmentMissing))?;
+        let salt = cs.new_witness_variable(|| self.salt.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce_constraint(
+            lc!() + subject + salt,
+            lc!() + input(PublicInput::AudienceHash),
+            lc!() + input(PublicInput::AddressSeed),
+        )
+    }
+}
+
+/// Proves logins with [`LoginCircuit`] on the curve `P`.
+pub struct Prover<P: Bls12Config> {
+    curve: Curve,
+    proving_key: ProvingKey<Bls12<P>>,
+}
+
+impl<P: Bls12Config> Prover<P> {
+    /// Set up the circuit on `P`, labelling envelopes as made on `curve`.
+    pub fn setup(curve: Curve) -> Self {
+        let rng = &mut ark_std::test_rng();
+        let proving_key =
+            Groth16::<Bls12<P>>::generate_random_parameters_with_reduction(LoginCircuit::default(), rng).unwrap();
+        Self { curve, proving_key }
+    }
+
+    /// The verifying key, as embedded in envelopes.
+    pub fn verifying_key(&self) -> String {
+        base64_of(&prepare_verifying_key(&self.proving_key.vk))
+    }
+
+    /// An envelope proving the login with `jwt`, whose claims are `claims`, on the chain whose
+    /// hex-encoded genesis hash is `chain_id`. The subject is blinded by `salt`.
+    pub fn prove(&self, jwt: &str, claims: &VerifiedClaims, salt: u64, chain_id: &str) -> Vec<u8> {
+        let salt = ScalarField::<P>::from(salt);
+        let seed = address_seed(&claims.subject, claims.audience.as_deref().unwrap_or_default(), salt);
+        let inputs =
+            public_inputs(CircuitVersion::V1, claims, seed, None, None).expect("the token has an audience and a nonce");
+        let circuit = LoginCircuit {
+            public_inputs: Some(inputs.clone()),
+            subject_hash: Some(hash_to_field(claims.subject.as_bytes())),
+            salt: Some(salt),
+        };
+        let proof = Groth16::<Bls12<P>>::create_random_proof_with_reduction(
+            circuit,
+            &self.proving_key,
+            &mut ark_std::test_rng(),
+        )
+        .unwrap();
+        envelope(self.curve, &proof, &self.verifying_key(), &inputs, jwt, chain_id)
+    }
+}
+
+/// The JSON envelope of a `CircuitVersion::V1` proof.
+pub fn envelope<P: Bls12Config>(
+    curve: Curve,
+    proof: &Proof<Bls12<P>>,
+    verifying_key: &str,
+    public_inputs: &[ScalarField<P>],
+    jwt: &str,
+    chain_id: &str,
+) -> Vec<u8> {
+    let envelope = JsonProof {
+        curve,
+        circuit_version: Some(1),
+        a: G1Point { x: base64_of(&proof.a.x).into(), y: base64_of(&proof.a.y).into() },
+        b: G2Point {
+            x: G2Coordinates { c0: base64_of(&proof.b.x.c0).into(), c1: base64_of(&proof.b.x.c1).into() },
+            y: G2Coordinates { c0: base64_of(&proof.b.y.c0).into(), c1: base64_of(&proof.b.y.c1).into() },
+        },
+        c: G1Point { x: base64_of(&proof.c.x).into(), y: base64_of(&proof.c.y).into() },
+        public_hash: "".into(),
+        verifying_key: verifying_key.into(),
+        jwt_token: jwt.into(),
+        chain_id: chain_id.into(),
+        public_inputs: public_inputs.iter().map(|input| base64_of(input).into()).collect(),
+        predicate: None,
+        token: None,
+        encoding: Default::default(),
+    };
+    serde_json::to_vec(&envelope).unwrap()
+}
+
+/// The decimal representation of a field element, as snarkjs writes it.
+fn decimal<F: PrimeField>(element: F) -> String {
+    element.into_bigint().to_string()
+}
+
+/// The snarkjs envelope, format byte included, of the BLS12-381 proof in the JSON envelope `json`.
+pub fn snarkjs_envelope(json: &[u8]) -> Vec<u8> {
+    type P = ark_bls12_381::Config;
+    let envelope = JsonProof::from_bytes(json).unwrap();
+    let proof = envelope.proof::<P>().unwrap();
+    let snarkjs = SnarkjsEnvelope {
+        proof: SnarkjsProof {
+            pi_a: vec![decimal(proof.a.x), decimal(proof.a.y), "1".into()],
+            pi_b: vec![
+                vec![decimal(proof.b.x.c0), decimal(proof.b.x.c1)],
+                vec![decimal(proof.b.y.c0), decimal(proof.b.y.c1)],
+                vec!["1".into(), "0".into()],
+            ],
+            pi_c: vec![decimal(proof.c.x), decimal(proof.c.y), "1".into()],
+            protocol: "groth16".into(),
+            curve: "bls12381".into(),
+        },
+        public_signals: envelope.public_inputs::<P>().unwrap().into_iter().map(decimal).collect(),
+        circuit_version: envelope.circuit_version,
+        verifying_key: envelope.verifying_key,
+        jwt_token: envelope.jwt_token,
+        chain_id: envelope.chain_id,
+        predicate: envelope.predicate,
+        token: envelope.token,
+    };
+    EnvelopeFormat::Snarkjs.prefix(&serde_json::to_vec(&snarkjs).unwrap())
+}
@@ -0,0 +1,48 @@
+//! Verification of the RS256 signatures of tokens: RSASSA-PKCS1-v1_5 with SHA-256, as RFC 7518
+//! specifies it for JWTs.
+
+use alloc::vec::Vec;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// The DER encoding of the `DigestInfo` of a SHA-256 digest, up to the digest itself.
+const SHA256_DIGEST_INFO: [u8; 19] =
+    [0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20];
+
+/// The length in bytes of the shortest modulus accepted: keys of fewer than 2048 bits are rejected,
+/// as RFC 7518 requires.
+pub const MIN_MODULUS_LENGTH: usize = 256;
+
+/// Whether `signature` is an RS256 signature of `message` by the key of big-endian `modulus` and
+/// `exponent`.
+pub fn verify_rs256(message: &[u8], signature: &[u8], modulus: &[u8], exponent: &[u8]) -> bool {
+    let modulus = BigUint::from_bytes_be(modulus);
+    let length = modulus.bits().div_ceil(8) as usize;
+    if length < MIN_MODULUS_LENGTH || signature.len() != length {
+        return false;
+    }
+    let signature = BigUint::from_bytes_be(signature);
+    if signature >= modulus {
+        return false;
+    }
+    let decrypted = signature.modpow(&BigUint::from_bytes_be(exponent), &modulus).to_bytes_be();
+    // The decrypted message is shorter than the modulus, so its leading zeros need restoring.
+    let mut encoded = Vec::with_capacity(length);
+    encoded.resize(length - decrypted.len(), 0);
+    encoded.extend(decrypted);
+    encoded == encode_pkcs1v15(message, length)
+}
+
+/// The EMSA-PKCS1-v1_5 encoding of the SHA-256 digest of `message`, `length` bytes long:
+/// `00 01 ff .. ff 00`, the `DigestInfo` and the digest. `length` is at least
+/// `MIN_MODULUS_LENGTH`, leaving room for the padding.
+pub(crate) fn encode_pkcs1v15(message: &[u8], length: usize) -> Vec<u8> {
+    let digest = Sha256::digest(message);
+    let mut encoded = Vec::with_capacity(length);
+    encoded.extend([0x00, 0x01]);
+    encoded.resize(length - SHA256_DIGEST_INFO.len() - digest.len() - 1, 0xff);
+    encoded.push(0x00);
+    encoded.extend(SHA256_DIGEST_INFO);
+    encoded.extend(digest);
+    encoded
+}
@@ -0,0 +1,80 @@
+//! The runtime API of the pallet, for nodes and clients to diagnose proof submissions.
+
+use crate::{
+    ClientId, Commitment, DeviceKey, FailureRecord, IdentityProof, IdentityRoot, IssuerId, JwkRecord, ProofTag,
+    ProviderInfo, ProviderStats, StorageStats, VerificationReport,
+};
+use alloc::vec::Vec;
+use codec::Codec;
+use sp_core::H256;
+use sp_runtime::DispatchError;
+
+sp_api::decl_runtime_apis! {
+    /// Diagnostics of proof submissions.
+    #[api_version(14)]
+    pub trait ZkProofApi<AccountId, BlockNumber>
+    where
+        AccountId: Codec,
+        BlockNumber: Codec,
+    {
+        /// The latest proof submissions included in a block and rejected, oldest first.
+        fn recent_failures() -> Vec<FailureRecord<AccountId, BlockNumber>>;
+        /// The usage of every provider whose tokens were ever submitted, to tell which providers
+        /// logins actually go through.
+        #[api_version(2)]
+        fn provider_stats() -> Vec<(IssuerId, ProviderStats<BlockNumber>)>;
+        /// The registered providers, for nodes fetching their keys to supply the JWKS inherent.
+        #[api_version(3)]
+        fn providers() -> Vec<(IssuerId, ProviderInfo)>;
+        /// The keys tokens of `provider` are currently accepted with, for wallets to tell which
+        /// keys the chain trusts; none if the issuer is not allowed or its keyset expired.
+        #[api_version(4)]
+        fn jwks(provider: IssuerId) -> Vec<JwkRecord>;
+        /// When (unix seconds) the current keyset of `provider` expires, if it ever does. Its keys
+        /// are still accepted for `JwksGracePeriod` after.
+        #[api_version(5)]
+        fn jwks_expiry(provider: IssuerId) -> Option<u64>;
+        /// Verify `proof` against the current state as `store_zk_proof` does, without storing
+        /// anything, for wallets to check a proof before submitting it.
+        #[api_version(6)]
+        fn check_proof(proof: Vec<u8>) -> Result<VerificationReport, DispatchError>;
+        /// The latest Merkle root of the identities holding a valid proof.
+        #[api_version(7)]
+        fn identity_root() -> Option<IdentityRoot<BlockNumber>>;
+        /// A proof that `commitment` is covered by the identity root, for light clients to check
+        /// memberships. Only meaningful at the block the root was computed in.
+        #[api_version(7)]
+        fn identity_proof(commitment: Commitment) -> Option<IdentityProof>;
+        /// The device keys of the identity `account` is a device key of, with their labels and
+        /// expiries, for users to tell which devices are logged in.
+        #[api_version(8)]
+        fn device_keys(account: AccountId) -> Vec<(AccountId, DeviceKey<BlockNumber>)>;
+        /// The hashes of the proofs stored from block `from` to block `to` included, with the
+        /// block each was stored in, for indexers to backfill the blocks they missed. At most
+        /// `MAX_PROOF_RANGE` blocks are covered; query the rest from `from + MAX_PROOF_RANGE`.
+        #[api_version(9)]
+        fn proofs_in_range(from: BlockNumber, to: BlockNumber) -> Vec<(BlockNumber, H256)>;
+        /// The application metadata stored alongside the proof with hash `proof_hash`, for dApps
+        /// to map proofs to their users without an off-chain table.
+        #[api_version(10)]
+        fn proof_metadata(proof_hash: H256) -> Option<Vec<u8>>;
+        /// Up to `limit` hashes of the proofs tagged with `tag`, resuming after `start_key`, for
+        /// dApps to list the proofs of a purpose, such as `kyc`. At most `MAX_PROOF_PAGE` are
+        /// returned; query the rest from the last one.
+        #[api_version(11)]
+        fn proofs_with_tag(tag: ProofTag, start_key: Option<H256>, limit: u32) -> Vec<H256>;
+        /// Up to `limit` hashes of the proofs stored in the namespace of the dApp `client_id`,
+        /// resuming after `start_key`. At most `MAX_PROOF_PAGE` are returned.
+        #[api_version(12)]
+        fn namespace_proofs(client_id: ClientId, start_key: Option<H256>, limit: u32) -> Vec<H256>;
+        /// The circuit versions deprecated, with the block from which their proofs are rejected,
+        /// for wallet teams to upgrade their provers in time. Version 0 stands for the proofs
+        /// declaring none.
+        #[api_version(13)]
+        fn circuit_sunsets() -> Vec<(u32, BlockNumber)>;
+        /// The storage footprint of the pallet: its proofs, attestations, device keys and keysets,
+        /// for operators to plan state growth.
+        #[api_version(14)]
+        fn storage_stats() -> StorageStats;
+    }
+}
@@ -1,57 +1,3353 @@
-use crate::{mock::*, Error};
-use frame_support::{assert_ok, assert_noop, BoundedVec};
+use crate::{
+    adapters::StandardAdapters,
+    circuit::{public_inputs, CircuitVersion, HashScheme},
+    claim_hash, decode_envelope, encoding,
+    envelope::{
+        commitment_of, jwt_segments, Curve, CurveInstance, DisclosedToken, G1Point, G2Coordinates, G2Point, JsonProof,
+        ScalarField,
+    },
+    google_jwks,
+    host::{self, dot_login},
+    inherent::INHERENT_IDENTIFIER,
+    migrations::{
+        v1::IndexIdentityProofs,
+        v2::{self, AttestationV1, ExtendAttestations},
+        v3::CountStorage,
+    },
+    mock::*,
+    offchain::{cache_expiry, parse_http_date},
+    poseidon::Poseidon,
+    prover::{address_seed, base64_of, snarkjs_envelope, Prover},
+    rsa::{self, encode_pkcs1v15},
+    validate_envelope, AccountProofs, Activation, AddressScheme, ApprovedVerifyingKeys, Attestation,
+    AttestationInspector, Attestations, Base64Alphabet, Base64Error, Base64Padding, Base64Variant, BlockedIdentity,
+    CheckProofStructure, CheckSpendingLimit, ClaimPolicy, ClaimPredicate, ClaimRequirement, ClaimViolation, ClientId,
+    Commitment, ConsumedNonces, DeviceKey, DeviceLabel, EnsureVerified, EnvelopeError, EnvelopeFormat, Error, Event,
+    FailedAttempts, FailureRecord, FailureStats, Fq2Bytes, G1Bytes, G2Bytes, IdentityFailures, IdentityProofs,
+    IdentityRoot, IssuerId, JwkRecord, Jwks, JwksExpiry, JwksUpdate, KeyId, KeyIdentities, LoginCommitments,
+    NamespaceQuota, NamespaceUsages, Nullifiers, Oracles, Parameters, PendingJwks, PredicateAttestation,
+    PredicateAttestations, Predicates, ProofMetadata, ProofNamespaces, ProofSubmission, ProofTag, ProofTags,
+    ProofTagsOf, ProviderAdapter, ProviderEncoding, ProviderEncodings, ProviderInfo, ProviderStats, ProviderUsage,
+    Providers, RecentFailures, RenewalReminders, RequiredClaims, ScheduledActivations, SpendingLimit, SpendingLimits,
+    Spent, StorageStats, StorageUsage, SubmissionContext, SubmitterFailures, Tenant, VerificationsInBlock,
+    VerifiedClaims, ZkLoginAuth, ZkProofData, ZkProofExpiry, ZkProofSubmitter, GOOGLE_ISSUER, IDENTITY_ROOT_ENGINE_ID,
+    MAX_PROOF_RANGE,
+};
+use ark_ec::bls12::{Bls12, Bls12Config};
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, Groth16};
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_serialize::CanonicalSerialize;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use codec::{Decode, Encode};
+use frame_support::{
+    assert_noop, assert_ok,
+    dispatch::DispatchInfo,
+    inherent::{InherentData, ProvideInherent},
+    migrations::SteppedMigration,
+    traits::{ConstU64, Contains, EnsureOrigin, Get, GetStorageVersion, Hooks, StorageVersion},
+    weights::WeightMeter,
+    BoundedVec,
+};
+use num_bigint::BigUint;
+use proptest::prelude::*;
+use sp_core::H256;
+use sp_runtime::{
+    traits::{Dispatchable, Hash, Keccak256, SignedExtension},
+    transaction_validity::{InvalidTransaction, TransactionValidityError},
+    DigestItem, DispatchError, Perbill, StateVersion,
+};
+use std::sync::OnceLock;
+
+fn google_issuer() -> IssuerId {
+    GOOGLE_ISSUER.to_vec().try_into().unwrap()
+}
+
+fn test_key(kid: &str) -> JwkRecord {
+    JwkRecord::new(kid, "RS256", "RSA", TEST_MODULUS, "AQAB").unwrap()
+}
+
+/// The RS256 signature of `message` by the test key.
+fn rs256(message: &str) -> Vec<u8> {
+    let decode = |value: &str| BigUint::from_bytes_be(&URL_SAFE_NO_PAD.decode(value).unwrap());
+    let encoded = BigUint::from_bytes_be(&encode_pkcs1v15(message.as_bytes(), rsa::MIN_MODULUS_LENGTH));
+    let signature = encoded.modpow(&decode(TEST_PRIVATE_EXPONENT), &decode(TEST_MODULUS)).to_bytes_be();
+    let mut padded = vec![0; rsa::MIN_MODULUS_LENGTH - signature.len()];
+    padded.extend(signature);
+    padded
+}
+
+/// Build a JWT from its header and payload JSON, signed by the test key.
+fn test_jwt(header: &str, payload: &str) -> String {
+    let signing_input = format!("{}.{}", URL_SAFE_NO_PAD.encode(header), URL_SAFE_NO_PAD.encode(payload));
+    format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(rs256(&signing_input)))
+}
+
+const GOOGLE_KID: &str = "0e345fd7e4a97271dffa991f5a893cd16b8e0827";
+
+/// An `exp` claim far in the future.
+const FAR_FUTURE: u64 = 4_102_444_800;
+
+/// The header of a token signed with one of Google's genesis keys.
+fn google_header() -> String {
+    format!(r#"{{"alg":"RS256","kid":"{}"}}"#, GOOGLE_KID)
+}
+
+/// A nonce embedding the hash of the genesis block.
+fn recent_nonce() -> String {
+    format!("0-{:x}", System::block_hash(0))
+}
+
+/// A Google token issued now, expiring at `exp` with a recent nonce, followed by the `extra` claims.
+/// Tokens with a `jti` are requested with a nonce of their own, as each login is for a fresh key.
+fn google_jwt(exp: u64, extra: &str) -> String {
+    let iat = Timestamp::now() / 1_000;
+    let jti = extra.split(r#""jti":""#).nth(1).and_then(|rest| rest.split('"').next());
+    let nonce = jti.map_or_else(recent_nonce, |jti| format!("{}-{}", recent_nonce(), jti));
+    let claims = format!(
+        r#"{{"iss":"https://accounts.google.com","sub":"1","iat":{},"exp":{},"nonce":"{}"{}}}"#,
+        iat, exp, nonce, extra
+    );
+    test_jwt(&google_header(), &claims)
+}
 
 #[test]
-fn store_zk_proof_works() {
+fn store_zk_proof_rejects_malformed_json() {
     new_test_ext().execute_with(|| {
         let json_data = vec![1, 2, 3, 4, 5];
-        let result = ZkProofModule::store_zk_proof(Origin::signed(1), json_data.clone());
-        assert_ok!(result);
-
-        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json_data);
-        let stored_data = pallet_zk_proof::ZkProofData::<Test>::get(proof_hash).unwrap();
-        assert_eq!(stored_data, BoundedVec::try_from(json_data).unwrap());
-
-        let expected_event = Event::ZkProofModule(crate::Event::ZkProofStored {
-            json: BoundedVec::try_from(vec![1, 2, 3, 4, 5]).unwrap(),
-            who: 1,
-            hash: proof_hash,
-        });
-        frame_system::Pallet::<Test>::assert_last_event(expected_event.into());
+        let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json_data, None);
+        assert_noop!(result, Error::<Test>::InvalidProof);
     });
 }
 
 #[test]
 fn store_zk_proof_too_large() {
     new_test_ext().execute_with(|| {
-        let json_data = vec![0; 2048]; // Larger than MaxJsonLength
-        let result = ZkProofModule::store_zk_proof(Origin::signed(1), json_data);
+        let json_data = vec![0; 4096]; // Larger than MaxJsonLength
+        let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json_data, None);
         assert_noop!(result, Error::<Test>::ZkProofTooLarge);
     });
 }
 
+/// Build a proof JSON with empty points around the given token and public inputs.
+fn test_proof_json(jwt: &str, public_inputs: &[&str]) -> String {
+    test_proof_json_with_vk("", jwt, public_inputs)
+}
+
+/// Build a proof JSON with empty points around the given verifying key, token and public inputs.
+fn test_proof_json_with_vk(vk: &str, jwt: &str, public_inputs: &[&str]) -> String {
+    test_proof_json_for_chain(&format!("{:x}", System::block_hash(0)), vk, jwt, public_inputs)
+}
+
+/// Build a proof JSON with empty points for the given chain, verifying key, token and public inputs.
+fn test_proof_json_for_chain(chain_id: &str, vk: &str, jwt: &str, public_inputs: &[&str]) -> String {
+    let point = r#"{"x":"","y":""}"#;
+    let g2_point = r#"{"x":{"c0":"","c1":""},"y":{"c0":"","c1":""}}"#;
+    let public_inputs = public_inputs.iter().map(|i| format!(r#""{}""#, i)).collect::<Vec<_>>().join(",");
+    format!(
+        r#"{{"a":{p},"b":{g2},"c":{p},"public_hash":"","verifying_key":"{vk}","jwt_token":"{jwt}","chain_id":"{chain_id}","public_inputs":[{inputs}]}}"#,
+        p = point,
+        g2 = g2_point,
+        vk = vk,
+        jwt = jwt,
+        chain_id = chain_id,
+        inputs = public_inputs,
+    )
+}
+
+#[test]
+fn store_zk_proof_rejects_oversized_jwt() {
+    new_test_ext().execute_with(|| {
+        let json = test_proof_json(&"a".repeat(MaxJwtLength::get() as usize + 1), &[]);
+        assert!(json.len() <= MaxJsonLength::get() as usize);
+
+        let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None);
+        assert_noop!(result, Error::<Test>::JwtTooLarge);
+    });
+}
+
+#[test]
+fn store_zk_proof_rejects_too_many_public_inputs() {
+    new_test_ext().execute_with(|| {
+        let json = test_proof_json("a.b.c", &["AA"; 6]);
+
+        let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None);
+        assert_noop!(result, Error::<Test>::TooManyPublicInputs);
+    });
+}
+
+#[test]
+fn store_zk_proof_rejects_corrupted_verifying_key() {
+    new_test_ext().execute_with(|| {
+        for vk in [&"AAAA".repeat(16), "not-a-key!", ""] {
+            let hash = ZkProofModule::verifying_key_hash(vk);
+            assert_ok!(ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), hash, None));
+        }
+
+        // Valid base64, but not a verifying key.
+        let json = test_proof_json_with_vk(&"AAAA".repeat(16), "a.b.c", &[]);
+        let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None);
+        assert_noop!(result, Error::<Test>::VkDeserializationFailed);
+
+        // Not even base64.
+        let json = test_proof_json_with_vk("not-a-key!", "a.b.c", &[]);
+        let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None);
+        assert_noop!(result, Error::<Test>::VkDeserializationFailed);
+
+        // Empty key.
+        let json = test_proof_json_with_vk("", "a.b.c", &[]);
+        let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None);
+        assert_noop!(result, Error::<Test>::VkDeserializationFailed);
+    });
+}
+
+#[test]
+fn duplicate_proofs_are_rejected() {
+    new_test_ext().execute_with(|| {
+        let json = test_proof_json("a.b.c", &[]).into_bytes();
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        ZkProofData::<Test>::insert(proof_hash, BoundedVec::try_from(json.clone()).unwrap());
+        ZkProofSubmitter::<Test>::insert(proof_hash, 1);
+
+        // By default nobody can store it again, not even the original submitter.
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.clone(), None),
+            Error::<Test>::ProofAlreadyExists
+        );
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), json.clone(), None),
+            Error::<Test>::ProofAlreadyExists
+        );
+
+        // With overwrites allowed, only the original submitter gets past the duplicate check.
+        AllowProofOverwrite::set(true);
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), json.clone(), None),
+            Error::<Test>::ProofAlreadyExists
+        );
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None),
+            Error::<Test>::VkDeserializationFailed
+        );
+    });
+}
+
+#[test]
+fn skipping_verification_still_validates_claims() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let jwt = test_jwt(&google_header(), r#"{"iss":"https://evil.example"}"#);
+        let json = test_proof_json(&jwt, &[]).into_bytes();
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None),
+            Error::<Test>::UntrustedIssuer
+        );
+
+        let jwt = google_jwt(FAR_FUTURE, "");
+        let json = test_proof_json(&jwt, &[]).into_bytes();
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.clone(), None));
+        assert_eq!(ZkProofData::<Test>::get(proof_hash).unwrap().into_inner(), json);
+        System::assert_last_event(
+            Event::ZkProofStored {
+                hash: proof_hash,
+                who: 1,
+                provider: google_issuer(),
+                commitment: Default::default(),
+                nullifier: ZkProofModule::nullifier(&google_issuer(), &Default::default()),
+                json: None,
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn proof_events_are_indexed_by_proof_and_identity() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let json = test_proof_json(&google_jwt(FAR_FUTURE, ""), &[]).into_bytes();
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None));
+
+        let commitment = ZkProofModule::commitment_topic(&Commitment::default());
+        assert_eq!(commitment, H256(Commitment::default()));
+        assert_eq!(System::events().last().unwrap().topics, vec![proof_hash, commitment]);
+        let index = System::event_count() - 1;
+        assert_eq!(frame_system::EventTopics::<Test>::get(proof_hash), vec![(1, index)]);
+        assert_eq!(frame_system::EventTopics::<Test>::get(commitment), vec![(1, index)]);
+    });
+}
+
+#[test]
+fn proofs_are_indexed_by_block() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let hash = |jti: u32| <Test as frame_system::Config>::Hashing::hash(&proof(jti));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(1), None));
+        System::set_block_number(3);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof(2), None));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(3), proof(3), None));
+
+        let mut third_block = vec![(3, hash(2)), (3, hash(3))];
+        third_block.sort();
+        assert_eq!(ZkProofModule::proofs_in_range(2, 10), third_block);
+        assert_eq!(ZkProofModule::proofs_in_range(0, 2), vec![(1, hash(1))]);
+        assert!(ZkProofModule::proofs_in_range(4, 10).is_empty());
+
+        // Queries cover at most `MAX_PROOF_RANGE` blocks.
+        let last = MAX_PROOF_RANGE as u64 + 1;
+        System::set_block_number(last);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(4), proof(4), None));
+        assert_eq!(ZkProofModule::proofs_in_range(1, u64::MAX), [vec![(1, hash(1))], third_block].concat());
+        assert_eq!(ZkProofModule::proofs_in_range(2, u64::MAX).last(), Some(&(last, hash(4))));
+    });
+}
+
+#[test]
+fn verified_proofs_are_reported_to_the_runtime() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let untrusted = test_jwt(&google_header(), r#"{"iss":"https://evil.example"}"#);
+        let json = test_proof_json(&untrusted, &[]).into_bytes();
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None),
+            Error::<Test>::UntrustedIssuer
+        );
+        assert_eq!(VerifiedProofs::get(), vec![]);
+
+        let jwt = google_jwt(FAR_FUTURE, "");
+        let claims = ZkProofModule::validate_jwt(&jwt).unwrap();
+        assert_ok!(ZkProofModule::store_zk_proof(
+            RuntimeOrigin::signed(1),
+            test_proof_json(&jwt, &[]).into_bytes(),
+            None
+        ));
+        assert_eq!(VerifiedProofs::get(), vec![(1, Commitment::default(), claims.digest())]);
+    });
+}
+
+#[test]
+fn unsupported_signature_algorithms_are_rejected() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let claims = format!(
+            r#"{{"iss":"https://accounts.google.com","sub":"1","iat":{},"exp":{},"nonce":"{}"}}"#,
+            Timestamp::now() / 1_000,
+            FAR_FUTURE,
+            recent_nonce()
+        );
+        let json = |header: &str| test_proof_json(&test_jwt(header, &claims), &[]);
+        let store =
+            |header: &str| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json(header).into_bytes(), None);
+
+        for alg in ["none", "NONE", "HS256", "RS512"] {
+            let header = format!(r#"{{"alg":"{}","kid":"{}"}}"#, alg, GOOGLE_KID);
+            assert_noop!(store(&header), Error::<Test>::UnsupportedAlgorithm);
+            assert_eq!(
+                ZkProofModule::check_structure(json(&header).as_bytes()),
+                Err(Error::<Test>::UnsupportedAlgorithm)
+            );
+        }
+        assert_noop!(store(&format!(r#"{{"kid":"{}"}}"#, GOOGLE_KID)), Error::<Test>::UnsupportedAlgorithm);
+
+        // Keys are only used for the algorithm they are meant for.
+        let key = JwkRecord::new(GOOGLE_KID, "RS512", "RSA", "modulus", "AQAB").unwrap();
+        Jwks::<Test>::insert(google_issuer(), BoundedVec::truncate_from(vec![key]));
+        assert_noop!(store(&google_header()), Error::<Test>::UnsupportedAlgorithm);
+    });
+}
+
+#[test]
+fn tokens_must_be_signed_by_the_key_they_name() {
+    new_test_ext().execute_with(|| {
+        let token = google_jwt(FAR_FUTURE, "");
+        assert_ok!(ZkProofModule::validate_jwt(&token));
+
+        // Tampering with the claims, or with the signature, breaks it.
+        let (header, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let other_claims = google_jwt(FAR_FUTURE, r#","email":"someone@example.com""#);
+        let other_payload = other_claims.split('.').nth(1).unwrap();
+        let forged = format!("{}.{}.{}", header, other_payload, signature);
+        assert_eq!(ZkProofModule::validate_jwt(&forged), Err(Error::<Test>::InvalidSignature));
+        let truncated = &token[..token.len() - 4];
+        assert_eq!(ZkProofModule::validate_jwt(truncated), Err(Error::<Test>::InvalidSignature));
+        let unsigned = format!("{}.{}.", header, rest.split_once('.').unwrap().0);
+        assert_eq!(ZkProofModule::validate_jwt(&unsigned), Err(Error::<Test>::InvalidSignature));
+
+        // Tokens are checked against the key they name, and only RSA keys are supported.
+        Jwks::<Test>::insert(google_issuer(), BoundedVec::truncate_from(google_jwks()));
+        assert_eq!(ZkProofModule::validate_jwt(&token), Err(Error::<Test>::InvalidSignature));
+        let key = JwkRecord::new(GOOGLE_KID, "RS256", "EC", TEST_MODULUS, "AQAB").unwrap();
+        Jwks::<Test>::insert(google_issuer(), BoundedVec::truncate_from(vec![key]));
+        assert_eq!(ZkProofModule::validate_jwt(&token), Err(Error::<Test>::UnsupportedAlgorithm));
+
+        // Keys shorter than 2048 bits are rejected.
+        let message = b"header.payload";
+        let short_modulus = [0xff; rsa::MIN_MODULUS_LENGTH - 1];
+        assert!(!rsa::verify_rs256(message, &[0x01; rsa::MIN_MODULUS_LENGTH - 1], &short_modulus, &[1, 0, 1]));
+    });
+}
+
+#[test]
+fn providers_can_require_custom_claims() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let requirement = |path: &str, allowed: &[&str]| ClaimRequirement {
+            path: BoundedVec::truncate_from(path.as_bytes().to_vec()),
+            allowed: allowed.iter().map(|value| claim_hash(&(*value).into())).collect::<Vec<_>>().try_into().unwrap(),
+        };
+        let require = |provider: IssuerId, requirements: Vec<ClaimRequirement>| {
+            ZkProofModule::set_required_claims(RuntimeOrigin::root(), provider, requirements.try_into().unwrap())
+        };
+        let unknown = IssuerId::truncate_from(b"https://unknown.example".to_vec());
+        assert_noop!(require(unknown, vec![]), Error::<Test>::UnknownProvider);
+        assert_noop!(
+            require(google_issuer(), vec![requirement("firebase.tenant", &[])]),
+            Error::<Test>::InvalidClaimPath
+        );
+        assert_ok!(require(
+            google_issuer(),
+            vec![requirement("/firebase/tenant", &[]), requirement("/roles", &["admin"])]
+        ));
+        System::assert_last_event(Event::RequiredClaimsSet { provider: google_issuer() }.into());
+
+        let store = |extra: &str| {
+            let json = test_proof_json(&google_jwt(FAR_FUTURE, extra), &[]);
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None)
+        };
+        let violation = |index: u8, violation: ClaimViolation| Error::<Test>::ClaimPolicyViolation {
+            policy: ClaimPolicy::Provider,
+            index,
+            violation,
+        };
+        assert_noop!(store(r#","roles":["admin"]"#), violation(0, ClaimViolation::Missing));
+        assert_noop!(
+            store(r#","firebase":{"tenant":"acme"},"roles":"user""#),
+            violation(1, ClaimViolation::NotAllowed)
+        );
+        assert_noop!(
+            store(r#","firebase":{"tenant":"acme"},"roles":["user"]"#),
+            violation(1, ClaimViolation::NotAllowed)
+        );
+
+        // The hashes of the required claims are part of the claims digest.
+        let extra = r#","firebase":{"tenant":"acme"},"roles":["user","admin"]"#;
+        assert_ok!(store(extra));
+        let claims = ZkProofModule::validate_jwt(&google_jwt(FAR_FUTURE, extra)).unwrap();
+        let roles = serde_json::json!(["user", "admin"]);
+        assert_eq!(claims.required_claims, vec![claim_hash(&"acme".into()), claim_hash(&roles)]);
+        assert_eq!(VerifiedProofs::get(), vec![(1, Commitment::default(), claims.digest())]);
+
+        assert_ok!(require(google_issuer(), vec![]));
+        assert!(!RequiredClaims::<Test>::contains_key(google_issuer()));
+    });
+}
+
+#[test]
+fn proof_calls_are_capped_to_their_share_of_the_block() {
+    new_test_ext().execute_with(|| {
+        use frame_support::dispatch::GetDispatchInfo;
+        let max_block = <Test as frame_system::Config>::BlockWeights::get().max_block.ref_time();
+        // Room for two proof calls per block.
+        ProofWeightShare::set(Perbill::from_rational(
+            ZkProofModule::verification_weight().ref_time() * 5 / 2,
+            max_block,
+        ));
+
+        let proof = |jti: u32| -> RuntimeCall {
+            let jwt = google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti));
+            crate::Call::store_zk_proof { json: test_proof_json(&jwt, &[]).into_bytes(), metadata: None }.into()
+        };
+        let pre_dispatch = |call: RuntimeCall| {
+            CheckProofStructure::<Test>::new().pre_dispatch(&1, &call, &call.get_dispatch_info(), 0).map(|_| ())
+        };
+
+        assert_ok!(pre_dispatch(proof(1)));
+        assert_ok!(pre_dispatch(proof(2)));
+        assert_eq!(pre_dispatch(proof(3)), Err(InvalidTransaction::ExhaustsResources.into()));
+        // Calls which are not proof calls are not capped.
+        let commit = crate::Call::commit_login { proof_hash: H256::zero() }.into();
+        assert_ok!(pre_dispatch(commit));
+
+        // The budget is replenished in the next block.
+        System::set_block_number(2);
+        ZkProofModule::on_initialize(2);
+        assert_ok!(pre_dispatch(proof(3)));
+    });
+}
+
+#[test]
+fn proof_call_weights_bound_their_storage_proofs() {
+    new_test_ext().execute_with(|| {
+        use codec::MaxEncodedLen;
+        use frame_support::dispatch::GetDispatchInfo;
+        let proof_size = |call: crate::Call<Test>| call.get_dispatch_info().weight.proof_size();
+        let retrieve = |limit| proof_size(crate::Call::retrieve_all_zk_proofs { start_key: None, limit });
+
+        // Every retrieved payload may be `MaxJsonLength` bytes long.
+        let per_proof = retrieve(2) - retrieve(1);
+        assert!(per_proof > MaxJsonLength::get() as u64);
+        MaxJsonLength::set(4096);
+        assert_eq!(retrieve(2) - retrieve(1), per_proof + 2048);
+
+        // Verifying a proof reads all the keys of its issuer.
+        let keys = 4 * JwkRecord::max_encoded_len() as u64;
+        assert!(proof_size(crate::Call::store_zk_proof { json: vec![], metadata: None }) > keys);
+        assert!(proof_size(crate::Call::erase_identity_data { proof: vec![] }) > keys);
+    });
+}
+
 #[test]
 fn retrieve_all_zk_proofs_works() {
     new_test_ext().execute_with(|| {
         let json_data_1 = vec![1, 2, 3, 4, 5];
         let json_data_2 = vec![6, 7, 8, 9, 10];
 
-        // Store the first proof
-        assert_ok!(ZkProofModule::store_zk_proof(Origin::signed(1), json_data_1.clone()));
-        // Store the second proof
-        assert_ok!(ZkProofModule::store_zk_proof(Origin::signed(1), json_data_2.clone()));
+        let proof_hash_1 = <Test as frame_system::Config>::Hashing::hash(&json_data_1);
+        let proof_hash_2 = <Test as frame_system::Config>::Hashing::hash(&json_data_2);
+
+        // Store both proofs
+        ZkProofData::<Test>::insert(proof_hash_1, BoundedVec::try_from(json_data_1.clone()).unwrap());
+        ZkProofData::<Test>::insert(proof_hash_2, BoundedVec::try_from(json_data_2.clone()).unwrap());
 
         // Retrieve all proofs
-        let result = ZkProofModule::retrieve_all_zk_proofs(Origin::signed(1));
+        let result = ZkProofModule::retrieve_all_zk_proofs(RuntimeOrigin::signed(1), None, 10);
         assert_ok!(result);
+        System::assert_last_event(Event::ZkProofsRetrieved { next: None }.into());
 
-        let proof_hash_1 = <Test as frame_system::Config>::Hashing::hash(&json_data_1);
-        let proof_hash_2 = <Test as frame_system::Config>::Hashing::hash(&json_data_2);
+        System::assert_has_event(Event::ZkProofRetrieved { hash: proof_hash_1, who: None, json: None }.into());
+        System::assert_has_event(Event::ZkProofRetrieved { hash: proof_hash_2, who: None, json: None }.into());
+
+        // Chains that want the payloads can still get them.
+        EmitProofPayloads::set(true);
+        assert_ok!(ZkProofModule::retrieve_all_zk_proofs(RuntimeOrigin::signed(1), None, 10));
+        System::assert_has_event(
+            Event::ZkProofRetrieved { hash: proof_hash_1, who: None, json: Some(json_data_1) }.into(),
+        );
+        System::assert_has_event(
+            Event::ZkProofRetrieved { hash: proof_hash_2, who: None, json: Some(json_data_2) }.into(),
+        );
+    });
+}
+
+#[test]
+fn retrieve_all_zk_proofs_is_paginated() {
+    new_test_ext().execute_with(|| {
+        for data in [vec![1], vec![2], vec![3]] {
+            let proof_hash = <Test as frame_system::Config>::Hashing::hash(&data);
+            ZkProofData::<Test>::insert(proof_hash, BoundedVec::try_from(data).unwrap());
+        }
+        let retrieved = || {
+            System::events()
+                .into_iter()
+                .filter_map(|record| match record.event {
+                    RuntimeEvent::ZkProofModule(Event::ZkProofRetrieved { hash, .. }) => Some(hash),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let next = || match System::events().last().map(|record| record.event.clone()) {
+            Some(RuntimeEvent::ZkProofModule(Event::ZkProofsRetrieved { next })) => next,
+            _ => panic!("missing ZkProofsRetrieved event"),
+        };
+
+        assert_ok!(ZkProofModule::retrieve_all_zk_proofs(RuntimeOrigin::signed(1), None, 2));
+        let first_page = retrieved();
+        assert_eq!(first_page.len(), 2);
+        let cursor = next();
+        assert_eq!(cursor, first_page.last().copied());
+
+        System::reset_events();
+        assert_ok!(ZkProofModule::retrieve_all_zk_proofs(RuntimeOrigin::signed(1), cursor, 2));
+        let second_page = retrieved();
+        assert_eq!(second_page.len(), 1);
+        assert!(!first_page.contains(&second_page[0]));
+        assert_eq!(next(), None);
+    });
+}
+
+#[test]
+fn genesis_seeds_google_keys_and_oracles() {
+    new_test_ext().execute_with(|| {
+        assert!(Oracles::<Test>::contains_key(ORACLE));
+        assert_eq!(Jwks::<Test>::get(google_issuer()).unwrap().len(), 2);
+    });
+}
+
+#[test]
+fn admin_manages_oracle_set() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(ZkProofModule::add_oracle(RuntimeOrigin::signed(1), 2), DispatchError::BadOrigin);
+
+        assert_ok!(ZkProofModule::add_oracle(RuntimeOrigin::root(), 2));
+        assert!(Oracles::<Test>::contains_key(2));
+        System::assert_last_event(Event::OracleAdded { oracle: 2 }.into());
+
+        assert_noop!(ZkProofModule::add_oracle(RuntimeOrigin::root(), 2), Error::<Test>::OracleAlreadyRegistered);
+
+        assert_ok!(ZkProofModule::remove_oracle(RuntimeOrigin::root(), 2));
+        assert!(!Oracles::<Test>::contains_key(2));
+        System::assert_last_event(Event::OracleRemoved { oracle: 2 }.into());
+
+        assert_noop!(ZkProofModule::remove_oracle(RuntimeOrigin::root(), 2), Error::<Test>::UnknownOracle);
+    });
+}
+
+#[test]
+fn only_oracles_can_submit_jwks() {
+    new_test_ext().execute_with(|| {
+        let keys: BoundedVec<_, _> = vec![test_key("rotated")].try_into().unwrap();
+
+        assert_noop!(
+            ZkProofModule::submit_jwks(RuntimeOrigin::signed(1), google_issuer(), keys, None),
+            Error::<Test>::NotOracle
+        );
+    });
+}
+
+#[test]
+fn jwks_update_requires_quorum() {
+    new_test_ext().execute_with(|| {
+        let keys: BoundedVec<_, _> = vec![test_key("rotated")].try_into().unwrap();
+        let keyset_hash = <Test as frame_system::Config>::Hashing::hash_of(&keys);
+
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE), google_issuer(), keys.clone(), None));
+        assert_eq!(Jwks::<Test>::get(google_issuer()).unwrap().len(), 2);
+        assert!(PendingJwks::<Test>::contains_key(google_issuer(), keyset_hash));
+        System::assert_last_event(
+            Event::JwksSubmitted { provider: google_issuer(), keyset_hash, who: ORACLE, approvals: 1 }.into(),
+        );
+
+        assert_noop!(
+            ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE), google_issuer(), keys.clone(), None),
+            Error::<Test>::DuplicateSubmission
+        );
+
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE_2), google_issuer(), keys.clone(), None));
+        assert_eq!(Jwks::<Test>::get(google_issuer()), Some(keys));
+        assert!(!PendingJwks::<Test>::contains_key(google_issuer(), keyset_hash));
+        System::assert_last_event(
+            Event::JwksUpdated { provider: google_issuer(), keyset_hash, expires_at: None }.into(),
+        );
+    });
+}
+
+#[test]
+fn mismatching_keysets_do_not_reach_quorum() {
+    new_test_ext().execute_with(|| {
+        let keys: BoundedVec<_, _> = vec![test_key("rotated")].try_into().unwrap();
+        let forged: BoundedVec<_, _> = vec![test_key("forged")].try_into().unwrap();
+
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE), google_issuer(), keys, None));
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE_2), google_issuer(), forged, None));
+
+        assert_eq!(Jwks::<Test>::get(google_issuer()).unwrap().len(), 2);
+    });
+}
+
+#[test]
+fn stale_candidates_restart_the_vote() {
+    new_test_ext().execute_with(|| {
+        let keys: BoundedVec<_, _> = vec![test_key("rotated")].try_into().unwrap();
+        let keyset_hash = <Test as frame_system::Config>::Hashing::hash_of(&keys);
+
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE), google_issuer(), keys.clone(), None));
+
+        System::set_block_number(1 + JwksSubmissionWindow::get() + 1);
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE_2), google_issuer(), keys, None));
+
+        assert_eq!(Jwks::<Test>::get(google_issuer()).unwrap().len(), 2);
+        assert_eq!(
+            PendingJwks::<Test>::get(google_issuer(), keyset_hash).unwrap().approvals.into_inner(),
+            vec![ORACLE_2]
+        );
+    });
+}
+
+#[test]
+fn keyset_expiry_is_the_earliest_reported() {
+    new_test_ext().execute_with(|| {
+        let keys: BoundedVec<_, _> = vec![test_key("rotated")].try_into().unwrap();
+
+        assert_ok!(ZkProofModule::submit_jwks(
+            RuntimeOrigin::signed(ORACLE),
+            google_issuer(),
+            keys.clone(),
+            Some(2_000)
+        ));
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE_2), google_issuer(), keys, Some(1_000)));
+
+        assert_eq!(JwksExpiry::<Test>::get(google_issuer()), Some(1_000));
+    });
+}
+
+#[test]
+fn expired_keysets_are_rejected_after_the_grace_period() {
+    new_test_ext().execute_with(|| {
+        assert!(!ZkProofModule::keyset_expired(&google_issuer()));
+
+        JwksExpiry::<Test>::insert(google_issuer(), 1_000);
+
+        Timestamp::set_timestamp((1_000 + JwksGracePeriod::get()) * 1_000);
+        assert!(!ZkProofModule::keyset_expired(&google_issuer()));
+        assert_eq!(
+            ZkProofModule::accepted_jwks(&google_issuer()),
+            Jwks::<Test>::get(google_issuer()).unwrap().to_vec()
+        );
+
+        Timestamp::set_timestamp((1_000 + JwksGracePeriod::get() + 1) * 1_000);
+        assert!(ZkProofModule::keyset_expired(&google_issuer()));
+        assert!(ZkProofModule::accepted_jwks(&google_issuer()).is_empty());
+
+        let untrusted: IssuerId = b"https://untrusted.example".to_vec().try_into().unwrap();
+        Jwks::<Test>::insert(&untrusted, BoundedVec::truncate_from(vec![test_key("untrusted")]));
+        assert!(ZkProofModule::accepted_jwks(&untrusted).is_empty());
+    });
+}
+
+#[test]
+fn cache_headers_determine_expiry() {
+    assert_eq!(cache_expiry(Some("public, max-age=19732, must-revalidate"), None, 100), Some(19_832));
+    // `max-age` wins over `Expires`.
+    assert_eq!(cache_expiry(Some("max-age=10"), Some("Sun, 06 Nov 1994 08:49:37 GMT"), 100), Some(110));
+    assert_eq!(cache_expiry(Some("no-cache"), Some("Sun, 06 Nov 1994 08:49:37 GMT"), 100), Some(784_111_777));
+    assert_eq!(cache_expiry(None, None, 100), None);
+
+    assert_eq!(parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"), Some(1_709_164_800));
+    assert_eq!(parse_http_date("Thu, 29 Foo 2024 00:00:00 GMT"), None);
+    assert_eq!(parse_http_date("-1"), None);
+}
+
+#[test]
+fn admin_manages_providers() {
+    new_test_ext().execute_with(|| {
+        let issuer: IssuerId = b"https://issuer.example".to_vec().try_into().unwrap();
+        let jwks_uri: BoundedVec<_, _> = b"https://issuer.example/jwks".to_vec().try_into().unwrap();
+
+        assert_noop!(
+            ZkProofModule::add_provider(RuntimeOrigin::signed(1), issuer.clone(), jwks_uri.clone(), None),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(ZkProofModule::add_provider(RuntimeOrigin::root(), issuer.clone(), jwks_uri.clone(), None));
+        assert_eq!(Providers::<Test>::get(&issuer).unwrap().jwks_uri, jwks_uri);
+        assert_noop!(
+            ZkProofModule::add_provider(RuntimeOrigin::root(), issuer.clone(), jwks_uri, None),
+            Error::<Test>::ProviderAlreadyRegistered
+        );
+
+        assert_ok!(ZkProofModule::remove_provider(RuntimeOrigin::root(), google_issuer()));
+        assert!(Jwks::<Test>::get(google_issuer()).is_none());
+        System::assert_last_event(Event::ProviderRemoved { provider: google_issuer() }.into());
+
+        let keys: BoundedVec<_, _> = vec![test_key("rotated")].try_into().unwrap();
+        assert_noop!(
+            ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE), google_issuer(), keys, None),
+            Error::<Test>::UnknownProvider
+        );
+    });
+}
+
+#[test]
+fn onboarding_takes_its_own_origin() {
+    new_test_ext().execute_with(|| {
+        let issuer: IssuerId = b"https://issuer.example".to_vec().try_into().unwrap();
+        let jwks_uri: BoundedVec<_, _> = b"https://issuer.example/jwks".to_vec().try_into().unwrap();
+        let vk_hash = [7; 32];
+        let council = RuntimeOrigin::signed(SecurityCouncil::get());
+
+        // The council trusts new providers, keys and circuits...
+        assert_ok!(ZkProofModule::add_provider(council.clone(), issuer.clone(), jwks_uri, None));
+        assert_ok!(ZkProofModule::force_set_jwk(council.clone(), issuer.clone(), test_key("council")));
+        assert_ok!(ZkProofModule::approve_verifying_key(council.clone(), vk_hash, None));
+        assert_noop!(
+            ZkProofModule::approve_verifying_key(RuntimeOrigin::signed(1), [8; 32], None),
+            DispatchError::BadOrigin
+        );
+
+        // ...while routine operations and removals stay with the admin.
+        assert_noop!(ZkProofModule::remove_provider(council.clone(), issuer.clone()), DispatchError::BadOrigin);
+        assert_noop!(ZkProofModule::revoke_verifying_key(council, vk_hash), DispatchError::BadOrigin);
+        assert_ok!(ZkProofModule::revoke_verifying_key(RuntimeOrigin::root(), vk_hash));
+        assert_ok!(ZkProofModule::remove_provider(RuntimeOrigin::root(), issuer));
+    });
+}
+
+#[test]
+fn block_authors_can_supply_keys_as_inherents() {
+    new_test_ext().execute_with(|| {
+        let update =
+            |provider: IssuerId, kid: &str| JwksUpdate { provider, keys: vec![test_key(kid)], expires_at: Some(5_000) };
+        let unknown: IssuerId = b"https://unknown.example".to_vec().try_into().unwrap();
+        let mut data = InherentData::new();
+        data.put_data(INHERENT_IDENTIFIER, &vec![update(google_issuer(), "rotated"), update(unknown.clone(), "other")])
+            .unwrap();
+        let set = |updates| ZkProofModule::set_authored_jwks(RuntimeOrigin::none(), updates);
+
+        // Authors are not trusted with keys by default.
+        assert_eq!(ZkProofModule::create_inherent(&data), None);
+        assert_noop!(set(vec![]), Error::<Test>::AuthoredJwksDisabled);
+
+        AuthoredJwks::set(true);
+        // Only keysets of registered providers are included.
+        let call = ZkProofModule::create_inherent(&data).unwrap();
+        assert!(ZkProofModule::is_inherent(&call));
+        assert_eq!(call, crate::Call::set_authored_jwks { updates: vec![update(google_issuer(), "rotated")] });
+        assert_ok!(ZkProofModule::check_inherent(&call, &data));
+
+        assert_noop!(ZkProofModule::set_authored_jwks(RuntimeOrigin::signed(ORACLE), vec![]), DispatchError::BadOrigin);
+        assert_ok!(set(vec![update(google_issuer(), "rotated")]));
+        assert_eq!(Jwks::<Test>::get(google_issuer()).unwrap().into_inner(), vec![test_key("rotated")]);
+        assert_eq!(JwksExpiry::<Test>::get(google_issuer()), Some(5_000));
+        assert_noop!(set(vec![]), Error::<Test>::AuthoredJwksAlreadySet);
+
+        // Keysets that did not change are left out.
+        System::set_block_number(2);
+        ZkProofModule::on_initialize(2);
+        assert_eq!(ZkProofModule::create_inherent(&data), None);
+        assert_noop!(
+            set(vec![update(google_issuer(), "other"), update(unknown, "other")]),
+            Error::<Test>::UnknownProvider
+        );
+    });
+}
+
+#[test]
+fn admin_can_force_set_and_remove_keys() {
+    new_test_ext().execute_with(|| {
+        let key = test_key("emergency");
+
+        assert_noop!(
+            ZkProofModule::force_set_jwk(RuntimeOrigin::signed(ORACLE), google_issuer(), key.clone()),
+            DispatchError::BadOrigin
+        );
+
+        JwksExpiry::<Test>::insert(google_issuer(), 1_000);
+        assert_ok!(ZkProofModule::force_set_jwk(RuntimeOrigin::root(), google_issuer(), key.clone()));
+        let keys = Jwks::<Test>::get(google_issuer()).unwrap();
+        assert_eq!(keys.len(), 3);
+        assert!(keys.contains(&key));
+        assert_eq!(JwksExpiry::<Test>::get(google_issuer()), None);
+        System::assert_last_event(Event::JwkForceSet { provider: google_issuer(), kid: key.kid.clone() }.into());
+
+        // Setting a key with the same id replaces it.
+        assert_ok!(ZkProofModule::force_set_jwk(RuntimeOrigin::root(), google_issuer(), key.clone()));
+        assert_eq!(Jwks::<Test>::get(google_issuer()).unwrap().len(), 3);
+
+        assert_ok!(ZkProofModule::force_remove_jwk(RuntimeOrigin::root(), google_issuer(), key.kid.clone()));
+        assert!(!Jwks::<Test>::get(google_issuer()).unwrap().contains(&key));
+        System::assert_last_event(Event::JwkForceRemoved { provider: google_issuer(), kid: key.kid.clone() }.into());
 
-        let expected_event_1 = Event::ZkProofModule(crate::Event::ZkProofRetrieved(proof_hash_1, json_data_1));
-        let expected_event_2 = Event::ZkProofModule(crate::Event::ZkProofRetrieved(proof_hash_2, json_data_2));
+        assert_noop!(
+            ZkProofModule::force_remove_jwk(RuntimeOrigin::root(), google_issuer(), key.kid),
+            Error::<Test>::UnknownKid
+        );
+    });
+}
+
+#[test]
+fn tokens_from_untrusted_issuers_are_rejected() {
+    new_test_ext().execute_with(|| {
+        let header = google_header();
+
+        assert_ok!(ZkProofModule::validate_jwt(&google_jwt(FAR_FUTURE, "")));
+        assert_eq!(
+            ZkProofModule::validate_jwt(&test_jwt(&header, r#"{"iss":"https://evil.example"}"#)),
+            Err(Error::<Test>::UntrustedIssuer)
+        );
+        assert_eq!(ZkProofModule::validate_jwt(&test_jwt(&header, r#"{"sub":"1"}"#)), Err(Error::<Test>::InvalidProof));
+    });
+}
+
+#[test]
+fn expired_tokens_are_rejected_beyond_the_clock_skew_tolerance() {
+    new_test_ext().execute_with(|| {
+        let jwt = google_jwt(1_000, "");
+
+        Timestamp::set_timestamp(1_030 * 1_000);
+        assert_ok!(ZkProofModule::validate_jwt(&jwt));
+
+        Timestamp::set_timestamp(1_031 * 1_000);
+        assert_eq!(ZkProofModule::validate_jwt(&jwt), Err(Error::<Test>::TokenExpired));
+    });
+}
+
+#[test]
+fn admin_tunes_parameters() {
+    new_test_ext().execute_with(|| {
+        let mut parameters = Parameters::<Test>::get();
+        assert_eq!(parameters.max_json_length, MaxJsonLength::get());
+        parameters.max_json_length = 16;
+
+        assert_noop!(ZkProofModule::set_config(RuntimeOrigin::signed(1), parameters.clone()), DispatchError::BadOrigin);
+        assert_ok!(ZkProofModule::set_config(RuntimeOrigin::root(), parameters.clone()));
+        System::assert_last_event(Event::ParametersUpdated { parameters: parameters.clone() }.into());
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), vec![0; 17], None),
+            Error::<Test>::ZkProofTooLarge
+        );
+
+        // The storage bound cannot be raised at runtime.
+        let mut oversized = parameters.clone();
+        oversized.max_json_length = MaxJsonLength::get() + 1;
+        assert_noop!(ZkProofModule::set_config(RuntimeOrigin::root(), oversized), Error::<Test>::InvalidParameters);
+
+        // Nor can the verifications a block includes, which the admission checks read.
+        assert_eq!(parameters.max_verifications_per_block, MaxVerificationsPerBlock::get());
+        parameters.max_verifications_per_block = MaxVerificationsPerBlock::get() + 1;
+        assert_noop!(
+            ZkProofModule::set_config(RuntimeOrigin::root(), parameters.clone()),
+            Error::<Test>::InvalidParameters
+        );
+        parameters.max_verifications_per_block = 1;
+        assert_ok!(ZkProofModule::set_config(RuntimeOrigin::root(), parameters));
+        assert!(ZkProofModule::reserve_verification());
+        assert!(!ZkProofModule::reserve_verification());
+    });
+}
+
+#[test]
+fn envelopes_are_validated_off_chain() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(validate_envelope(b"{}").err(), Some(EnvelopeError::MalformedJson));
+        assert_eq!(validate_envelope(&[0xff]).err(), Some(EnvelopeError::InvalidUtf8));
 
-        frame_system::Pallet::<Test>::assert_has_event(expected_event_1.into());
-        frame_system::Pallet::<Test>::assert_last_event(expected_event_2.into());
+        let json = test_proof_json_with_vk("not-a-key!", "a.b.c", &[]);
+        assert_eq!(validate_envelope(json.as_bytes()).err(), Some(EnvelopeError::InvalidVerifyingKey));
+
+        let json = test_proof_json("a.b.c", &["!!"]);
+        let envelope = JsonProof::from_bytes(json.as_bytes()).unwrap();
+        assert_eq!(envelope.proof::<ark_bls12_381::Config>().err(), Some(EnvelopeError::InvalidPoint));
+        assert_eq!(
+            envelope.public_inputs::<ark_bls12_381::Config>().err(),
+            Some(EnvelopeError::InvalidBase64(Base64Error::InvalidPadding))
+        );
+        assert_eq!(envelope.public_input_count(), 1);
+        assert_eq!(envelope.chain_id().map(H256::from), Ok(System::block_hash(0)));
     });
 }
+
+#[test]
+fn tokens_are_consumed_until_their_nonce_is_stale() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof = |extra: &str| test_proof_json(&google_jwt(FAR_FUTURE, extra), &[]).into_bytes();
+        let store =
+            |who: u64, extra: &str| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(who), proof(extra), None);
+
+        let token_proof = |nonce: &str, jti: &str| {
+            let claims = format!(
+                r#"{{"iss":"https://accounts.google.com","sub":"1","iat":{},"exp":{},"nonce":"{}-{}","jti":"{}"}}"#,
+                Timestamp::now() / 1_000,
+                FAR_FUTURE,
+                recent_nonce(),
+                nonce,
+                jti
+            );
+            test_proof_json(&test_jwt(&google_header(), &claims), &[]).into_bytes()
+        };
+
+        // A token is spent by the first proof of it, along with its nonce and its id.
+        assert_ok!(store(1, r#","jti":"a""#));
+        assert_noop!(store(2, r#","jti":"a","email":"a@corp.com""#), Error::<Test>::NonceAlreadyUsed);
+        assert_eq!(
+            ZkProofModule::check_proof(&proof(r#","jti":"a","email":"a@corp.com""#)),
+            Err(Error::<Test>::NonceAlreadyUsed.into())
+        );
+        assert!(ZkProofModule::check_proof(&proof(r#","jti":"a""#)).unwrap().already_stored);
+        assert_ok!(store(2, r#","jti":"b""#));
+        // Neither a spent nonce under another id, nor a spent id under another nonce, is accepted.
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), token_proof("a", "c"), None),
+            Error::<Test>::NonceAlreadyUsed
+        );
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), token_proof("c", "b"), None),
+            Error::<Test>::NonceAlreadyUsed
+        );
+        // Tokens without an id are spent by their nonce.
+        assert_ok!(store(3, ""));
+        assert_noop!(store(3, r#","email":"a@corp.com""#), Error::<Test>::NonceAlreadyUsed);
+        assert_eq!(ConsumedNonces::<Test>::iter_prefix(0).count(), 5);
+
+        // Once the nonce is stale, the token is forgotten.
+        ZkProofModule::on_initialize(NonceBlockWindow::get());
+        assert_eq!(ConsumedNonces::<Test>::iter_prefix(0).count(), 5);
+        ZkProofModule::on_initialize(NonceBlockWindow::get() + 1);
+        assert_eq!(ConsumedNonces::<Test>::iter_prefix(0).count(), 0);
+    });
+}
+
+#[test]
+fn nonces_must_embed_a_recent_block_hash() {
+    new_test_ext().execute_with(|| {
+        let jwt_with_nonce = |nonce: &str| {
+            let claims = format!(
+                r#"{{"iss":"https://accounts.google.com","sub":"1","iat":0,"exp":{},"nonce":"{}"}}"#,
+                FAR_FUTURE, nonce
+            );
+            test_jwt(&google_header(), &claims)
+        };
+        let genesis_hash = format!("{:x}", System::block_hash(0));
+
+        assert_ok!(ZkProofModule::validate_jwt(&jwt_with_nonce(&format!("0-0x{}-client-randomness", genesis_hash))));
+        assert_eq!(
+            ZkProofModule::validate_jwt(&jwt_with_nonce(&format!("0-{}", "00".repeat(32)))),
+            Err(Error::<Test>::StaleNonce)
+        );
+        assert_eq!(ZkProofModule::validate_jwt(&jwt_with_nonce("not-a-nonce")), Err(Error::<Test>::InvalidNonce));
+
+        // The current block has no hash yet.
+        assert_eq!(
+            ZkProofModule::validate_jwt(&jwt_with_nonce(&format!("1-{}", genesis_hash))),
+            Err(Error::<Test>::StaleNonce)
+        );
+
+        // Past the window, even the right hash is stale.
+        System::set_block_number(NonceBlockWindow::get());
+        assert_ok!(ZkProofModule::validate_jwt(&jwt_with_nonce(&format!("0-{}", genesis_hash))));
+        System::set_block_number(NonceBlockWindow::get() + 1);
+        assert_eq!(
+            ZkProofModule::validate_jwt(&jwt_with_nonce(&format!("0-{}", genesis_hash))),
+            Err(Error::<Test>::StaleNonce)
+        );
+    });
+}
+
+#[test]
+fn tokens_must_have_been_issued_recently() {
+    new_test_ext().execute_with(|| {
+        Timestamp::set_timestamp(10_000 * 1_000);
+        let jwt_issued_at = |iat: u64| {
+            let claims = format!(
+                r#"{{"iss":"https://accounts.google.com","sub":"1","iat":{},"exp":{},"nonce":"{}"}}"#,
+                iat,
+                FAR_FUTURE,
+                recent_nonce()
+            );
+            test_jwt(&google_header(), &claims)
+        };
+
+        assert_ok!(ZkProofModule::validate_jwt(&jwt_issued_at(10_000 - MaxTokenAge::get())));
+        assert_eq!(
+            ZkProofModule::validate_jwt(&jwt_issued_at(10_000 - MaxTokenAge::get() - 1)),
+            Err(Error::<Test>::StaleToken)
+        );
+        // Tokens from the future are only accepted within the clock skew tolerance.
+        assert_ok!(ZkProofModule::validate_jwt(&jwt_issued_at(10_030)));
+        assert_eq!(ZkProofModule::validate_jwt(&jwt_issued_at(10_031)), Err(Error::<Test>::StaleToken));
+    });
+}
+
+#[test]
+fn proofs_are_bound_to_the_chain() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let jwt = google_jwt(FAR_FUTURE, "");
+
+        let other_chain = format!("{:x}", H256::repeat_byte(1));
+        let json = test_proof_json_for_chain(&other_chain, "", &jwt, &[]).into_bytes();
+        assert_noop!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None), Error::<Test>::WrongChain);
+
+        let json = test_proof_json_for_chain("not-hex", "", &jwt, &[]).into_bytes();
+        assert_noop!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None), Error::<Test>::WrongChain);
+
+        let this_chain = format!("0x{:x}", System::block_hash(0));
+        let json = test_proof_json_for_chain(&this_chain, "", &jwt, &[]).into_bytes();
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None));
+    });
+}
+
+#[test]
+fn logins_are_committed_then_revealed() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let json = test_proof_json(&google_jwt(FAR_FUTURE, ""), &[]).into_bytes();
+        let hash = <Test as frame_system::Config>::Hashing::hash(&json);
+
+        assert_noop!(
+            ZkProofModule::reveal_login(RuntimeOrigin::signed(1), json.clone()),
+            Error::<Test>::UnknownCommitment
+        );
+        assert_ok!(ZkProofModule::commit_login(RuntimeOrigin::signed(1), hash));
+        System::assert_last_event(Event::LoginCommitted { hash, who: 1 }.into());
+        assert_noop!(
+            ZkProofModule::commit_login(RuntimeOrigin::signed(2), hash),
+            Error::<Test>::CommitmentAlreadyExists
+        );
+        assert_noop!(
+            ZkProofModule::reveal_login(RuntimeOrigin::signed(1), json.clone()),
+            Error::<Test>::RevealTooEarly
+        );
+
+        System::set_block_number(2);
+        // Someone copying the revealed proof from the pool cannot claim it.
+        assert_noop!(ZkProofModule::reveal_login(RuntimeOrigin::signed(2), json.clone()), Error::<Test>::NotCommitter);
+        assert_ok!(ZkProofModule::reveal_login(RuntimeOrigin::signed(1), json.clone()));
+        assert_eq!(ZkProofSubmitter::<Test>::get(hash), Some(1));
+        assert!(!LoginCommitments::<Test>::contains_key(hash));
+    });
+}
+
+#[test]
+fn commitments_expire_after_the_reveal_window() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let json = test_proof_json(&google_jwt(FAR_FUTURE, ""), &[]).into_bytes();
+        let hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(ZkProofModule::commit_login(RuntimeOrigin::signed(1), hash));
+
+        System::set_block_number(5);
+        assert_noop!(
+            ZkProofModule::reveal_login(RuntimeOrigin::signed(1), json.clone()),
+            Error::<Test>::CommitmentExpired
+        );
+
+        // An expired commitment may be taken over.
+        assert_ok!(ZkProofModule::commit_login(RuntimeOrigin::signed(2), hash));
+        assert_eq!(LoginCommitments::<Test>::get(hash), Some((2, 5)));
+    });
+}
+
+#[test]
+fn privacy_mode_discards_raw_proofs() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        PrivacyMode::set(true);
+        EmitProofPayloads::set(true);
+        let json = test_proof_json(&google_jwt(FAR_FUTURE, r#","email":"alice@example.com""#), &[]).into_bytes();
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None));
+
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+        assert!(!ZkProofData::<Test>::contains_key(proof_hash));
+        assert_eq!(
+            Attestations::<Test>::get(proof_hash),
+            Some(Attestation {
+                provider: google_issuer(),
+                commitment: Default::default(),
+                nullifier,
+                circuit_version: 0,
+                verified_at: Timestamp::now() / 1_000,
+                expires_at: Timestamp::now() / 1_000 + 3_600,
+            })
+        );
+        assert_eq!(Nullifiers::<Test>::get(nullifier), Some(proof_hash));
+        assert!(ZkProofExpiry::<Test>::contains_key(proof_hash));
+        System::assert_last_event(
+            Event::ZkProofStored {
+                hash: proof_hash,
+                who: 1,
+                provider: google_issuer(),
+                commitment: Default::default(),
+                nullifier,
+                json: None,
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn owners_erase_their_identity_data() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let hashes: Vec<_> = (0..3)
+            .map(|jti| {
+                assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(jti), None));
+                <Test as frame_system::Config>::Hashing::hash(&proof(jti))
+            })
+            .collect();
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+
+        // The identity proof must not be one of the stored proofs.
+        assert_noop!(
+            ZkProofModule::erase_identity_data(RuntimeOrigin::signed(2), proof(0)),
+            Error::<Test>::ProofAlreadyExists
+        );
+
+        // At most `MaxErasedProofs` proofs are erased per call.
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(2), proof(3)));
+        System::assert_last_event(Event::IdentityErased { nullifier, who: 2, erased: 2, complete: false }.into());
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(2), proof(4)));
+        System::assert_last_event(Event::IdentityErased { nullifier, who: 2, erased: 1, complete: true }.into());
+
+        for hash in hashes {
+            assert!(!ZkProofData::<Test>::contains_key(hash));
+            assert!(!Attestations::<Test>::contains_key(hash));
+            assert!(!ZkProofSubmitter::<Test>::contains_key(hash));
+            assert!(!ZkProofExpiry::<Test>::contains_key(hash));
+        }
+        assert_eq!(IdentityProofs::<Test>::iter_prefix(nullifier).count(), 0);
+        assert!(!AccountProofs::<Test>::contains_key(1));
+        assert_eq!(ZkProofModule::attestation_of(&1), None);
+        // The nullifier is kept, so the identity cannot pass for a new one.
+        assert!(Nullifiers::<Test>::contains_key(nullifier));
+    });
+}
+
+#[test]
+fn proofs_carry_application_metadata() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let hash = |jti: u32| <Test as frame_system::Config>::Hashing::hash(&proof(jti));
+        let metadata = BoundedVec::truncate_from(b"user-42".to_vec());
+
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(0), Some(metadata.clone())));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(1), None));
+        assert_eq!(ProofMetadata::<Test>::get(hash(0)), Some(metadata));
+        assert_eq!(ProofMetadata::<Test>::get(hash(1)), None);
+
+        // Metadata is erased along with the proofs.
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(1), proof(2)));
+        assert!(!ProofMetadata::<Test>::contains_key(hash(0)));
+    });
+}
+
+#[test]
+fn proofs_are_listed_by_tag() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let hash = |jti: u32| <Test as frame_system::Config>::Hashing::hash(&proof(jti));
+        let tag = |tag: &str| ProofTag::truncate_from(tag.as_bytes().to_vec());
+        let tags = |names: &[&str]| ProofTags::truncate_from(names.iter().map(|name| tag(name)).collect());
+        for jti in 0..3 {
+            assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(jti), None));
+        }
+
+        assert_noop!(
+            ZkProofModule::tag_proof(RuntimeOrigin::signed(1), hash(9), tags(&["kyc"])),
+            Error::<Test>::UnknownProof
+        );
+        assert_noop!(
+            ZkProofModule::tag_proof(RuntimeOrigin::signed(2), hash(0), tags(&["kyc"])),
+            Error::<Test>::NotSubmitter
+        );
+
+        assert_ok!(ZkProofModule::tag_proof(RuntimeOrigin::signed(1), hash(0), tags(&["login", "kyc"])));
+        System::assert_last_event(Event::ProofTagged { hash: hash(0), tags: tags(&["login", "kyc"]) }.into());
+        assert_ok!(ZkProofModule::tag_proof(RuntimeOrigin::signed(1), hash(1), tags(&["kyc"])));
+        assert_ok!(ZkProofModule::tag_proof(RuntimeOrigin::signed(1), hash(2), tags(&["recovery"])));
+
+        assert_eq!(ZkProofModule::proofs_with_tag(&tag("login"), None, 10), vec![hash(0)]);
+        let mut kyc = ZkProofModule::proofs_with_tag(&tag("kyc"), None, 10);
+        kyc.sort();
+        let mut expected = vec![hash(0), hash(1)];
+        expected.sort();
+        assert_eq!(kyc, expected);
+        // Pages resume after the last proof of the previous one.
+        let first = ZkProofModule::proofs_with_tag(&tag("kyc"), None, 1);
+        let second = ZkProofModule::proofs_with_tag(&tag("kyc"), first.last().copied(), 10);
+        assert_eq!([first, second].concat().len(), 2);
+
+        // Tags are replaced, and erased along with the proofs.
+        assert_ok!(ZkProofModule::tag_proof(RuntimeOrigin::signed(1), hash(0), tags(&["recovery"])));
+        assert_eq!(ZkProofModule::proofs_with_tag(&tag("kyc"), None, 10), vec![hash(1)]);
+        assert!(ZkProofModule::proofs_with_tag(&tag("login"), None, 10).is_empty());
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(1), proof(3)));
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(1), proof(4)));
+        assert!(ZkProofModule::proofs_with_tag(&tag("kyc"), None, 10).is_empty());
+        assert!(ZkProofModule::proofs_with_tag(&tag("recovery"), None, 10).is_empty());
+        assert!(!ProofTagsOf::<Test>::contains_key(hash(0)));
+    });
+}
+
+#[test]
+fn namespaces_scope_identities_per_dapp() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let client_id = ClientId::truncate_from(b"dapp-a".to_vec());
+        let proof = |aud: &str, jti: u32| {
+            let extra = format!(r#","aud":"{}","jti":"{}""#, aud, jti);
+            test_proof_json(&google_jwt(FAR_FUTURE, &extra), &[]).into_bytes()
+        };
+        let hash = |aud: &str, jti: u32| <Test as frame_system::Config>::Hashing::hash(&proof(aud, jti));
+        let namespaced = ZkProofModule::namespace_nullifier(&google_issuer(), &client_id, &Default::default());
+        let global = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+        assert_ne!(namespaced, global);
+
+        assert_noop!(
+            ZkProofModule::register_namespace(RuntimeOrigin::signed(1), client_id.clone(), 1),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id.clone(), 1));
+        System::assert_last_event(Event::NamespaceRegistered { client_id: client_id.clone(), owner: 1 }.into());
+        assert_noop!(
+            ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id.clone(), 2),
+            Error::<Test>::NamespaceAlreadyRegistered
+        );
+
+        // The same user gets distinct identities in the dApp and outside of it.
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof("dapp-a", 0), None));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(3), proof("dapp-b", 0), None));
+        assert_eq!(Attestations::<Test>::get(hash("dapp-a", 0)).map(|a| a.nullifier), Some(namespaced));
+        assert_eq!(Attestations::<Test>::get(hash("dapp-b", 0)).map(|a| a.nullifier), Some(global));
+        assert_eq!(ZkProofModule::namespace_proofs(&client_id, None, 10), vec![hash("dapp-a", 0)]);
+
+        // The owner administers the namespace.
+        assert_noop!(
+            ZkProofModule::set_namespace_paused(RuntimeOrigin::signed(2), client_id.clone(), true),
+            Error::<Test>::NotNamespaceOwner
+        );
+        assert_noop!(
+            ZkProofModule::set_namespace_paused(RuntimeOrigin::signed(1), ClientId::truncate_from(b"x".to_vec()), true),
+            Error::<Test>::UnknownNamespace
+        );
+        assert_ok!(ZkProofModule::set_namespace_paused(RuntimeOrigin::signed(1), client_id.clone(), true));
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof("dapp-a", 1), None),
+            Error::<Test>::NamespacePaused
+        );
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(3), proof("dapp-b", 1), None));
+        assert_ok!(ZkProofModule::set_namespace_owner(RuntimeOrigin::signed(1), client_id.clone(), 2));
+        assert_noop!(
+            ZkProofModule::set_namespace_paused(RuntimeOrigin::signed(1), client_id.clone(), false),
+            Error::<Test>::NotNamespaceOwner
+        );
+        assert_ok!(ZkProofModule::set_namespace_paused(RuntimeOrigin::signed(2), client_id.clone(), false));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof("dapp-a", 1), None));
+
+        // Erasing the identity in the dApp leaves the one outside of it.
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(2), proof("dapp-a", 2)));
+        assert!(ZkProofModule::namespace_proofs(&client_id, None, 10).is_empty());
+        assert!(!ProofNamespaces::<Test>::contains_key(hash("dapp-a", 0)));
+        assert_eq!(IdentityProofs::<Test>::iter_prefix(global).count(), 2);
+    });
+}
+
+#[test]
+fn namespaces_hold_tokens_to_their_claim_policy() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let client_id = ClientId::truncate_from(b"dapp-a".to_vec());
+        let requirement = |path: &str, allowed: Vec<serde_json::Value>| ClaimRequirement {
+            path: BoundedVec::truncate_from(path.as_bytes().to_vec()),
+            allowed: allowed.iter().map(claim_hash).collect::<Vec<_>>().try_into().unwrap(),
+        };
+        let require = |who: u64, client_id: &ClientId, requirements: Vec<ClaimRequirement>| {
+            ZkProofModule::set_namespace_claims(
+                RuntimeOrigin::signed(who),
+                client_id.clone(),
+                requirements.try_into().unwrap(),
+            )
+        };
+        let store = |aud: &str, extra: &str| {
+            let extra = format!(r#","aud":"{}"{}"#, aud, extra);
+            let json = test_proof_json(&google_jwt(FAR_FUTURE, &extra), &[]);
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), json.into_bytes(), None)
+        };
+        let policy = vec![requirement("/nonce", vec![]), requirement("/email_verified", vec![true.into()])];
+
+        assert_noop!(require(1, &client_id, policy.clone()), Error::<Test>::UnknownNamespace);
+        assert_ok!(ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id.clone(), 1));
+        assert_noop!(require(2, &client_id, policy.clone()), Error::<Test>::NotNamespaceOwner);
+        assert_noop!(require(1, &client_id, vec![requirement("nonce", vec![])]), Error::<Test>::InvalidClaimPath);
+        assert_ok!(require(1, &client_id, policy));
+        System::assert_last_event(Event::NamespaceClaimsSet { client_id: client_id.clone() }.into());
+
+        // The error tells which requirement of which policy the token fails.
+        let violation = |index: u8, violation: ClaimViolation| Error::<Test>::ClaimPolicyViolation {
+            policy: ClaimPolicy::Namespace,
+            index,
+            violation,
+        };
+        assert_noop!(store("dapp-a", ""), violation(1, ClaimViolation::Missing));
+        assert_noop!(store("dapp-a", r#","email_verified":false"#), violation(1, ClaimViolation::NotAllowed));
+        assert_ok!(store("dapp-a", r#","email_verified":true"#));
+        // Other dApps are not held to the policy.
+        assert_ok!(store("dapp-b", ""));
+
+        assert_ok!(require(1, &client_id, vec![]));
+        assert_ok!(store("dapp-a", r#","jti":"1""#));
+    });
+}
+
+#[test]
+fn namespaces_restrict_logins_to_their_tenants() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let client_id = ClientId::truncate_from(b"dapp-a".to_vec());
+        let restrict = |who: u64, tenants: &[&str]| {
+            let tenants =
+                tenants.iter().map(|tenant| Tenant::truncate_from(tenant.as_bytes().to_vec())).collect::<Vec<_>>();
+            ZkProofModule::set_namespace_tenants(
+                RuntimeOrigin::signed(who),
+                client_id.clone(),
+                tenants.try_into().unwrap(),
+            )
+        };
+        let store = |aud: &str, extra: &str| {
+            let extra = format!(r#","aud":"{}"{}"#, aud, extra);
+            let json = test_proof_json(&google_jwt(FAR_FUTURE, &extra), &[]);
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), json.into_bytes(), None)
+        };
+
+        assert_noop!(restrict(1, &["corp.com"]), Error::<Test>::UnknownNamespace);
+        assert_ok!(ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id.clone(), 1));
+        assert_noop!(restrict(2, &["corp.com"]), Error::<Test>::NotNamespaceOwner);
+        assert_ok!(restrict(1, &["corp.com", "corp.org"]));
+        System::assert_last_event(Event::NamespaceTenantsSet { client_id: client_id.clone() }.into());
+
+        // Only Workspace accounts of the allowed domains log into the dApp.
+        assert_noop!(store("dapp-a", ""), Error::<Test>::TenantNotAllowed);
+        assert_noop!(store("dapp-a", r#","hd":"other.com""#), Error::<Test>::TenantNotAllowed);
+        assert_ok!(store("dapp-a", r#","hd":"Corp.com""#));
+        assert_ok!(store("dapp-b", ""));
+
+        assert_ok!(restrict(1, &[]));
+        assert_ok!(store("dapp-a", r#","hd":"other.com","jti":"1""#));
+    });
+}
+
+#[test]
+fn namespaces_may_derive_sui_zklogin_addresses() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let client_id = ClientId::truncate_from(b"dapp-a".to_vec());
+        let set_scheme = |who: u64, scheme: AddressScheme| {
+            ZkProofModule::set_namespace_address_scheme(RuntimeOrigin::signed(who), client_id.clone(), scheme)
+        };
+        let address_seed = base64_of(&ark_bls12_381::Fr::from(42u64));
+        let store = |jti: u32| {
+            let extra = format!(r#","aud":"dapp-a","jti":"{}""#, jti);
+            let json = test_proof_json(&google_jwt(FAR_FUTURE, &extra), &[&address_seed]).into_bytes();
+            let hash = <Test as frame_system::Config>::Hashing::hash(&json);
+            assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), json, None));
+            Attestations::<Test>::get(hash).unwrap().nullifier
+        };
+
+        assert_noop!(set_scheme(1, AddressScheme::Sui), Error::<Test>::UnknownNamespace);
+        assert_ok!(ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id.clone(), 1));
+        assert_noop!(set_scheme(2, AddressScheme::Sui), Error::<Test>::NotNamespaceOwner);
+        let native = store(0);
+
+        assert_ok!(set_scheme(1, AddressScheme::Sui));
+        System::assert_last_event(
+            Event::NamespaceAddressSchemeSet { client_id: client_id.clone(), scheme: AddressScheme::Sui }.into(),
+        );
+        // The identity is the address Sui derives from the flag, the issuer and the seed.
+        let mut preimage = vec![0x05, GOOGLE_ISSUER.len() as u8];
+        preimage.extend_from_slice(GOOGLE_ISSUER);
+        preimage.extend_from_slice(&[0; 31]);
+        preimage.push(42);
+        let sui = store(1);
+        assert_eq!(sui, sp_io::hashing::blake2_256(&preimage));
+        assert_ne!(sui, native);
+
+        assert_ok!(set_scheme(1, AddressScheme::Native));
+        assert_eq!(store(2), native);
+    });
+}
+
+#[test]
+fn namespaces_are_held_to_their_quota() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let client_id = ClientId::truncate_from(b"dapp-a".to_vec());
+        let proof = |jti: u32| {
+            let extra = format!(r#","aud":"dapp-a","jti":"{}""#, jti);
+            test_proof_json(&google_jwt(FAR_FUTURE, &extra), &[]).into_bytes()
+        };
+        let store = |jti: u32| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(jti), None);
+        let quota = |logins_per_block, logins_per_period, stored_proofs| NamespaceQuota {
+            logins_per_block,
+            logins_per_period,
+            period: 4,
+            stored_proofs,
+        };
+        assert_ok!(ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id.clone(), 1));
+
+        assert_noop!(
+            ZkProofModule::set_namespace_quota(RuntimeOrigin::signed(1), client_id.clone(), Some(quota(2, 3, 0))),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            ZkProofModule::set_namespace_quota(
+                RuntimeOrigin::root(),
+                client_id.clone(),
+                Some(NamespaceQuota { period: 0, ..quota(2, 3, 0) })
+            ),
+            Error::<Test>::InvalidQuota
+        );
+        assert_ok!(ZkProofModule::set_namespace_quota(RuntimeOrigin::root(), client_id.clone(), Some(quota(2, 3, 0))));
+        System::assert_last_event(
+            Event::NamespaceQuotaSet { client_id: client_id.clone(), quota: Some(quota(2, 3, 0)) }.into(),
+        );
+
+        // Logins are limited per block, and per period.
+        assert_ok!(store(0));
+        assert_ok!(store(1));
+        assert_noop!(store(2), Error::<Test>::QuotaExceeded);
+        // So are the logins of identities dispatching calls.
+        let remark = Box::new(RuntimeCall::System(frame_system::Call::remark { remark: vec![] }));
+        assert_noop!(
+            ZkProofModule::zklogin_execute(RuntimeOrigin::signed(1), ZkLoginAuth::Proof(proof(2)), remark),
+            Error::<Test>::QuotaExceeded
+        );
+        System::set_block_number(2);
+        assert_ok!(store(2));
+        assert_noop!(store(3), Error::<Test>::QuotaExceeded);
+        System::set_block_number(4);
+        assert_ok!(store(3));
+
+        // Stored proofs are limited, erasing proofs making room.
+        assert_ok!(ZkProofModule::set_namespace_quota(RuntimeOrigin::root(), client_id.clone(), Some(quota(0, 0, 4))));
+        assert_noop!(store(4), Error::<Test>::QuotaExceeded);
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(1), proof(5)));
+        assert_eq!(NamespaceUsages::<Test>::get(&client_id).stored_proofs, 2);
+        assert_ok!(store(4));
+
+        // Lifted quotas no longer limit logins.
+        assert_ok!(ZkProofModule::set_namespace_quota(RuntimeOrigin::root(), client_id.clone(), None));
+        for jti in 6..10 {
+            assert_ok!(store(jti));
+        }
+    });
+}
+
+#[test]
+fn disabled_providers_are_rejected_at_once() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let jwks_uri = BoundedVec::truncate_from(b"https://www.googleapis.com/oauth2/v3/certs".to_vec());
+        assert_ok!(ZkProofModule::add_provider(RuntimeOrigin::root(), google_issuer(), jwks_uri, None));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(0), None));
+
+        assert_noop!(
+            ZkProofModule::set_provider_status(RuntimeOrigin::signed(1), google_issuer(), false),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            ZkProofModule::set_provider_status(RuntimeOrigin::root(), IssuerId::truncate_from(b"x".to_vec()), false),
+            Error::<Test>::UnknownProvider
+        );
+        assert_ok!(ZkProofModule::set_provider_status(RuntimeOrigin::root(), google_issuer(), false));
+        System::assert_last_event(Event::ProviderStatusSet { provider: google_issuer(), enabled: false }.into());
+
+        // New proofs are rejected, in the pool and at dispatch, while stored ones still attest.
+        assert_eq!(ZkProofModule::check_structure(&proof(1)), Err(Error::<Test>::ProviderDisabled));
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof(1), None),
+            Error::<Test>::ProviderDisabled
+        );
+        assert!(ZkProofModule::attestation_of(&1).is_some());
+
+        assert_ok!(ZkProofModule::set_provider_status(RuntimeOrigin::root(), google_issuer(), true));
+        System::assert_last_event(Event::ProviderStatusSet { provider: google_issuer(), enabled: true }.into());
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof(1), None));
+    });
+}
+
+#[test]
+fn deprecated_circuits_are_rejected_from_their_sunset() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        CircuitWarningPeriod::set(2);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+
+        assert_noop!(ZkProofModule::deprecate_circuit(RuntimeOrigin::signed(1), 0, Some(4)), DispatchError::BadOrigin);
+        assert_noop!(
+            ZkProofModule::deprecate_circuit(RuntimeOrigin::root(), 5, Some(4)),
+            Error::<Test>::UnknownCircuitVersion
+        );
+        assert_noop!(
+            ZkProofModule::deprecate_circuit(RuntimeOrigin::root(), 0, Some(1)),
+            Error::<Test>::SunsetNotInFuture
+        );
+
+        // Proofs declaring no circuit version are retired at block 4.
+        assert_ok!(ZkProofModule::deprecate_circuit(RuntimeOrigin::root(), 0, Some(4)));
+        System::assert_last_event(Event::CircuitDeprecated { version: 0, sunset_at: 4 }.into());
+        assert_eq!(ZkProofModule::circuit_sunsets(), vec![(0, 4)]);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(0), None));
+
+        // The sunset is announced every period until then.
+        ZkProofModule::on_initialize(2);
+        System::assert_last_event(Event::CircuitSunsetApproaching { version: 0, sunset_at: 4, blocks_left: 2 }.into());
+        System::reset_events();
+        ZkProofModule::on_initialize(3);
+        ZkProofModule::on_initialize(4);
+        assert!(System::events().is_empty());
+
+        System::set_block_number(4);
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof(1), None),
+            Error::<Test>::CircuitSunset
+        );
+        assert!(ZkProofModule::attestation_of(&1).is_some());
+
+        assert_ok!(ZkProofModule::deprecate_circuit(RuntimeOrigin::root(), 0, None));
+        System::assert_last_event(Event::CircuitDeprecationCancelled { version: 0 }.into());
+        assert!(ZkProofModule::circuit_sunsets().is_empty());
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), proof(1), None));
+    });
+}
+
+#[test]
+fn claims_are_normalized_per_provider() {
+    let normalize = |issuer: &str, claims: &str| {
+        let issuer = IssuerId::truncate_from(issuer.as_bytes().to_vec());
+        StandardAdapters::normalize(issuer, &serde_json::from_str(claims).unwrap()).unwrap()
+    };
+
+    let google = normalize(
+        "https://accounts.google.com",
+        r#"{"sub":"42","email":"a@corp.com","email_verified":true,"hd":"corp.com","iat":1,"exp":2,"nonce":"n"}"#,
+    );
+    assert_eq!(
+        (google.subject.as_str(), google.email.as_deref(), google.email_verified, google.tenant.as_deref()),
+        ("42", Some("a@corp.com"), true, Some("corp.com"))
+    );
+    assert_eq!((google.issued_at, google.expires_at, google.nonce.as_deref()), (1, 2, Some("n")));
+
+    let microsoft = normalize(
+        "https://login.microsoftonline.com/tenant-id/v2.0",
+        r#"{"sub":"pairwise","oid":"object-id","tid":"tenant-id","preferred_username":"a@corp.com","iat":1,"exp":2}"#,
+    );
+    assert_eq!(
+        (microsoft.subject.as_str(), microsoft.email.as_deref(), microsoft.email_verified, microsoft.tenant.as_deref()),
+        ("object-id", Some("a@corp.com"), false, Some("tenant-id"))
+    );
+
+    let apple = normalize("https://appleid.apple.com", r#"{"sub":"7","email_verified":"true","iat":1,"exp":2}"#);
+    assert!(apple.email_verified);
+    assert_eq!(apple.nonce, None);
+
+    let generic = normalize("https://idp.example", r#"{"sub":"7","email_verified":"true","iat":1,"exp":2}"#);
+    assert!(!generic.email_verified);
+
+    // The stable user id is required.
+    let issuer = IssuerId::truncate_from(b"https://idp.example".to_vec());
+    let claims = serde_json::from_str(r#"{"iat":1,"exp":2}"#).unwrap();
+    assert_eq!(StandardAdapters::normalize(issuer, &claims), None);
+}
+
+/// A circuit proving the knowledge of two factors of its public input.
+struct Product<F: PrimeField> {
+    x: Option<F>,
+    y: Option<F>,
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for Product<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let product = self.x.zip(self.y).map(|(x, y)| x * y);
+        let product = cs.new_input_variable(|| product.ok_or(SynthesisError::AssignmentMissing))?;
+        let x = cs.new_witness_variable(|| self.x.ok_or(SynthesisError::AssignmentMissing))?;
+        let y = cs.new_witness_variable(|| self.y.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce_constraint(lc!() + x, lc!() + y, lc!() + product)
+    }
+}
+
+/// An envelope carrying a genuine proof of `Product` on the curve `P`, labelled as made on `curve`.
+fn groth16_envelope<P: Bls12Config>(curve: Curve) -> Vec<u8> {
+    let rng = &mut ark_std::test_rng();
+    let (x, y) = (ScalarField::<P>::from(3u64), ScalarField::<P>::from(5u64));
+    let pk = Groth16::<Bls12<P>>::generate_random_parameters_with_reduction(Product { x: None, y: None }, rng).unwrap();
+    let proof =
+        Groth16::<Bls12<P>>::create_random_proof_with_reduction(Product { x: Some(x), y: Some(y) }, &pk, rng).unwrap();
+    let vk = prepare_verifying_key(&pk.vk);
+
+    let envelope = JsonProof {
+        curve,
+        circuit_version: None,
+        a: G1Point { x: base64_of(&proof.a.x).into(), y: base64_of(&proof.a.y).into() },
+        b: G2Point {
+            x: G2Coordinates { c0: base64_of(&proof.b.x.c0).into(), c1: base64_of(&proof.b.x.c1).into() },
+            y: G2Coordinates { c0: base64_of(&proof.b.y.c0).into(), c1: base64_of(&proof.b.y.c1).into() },
+        },
+        c: G1Point { x: base64_of(&proof.c.x).into(), y: base64_of(&proof.c.y).into() },
+        public_hash: base64_of(&(x * y)).into(),
+        verifying_key: base64_of(&vk).into(),
+        jwt_token: test_jwt(
+            &google_header(),
+            r#"{"iss":"https://accounts.google.com","aud":"a","sub":"1","iat":0,"exp":0}"#,
+        )
+        .into(),
+        chain_id: "00".repeat(32).into(),
+        public_inputs: Vec::new(),
+        predicate: None,
+        token: None,
+        encoding: Default::default(),
+    };
+    serde_json::to_vec(&envelope).unwrap()
+}
+
+#[test]
+fn groth16_proofs_verify_on_each_curve() {
+    let bls12_381 = validate_envelope(&groth16_envelope::<ark_bls12_381::Config>(Curve::Bls12_381)).unwrap();
+    assert!(matches!(bls12_381.instance, CurveInstance::Bls12_381(_)));
+    assert!(bls12_381.verify());
+
+    let bls12_377 = validate_envelope(&groth16_envelope::<ark_bls12_377::Config>(Curve::Bls12_377)).unwrap();
+    assert!(matches!(bls12_377.instance, CurveInstance::Bls12_377(_)));
+    assert!(bls12_377.verify());
+    let fifteen = ScalarField::<ark_bls12_377::Config>::from(15u64);
+    assert_eq!(bls12_377.commitment(), commitment_of(&fifteen));
+
+    // A proof is only accepted on the curve it was made on.
+    let mislabelled = validate_envelope(&groth16_envelope::<ark_bls12_377::Config>(Curve::Bls12_381));
+    assert!(!mislabelled.is_ok_and(|parsed| parsed.verify()));
+}
+
+#[test]
+fn envelopes_default_to_bls12_381() {
+    new_test_ext().execute_with(|| {
+        let json = test_proof_json("a.b.c", &[]);
+        assert_eq!(JsonProof::from_bytes(json.as_bytes()).unwrap().curve, Curve::Bls12_381);
+
+        let labelled = test_proof_json("a.b.c", &[]).replacen('{', r#"{"curve":"bls12-377","#, 1);
+        assert_eq!(JsonProof::from_bytes(labelled.as_bytes()).unwrap().curve, Curve::Bls12_377);
+        let unknown = test_proof_json("a.b.c", &[]).replacen('{', r#"{"curve":"bn254","#, 1);
+        assert_eq!(JsonProof::from_bytes(unknown.as_bytes()).err(), Some(EnvelopeError::MalformedJson));
+    });
+}
+
+#[test]
+fn only_approved_verifying_keys_are_accepted() {
+    new_test_ext().execute_with(|| {
+        let vk = "AAAA".repeat(16);
+        let hash = ZkProofModule::verifying_key_hash(&vk);
+        let store = || {
+            let json = test_proof_json_with_vk(&vk, &google_jwt(FAR_FUTURE, ""), &[]);
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None)
+        };
+        assert_noop!(store(), Error::<Test>::UnapprovedVerifyingKey);
+
+        assert_noop!(
+            ZkProofModule::approve_verifying_key(RuntimeOrigin::signed(1), hash, None),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), hash, None));
+        System::assert_last_event(Event::VerifyingKeyApproved { hash }.into());
+        assert_noop!(
+            ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), hash, None),
+            Error::<Test>::VerifyingKeyAlreadyApproved
+        );
+        // Approved, the key goes on to be deserialized.
+        assert_noop!(store(), Error::<Test>::VkDeserializationFailed);
+
+        assert_ok!(ZkProofModule::revoke_verifying_key(RuntimeOrigin::root(), hash));
+        System::assert_last_event(Event::VerifyingKeyRevoked { hash }.into());
+        assert_noop!(store(), Error::<Test>::UnapprovedVerifyingKey);
+        assert_noop!(
+            ZkProofModule::revoke_verifying_key(RuntimeOrigin::root(), hash),
+            Error::<Test>::UnknownVerifyingKey
+        );
+    });
+}
+
+#[test]
+fn public_inputs_follow_the_circuit_layout() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let jwt = google_jwt(FAR_FUTURE, r#","aud":"client""#);
+        let claims = ZkProofModule::validate_jwt(&jwt).unwrap();
+        let address_seed = ark_bls12_381::Fr::from(42u64);
+        let inputs: Vec<String> = public_inputs(CircuitVersion::V1, &claims, address_seed, None, None)
+            .unwrap()
+            .iter()
+            .map(base64_of)
+            .collect();
+        let envelope = |version: u32, inputs: &[String]| {
+            let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+            let json = test_proof_json(&jwt, &inputs);
+            json.replacen('{', &format!(r#"{{"circuit_version":{},"#, version), 1).into_bytes()
+        };
+        let store = |json: Vec<u8>| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None);
+
+        assert_noop!(store(envelope(5, &inputs)), Error::<Test>::UnknownCircuitVersion);
+        let mut swapped = inputs.clone();
+        swapped.swap(1, 2);
+        assert_noop!(store(envelope(1, &swapped)), Error::<Test>::PublicInputMismatch);
+        assert_noop!(store(envelope(1, &inputs[..4])), Error::<Test>::PublicInputMismatch);
+
+        let json = envelope(1, &inputs);
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(store(json));
+        // The address seed is the identity commitment.
+        assert_eq!(Attestations::<Test>::get(proof_hash).unwrap().commitment, commitment_of(&address_seed));
+    });
+}
+
+#[test]
+fn circuits_hash_their_public_inputs_with_their_scheme() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let address_seed = ark_bls12_381::Fr::from(42u64);
+        let envelope = |version: u32, jwt: &str, scheme: CircuitVersion| {
+            let claims = ZkProofModule::validate_jwt(jwt).unwrap();
+            let inputs = public_inputs(scheme, &claims, address_seed, None, None).unwrap();
+            let inputs: Vec<String> = inputs.iter().map(base64_of).collect();
+            let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+            let json = test_proof_json(jwt, &inputs);
+            json.replacen('{', &format!(r#"{{"circuit_version":{},"#, version), 1).into_bytes()
+        };
+        let store = |who: u64, json: Vec<u8>| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(who), json, None);
+        let jwt = |jti: u32| google_jwt(FAR_FUTURE, &format!(r#","aud":"client","jti":"{}""#, jti));
+        assert_eq!(CircuitVersion::V1.hash_scheme(), HashScheme::Sha256);
+        assert_eq!(CircuitVersion::V4.hash_scheme(), HashScheme::Poseidon);
+
+        // Both generations lay out the same inputs, but only agree on those not hashed.
+        let claims = ZkProofModule::validate_jwt(&jwt(0)).unwrap();
+        let sha256 = public_inputs(CircuitVersion::V1, &claims, address_seed, None, None).unwrap();
+        let poseidon = public_inputs(CircuitVersion::V4, &claims, address_seed, None, None).unwrap();
+        assert_eq!((sha256[0], sha256[4]), (poseidon[0], poseidon[4]));
+        assert!((1..4).all(|i| sha256[i] != poseidon[i]));
+        // Lengths are absorbed, so trailing zeros change the hash.
+        let hasher = Poseidon::<ark_bls12_381::Fr>::new();
+        assert_ne!(hasher.hash(b""), hasher.hash(&[0]));
+
+        assert_noop!(store(1, envelope(4, &jwt(0), CircuitVersion::V1)), Error::<Test>::PublicInputMismatch);
+        assert_noop!(store(1, envelope(1, &jwt(0), CircuitVersion::V4)), Error::<Test>::PublicInputMismatch);
+        assert_ok!(store(1, envelope(1, &jwt(0), CircuitVersion::V1)));
+        assert_ok!(store(2, envelope(4, &jwt(1), CircuitVersion::V4)));
+    });
+}
+
+#[test]
+fn predicate_proofs_store_only_the_result() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        MaxJsonLength::set(4096);
+        MaxPublicInputs::set(7);
+        let jwt = google_jwt(FAR_FUTURE, r#","aud":"client","email":"alice@company.com""#);
+        let claims = ZkProofModule::validate_jwt(&jwt).unwrap();
+        let address_seed = ark_bls12_381::Fr::from(42u64);
+        let predicate = ClaimPredicate::EmailDomain(b"company.com".to_vec().try_into().unwrap());
+        let id = predicate.id();
+        let envelope = |result: u64| {
+            let mut inputs = public_inputs(CircuitVersion::V2, &claims, address_seed, Some((id, true)), None).unwrap();
+            *inputs.last_mut().unwrap() = ark_bls12_381::Fr::from(result);
+            let inputs: Vec<String> = inputs.iter().map(base64_of).collect();
+            let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+            let hex_id: String = id.iter().map(|b| format!("{:02x}", b)).collect();
+            let prefix = format!(r#"{{"circuit_version":2,"predicate":"{}","#, hex_id);
+            test_proof_json(&jwt, &inputs).replacen('{', &prefix, 1).into_bytes()
+        };
+        let store = |json: Vec<u8>| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None);
+
+        assert_noop!(store(envelope(1)), Error::<Test>::UnknownPredicate);
+        assert_ok!(ZkProofModule::register_predicate(RuntimeOrigin::root(), predicate.clone()));
+        assert!(Predicates::<Test>::contains_key(id));
+        // The result is a boolean.
+        assert_noop!(store(envelope(2)), Error::<Test>::PublicInputMismatch);
+
+        let json = envelope(1);
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(store(json));
+        let attestation = PredicateAttestation { predicate: id, holds: true };
+        assert_eq!(PredicateAttestations::<Test>::get(proof_hash), Some(attestation));
+        // Only the result is kept, not the token disclosing the claims.
+        assert!(ZkProofData::<Test>::get(proof_hash).is_none());
+        assert_eq!(Attestations::<Test>::get(proof_hash).unwrap().commitment, commitment_of(&address_seed));
+        System::assert_has_event(Event::PredicateAttested { hash: proof_hash, predicate: id, holds: true }.into());
+
+        assert_ok!(ZkProofModule::remove_predicate(RuntimeOrigin::root(), id));
+        assert_noop!(ZkProofModule::remove_predicate(RuntimeOrigin::root(), id), Error::<Test>::UnknownPredicate);
+    });
+}
+
+#[test]
+fn proofs_can_keep_their_token_private() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        MaxPublicInputs::set(8);
+        let token = DisclosedToken {
+            iss: "https://accounts.google.com".into(),
+            kid: GOOGLE_KID.into(),
+            aud: "dapp-a".into(),
+            nonce: recent_nonce().into(),
+            iat: Timestamp::now() / 1_000,
+            exp: FAR_FUTURE,
+        };
+        let (claims, key) = ZkProofModule::validate_private_token(&token).unwrap();
+        // The chain learns nothing of the token beyond what it discloses.
+        assert!(claims.subject.is_empty() && claims.email.is_none());
+        assert_eq!(claims.audience.as_deref(), Some("dapp-a"));
+        let address_seed = ark_bls12_381::Fr::from(42u64);
+        let inputs_of = |token: &DisclosedToken, modulus: &[u8]| -> Vec<String> {
+            let claims = VerifiedClaims {
+                audience: Some(token.aud.clone().into_owned()),
+                issued_at: token.iat,
+                ..claims.clone()
+            };
+            let inputs = public_inputs(CircuitVersion::V3, &claims, address_seed, None, Some(modulus)).unwrap();
+            inputs.iter().map(base64_of).collect()
+        };
+        let inputs = |modulus: &[u8]| inputs_of(&token, modulus);
+        let envelope = |version: u32, token: Option<&DisclosedToken>, inputs: &[String]| {
+            let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+            let mut prefix = format!(r#"{{"circuit_version":{},"#, version);
+            if let Some(token) = token {
+                prefix += &format!(r#""token":{},"#, serde_json::to_string(token).unwrap());
+            }
+            test_proof_json("", &inputs).replacen('{', &prefix, 1).into_bytes()
+        };
+        let store = |json: Vec<u8>| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None);
+
+        // Only circuits keeping the token private go without it.
+        assert_noop!(store(envelope(3, None, &inputs(&key.n))), Error::<Test>::PrivateTokenMismatch);
+        assert_noop!(store(envelope(1, Some(&token), &inputs(&key.n))), Error::<Test>::PrivateTokenMismatch);
+        // The circuit checks the signature against the registered key.
+        assert_noop!(store(envelope(3, Some(&token), &inputs(b"modulus"))), Error::<Test>::PublicInputMismatch);
+        let unknown_key = DisclosedToken { kid: "unknown".into(), ..token.clone() };
+        assert_noop!(store(envelope(3, Some(&unknown_key), &inputs(&key.n))), Error::<Test>::InvalidProof);
+        let expired = DisclosedToken { exp: 0, ..token.clone() };
+        assert_noop!(store(envelope(3, Some(&expired), &inputs(&key.n))), Error::<Test>::TokenExpired);
+        // The disclosed audience and issue time are bound by the circuit, and held to the policies.
+        let other_audience = DisclosedToken { aud: "dapp-b".into(), ..token.clone() };
+        assert_noop!(store(envelope(3, Some(&other_audience), &inputs(&key.n))), Error::<Test>::PublicInputMismatch);
+        let stale = DisclosedToken { iat: 0, ..token.clone() };
+        assert_noop!(store(envelope(3, Some(&stale), &inputs_of(&stale, &key.n))), Error::<Test>::StaleToken);
+
+        // Claims required by the provider would go unchecked.
+        let json = envelope(3, Some(&token), &inputs(&key.n));
+        let require = |requirements: Vec<ClaimRequirement>| {
+            ZkProofModule::set_required_claims(RuntimeOrigin::root(), google_issuer(), requirements.try_into().unwrap())
+        };
+        let roles =
+            ClaimRequirement { path: BoundedVec::truncate_from(b"/roles".to_vec()), allowed: Default::default() };
+        assert_ok!(require(vec![roles]));
+        assert_noop!(store(json.clone()), Error::<Test>::PrivateTokenUnsupported);
+        assert_ok!(require(vec![]));
+
+        // So would the tenants the namespace of the audience admits, while its pause applies.
+        let client_id = ClientId::truncate_from(b"dapp-a".to_vec());
+        assert_ok!(ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id.clone(), 1));
+        let restrict = |tenants: &[&str]| {
+            let tenants =
+                tenants.iter().map(|tenant| Tenant::truncate_from(tenant.as_bytes().to_vec())).collect::<Vec<_>>();
+            ZkProofModule::set_namespace_tenants(
+                RuntimeOrigin::signed(1),
+                client_id.clone(),
+                tenants.try_into().unwrap(),
+            )
+        };
+        let pause =
+            |paused: bool| ZkProofModule::set_namespace_paused(RuntimeOrigin::signed(1), client_id.clone(), paused);
+        assert_ok!(restrict(&["acme"]));
+        assert_noop!(store(json.clone()), Error::<Test>::PrivateTokenUnsupported);
+        assert_ok!(restrict(&[]));
+        assert_ok!(pause(true));
+        assert_noop!(store(json.clone()), Error::<Test>::NamespacePaused);
+        assert_ok!(pause(false));
+
+        assert_eq!(ZkProofModule::check_structure(&json).unwrap().kid, Some(KeyId::truncate_from(GOOGLE_KID.into())));
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(store(json));
+        let attestation = Attestations::<Test>::get(proof_hash).unwrap();
+        assert_eq!(attestation.commitment, commitment_of(&address_seed));
+        // The login is one into the namespace of its audience.
+        let commitment = commitment_of(&address_seed);
+        assert_eq!(
+            attestation.nullifier,
+            ZkProofModule::namespace_nullifier(&google_issuer(), &client_id, &commitment)
+        );
+    });
+}
+
+#[test]
+fn genuine_login_proofs_are_verified_and_stored() {
+    new_test_ext().execute_with(|| {
+        // Envelopes embed the whole prepared verifying key.
+        MaxJsonLength::set(100_000);
+        let prover = Prover::<ark_bls12_381::Config>::setup(Curve::Bls12_381);
+        let jwt = google_jwt(FAR_FUTURE, r#","aud":"client""#);
+        let claims = ZkProofModule::validate_jwt(&jwt).unwrap();
+        let json = prover.prove(&jwt, &claims, 7, &format!("{:x}", System::block_hash(0)));
+        let store = |json: Vec<u8>| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None);
+
+        assert_noop!(store(json.clone()), Error::<Test>::UnapprovedVerifyingKey);
+        let vk_hash = ZkProofModule::verifying_key_hash(&prover.verifying_key());
+        assert_ok!(ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), vk_hash, None));
+
+        // The proof does not hold for another address seed.
+        let mut forged = JsonProof::from_bytes(&json).unwrap();
+        let other_seed = address_seed("1", "client", ark_bls12_381::Fr::from(8u64));
+        forged.public_inputs[0] = base64_of(&other_seed).into();
+        assert_noop!(store(serde_json::to_vec(&forged).unwrap()), Error::<Test>::InvalidProof);
+
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_ok!(store(json));
+        let seed = address_seed("1", "client", ark_bls12_381::Fr::from(7u64));
+        assert_eq!(Attestations::<Test>::get(proof_hash).unwrap().commitment, commitment_of(&seed));
+    });
+}
+
+#[test]
+fn proofs_can_be_checked_without_being_stored() {
+    new_test_ext().execute_with(|| {
+        MaxJsonLength::set(100_000);
+        let prover = Prover::<ark_bls12_381::Config>::setup(Curve::Bls12_381);
+        let jwt = google_jwt(FAR_FUTURE, r#","aud":"client""#);
+        let claims = ZkProofModule::validate_jwt(&jwt).unwrap();
+        let json = prover.prove(&jwt, &claims, 7, &format!("{:x}", System::block_hash(0)));
+
+        assert_eq!(ZkProofModule::check_proof(&json), Err(Error::<Test>::UnapprovedVerifyingKey.into()));
+        let vk_hash = ZkProofModule::verifying_key_hash(&prover.verifying_key());
+        assert_ok!(ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), vk_hash, None));
+
+        let root = sp_io::storage::root(StateVersion::V1);
+        let commitment = commitment_of(&address_seed("1", "client", ark_bls12_381::Fr::from(7u64)));
+        let report = ZkProofModule::check_proof(&json).unwrap();
+        let now = Timestamp::now() / 1_000;
+        assert_eq!(
+            report.attestation,
+            Attestation {
+                provider: google_issuer(),
+                commitment,
+                nullifier: ZkProofModule::nullifier(&google_issuer(), &commitment),
+                circuit_version: 1,
+                verified_at: now,
+                expires_at: now + 3_600,
+            }
+        );
+        assert_eq!(report.expires_at, FAR_FUTURE);
+        assert!(!report.already_stored);
+        assert_eq!(sp_io::storage::root(StateVersion::V1), root);
+
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.clone(), None));
+        assert!(ZkProofModule::check_proof(&json).unwrap().already_stored);
+    });
+}
+
+#[test]
+fn accounts_are_attested_by_their_latest_proof_until_it_expires() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        Timestamp::set_timestamp(1_000 * 1_000);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        assert_eq!(ZkProofModule::attestation_of(&1), None);
+
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(0), None));
+        Timestamp::set_timestamp(2_000 * 1_000);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(1), None));
+        let latest = <Test as frame_system::Config>::Hashing::hash(&proof(1));
+        assert_eq!(AccountProofs::<Test>::get(1), Some(latest));
+        assert_eq!(ZkProofModule::attestation_of(&1), Attestations::<Test>::get(latest));
+        assert_eq!(
+            ZkProofModule::attestation_of(&1).map(|attestation| (attestation.verified_at, attestation.expires_at)),
+            Some((2_000, 5_600))
+        );
+        assert_eq!(ZkProofModule::attestation_of(&2), None);
+
+        Timestamp::set_timestamp(5_600 * 1_000);
+        assert_eq!(ZkProofModule::attestation_of(&1), None);
+    });
+}
+
+/// A proof of the identity committed to by the `input` public input, whose token's nonce binds
+/// `key`.
+fn device_proof(key: u64, input: &str) -> Vec<u8> {
+    let key: String = key.encode().iter().map(|byte| format!("{:02x}", byte)).collect();
+    let claims = format!(
+        r#"{{"iss":"https://accounts.google.com","sub":"1","iat":{},"exp":{},"nonce":"{}-{}"}}"#,
+        Timestamp::now() / 1_000,
+        FAR_FUTURE,
+        recent_nonce(),
+        key
+    );
+    test_proof_json(&test_jwt(&google_header(), &claims), &[input]).into_bytes()
+}
+
+fn register_key(key: u64, input: &str, label: &str) -> frame_support::dispatch::DispatchResult {
+    let label = DeviceLabel::truncate_from(label.as_bytes().to_vec());
+    ZkProofModule::register_key(RuntimeOrigin::signed(key), device_proof(key, input), label)
+}
+
+#[test]
+fn identities_register_several_device_keys() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        Timestamp::set_timestamp(1_000 * 1_000);
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+
+        // The token must be requested for the key being registered.
+        assert_noop!(
+            ZkProofModule::register_key(RuntimeOrigin::signed(1), device_proof(2, "AA"), Default::default()),
+            Error::<Test>::KeyNotBound
+        );
+
+        assert_ok!(register_key(1, "AA", "phone"));
+        assert_ok!(register_key(2, "AA", "laptop"));
+        System::assert_last_event(
+            Event::DeviceKeyRegistered {
+                nullifier,
+                key: 2,
+                label: DeviceLabel::truncate_from(b"laptop".to_vec()),
+                expires_at: 4_600,
+            }
+            .into(),
+        );
+        let mut keys = ZkProofModule::device_keys(&1);
+        keys.sort_by_key(|(key, _)| *key);
+        let device = |label: &[u8]| DeviceKey {
+            label: DeviceLabel::truncate_from(label.to_vec()),
+            registered_at: 1,
+            expires_at: 4_600,
+        };
+        assert_eq!(keys, vec![(1, device(b"phone")), (2, device(b"laptop"))]);
+        assert_eq!(ZkProofModule::attestation_of(&2).map(|attestation| attestation.nullifier), Some(nullifier));
+
+        // At most `MaxDeviceKeys` keys per identity, and a key belongs to a single identity.
+        assert_noop!(register_key(3, "AA", "tablet"), Error::<Test>::TooManyDeviceKeys);
+        assert_noop!(register_key(1, "AQ", "phone"), Error::<Test>::KeyRegisteredElsewhere);
+
+        // Expired keys give their slot up.
+        Timestamp::set_timestamp(4_600 * 1_000);
+        assert_ok!(register_key(3, "AA", "tablet"));
+        assert_eq!(ZkProofModule::device_keys(&3).len(), 1);
+        assert!(!KeyIdentities::<Test>::contains_key(1));
+    });
+}
+
+#[test]
+fn device_keys_revoke_the_keys_of_their_identity() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        Timestamp::set_timestamp(1_000 * 1_000);
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+        assert_ok!(register_key(1, "AA", "phone"));
+        assert_ok!(register_key(2, "AA", "laptop"));
+
+        assert_noop!(ZkProofModule::revoke_key(RuntimeOrigin::signed(3), 1), Error::<Test>::UnknownDeviceKey);
+        assert_ok!(ZkProofModule::revoke_key(RuntimeOrigin::signed(1), 2));
+        System::assert_last_event(Event::DeviceKeyRevoked { nullifier, key: 2, by: 1 }.into());
+        assert_eq!(ZkProofModule::device_keys(&1).len(), 1);
+        // The proofs the key stored no longer attest to the identity.
+        assert_eq!(ZkProofModule::attestation_of(&2), None);
+        assert_noop!(ZkProofModule::revoke_key(RuntimeOrigin::signed(1), 2), Error::<Test>::UnknownDeviceKey);
+
+        assert_ok!(register_key(3, "AA", "tablet"));
+        assert_ok!(ZkProofModule::revoke_all(RuntimeOrigin::signed(3)));
+        System::assert_last_event(Event::AllDeviceKeysRevoked { nullifier, by: 3, revoked: 2 }.into());
+        assert!(ZkProofModule::device_keys(&1).is_empty());
+        assert_eq!(ZkProofModule::attestation_of(&1), None);
+
+        // Expired keys revoke nothing.
+        assert_ok!(register_key(4, "AA", "phone"));
+        Timestamp::set_timestamp(4_600 * 1_000);
+        assert_noop!(ZkProofModule::revoke_all(RuntimeOrigin::signed(4)), Error::<Test>::DeviceKeyExpired);
+    });
+}
+
+/// The rejection of a transaction by an extension, failing with `error`.
+fn invalid(error: Error<Test>) -> TransactionValidityError {
+    match DispatchError::from(error) {
+        DispatchError::Module(error) => InvalidTransaction::Custom(error.error[0]).into(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn device_keys_spend_within_their_limit() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        Timestamp::set_timestamp(1_000 * 1_000);
+        assert_ok!(register_key(1, "AA", "phone"));
+        assert_ok!(register_key(2, "AA", "browser"));
+        let set = |who: u64, key: u64, limit| ZkProofModule::set_spending_limit(RuntimeOrigin::signed(who), key, limit);
+        // Remarks only, spending their length.
+        let limit = SpendingLimit { amount: 10, period: 5, calls: Some(BoundedVec::truncate_from(vec![(0, 0)])) };
+
+        assert_noop!(set(1, 3, Some(limit.clone())), Error::<Test>::UnknownDeviceKey);
+        assert_noop!(
+            set(1, 2, Some(SpendingLimit { period: 0, ..limit.clone() })),
+            Error::<Test>::InvalidSpendingLimit
+        );
+        assert_ok!(set(1, 2, Some(limit.clone())));
+        System::assert_last_event(Event::SpendingLimitSet { key: 2, by: 1, limit: Some(limit) }.into());
+        // A limited key cannot lift its own limit.
+        assert_noop!(set(2, 2, None), Error::<Test>::SpendingLimited);
+
+        let remark = |len: usize| RuntimeCall::System(frame_system::Call::remark { remark: vec![0; len] });
+        let pre_dispatch = |who: u64, call: RuntimeCall| {
+            CheckSpendingLimit::<Test>::new().pre_dispatch(&who, &call, &DispatchInfo::default(), 0)
+        };
+
+        assert_ok!(pre_dispatch(2, remark(6)));
+        assert_eq!(pre_dispatch(2, remark(5)), Err(invalid(Error::<Test>::SpendingLimitExceeded)));
+        assert_ok!(pre_dispatch(2, remark(4)));
+        let revoke_all = RuntimeCall::ZkProofModule(crate::Call::revoke_all {});
+        assert_eq!(
+            CheckSpendingLimit::<Test>::new().validate(&2, &revoke_all, &DispatchInfo::default(), 0),
+            Err(invalid(Error::<Test>::CallNotAllowed))
+        );
+        // Wrapping a call does not escape the allowlist, whether the wrapper is allowed or not.
+        let execute = |call: RuntimeCall| {
+            RuntimeCall::ZkProofModule(crate::Call::zklogin_execute {
+                auth: ZkLoginAuth::Session,
+                call: Box::new(call),
+            })
+        };
+        assert_eq!(pre_dispatch(2, execute(revoke_all.clone())), Err(invalid(Error::<Test>::CallNotAllowed)));
+        let execute_index = execute(remark(0)).using_encoded(|call| (call[0], call[1]));
+        let with_wrapper = SpendingLimit {
+            amount: 10,
+            period: 5,
+            calls: Some(BoundedVec::truncate_from(vec![(0, 0), execute_index])),
+        };
+        assert_ok!(set(1, 2, Some(with_wrapper)));
+        assert_eq!(pre_dispatch(2, execute(revoke_all)), Err(invalid(Error::<Test>::CallNotAllowed)));
+        assert_ok!(pre_dispatch(2, execute(remark(0))));
+        // Keys without a limit are not checked.
+        assert_ok!(pre_dispatch(1, remark(100)));
+
+        // The spending starts over with each period.
+        System::set_block_number(5);
+        assert_ok!(pre_dispatch(2, remark(10)));
+
+        // Revoking a key drops its limit.
+        assert_ok!(ZkProofModule::revoke_key(RuntimeOrigin::signed(1), 2));
+        assert!(!SpendingLimits::<Test>::contains_key(2));
+        assert!(!Spent::<Test>::contains_key(2));
+    });
+}
+
+#[test]
+fn locked_identities_freeze_their_accounts_until_unlocked() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        Timestamp::set_timestamp(1_000 * 1_000);
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        assert_ok!(register_key(1, "AA", "phone"));
+        assert_noop!(
+            ZkProofModule::unlock_identity(RuntimeOrigin::signed(3), proof(0)),
+            Error::<Test>::IdentityNotLocked
+        );
+
+        assert_ok!(ZkProofModule::emergency_lock(RuntimeOrigin::signed(3), proof(1)));
+        System::assert_last_event(Event::IdentityLocked { nullifier, who: 3, revoked: 1 }.into());
+        assert!(ZkProofModule::device_keys(&1).is_empty());
+        assert_eq!(ZkProofModule::attestation_of(&1), None);
+        assert_eq!(ZkProofModule::attestation_of(&3), None);
+
+        // The accounts of the identity may only lock or unlock it.
+        let validate = |who: u64, call: RuntimeCall| {
+            CheckSpendingLimit::<Test>::new().validate(&who, &call, &DispatchInfo::default(), 0)
+        };
+        let remark = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+        assert_eq!(validate(1, remark.clone()), Err(invalid(Error::<Test>::AccountFrozen)));
+        assert_ok!(validate(2, remark.clone()));
+        assert_ok!(validate(1, RuntimeCall::ZkProofModule(crate::Call::unlock_identity { proof: proof(2) })));
+
+        // A fresh proof unlocks the identity after `UnlockDelay`.
+        System::set_block_number(2);
+        assert_ok!(ZkProofModule::unlock_identity(RuntimeOrigin::signed(3), proof(2)));
+        System::assert_last_event(Event::UnlockRequested { nullifier, who: 3, unlocks_at: 12 }.into());
+        assert_noop!(
+            ZkProofModule::unlock_identity(RuntimeOrigin::signed(3), proof(3)),
+            Error::<Test>::UnlockAlreadyRequested
+        );
+        System::set_block_number(11);
+        assert!(ZkProofModule::is_frozen(&1));
+        System::set_block_number(12);
+        assert!(!ZkProofModule::is_frozen(&1));
+        assert_ok!(validate(1, remark));
+        assert!(ZkProofModule::attestation_of(&3).is_some());
+    });
+}
+
+#[test]
+fn blocked_identities_are_turned_away() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let store = |who: u64, jti: u32| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(who), proof(jti), None);
+        let validate = |who: u64| {
+            let remark = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+            CheckSpendingLimit::<Test>::new().validate(&who, &remark, &DispatchInfo::default(), 0)
+        };
+        assert_ok!(store(1, 0));
+
+        for identity in [BlockedIdentity::Nullifier(nullifier), BlockedIdentity::Commitment(Default::default())] {
+            assert_noop!(ZkProofModule::block_identity(RuntimeOrigin::signed(1), identity), DispatchError::BadOrigin);
+            assert_ok!(ZkProofModule::block_identity(RuntimeOrigin::root(), identity));
+            System::assert_last_event(Event::IdentityBlocked { identity }.into());
+            assert_noop!(ZkProofModule::block_identity(RuntimeOrigin::root(), identity), Error::<Test>::AlreadyBlocked);
+
+            // Its proofs are rejected, and the accounts logged in with it turned away.
+            assert_noop!(store(2, 1), Error::<Test>::IdentityBlocked);
+            assert_eq!(ZkProofModule::attestation_of(&1), None);
+            assert_eq!(validate(1), Err(invalid(Error::<Test>::IdentityBlocked)));
+            assert_ok!(validate(2));
+
+            assert_ok!(ZkProofModule::unblock_identity(RuntimeOrigin::root(), identity));
+            System::assert_last_event(Event::IdentityUnblocked { identity }.into());
+            assert_noop!(ZkProofModule::unblock_identity(RuntimeOrigin::root(), identity), Error::<Test>::NotBlocked);
+            assert!(ZkProofModule::attestation_of(&1).is_some());
+            assert_ok!(validate(1));
+        }
+        assert_ok!(store(2, 1));
+    });
+}
+
+#[test]
+fn base64_is_decoded_in_the_variant_of_each_provider() {
+    let url_safe_unpadded = Base64Variant { alphabet: Base64Alphabet::UrlSafe, padding: Base64Padding::Forbidden };
+    let url_safe_padded = Base64Variant { alphabet: Base64Alphabet::UrlSafe, padding: Base64Padding::Required };
+    assert_eq!(encoding::decode(b"AAE=", Base64Variant::STANDARD), Ok(vec![0, 1]));
+    assert_eq!(encoding::decode(b"AAE", Base64Variant::STANDARD), Err(Base64Error::InvalidPadding));
+    assert_eq!(encoding::decode(b"AAE", Base64Variant::URL_SAFE), Ok(vec![0, 1]));
+    assert_eq!(encoding::decode(b"AAE=", url_safe_unpadded), Err(Base64Error::InvalidPadding));
+    assert_eq!(encoding::decode(b"AA-_", Base64Variant::STANDARD), Err(Base64Error::InvalidCharacter));
+    assert_eq!(encoding::decode(b"AA-_", Base64Variant::URL_SAFE), Ok(vec![0, 15, 191]));
+    assert_eq!(encoding::decode(b"AA+/", Base64Variant::ANY), Ok(vec![0, 15, 191]));
+    assert_eq!(encoding::decode(b"AAF=", Base64Variant::STANDARD), Err(Base64Error::NonCanonical));
+    assert_eq!(encoding::decode(b"AAAAA", Base64Variant::ANY), Err(Base64Error::InvalidLength));
+    assert_eq!(encoding::decode_slice(b"AAE=", Base64Variant::STANDARD, &mut [0; 1]), Err(Base64Error::BufferTooSmall));
+
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let store =
+            |who: u64, proof: String| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(who), proof.into(), None);
+        let set = |proof, jwt| {
+            ZkProofModule::set_provider_encoding(
+                RuntimeOrigin::root(),
+                google_issuer(),
+                ProviderEncoding { proof, jwt },
+            )
+        };
+        let encoding = ProviderEncoding { proof: url_safe_unpadded, jwt: Base64Variant::URL_SAFE };
+        assert_noop!(
+            ZkProofModule::set_provider_encoding(RuntimeOrigin::signed(1), google_issuer(), encoding),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            ZkProofModule::set_provider_encoding(
+                RuntimeOrigin::root(),
+                IssuerId::truncate_from(b"https://unknown.example".to_vec()),
+                encoding
+            ),
+            Error::<Test>::UnknownProvider
+        );
+        assert_ok!(set(url_safe_unpadded, Base64Variant::URL_SAFE));
+        System::assert_last_event(Event::ProviderEncodingSet { provider: google_issuer(), encoding }.into());
+
+        // The proof fields must be URL-safe and unpadded, as the provider's tooling writes them.
+        let seed = base64_of(&ark_bls12_381::Fr::from(7u64));
+        assert!(seed.ends_with('='));
+        let jwt = |jti: u32| google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti));
+        assert_noop!(store(1, test_proof_json(&jwt(0), &[&seed])), Error::<Test>::InvalidProof);
+        let url_safe_seed = seed.trim_end_matches('=').replace('+', "-").replace('/', "_");
+        assert_ok!(store(1, test_proof_json(&jwt(0), &[&url_safe_seed])));
+        // Typed submissions are encoded by the runtime, in standard base64 whatever the provider.
+        let mut seed_bytes = Vec::new();
+        ark_bls12_381::Fr::from(7u64).serialize_compressed(&mut seed_bytes).unwrap();
+        let submission = ProofSubmission {
+            curve: Curve::Bls12_381,
+            circuit_version: None,
+            a: G1Bytes { x: vec![], y: vec![] },
+            b: G2Bytes { x: Fq2Bytes { c0: vec![], c1: vec![] }, y: Fq2Bytes { c0: vec![], c1: vec![] } },
+            c: G1Bytes { x: vec![], y: vec![] },
+            verifying_key: vec![],
+            jwt_token: jwt(1),
+            chain_id: System::block_hash(0).0,
+            public_inputs: vec![seed_bytes],
+            predicate: None,
+        };
+        let scale = EnvelopeFormat::ScaleV2.prefix(&submission.encode());
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(2), scale, None));
+
+        // Tokens whose segments are unpadded are rejected once the provider pads them.
+        assert_ok!(set(Base64Variant::STANDARD, url_safe_padded));
+        let unpadded =
+            (2..5).map(jwt).find(|token| token.split('.').take(2).any(|segment| segment.len() % 4 != 0)).unwrap();
+        let pad = |segment: &str| format!("{}{}", segment, "=".repeat((4 - segment.len() % 4) % 4));
+        // The provider signs its tokens as it pads them.
+        let signing_input = unpadded.split('.').take(2).map(pad).collect::<Vec<_>>().join(".");
+        let padded = format!("{}.{}", signing_input, pad(&URL_SAFE_NO_PAD.encode(rs256(&signing_input))));
+        assert_eq!(
+            ZkProofModule::check_structure(test_proof_json(&unpadded, &[]).as_bytes()).err(),
+            Some(Error::<Test>::InvalidEncoding)
+        );
+        assert_noop!(store(3, test_proof_json(&unpadded, &[])), Error::<Test>::InvalidEncoding);
+        assert_ok!(store(3, test_proof_json(&padded, &[])));
+    });
+}
+
+#[test]
+fn identities_dispatch_calls_as_their_zklogin_account() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        Timestamp::set_timestamp(1_000 * 1_000);
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+        let account = ZkProofModule::zklogin_account(&nullifier);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let execute = |who: u64, auth: ZkLoginAuth, call: frame_system::Call<Test>| {
+            ZkProofModule::zklogin_execute(RuntimeOrigin::signed(who), auth, Box::new(RuntimeCall::System(call)))
+        };
+        let remark = || frame_system::Call::remark_with_event { remark: b"hello".to_vec() };
+
+        // With a fresh proof of the identity.
+        assert_ok!(execute(1, ZkLoginAuth::Proof(proof(1)), remark()));
+        let hash = <Test as frame_system::Config>::Hashing::hash(b"hello");
+        System::assert_has_event(frame_system::Event::Remarked { sender: account, hash }.into());
+        System::assert_last_event(Event::ZkLoginExecuted { nullifier, account, who: 1, result: Ok(()) }.into());
+
+        // From a device key of the identity, as the same account.
+        assert_noop!(execute(2, ZkLoginAuth::Session, remark()), Error::<Test>::UnknownDeviceKey);
+        assert_ok!(register_key(2, "AA", "phone"));
+        assert_ok!(execute(2, ZkLoginAuth::Session, remark()));
+        System::assert_last_event(Event::ZkLoginExecuted { nullifier, account, who: 2, result: Ok(()) }.into());
+        // The call's own failure is reported by the event.
+        assert_ok!(execute(2, ZkLoginAuth::Session, frame_system::Call::set_heap_pages { pages: 1 }));
+        System::assert_last_event(
+            Event::ZkLoginExecuted { nullifier, account, who: 2, result: Err(DispatchError::BadOrigin) }.into(),
+        );
+
+        // Only the calls `ZkLoginCalls` allows are dispatched.
+        let revoke_all = Box::new(RuntimeCall::ZkProofModule(crate::Call::revoke_all {}));
+        assert_noop!(
+            ZkProofModule::zklogin_execute(RuntimeOrigin::signed(2), ZkLoginAuth::Session, revoke_all),
+            Error::<Test>::CallFiltered
+        );
+
+        // The account of a locked identity is frozen.
+        assert_ok!(ZkProofModule::emergency_lock(RuntimeOrigin::signed(3), proof(2)));
+        assert_noop!(execute(4, ZkLoginAuth::Proof(proof(3)), remark()), Error::<Test>::AccountFrozen);
+
+        // Calls are dispatched on behalf of users, whatever class they would have on their own.
+        use frame_support::dispatch::{DispatchClass, GetDispatchInfo};
+        let set_heap_pages = Box::new(RuntimeCall::System(frame_system::Call::set_heap_pages { pages: 1 }));
+        assert_eq!(set_heap_pages.get_dispatch_info().class, DispatchClass::Operational);
+        let call = crate::Call::<Test>::zklogin_execute { auth: ZkLoginAuth::Session, call: set_heap_pages };
+        assert_eq!(call.get_dispatch_info().class, DispatchClass::Normal);
+    });
+}
+
+#[test]
+fn gated_calls_are_reserved_to_attested_accounts() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let validate = |who: u64, call: &RuntimeCall| {
+            CheckSpendingLimit::<Test>::new().validate(&who, call, &DispatchInfo::default(), 0)
+        };
+        let remark_with_event = RuntimeCall::System(frame_system::Call::remark_with_event { remark: vec![] });
+        let remark = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+
+        assert_eq!(validate(1, &remark_with_event), Err(invalid(Error::<Test>::AttestationRequired)));
+        assert_ok!(validate(1, &remark));
+
+        assert_ok!(ZkProofModule::store_zk_proof(
+            RuntimeOrigin::signed(1),
+            test_proof_json(&google_jwt(FAR_FUTURE, ""), &[]).into_bytes(),
+            None
+        ));
+        assert_ok!(validate(1, &remark_with_event));
+
+        // Expired attestations do not count.
+        Timestamp::set_timestamp(3_600 * 1_000);
+        assert_eq!(validate(1, &remark_with_event), Err(invalid(Error::<Test>::AttestationExpired)));
+    });
+}
+
+#[test]
+fn attestations_are_renewed_with_a_fresh_proof_of_their_identity() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        RenewalNotice::set(600);
+        let proof = |jti: u32, inputs: &[&str]| {
+            test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), inputs).into_bytes()
+        };
+        let renew = |who: u64, proof: Vec<u8>| ZkProofModule::renew_attestation(RuntimeOrigin::signed(who), proof);
+        let hash = <Test as frame_system::Config>::Hashing::hash(&proof(0, &[]));
+        let nullifier = ZkProofModule::nullifier(&google_issuer(), &Default::default());
+
+        assert_noop!(renew(1, proof(1, &[])), Error::<Test>::AttestationRequired);
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(0, &[]), None));
+        assert!(RenewalReminders::<Test>::contains_key(3_000u64.to_be_bytes(), hash));
+        assert_noop!(renew(1, proof(0, &[])), Error::<Test>::ProofAlreadyExists);
+        assert_noop!(renew(1, proof(1, &["AQ"])), Error::<Test>::IdentityMismatch);
+
+        // The submitter is reminded `RenewalNotice` before the attestation expires.
+        Timestamp::set_timestamp(3_000 * 1_000);
+        System::set_block_number(2);
+        ZkProofModule::on_initialize(2);
+        System::assert_has_event(Event::AttestationExpiring { hash, who: 1, nullifier, expires_at: 3_600 }.into());
+        assert_eq!(RenewalReminders::<Test>::iter().count(), 0);
+
+        Timestamp::set_timestamp(3_600 * 1_000);
+        assert_eq!(ZkProofModule::valid_attestation(&1), Err(Error::<Test>::AttestationExpired));
+        assert!(<ZkProofModule as AttestationInspector<u64>>::attestation_expired(&1));
+
+        // Expired attestations are renewed in place, without storing the renewing proof.
+        assert_ok!(renew(1, proof(1, &[])));
+        System::assert_last_event(Event::AttestationRenewed { hash, who: 1, nullifier, expires_at: 7_200 }.into());
+        let attestation = ZkProofModule::attestation_of(&1).unwrap();
+        assert_eq!((attestation.verified_at, attestation.expires_at), (3_600, 7_200));
+        assert_eq!(ZkProofExpiry::<Test>::get(hash), Some(7_200));
+        assert_eq!(AccountProofs::<Test>::get(1), Some(hash));
+        assert!(!ZkProofSubmitter::<Test>::contains_key(<Test as frame_system::Config>::Hashing::hash(&proof(1, &[]))));
+        assert!(RenewalReminders::<Test>::contains_key(6_600u64.to_be_bytes(), hash));
+        assert_ok!(ZkProofModule::do_try_state());
+
+        // Renewing again replaces the pending reminder.
+        Timestamp::set_timestamp(4_000 * 1_000);
+        assert_ok!(renew(1, proof(2, &[])));
+        assert!(!RenewalReminders::<Test>::contains_key(6_600u64.to_be_bytes(), hash));
+        assert!(RenewalReminders::<Test>::contains_key(7_000u64.to_be_bytes(), hash));
+    });
+}
+
+#[test]
+fn sensitive_calls_require_a_fresh_proof() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let validate = |who: u64, call: &RuntimeCall| {
+            CheckSpendingLimit::<Test>::new().validate(&who, call, &DispatchInfo::default(), 0)
+        };
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let set_heap_pages = RuntimeCall::System(frame_system::Call::set_heap_pages { pages: 1 });
+
+        assert_eq!(validate(1, &set_heap_pages), Err(invalid(Error::<Test>::StepUpRequired)));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(0), None));
+        assert_ok!(validate(1, &set_heap_pages));
+
+        // The attestation is still valid, but the proof is no longer fresh.
+        System::set_block_number(3);
+        assert_ok!(validate(1, &set_heap_pages));
+        System::set_block_number(4);
+        assert_eq!(validate(1, &set_heap_pages), Err(invalid(Error::<Test>::StepUpRequired)));
+        assert!(ZkProofModule::attestation_of(&1).is_some());
+
+        // Logging in again steps the account up.
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(1), None));
+        assert_ok!(validate(1, &set_heap_pages));
+    });
+}
+
+#[test]
+fn verified_origins_hold_a_valid_attestation() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        type Verified = EnsureVerified<Test>;
+        type VerifiedForAnHour = EnsureVerified<Test, ConstU64<3_600>>;
+
+        assert!(Verified::try_origin(RuntimeOrigin::signed(1)).is_err());
+        assert!(!<Verified as Contains<u64>>::contains(&1));
+
+        assert_ok!(ZkProofModule::store_zk_proof(
+            RuntimeOrigin::signed(1),
+            test_proof_json(&google_jwt(FAR_FUTURE, ""), &[]).into_bytes(),
+            None
+        ));
+        assert_eq!(Verified::try_origin(RuntimeOrigin::signed(1)).ok(), Some(1));
+        assert!(<Verified as Contains<u64>>::contains(&1));
+        assert!(Verified::try_origin(RuntimeOrigin::signed(2)).is_err());
+        assert!(Verified::try_origin(RuntimeOrigin::root()).is_err());
+        assert_eq!(VerifiedForAnHour::try_origin(RuntimeOrigin::signed(1)).ok(), Some(1));
+
+        // Attestations expiring too soon, or expired, do not count.
+        Timestamp::set_timestamp(1_000);
+        assert!(VerifiedForAnHour::try_origin(RuntimeOrigin::signed(1)).is_err());
+        assert_eq!(Verified::try_origin(RuntimeOrigin::signed(1)).ok(), Some(1));
+        Timestamp::set_timestamp(3_600 * 1_000);
+        assert!(Verified::try_origin(RuntimeOrigin::signed(1)).is_err());
+    });
+}
+
+#[test]
+fn malformed_proofs_are_rejected_at_pool_admission() {
+    new_test_ext().execute_with(|| {
+        let check = |json: String| ZkProofModule::check_structure(json.as_bytes());
+        let jwt = google_jwt(FAR_FUTURE, "");
+
+        assert_ok!(check(test_proof_json(&jwt, &["AA"])));
+        assert_eq!(check("x".repeat(4096)), Err(Error::<Test>::ZkProofTooLarge));
+        assert_eq!(check("{}".into()), Err(Error::<Test>::InvalidProof));
+        assert_eq!(check(test_proof_json(&jwt, &["AA"; 6])), Err(Error::<Test>::TooManyPublicInputs));
+        let untrusted = test_jwt(&google_header(), r#"{"iss":"https://evil.example"}"#);
+        assert_eq!(check(test_proof_json(&untrusted, &["AA"])), Err(Error::<Test>::UntrustedIssuer));
+        let unknown_kid = test_jwt(r#"{"alg":"RS256","kid":"unknown"}"#, r#"{"iss":"https://accounts.google.com"}"#);
+        assert_eq!(check(test_proof_json(&unknown_kid, &["AA"])), Err(Error::<Test>::InvalidProof));
+
+        // The extension reports the pallet error, and lets other calls through.
+        let validate = |call: crate::Call<Test>| {
+            CheckProofStructure::<Test>::new().validate(&1, &call.into(), &DispatchInfo::default(), 0)
+        };
+        let index = match DispatchError::from(Error::<Test>::UntrustedIssuer) {
+            DispatchError::Module(error) => error.error[0],
+            _ => unreachable!(),
+        };
+        let untrusted = test_proof_json(&untrusted, &["AA"]).into_bytes();
+        assert_eq!(
+            validate(crate::Call::store_zk_proof { json: untrusted.clone(), metadata: None }),
+            Err(InvalidTransaction::Custom(index).into())
+        );
+        assert!(validate(crate::Call::reveal_login { json: untrusted.clone() }).is_err());
+        assert!(validate(crate::Call::erase_identity_data { proof: untrusted }).is_err());
+        assert_ok!(validate(crate::Call::store_zk_proof {
+            json: test_proof_json(&jwt, &["AA"]).into_bytes(),
+            metadata: None
+        }));
+        assert_ok!(validate(crate::Call::commit_login { proof_hash: H256::zero() }));
+    });
+}
+
+#[test]
+fn rejected_submissions_are_recorded() {
+    new_test_ext().execute_with(|| {
+        // Submit `call` as a signed transaction would, through the extension.
+        let submit = |call: crate::Call<Test>| {
+            let call: RuntimeCall = call.into();
+            let info = DispatchInfo::default();
+            let pre = CheckProofStructure::<Test>::new().pre_dispatch(&1, &call, &info, 0).unwrap();
+            let result = call.clone().dispatch(RuntimeOrigin::signed(1));
+            let result = result.map(|_| ()).map_err(|e| e.error);
+            CheckProofStructure::<Test>::post_dispatch(Some(pre), &info, &Default::default(), 0, &result).unwrap();
+        };
+        let jwt = google_jwt(FAR_FUTURE, "");
+
+        submit(crate::Call::store_zk_proof { json: test_proof_json(&jwt, &[]).into_bytes(), metadata: None });
+        submit(crate::Call::reveal_login { json: test_proof_json(&jwt, &[]).into_bytes() });
+        submit(crate::Call::erase_identity_data { proof: test_proof_json(&jwt, &[]).into_bytes() });
+        assert_eq!(FailureStats::<Test>::get(Error::<Test>::UnapprovedVerifyingKey), 2);
+        assert_eq!(FailureStats::<Test>::get(Error::<Test>::UnknownCommitment), 1);
+        // Only the latest `MaxRecentFailures` are kept.
+        let context = SubmissionContext {
+            kid: Some(KeyId::truncate_from(GOOGLE_KID.into())),
+            circuit_version: None,
+            provider: Some(google_issuer()),
+        };
+        let record = |error: Error<Test>| FailureRecord {
+            block: System::block_number(),
+            submitter: 1,
+            error: error.into(),
+            context: context.clone(),
+        };
+        assert_eq!(
+            ZkProofModule::recent_failures(),
+            vec![record(Error::<Test>::UnknownCommitment), record(Error::<Test>::UnapprovedVerifyingKey)]
+        );
+
+        // Other calls are not counted, successful or not.
+        submit(crate::Call::commit_login { proof_hash: H256::zero() });
+        submit(crate::Call::revoke_verifying_key { hash: [0; 32] });
+        assert_eq!(FailureStats::<Test>::iter().count(), 2);
+        assert_eq!(RecentFailures::<Test>::get().len(), 2);
+        assert_eq!(ProviderUsage::<Test>::get(google_issuer()).failures, 3);
+    });
+}
+
+#[test]
+fn repeated_failures_lock_submitters_and_identities_out() {
+    new_test_ext().execute_with(|| {
+        Timestamp::set_timestamp(100_000);
+        // Submit `call` of `who` as a signed transaction would, through the extension.
+        let submit = |who: u64, call: crate::Call<Test>| {
+            let call: RuntimeCall = call.into();
+            let info = DispatchInfo::default();
+            let pre = CheckProofStructure::<Test>::new().pre_dispatch(&who, &call, &info, 0)?;
+            let result = call.clone().dispatch(RuntimeOrigin::signed(who));
+            let result = result.map(|_| ()).map_err(|e| e.error);
+            CheckProofStructure::<Test>::post_dispatch(Some(pre), &info, &Default::default(), 0, &result)?;
+            Ok::<_, TransactionValidityError>(result)
+        };
+        let token_proof = |seed: u64, jti: u32, exp: u64| {
+            let jwt = google_jwt(exp, &format!(r#","jti":"{}""#, jti));
+            let json = test_proof_json(&jwt, &[&base64_of(&ark_bls12_381::Fr::from(seed))]);
+            crate::Call::store_zk_proof { json: json.into_bytes(), metadata: None }
+        };
+        let proof = |seed: u64, jti: u32| token_proof(seed, jti, FAR_FUTURE);
+        let identity =
+            |seed: u64| ZkProofModule::nullifier(&google_issuer(), &commitment_of(&ark_bls12_381::Fr::from(seed)));
+        let locked_out = Err(invalid(Error::<Test>::TooManyFailedAttempts));
+
+        for jti in 0..3 {
+            assert!(submit(1, proof(1, jti)).unwrap().is_err());
+        }
+        assert!(submit(3, proof(2, 3)).unwrap().is_err());
+        assert_eq!(SubmitterFailures::<Test>::get(1), FailedAttempts { count: 3, locked_until: 11 });
+        assert_eq!(SubmitterFailures::<Test>::get(3), FailedAttempts { count: 1, locked_until: 0 });
+        assert_eq!(submit(1, proof(2, 4)), locked_out);
+        // Proofs failing before the pairing are not charged to the identity they claim.
+        assert!(!IdentityFailures::<Test>::contains_key(identity(1)));
+        assert!(submit(2, proof(1, 5)).unwrap().is_err());
+
+        // Proofs failing past the pairing are.
+        InsecureSkipVerification::set(true);
+        for jti in 6..9 {
+            assert_eq!(submit(4, token_proof(1, jti, 1)), Ok(Err(Error::<Test>::TokenExpired.into())));
+        }
+        assert_eq!(IdentityFailures::<Test>::get(identity(1)), FailedAttempts { count: 3, locked_until: 11 });
+        assert_eq!(submit(5, proof(1, 9)), locked_out);
+        assert_eq!(
+            CheckProofStructure::<Test>::new().validate(&5, &proof(1, 9).into(), &DispatchInfo::default(), 0),
+            Err(invalid(Error::<Test>::TooManyFailedAttempts))
+        );
+
+        // A successful submission clears the failures of its submitter and identity.
+        assert_eq!(submit(3, proof(2, 10)), Ok(Ok(())));
+        assert!(!SubmitterFailures::<Test>::contains_key(3));
+        assert_eq!(IdentityFailures::<Test>::iter().count(), 1);
+
+        // Calls refused for the state of the chain do not count.
+        assert_eq!(submit(6, proof(2, 10)), Ok(Err(Error::<Test>::ProofAlreadyExists.into())));
+        assert!(!SubmitterFailures::<Test>::contains_key(6));
+        assert_eq!(FailureStats::<Test>::get(Error::<Test>::ProofAlreadyExists), 1);
+
+        // Once the lockout is over, failing again locks out for twice as long.
+        InsecureSkipVerification::set(false);
+        System::set_block_number(11);
+        for jti in 11..14 {
+            assert!(submit(1, proof(1, jti)).unwrap().is_err());
+        }
+        assert_eq!(SubmitterFailures::<Test>::get(1), FailedAttempts { count: 6, locked_until: 31 });
+        assert_eq!(submit(1, proof(2, 14)), locked_out);
+    });
+}
+
+#[test]
+fn verifications_per_block_are_capped() {
+    new_test_ext().execute_with(|| {
+        MaxVerificationsPerBlock::set(2);
+        let pre_dispatch = |call: crate::Call<Test>| {
+            let call: RuntimeCall = call.into();
+            CheckProofStructure::<Test>::new().pre_dispatch(&1, &call, &DispatchInfo::default(), 0).map(|_| ())
+        };
+        let proof = || crate::Call::store_zk_proof {
+            json: test_proof_json(&google_jwt(FAR_FUTURE, ""), &[]).into_bytes(),
+            metadata: None,
+        };
+
+        assert_ok!(pre_dispatch(proof()));
+        assert_ok!(pre_dispatch(proof()));
+        assert_eq!(pre_dispatch(proof()), Err(InvalidTransaction::ExhaustsResources.into()));
+        // Only proof submissions are capped.
+        assert_ok!(pre_dispatch(crate::Call::commit_login { proof_hash: H256::zero() }));
+
+        // The next block includes verifications again.
+        System::set_block_number(2);
+        ZkProofModule::on_initialize(2);
+        assert_ok!(pre_dispatch(proof()));
+        assert_eq!(VerificationsInBlock::<Test>::get(), 1);
+    });
+}
+
+#[test]
+fn renewals_are_admitted_as_proof_submissions() {
+    new_test_ext().execute_with(|| {
+        MaxVerificationsPerBlock::set(1);
+        let renewal = |jwt: &str| -> RuntimeCall {
+            crate::Call::renew_attestation { proof: test_proof_json(jwt, &[]).into_bytes() }.into()
+        };
+        let info = DispatchInfo::default();
+
+        // Malformed renewals do not enter the pool.
+        let untrusted = test_jwt(&google_header(), r#"{"iss":"https://evil.example"}"#);
+        assert_eq!(
+            CheckProofStructure::<Test>::new().validate(&1, &renewal(&untrusted), &info, 0),
+            Err(invalid(Error::<Test>::UntrustedIssuer))
+        );
+
+        // Renewals take up the verifications of the block, and their failures are recorded.
+        let call = renewal(&google_jwt(FAR_FUTURE, ""));
+        let pre = CheckProofStructure::<Test>::new().pre_dispatch(&1, &call, &info, 0).unwrap();
+        assert_eq!(
+            CheckProofStructure::<Test>::new().pre_dispatch(&1, &call, &info, 0).map(|_| ()),
+            Err(InvalidTransaction::ExhaustsResources.into())
+        );
+        let result = call.dispatch(RuntimeOrigin::signed(1)).map(|_| ()).map_err(|e| e.error);
+        assert_eq!(result, Err(Error::<Test>::AttestationRequired.into()));
+        CheckProofStructure::<Test>::post_dispatch(Some(pre), &info, &Default::default(), 0, &result).unwrap();
+        assert_eq!(FailureStats::<Test>::get(Error::<Test>::AttestationRequired), 1);
+    });
+}
+
+#[test]
+fn logins_are_prioritized_by_freshness_and_deduplicated() {
+    new_test_ext().execute_with(|| {
+        Timestamp::set_timestamp(10_000 * 1_000);
+        let proof = |iat: u64, seed: u64| {
+            let claims = format!(
+                r#"{{"iss":"https://accounts.google.com","sub":"1","iat":{},"exp":{},"nonce":"{}"}}"#,
+                iat,
+                FAR_FUTURE,
+                recent_nonce()
+            );
+            let jwt = test_jwt(&google_header(), &claims);
+            test_proof_json(&jwt, &[&base64_of(&ark_bls12_381::Fr::from(seed))]).into_bytes()
+        };
+        let validate = |call: crate::Call<Test>| {
+            CheckProofStructure::<Test>::new().validate(&1, &call.into(), &DispatchInfo::default(), 0).unwrap()
+        };
+
+        let fresh = validate(crate::Call::store_zk_proof { json: proof(10_000, 1), metadata: None });
+        let older = validate(crate::Call::store_zk_proof { json: proof(9_900, 1), metadata: None });
+        assert_eq!(fresh.priority, MaxTokenAge::get());
+        assert_eq!(older.priority, MaxTokenAge::get() - 100);
+
+        // Competing logins of the same identity provide the same tag.
+        assert_eq!(fresh.provides.len(), 1);
+        assert_eq!(fresh.provides, older.provides);
+        assert_eq!(validate(crate::Call::reveal_login { json: proof(10_000, 1) }).provides, fresh.provides);
+        assert_ne!(
+            validate(crate::Call::store_zk_proof { json: proof(10_000, 2), metadata: None }).provides,
+            fresh.provides
+        );
+        // Erasing an identity does not compete with its logins.
+        assert!(validate(crate::Call::erase_identity_data { proof: proof(10_000, 1) }).provides.is_empty());
+    });
+}
+
+#[test]
+fn logins_into_different_namespaces_do_not_compete() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        for dapp in ["dapp-a", "dapp-b"] {
+            let client_id = ClientId::truncate_from(dapp.as_bytes().to_vec());
+            assert_ok!(ZkProofModule::register_namespace(RuntimeOrigin::root(), client_id, 1));
+        }
+        let proof = |dapp: &str| {
+            let jwt = google_jwt(FAR_FUTURE, &format!(r#","aud":"{}""#, dapp));
+            test_proof_json(&jwt, &[&base64_of(&ark_bls12_381::Fr::from(1u64))]).into_bytes()
+        };
+        let call = |json: Vec<u8>| RuntimeCall::from(crate::Call::store_zk_proof { json, metadata: None });
+        let validate = |json: Vec<u8>| {
+            CheckProofStructure::<Test>::new().validate(&1, &call(json), &DispatchInfo::default(), 0).unwrap()
+        };
+
+        // The logins of one user into two dApps both stay in the pool, each tagged with the
+        // identity it is stored under.
+        let a = validate(proof("dapp-a"));
+        let b = validate(proof("dapp-b"));
+        assert_ne!(a.provides, b.provides);
+        for (dapp, valid) in [("dapp-a", a), ("dapp-b", b)] {
+            let json = proof(dapp);
+            let hash = <Test as frame_system::Config>::Hashing::hash(&json);
+            assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None));
+            let nullifier = Attestations::<Test>::get(hash).unwrap().nullifier;
+            assert_eq!(valid.provides, vec![("DotLogin", nullifier).encode()]);
+        }
+    });
+}
+
+#[test]
+fn provider_usage_is_tracked() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof = |seed: u64, jti: u32| {
+            let jwt = google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti));
+            test_proof_json(&jwt, &[&base64_of(&ark_bls12_381::Fr::from(seed))]).into_bytes()
+        };
+        let store = |json: Vec<u8>| ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None);
+        assert_eq!(ZkProofModule::provider_stats(), vec![]);
+
+        System::set_block_number(2);
+        assert_ok!(store(proof(1, 1)));
+        assert_ok!(store(proof(2, 2)));
+        System::set_block_number(3);
+        // Another login of the first identity.
+        assert_ok!(store(proof(1, 3)));
+
+        let stats = ProviderStats { logins: 3, failures: 0, accounts: 2, last_active: 3 };
+        assert_eq!(ZkProofModule::provider_stats(), vec![(google_issuer(), stats)]);
+    });
+}
+
+#[test]
+fn typed_submissions_are_stored_as_envelopes() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let address_seed = ark_bls12_381::Fr::from(42u64);
+        let mut seed = Vec::new();
+        address_seed.serialize_compressed(&mut seed).unwrap();
+        let point = G1Bytes { x: vec![], y: vec![] };
+        let coordinates = Fq2Bytes { c0: vec![], c1: vec![] };
+        let submission = ProofSubmission {
+            curve: Curve::Bls12_381,
+            circuit_version: None,
+            a: point.clone(),
+            b: G2Bytes { x: coordinates.clone(), y: coordinates },
+            c: point,
+            verifying_key: vec![],
+            jwt_token: google_jwt(FAR_FUTURE, ""),
+            chain_id: System::block_hash(0).0,
+            public_inputs: vec![seed],
+            predicate: None,
+        };
+        let json = submission.to_envelope();
+        assert_eq!(JsonProof::from_bytes(&json).unwrap().public_inputs, vec![base64_of(&address_seed)]);
+
+        // The extension checks the equivalent envelope.
+        let call = crate::Call::submit_proof { submission: submission.clone() }.into();
+        assert_ok!(CheckProofStructure::<Test>::new().validate(&1, &call, &DispatchInfo::default(), 0));
+
+        assert_ok!(ZkProofModule::submit_proof(RuntimeOrigin::signed(1), submission));
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+        assert_eq!(ZkProofData::<Test>::get(proof_hash).unwrap().into_inner(), json);
+        assert_eq!(Attestations::<Test>::get(proof_hash).unwrap().commitment, commitment_of(&address_seed));
+    });
+}
+
+#[test]
+fn envelopes_decode_from_every_format() {
+    new_test_ext().execute_with(|| {
+        let json = test_proof_json(&google_jwt(FAR_FUTURE, ""), &["AA"]);
+        let json = json.as_bytes();
+        let fields = |json: &[u8]| serde_json::from_slice::<serde_json::Value>(json).unwrap();
+
+        assert_eq!(&*decode_envelope(json).unwrap(), json);
+        assert_eq!(&*decode_envelope(&EnvelopeFormat::JsonV1.prefix(json)).unwrap(), json);
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&fields(json), &mut cbor).unwrap();
+        let decoded = decode_envelope(&EnvelopeFormat::Cbor.prefix(&cbor)).unwrap();
+        assert_eq!(fields(&decoded), fields(json));
+
+        let coordinates = Fq2Bytes { c0: vec![1], c1: vec![2] };
+        let submission = ProofSubmission {
+            curve: Curve::Bls12_377,
+            circuit_version: Some(1),
+            a: G1Bytes { x: vec![3], y: vec![4] },
+            b: G2Bytes { x: coordinates.clone(), y: coordinates },
+            c: G1Bytes { x: vec![5], y: vec![6] },
+            verifying_key: vec![7],
+            jwt_token: google_jwt(FAR_FUTURE, ""),
+            chain_id: [8; 32],
+            public_inputs: vec![vec![9]],
+            predicate: Some([10; 32]),
+        };
+        let scale = EnvelopeFormat::ScaleV2.prefix(&submission.encode());
+        assert_eq!(decode_envelope(&scale).unwrap().into_owned(), submission.to_envelope());
+
+        assert_eq!(decode_envelope(&[0x07]), Err(EnvelopeError::UnknownFormat));
+        assert_eq!(decode_envelope(&[0x02, 0xff]), Err(EnvelopeError::MalformedEncoding));
+        assert_eq!(decode_envelope(&[0x03]), Err(EnvelopeError::MalformedEncoding));
+        assert_eq!(decode_envelope(&[0x04]), Err(EnvelopeError::MalformedJson));
+        assert_noop!(
+            ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), [&[0x07][..], json].concat(), None),
+            Error::<Test>::UnknownEnvelopeFormat
+        );
+    });
+}
+
+#[test]
+fn genuine_snarkjs_proofs_are_verified_and_stored() {
+    new_test_ext().execute_with(|| {
+        MaxJsonLength::set(100_000);
+        let prover = Prover::<ark_bls12_381::Config>::setup(Curve::Bls12_381);
+        let vk_hash = ZkProofModule::verifying_key_hash(&prover.verifying_key());
+        assert_ok!(ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), vk_hash, None));
+        let jwt = google_jwt(FAR_FUTURE, r#","aud":"client""#);
+        let claims = ZkProofModule::validate_jwt(&jwt).unwrap();
+        let json = prover.prove(&jwt, &claims, 7, &format!("{:x}", System::block_hash(0)));
+        let snarkjs = snarkjs_envelope(&json);
+
+        // Proofs on other curves are not supported.
+        let bn128 = String::from_utf8(snarkjs.clone()).unwrap().replace("bls12381", "bn128");
+        assert_eq!(validate_envelope(bn128.as_bytes()).err(), Some(EnvelopeError::MalformedJson));
+
+        // The proof is stored as submitted.
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), snarkjs.clone(), None));
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&snarkjs);
+        assert_eq!(ZkProofData::<Test>::get(proof_hash).unwrap().into_inner(), snarkjs);
+        let seed = address_seed("1", "client", ark_bls12_381::Fr::from(7u64));
+        assert_eq!(Attestations::<Test>::get(proof_hash).unwrap().commitment, commitment_of(&seed));
+    });
+}
+
+#[test]
+fn host_functions_decode_like_the_runtime() {
+    new_test_ext().execute_with(|| {
+        assert_eq!(dot_login::base64_decode(b"AAE="), Some(vec![0, 1]));
+        assert_eq!(dot_login::base64_decode(b"AA-_"), None);
+        assert_eq!(dot_login::base64url_decode(b"AA-_"), Some(vec![0, 15, 191]));
+        assert_eq!(dot_login::base64url_decode(b"AAE"), dot_login::base64url_decode(b"AAE="));
+
+        // Envelopes parsed natively are handed to the runtime SCALE-encoded.
+        let json = test_proof_json(&google_jwt(FAR_FUTURE, ""), &["AA"]);
+        let encoded = dot_login::parse_envelope(json.as_bytes()).unwrap();
+        let envelope = JsonProof::decode(&mut &encoded[..]).unwrap();
+        let expected: JsonProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&envelope).unwrap(), serde_json::to_string(&expected).unwrap());
+        assert_eq!(dot_login::parse_envelope(b"{}"), None);
+    });
+}
+
+#[test]
+fn natively_verified_proofs_are_remembered() {
+    new_test_ext().execute_with(|| {
+        let parsed = validate_envelope(&groth16_envelope::<ark_bls12_381::Config>(Curve::Bls12_381)).unwrap();
+        let (verifying_key, proof, public_inputs) = parsed.instance.to_compressed().unwrap();
+        let curve = parsed.instance.curve() as u8;
+        let key = host::cache::key(curve, &verifying_key, &proof, &public_inputs);
+
+        // Nothing is remembered until the node enables the cache.
+        assert!(dot_login::verify_groth16(curve, &verifying_key, &proof, &public_inputs));
+        assert!(!host::cache::contains(&key));
+
+        host::cache::enable(2);
+        assert!(dot_login::verify_groth16(curve, &verifying_key, &proof, &public_inputs));
+        assert!(host::cache::contains(&key));
+        // Invalid proofs are not remembered.
+        let other_curve = Curve::Bls12_377 as u8;
+        assert!(!dot_login::verify_groth16(other_curve, &verifying_key, &proof, &public_inputs));
+        assert!(!host::cache::contains(&host::cache::key(other_curve, &verifying_key, &proof, &public_inputs)));
+
+        // The oldest proofs are forgotten first.
+        host::cache::insert([1; 32]);
+        host::cache::insert([2; 32]);
+        assert!(!host::cache::contains(&key));
+        assert_eq!(host::cache::len(), 2);
+        host::cache::enable(0);
+        assert_eq!(host::cache::len(), 0);
+    });
+}
+
+#[test]
+fn activations_can_be_scheduled() {
+    new_test_ext().execute_with(|| {
+        let hash = [7; 32];
+        let issuer: IssuerId = b"https://issuer.example".to_vec().try_into().unwrap();
+        let info = ProviderInfo { jwks_uri: b"https://issuer.example/jwks".to_vec().try_into().unwrap() };
+
+        assert_noop!(
+            ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), hash, Some(1)),
+            Error::<Test>::ActivationNotInFuture
+        );
+        assert_ok!(ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), hash, Some(3)));
+        System::assert_last_event(
+            Event::ActivationScheduled { activation: Activation::VerifyingKey(hash), at: 3 }.into(),
+        );
+        assert_ok!(ZkProofModule::add_provider(RuntimeOrigin::root(), issuer.clone(), info.jwks_uri.clone(), Some(3)));
+        assert_noop!(
+            ZkProofModule::approve_verifying_key(RuntimeOrigin::root(), [8; 32], Some(3)),
+            Error::<Test>::TooManyScheduledActivations
+        );
+
+        System::set_block_number(2);
+        ZkProofModule::on_initialize(2);
+        assert!(!ApprovedVerifyingKeys::<Test>::contains_key(hash));
+        assert!(!Providers::<Test>::contains_key(&issuer));
+
+        System::set_block_number(3);
+        ZkProofModule::on_initialize(3);
+        assert!(ApprovedVerifyingKeys::<Test>::contains_key(hash));
+        assert_eq!(Providers::<Test>::get(&issuer), Some(info));
+        System::assert_has_event(Event::VerifyingKeyApproved { hash }.into());
+        System::assert_last_event(Event::ProviderAdded { provider: issuer }.into());
+        assert!(ScheduledActivations::<Test>::get(3).is_empty());
+    });
+}
+
+#[test]
+fn proofs_stored_before_identity_indexing_are_migrated() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<ZkProofModule>();
+        for i in 0..3u8 {
+            let attestation = AttestationV1 { provider: google_issuer(), commitment: [i; 32], nullifier: [i; 32] };
+            v2::Attestations::<Test>::insert(H256::repeat_byte(i), attestation);
+        }
+        assert_ok!(ZkProofModule::do_try_state());
+
+        let mut meter = WeightMeter::new();
+        assert_eq!(IndexIdentityProofs::<Test>::step(None, &mut meter), Ok(None));
+        for i in 0..3u8 {
+            assert!(IdentityProofs::<Test>::contains_key([i; 32], H256::repeat_byte(i)));
+        }
+        assert_ok!(ZkProofModule::do_try_state());
+        assert_eq!(ZkProofModule::on_chain_storage_version(), 1);
+        System::assert_last_event(Event::MigrationProgressed { migrated: 3, complete: true }.into());
+
+        // Already migrated chains are left untouched.
+        IdentityProofs::<Test>::remove([0; 32], H256::repeat_byte(0));
+        assert_eq!(IndexIdentityProofs::<Test>::step(None, &mut meter), Ok(None));
+        assert!(!IdentityProofs::<Test>::contains_key([0; 32], H256::repeat_byte(0)));
+        assert!(ZkProofModule::do_try_state().is_err());
+    });
+}
+
+#[test]
+fn attestations_stored_before_version_2_are_extended() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(1).put::<ZkProofModule>();
+        for i in 0..3u8 {
+            let attestation = AttestationV1 { provider: google_issuer(), commitment: [i; 32], nullifier: [i; 32] };
+            v2::Attestations::<Test>::insert(H256::repeat_byte(i), attestation);
+            ZkProofExpiry::<Test>::insert(H256::repeat_byte(i), 1_000 * i as u64);
+        }
+
+        let mut meter = WeightMeter::new();
+        assert_eq!(ExtendAttestations::<Test>::step(None, &mut meter), Ok(None));
+        for i in 0..3u8 {
+            let attestation = Attestations::<Test>::get(H256::repeat_byte(i)).unwrap();
+            assert_eq!((attestation.nullifier, attestation.expires_at), ([i; 32], 1_000 * i as u64));
+            assert_eq!((attestation.circuit_version, attestation.verified_at), (0, 0));
+        }
+        assert_eq!(ZkProofModule::on_chain_storage_version(), 2);
+        System::assert_last_event(Event::MigrationProgressed { migrated: 3, complete: true }.into());
+    });
+}
+
+#[test]
+fn storage_usage_follows_stored_entries() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let proof =
+            |jti: u32| test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]).into_bytes();
+        let hash = |jti: u32| <Test as frame_system::Config>::Hashing::hash(&proof(jti));
+        let genesis_keys = Jwks::<Test>::get(google_issuer()).unwrap();
+        let genesis = ZkProofModule::storage_stats();
+        assert_eq!((genesis.proofs, genesis.attestations, genesis.device_keys), (0, 0, 0));
+        assert_eq!(genesis.jwks_keys, genesis_keys.len() as u64);
+        assert_eq!(genesis.jwks_bytes, genesis_keys.encoded_size() as u64);
+
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(0), None));
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof(1), None));
+        let stats = ZkProofModule::storage_stats();
+        assert_eq!((stats.proofs, stats.attestations), (2, 2));
+        assert_eq!(stats.proof_bytes, (proof(0).len() + proof(1).len()) as u64);
+
+        // Pruning drops the raw proof, not its attestation.
+        assert_ok!(ZkProofModule::prune_proof_data(RuntimeOrigin::signed(1), hash(0)));
+        let stats = ZkProofModule::storage_stats();
+        assert_eq!((stats.proofs, stats.attestations), (1, 2));
+        assert_eq!(stats.proof_bytes, proof(1).len() as u64);
+
+        assert_ok!(ZkProofModule::erase_identity_data(RuntimeOrigin::signed(2), proof(2)));
+        let stats = ZkProofModule::storage_stats();
+        assert_eq!((stats.proofs, stats.proof_bytes, stats.attestations), (0, 0, 0));
+
+        assert_ok!(register_key(1, "AA", "phone"));
+        assert_eq!(ZkProofModule::storage_stats().device_keys, 1);
+        assert_ok!(ZkProofModule::revoke_key(RuntimeOrigin::signed(1), 1));
+        assert_eq!(ZkProofModule::storage_stats().device_keys, 0);
+
+        let keys: BoundedVec<_, _> = vec![test_key("rotated")].try_into().unwrap();
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE), google_issuer(), keys.clone(), None));
+        assert_ok!(ZkProofModule::submit_jwks(RuntimeOrigin::signed(ORACLE_2), google_issuer(), keys.clone(), None));
+        let stats = ZkProofModule::storage_stats();
+        assert_eq!((stats.jwks_keys, stats.jwks_bytes), (1, keys.encoded_size() as u64));
+        assert_ok!(ZkProofModule::remove_provider(RuntimeOrigin::root(), google_issuer()));
+        let stats = ZkProofModule::storage_stats();
+        assert_eq!((stats.jwks_keys, stats.jwks_bytes), (0, 0));
+    });
+}
+
+#[test]
+fn storage_stored_before_version_3_is_counted() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        for jti in 0..2 {
+            let proof = test_proof_json(&google_jwt(FAR_FUTURE, &format!(r#","jti":"{}""#, jti)), &[]);
+            assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), proof.into_bytes(), None));
+        }
+        assert_ok!(register_key(1, "AA", "phone"));
+        let expected = ZkProofModule::storage_stats();
+        StorageVersion::new(2).put::<ZkProofModule>();
+        StorageUsage::<Test>::put(StorageStats { proofs: 42, ..Default::default() });
+
+        let mut meter = WeightMeter::new();
+        assert_eq!(CountStorage::<Test>::step(None, &mut meter), Ok(None));
+        assert_eq!(ZkProofModule::storage_stats(), expected);
+        assert_eq!(ZkProofModule::on_chain_storage_version(), 3);
+        let migrated = expected.proofs + expected.attestations + expected.device_keys + 1;
+        System::assert_last_event(Event::MigrationProgressed { migrated: migrated as u32, complete: true }.into());
+    });
+}
+
+#[test]
+fn forced_key_changes_keep_the_storage_usage_up_to_date() {
+    new_test_ext().execute_with(|| {
+        let recounted = || {
+            Jwks::<Test>::iter_values()
+                .fold((0, 0), |(keys, bytes), set| (keys + set.len() as u64, bytes + set.encoded_size() as u64))
+        };
+        let counted = || {
+            let stats = ZkProofModule::storage_stats();
+            (stats.jwks_keys, stats.jwks_bytes)
+        };
+
+        let key = test_key("emergency");
+        assert_ok!(ZkProofModule::force_set_jwk(RuntimeOrigin::root(), google_issuer(), key.clone()));
+        assert_eq!(counted(), recounted());
+        assert_ok!(ZkProofModule::force_remove_jwk(RuntimeOrigin::root(), google_issuer(), key.kid));
+        assert_eq!(counted(), recounted());
+
+        // Removing the last key of a provider removes its keyset.
+        for key in Jwks::<Test>::get(google_issuer()).unwrap() {
+            assert_ok!(ZkProofModule::force_remove_jwk(RuntimeOrigin::root(), google_issuer(), key.kid));
+        }
+        assert!(!Jwks::<Test>::contains_key(google_issuer()));
+        assert_eq!(counted(), recounted());
+    });
+}
+
+#[test]
+fn submitters_prune_raw_proofs_but_keep_their_attestation() {
+    new_test_ext().execute_with(|| {
+        InsecureSkipVerification::set(true);
+        let json = test_proof_json(&google_jwt(FAR_FUTURE, ""), &[]).into_bytes();
+        let proof_hash = <Test as frame_system::Config>::Hashing::hash(&json);
+
+        assert_noop!(
+            ZkProofModule::prune_proof_data(RuntimeOrigin::signed(1), proof_hash),
+            Error::<Test>::UnknownProof
+        );
+        assert_ok!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None));
+        assert_noop!(
+            ZkProofModule::prune_proof_data(RuntimeOrigin::signed(2), proof_hash),
+            Error::<Test>::NotSubmitter
+        );
+
+        assert_ok!(ZkProofModule::prune_proof_data(RuntimeOrigin::signed(1), proof_hash));
+        System::assert_last_event(Event::ProofDataPruned { hash: proof_hash }.into());
+        assert!(!ZkProofData::<Test>::contains_key(proof_hash));
+        assert_eq!(ZkProofModule::attestation_of(&1), Attestations::<Test>::get(proof_hash));
+        assert!(<EnsureVerified<Test>>::is_verified(&1));
+    });
+}
+
+#[test]
+#[should_panic(expected = "`MaxJsonLength` must fit an envelope around a token of `MaxJwtLength`")]
+fn runtimes_must_fit_tokens_in_their_envelopes() {
+    MaxJsonLength::set(MaxJwtLength::get());
+    ZkProofModule::integrity_test();
+}
+
+#[test]
+fn identity_roots_are_computed_periodically() {
+    new_test_ext().execute_with(|| {
+        IdentityRootPeriod::set(2);
+        Timestamp::set_timestamp(1_000_000);
+        let store = |i: u8, commitment: Commitment, expires_at: u64| {
+            let attestation = Attestation {
+                provider: google_issuer(),
+                commitment,
+                nullifier: commitment,
+                circuit_version: 0,
+                verified_at: 0,
+                expires_at,
+            };
+            Attestations::<Test>::insert(H256::repeat_byte(i), attestation);
+            ZkProofExpiry::<Test>::insert(H256::repeat_byte(i), expires_at);
+        };
+        store(0, [3; 32], 2_000);
+        store(1, [1; 32], 2_000);
+        store(2, [1; 32], 3_000);
+        store(3, [2; 32], 1_000);
+
+        ZkProofModule::on_finalize(1);
+        assert_eq!(ZkProofModule::identity_root(), None);
+
+        ZkProofModule::on_finalize(2);
+        // Expired proofs are left out, and identities with several proofs counted once.
+        let root = binary_merkle_tree::merkle_root::<Keccak256, _>(&[[1u8; 32], [3; 32]]);
+        assert_eq!(ZkProofModule::identity_root(), Some(IdentityRoot { block: 2, root, leaves: 2, complete: true }));
+        System::assert_last_event(Event::IdentityRootComputed { root, leaves: 2, complete: true }.into());
+        // The root is also published in the header.
+        assert!(System::digest().logs.contains(&DigestItem::Consensus(IDENTITY_ROOT_ENGINE_ID, root.encode())));
+
+        let proof = ZkProofModule::identity_proof(&[3; 32]).unwrap();
+        assert_eq!(proof.root, root);
+        assert!(binary_merkle_tree::verify_proof::<Keccak256, _, _>(
+            &root,
+            proof.proof,
+            proof.leaves as usize,
+            proof.leaf_index as usize,
+            &[3u8; 32],
+        ));
+        assert_eq!(ZkProofModule::identity_proof(&[2; 32]), None);
+
+        // Proofs past `MaxIdentityRootLeaves` are left out.
+        store(4, [4; 32], 2_000);
+        ZkProofModule::on_finalize(4);
+        assert!(!ZkProofModule::identity_root().unwrap().complete);
+    });
+}
+
+/// A genuine envelope, made once for all the cases mutating it.
+fn genuine_envelope() -> &'static [u8] {
+    static ENVELOPE: OnceLock<Vec<u8>> = OnceLock::new();
+    ENVELOPE.get_or_init(|| groth16_envelope::<ark_bls12_381::Config>(Curve::Bls12_381))
+}
+
+/// Overwrite bytes of `bytes`, each mutation being a position and the byte written there.
+fn mutate(bytes: &[u8], mutations: &[(prop::sample::Index, u8)]) -> Vec<u8> {
+    let mut mutated = bytes.to_vec();
+    for (index, byte) in mutations {
+        mutated[index.index(bytes.len())] = *byte;
+    }
+    mutated
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_are_rejected_as_envelopes(bytes in prop::collection::vec(any::<u8>(), 0..2048)) {
+        prop_assert!(validate_envelope(&bytes).is_err());
+        let _ = host::parse_envelope(&bytes);
+        let _ = host::base64_decode(&bytes);
+        let _ = host::base64url_decode(&bytes);
+    }
+
+    #[test]
+    fn mutated_envelopes_are_rejected_without_panicking(
+        mutations in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8)
+    ) {
+        let json = mutate(genuine_envelope(), &mutations);
+        let _ = validate_envelope(&json);
+
+        new_test_ext().execute_with(|| {
+            MaxJsonLength::set(100_000);
+            let _ = ZkProofModule::check_structure(&json);
+            // The envelope is bound to another chain, so it is rejected even if left valid.
+            let result = ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json, None);
+            prop_assert!(matches!(result, Err(DispatchError::Module(_))));
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn arbitrary_tokens_are_rejected_without_panicking(
+        token in "[A-Za-z0-9_=+/.-]{0,512}",
+        header in any::<String>(),
+        payload in any::<String>(),
+    ) {
+        let _ = jwt_segments(&token);
+        let _ = jwt_segments(&test_jwt(&header, &payload));
+
+        new_test_ext().execute_with(|| {
+            prop_assert!(ZkProofModule::validate_jwt(&token).is_err());
+            prop_assert!(ZkProofModule::validate_jwt(&test_jwt(&header, &payload)).is_err());
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn mutated_tokens_are_rejected_without_panicking(
+        mutations in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8)
+    ) {
+        new_test_ext().execute_with(|| {
+            let token = mutate(google_jwt(FAR_FUTURE, "").as_bytes(), &mutations);
+            let token = String::from_utf8_lossy(&token);
+            let _ = ZkProofModule::validate_jwt(&token);
+            let json = test_proof_json(&token.replace('"', ""), &[]);
+            prop_assert!(ZkProofModule::store_zk_proof(RuntimeOrigin::signed(1), json.into_bytes(), None).is_err());
+            Ok(())
+        })?;
+    }
+}
@@ -0,0 +1,507 @@
+//! On-chain types shared by the pallet's storage, calls and events.
+
+use crate::{encoding::Base64Variant, envelope::Curve};
+use alloc::{string::String, vec::Vec};
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+    sp_runtime::{DispatchError, Perbill},
+    traits::ConstU32,
+    BoundedVec, RuntimeDebug,
+};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+
+/// Maximum length of an issuer identifier (the `iss` claim of a JWT).
+pub const MAX_ISSUER_LENGTH: u32 = 128;
+/// Maximum length of a key id (the `kid` field of a JWK / JWT header).
+pub const MAX_KID_LENGTH: u32 = 64;
+/// Maximum length of a base64url encoded RSA modulus (enough for 4096 bit keys).
+pub const MAX_MODULUS_LENGTH: u32 = 704;
+/// Maximum length of the short JWK fields (`alg`, `kty`, `e`).
+pub const MAX_JWK_FIELD_LENGTH: u32 = 16;
+/// Maximum length of a provider URL.
+pub const MAX_URI_LENGTH: u32 = 256;
+/// Maximum number of claims a provider may require beyond the standard ones.
+pub const MAX_REQUIRED_CLAIMS: u32 = 8;
+/// Maximum length of the JSON pointer to a required claim.
+pub const MAX_CLAIM_PATH_LENGTH: u32 = 128;
+/// Maximum number of values a required claim may be restricted to.
+pub const MAX_ALLOWED_CLAIM_VALUES: u32 = 16;
+/// Maximum length of the email domain of a predicate.
+pub const MAX_DOMAIN_LENGTH: u32 = 64;
+/// Maximum length of the label of a device key.
+pub const MAX_DEVICE_LABEL_LENGTH: u32 = 32;
+/// Maximum number of calls a spending limit allows.
+pub const MAX_ALLOWED_CALLS: u32 = 16;
+/// Maximum length of a proof tag.
+pub const MAX_TAG_LENGTH: u32 = 16;
+/// Maximum number of tags of a proof.
+pub const MAX_PROOF_TAGS: u32 = 4;
+/// Maximum length of the OAuth client id of a dApp.
+pub const MAX_CLIENT_ID_LENGTH: u32 = 128;
+/// Maximum length of a tenant, such as a Workspace domain.
+pub const MAX_TENANT_LENGTH: u32 = 64;
+/// Maximum number of tenants a dApp may restrict its logins to.
+pub const MAX_NAMESPACE_TENANTS: u32 = 16;
+
+/// Identifier of an OIDC provider, as it appears in the `iss` claim.
+pub type IssuerId = BoundedVec<u8, ConstU32<MAX_ISSUER_LENGTH>>;
+
+/// The id of a signing key, as it appears in the `kid` field of a JWT header.
+pub type KeyId = BoundedVec<u8, ConstU32<MAX_KID_LENGTH>>;
+
+/// The hash of the value of a claim, see `adapters::claim_hash`.
+pub type ClaimHash = [u8; 32];
+
+/// The id of a claim predicate: the blake2-256 hash of its SCALE encoding.
+pub type PredicateId = [u8; 32];
+
+/// The commitment a proof is bound to: its first public input, as a compressed field element.
+pub type Commitment = [u8; 32];
+
+/// The blake2-256 hash of the base64 `verifying_key` field of a proof envelope.
+pub type VkHash = [u8; 32];
+
+/// A stable identifier of an identity, derived from its provider and commitment, that does not
+/// disclose any claim of its tokens.
+pub type Nullifier = [u8; 32];
+
+/// What the chain keeps of a verified proof, free of any token claim. It outlives the raw proof,
+/// which its submitter may prune once stored.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Attestation {
+    /// The issuer of the token the proof was made for.
+    pub provider: IssuerId,
+    /// The identity commitment the proof is bound to.
+    pub commitment: Commitment,
+    /// The nullifier of the identity.
+    pub nullifier: Nullifier,
+    /// The version of the circuit the proof was made with, 0 if it declared none.
+    pub circuit_version: u32,
+    /// When (unix seconds) the proof was verified, 0 if it was stored before this was recorded.
+    pub verified_at: u64,
+    /// When (unix seconds) the attestation expires.
+    pub expires_at: u64,
+}
+
+impl Attestation {
+    /// Whether the attestation is still valid at `now` (unix seconds).
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// A JSON Web Key as stored on-chain.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen, Serialize, Deserialize)]
+pub struct JwkRecord {
+    /// The key id, matched against the `kid` of the JWT header.
+    pub kid: BoundedVec<u8, ConstU32<MAX_KID_LENGTH>>,
+    /// The signature algorithm, e.g. `RS256`.
+    pub alg: BoundedVec<u8, ConstU32<MAX_JWK_FIELD_LENGTH>>,
+    /// The key type, e.g. `RSA`.
+    pub kty: BoundedVec<u8, ConstU32<MAX_JWK_FIELD_LENGTH>>,
+    /// The base64url encoded RSA modulus.
+    pub n: BoundedVec<u8, ConstU32<MAX_MODULUS_LENGTH>>,
+    /// The base64url encoded RSA public exponent.
+    pub e: BoundedVec<u8, ConstU32<MAX_JWK_FIELD_LENGTH>>,
+}
+
+impl JwkRecord {
+    /// Build a record from its string fields. Returns `None` if any field exceeds its bound.
+    pub fn new(kid: &str, alg: &str, kty: &str, n: &str, e: &str) -> Option<Self> {
+        Some(Self {
+            kid: kid.as_bytes().to_vec().try_into().ok()?,
+            alg: alg.as_bytes().to_vec().try_into().ok()?,
+            kty: kty.as_bytes().to_vec().try_into().ok()?,
+            n: n.as_bytes().to_vec().try_into().ok()?,
+            e: e.as_bytes().to_vec().try_into().ok()?,
+        })
+    }
+}
+
+/// The keys of a provider as fetched by a block author, supplied to the JWKS inherent.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct JwksUpdate {
+    /// The issuer the keys belong to.
+    pub provider: IssuerId,
+    /// The provider's current JWKS.
+    pub keys: Vec<JwkRecord>,
+    /// When (unix seconds) the provider's cache headers say the keys expire.
+    pub expires_at: Option<u64>,
+}
+
+/// A claim the tokens of a provider must carry beyond the standard ones, such as a tenant id or
+/// roles.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ClaimRequirement {
+    /// The JSON pointer (RFC 6901) to the claim in the token payload, e.g. `/firebase/tenant`.
+    pub path: BoundedVec<u8, ConstU32<MAX_CLAIM_PATH_LENGTH>>,
+    /// The hashes of the values the claim may take; any value if empty. An array claim is allowed
+    /// if any of its elements is.
+    pub allowed: BoundedVec<ClaimHash, ConstU32<MAX_ALLOWED_CLAIM_VALUES>>,
+}
+
+/// The sets of claim requirements a token is checked against.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, frame_support::PalletError)]
+pub enum ClaimPolicy {
+    /// The claims the provider of the token requires.
+    Provider,
+    /// The claims the dApp the token is issued to requires.
+    Namespace,
+}
+
+/// How a claim fails a requirement of a claim policy.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, frame_support::PalletError)]
+pub enum ClaimViolation {
+    /// The token lacks the claim.
+    Missing,
+    /// The claim has a value outside of the allowlist.
+    NotAllowed,
+}
+
+/// A predicate about the claims of a token, which proofs can attest without disclosing the claims.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum ClaimPredicate {
+    /// The email is at `domain`, i.e. ends with `@<domain>`.
+    EmailDomain(BoundedVec<u8, ConstU32<MAX_DOMAIN_LENGTH>>),
+    /// The issuer vouches for the email.
+    EmailVerified,
+    /// The claim at the JSON pointer `path` has the value hashing to `value`, see
+    /// `adapters::claim_hash`.
+    ClaimEquals { path: BoundedVec<u8, ConstU32<MAX_CLAIM_PATH_LENGTH>>, value: ClaimHash },
+}
+
+impl ClaimPredicate {
+    /// The id of the predicate, which predicate circuits take as a public input.
+    pub fn id(&self) -> PredicateId {
+        sp_io::hashing::blake2_256(&self.encode())
+    }
+}
+
+/// What a predicate proof attests, in place of the claims.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PredicateAttestation {
+    /// The predicate the proof is about.
+    pub predicate: PredicateId,
+    /// Whether the predicate holds for the claims of the token.
+    pub holds: bool,
+}
+
+/// Limits operators can tune at runtime with `set_config`. They default to the values of the
+/// pallet's `Config`.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ProofParameters {
+    /// The maximum length of the proof JSON; at most `Config::MaxJsonLength`.
+    pub max_json_length: u32,
+    /// How long (in seconds) a stored proof stays valid.
+    pub proof_lifetime: u64,
+    /// How far (in seconds) the chain clock may lag behind the token issuer's.
+    pub clock_skew_tolerance: u64,
+    /// The share of the maximum block weight proof calls may use together.
+    pub proof_weight_share: Perbill,
+    /// The maximum number of proof verifications a block may include; at most
+    /// `Config::MaxVerificationsPerBlock`.
+    pub max_verifications_per_block: u32,
+}
+
+/// A registered OIDC provider.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ProviderInfo {
+    /// The URL the provider serves its JWKS from.
+    pub jwks_uri: BoundedVec<u8, ConstU32<MAX_URI_LENGTH>>,
+}
+
+/// A change scheduled to take effect at a later block.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum Activation {
+    /// Approve a verifying key, as `approve_verifying_key` does.
+    VerifyingKey(VkHash),
+    /// Register a provider, as `add_provider` does.
+    Provider { provider: IssuerId, info: ProviderInfo },
+}
+
+/// What the admission checks learn of a proof submission, kept to diagnose its failure.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen, Default)]
+pub struct SubmissionContext {
+    /// The id of the key the token claims to be signed with.
+    pub kid: Option<KeyId>,
+    /// The circuit version the envelope declares.
+    pub circuit_version: Option<u32>,
+    /// The issuer of the token.
+    pub provider: Option<IssuerId>,
+}
+
+/// How much the logins of a provider are used.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen, Default)]
+pub struct ProviderStats<BlockNumber> {
+    /// The proofs of its tokens stored.
+    pub logins: u64,
+    /// The submissions of proofs of its tokens rejected once included in a block.
+    pub failures: u64,
+    /// The identities whose first proof was stored.
+    pub accounts: u64,
+    /// The block a proof of its tokens was last stored or rejected in.
+    pub last_active: BlockNumber,
+}
+
+/// The storage footprint of the pallet, kept up to date as entries are written and removed.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen, Default)]
+pub struct StorageStats {
+    /// The raw proofs stored.
+    pub proofs: u64,
+    /// The total length of the raw proofs stored, in bytes.
+    pub proof_bytes: u64,
+    /// The attestations of stored proofs.
+    pub attestations: u64,
+    /// The device keys registered to identities.
+    pub device_keys: u64,
+    /// The keys of the providers' current keysets.
+    pub jwks_keys: u64,
+    /// The encoded size of the providers' current keysets, in bytes.
+    pub jwks_bytes: u64,
+}
+
+/// The label a user gives a device key, e.g. `phone` or `laptop`.
+pub type DeviceLabel = BoundedVec<u8, ConstU32<MAX_DEVICE_LABEL_LENGTH>>;
+
+/// A tag telling what a proof was submitted for, e.g. `login`, `recovery` or `kyc`.
+pub type ProofTag = BoundedVec<u8, ConstU32<MAX_TAG_LENGTH>>;
+
+/// The tags of a proof.
+pub type ProofTags = BoundedVec<ProofTag, ConstU32<MAX_PROOF_TAGS>>;
+
+/// The OAuth client id of a dApp, as it appears in the `aud` claim of the tokens issued to it.
+pub type ClientId = BoundedVec<u8, ConstU32<MAX_CLIENT_ID_LENGTH>>;
+
+/// The organization a user belongs to at a multi-tenant provider: the Workspace domain (`hd`) of
+/// Google accounts, or the directory id (`tid`) of Microsoft accounts.
+pub type Tenant = BoundedVec<u8, ConstU32<MAX_TENANT_LENGTH>>;
+
+/// A dApp registered under its client id, the identities of whose users are scoped to it.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Namespace<AccountId> {
+    /// The account administering the namespace on behalf of the dApp.
+    pub owner: AccountId,
+    /// Whether logins into the namespace are suspended.
+    pub paused: bool,
+}
+
+/// How the identities of a namespace are derived from the address seeds of their proofs.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen, Default)]
+pub enum AddressScheme {
+    /// Nullifiers of the namespace's own, unlinkable to the identities of other dApps.
+    #[default]
+    Native,
+    /// Sui zkLogin addresses, for provers deriving the address seed as Sui does: the Poseidon
+    /// hash of the key claim name and value, the audience and the hash of the salt. The same
+    /// user and salt then have the same address on Sui and here.
+    Sui,
+}
+
+/// The base64 variants the tooling of a provider's users emits.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ProviderEncoding {
+    /// The variant of the proof points, verifying key and public inputs of JSON and CBOR envelopes.
+    pub proof: Base64Variant,
+    /// The variant of the segments of the provider's tokens.
+    pub jwt: Base64Variant,
+}
+
+impl Default for ProviderEncoding {
+    fn default() -> Self {
+        Self { proof: Base64Variant::STANDARD, jwt: Base64Variant::URL_SAFE }
+    }
+}
+
+/// The limits of a namespace, protecting the chain from a misbehaving dApp. Zero lifts a limit.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct NamespaceQuota<BlockNumber> {
+    /// How many logins into the namespace a block may carry.
+    pub logins_per_block: u32,
+    /// How many logins into the namespace a period may carry.
+    pub logins_per_period: u32,
+    /// The length of a period, in blocks, e.g. a day.
+    pub period: BlockNumber,
+    /// How many proofs may be stored in the namespace at once.
+    pub stored_proofs: u32,
+}
+
+/// How much of its quota a namespace used.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen, Default)]
+pub struct NamespaceUsage<BlockNumber> {
+    /// The block of the latest login.
+    pub block: BlockNumber,
+    /// The logins in `block`.
+    pub block_logins: u32,
+    /// The first block of the period of the latest login.
+    pub period_start: BlockNumber,
+    /// The logins in the period starting at `period_start`.
+    pub period_logins: u32,
+    /// The proofs stored in the namespace.
+    pub stored_proofs: u32,
+}
+
+/// A device key registered to an identity.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct DeviceKey<BlockNumber> {
+    /// The label of the device.
+    pub label: DeviceLabel,
+    /// The block the key was registered in.
+    pub registered_at: BlockNumber,
+    /// When (unix seconds) the key expires, along with the proof it was registered with.
+    pub expires_at: u64,
+}
+
+/// An identity locked with `emergency_lock`.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct IdentityLock<BlockNumber> {
+    /// The block the identity was locked in.
+    pub locked_at: BlockNumber,
+    /// The block the identity unlocks at, once a fresh proof requested it.
+    pub unlocks_at: Option<BlockNumber>,
+}
+
+/// A call of the runtime, by pallet index and call index, as the first two bytes of its encoding.
+pub type CallIndex = (u8, u8);
+
+/// What a device key may do, so that a compromised device cannot drain the account.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct SpendingLimit<BlockNumber> {
+    /// How much the key may spend per period, as measured by `Config::CallSpending`.
+    pub amount: u128,
+    /// The length of a period, in blocks.
+    pub period: BlockNumber,
+    /// The calls the key may dispatch; any call if `None`.
+    pub calls: Option<BoundedVec<CallIndex, ConstU32<MAX_ALLOWED_CALLS>>>,
+}
+
+/// What `ZkProofApi::check_proof` learns of a proof that verifies.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct VerificationReport {
+    /// What storing the proof would attest.
+    pub attestation: Attestation,
+    /// The predicate a predicate proof attests to, and whether it holds.
+    pub predicate: Option<PredicateAttestation>,
+    /// When (unix seconds) the token expires.
+    pub expires_at: u64,
+    /// Whether the proof is already stored, in which case only its submitter may store it again,
+    /// and only if `AllowProofOverwrite` is set.
+    pub already_stored: bool,
+}
+
+/// The Merkle root of the identities holding a valid proof, computed at the end of a block.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct IdentityRoot<BlockNumber> {
+    /// The block the root was computed at the end of.
+    pub block: BlockNumber,
+    /// The binary Merkle root (Keccak-256) of the sorted, distinct commitments of the proofs valid
+    /// then.
+    pub root: H256,
+    /// The number of commitments the root covers.
+    pub leaves: u32,
+    /// Whether every stored proof was considered, rather than only the first
+    /// `MaxIdentityRootLeaves`.
+    pub complete: bool,
+}
+
+/// A proof that a commitment is covered by an `IdentityRoot`, to check with
+/// `binary_merkle_tree::verify_proof`.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct IdentityProof {
+    /// The root the proof leads to.
+    pub root: H256,
+    /// The hashes of the siblings on the path from the commitment to the root.
+    pub proof: Vec<H256>,
+    /// The number of commitments the root covers.
+    pub leaves: u32,
+    /// The position of the commitment among them.
+    pub leaf_index: u32,
+}
+
+/// A proof submission that failed once included in a block.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct FailureRecord<AccountId, BlockNumber> {
+    /// The block the submission was included in.
+    pub block: BlockNumber,
+    /// The account that submitted the proof.
+    pub submitter: AccountId,
+    /// Why the submission failed.
+    pub error: DispatchError,
+    /// What the admission checks learnt of the proof.
+    pub context: SubmissionContext,
+}
+
+/// The failed proof submissions of a submitter or identity since its latest successful one.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen, Default)]
+pub struct FailedAttempts<BlockNumber> {
+    /// How many submissions failed.
+    pub count: u32,
+    /// The block submissions are rejected until, once `Config::MaxFailedAttempts` failed in a row.
+    pub locked_until: BlockNumber,
+}
+
+/// A G1 point, as the compressed encodings of its coordinates.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct G1Bytes {
+    pub x: Vec<u8>,
+    pub y: Vec<u8>,
+}
+
+/// An element of the quadratic extension field, as the compressed encodings of its components.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct Fq2Bytes {
+    pub c0: Vec<u8>,
+    pub c1: Vec<u8>,
+}
+
+/// A G2 point, as the compressed encodings of its coordinates.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct G2Bytes {
+    pub x: Fq2Bytes,
+    pub y: Fq2Bytes,
+}
+
+/// A proof envelope as a typed call argument, for `submit_proof`. It holds the same fields as the
+/// JSON envelope, with the binary ones as raw bytes rather than base64 or hex.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct ProofSubmission {
+    /// The curve the proof is made on.
+    pub curve: Curve,
+    /// The version of the circuit the proof is made with; unset for legacy proofs.
+    pub circuit_version: Option<u32>,
+    /// The `A` point of the Groth16 proof.
+    pub a: G1Bytes,
+    /// The `B` point of the Groth16 proof.
+    pub b: G2Bytes,
+    /// The `C` point of the Groth16 proof.
+    pub c: G1Bytes,
+    /// The compressed verifying key.
+    pub verifying_key: Vec<u8>,
+    /// The ID token the proof was made for.
+    pub jwt_token: String,
+    /// The genesis hash of the chain the proof is meant for.
+    pub chain_id: [u8; 32],
+    /// The compressed public inputs, in the order of the circuit.
+    pub public_inputs: Vec<Vec<u8>>,
+    /// The predicate the proof attests, for predicate circuits.
+    pub predicate: Option<PredicateId>,
+}
+
+/// An identity barred from the chain, e.g. after its account at the provider was compromised.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum BlockedIdentity {
+    /// Every identity with the commitment, at any provider and in any namespace.
+    Commitment(Commitment),
+    /// The identity with the nullifier.
+    Nullifier(Nullifier),
+}
+
+/// How `zklogin_execute` authenticates the identity whose zkLogin account dispatches the call.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub enum ZkLoginAuth {
+    /// The signer is an active device key of the identity, registered with `register_key`.
+    Session,
+    /// A fresh proof of the identity, stored like with `store_zk_proof`.
+    Proof(Vec<u8>),
+}
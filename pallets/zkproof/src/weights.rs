@@ -0,0 +1,148 @@
+//! Weights for `pallet_zkproof`
+//!
+//! ESTIMATES, NOT MEASURED: these stand in until the `benchmark pallet` command of the README is
+//! run on the reference hardware of the chain, which overwrites this file with measured weights
+//! following `.maintain/frame-weight-template.hbs`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+#![allow(missing_docs)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for `pallet_zkproof`.
+pub trait WeightInfo {
+	fn parse_envelope() -> Weight;
+	fn parse_proof_points() -> Weight;
+	fn public_inputs_poseidon() -> Weight;
+	fn public_inputs_sha256() -> Weight;
+	fn retrieve_all_zk_proofs(n: u32, ) -> Weight;
+	fn verify_bls12_377() -> Weight;
+	fn verify_bls12_381() -> Weight;
+	fn verify_rs256() -> Weight;
+}
+
+/// Weights for `pallet_zkproof` using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn parse_envelope() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(60_000_000, 0)
+	}
+	fn parse_proof_points() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(2_500_000_000, 0)
+	}
+	fn public_inputs_poseidon() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(4_000_000_000, 0)
+	}
+	fn public_inputs_sha256() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(20_000_000, 0)
+	}
+	/// Storage: `ZkProofModule::ZkProofData` (r:65 w:0)
+	/// Proof: `ZkProofModule::ZkProofData` (`max_values`: None, `max_size`: Some(100050), added: 102525, mode: `MaxEncodedLen`)
+	/// Storage: `ZkProofModule::ZkProofSubmitter` (r:64 w:0)
+	/// Proof: `ZkProofModule::ZkProofSubmitter` (`max_values`: None, `max_size`: Some(72), added: 2547, mode: `MaxEncodedLen`)
+	/// The range of component `n` is `[1, 64]`.
+	fn retrieve_all_zk_proofs(n: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `102525`
+		Weight::from_parts(20_000_000, 102525)
+			.saturating_add(Weight::from_parts(40_000_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 105072).saturating_mul(n.into()))
+	}
+	fn verify_bls12_377() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(27_000_000_000, 0)
+	}
+	fn verify_bls12_381() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(25_000_000_000, 0)
+	}
+	fn verify_rs256() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(8_000_000_000, 0)
+	}
+}
+
+// For backwards compatibility and tests.
+impl WeightInfo for () {
+	fn parse_envelope() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(60_000_000, 0)
+	}
+	fn parse_proof_points() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(2_500_000_000, 0)
+	}
+	fn public_inputs_poseidon() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(4_000_000_000, 0)
+	}
+	fn public_inputs_sha256() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(20_000_000, 0)
+	}
+	/// Storage: `ZkProofModule::ZkProofData` (r:65 w:0)
+	/// Proof: `ZkProofModule::ZkProofData` (`max_values`: None, `max_size`: Some(100050), added: 102525, mode: `MaxEncodedLen`)
+	/// Storage: `ZkProofModule::ZkProofSubmitter` (r:64 w:0)
+	/// Proof: `ZkProofModule::ZkProofSubmitter` (`max_values`: None, `max_size`: Some(72), added: 2547, mode: `MaxEncodedLen`)
+	/// The range of component `n` is `[1, 64]`.
+	fn retrieve_all_zk_proofs(n: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `102525`
+		Weight::from_parts(20_000_000, 102525)
+			.saturating_add(Weight::from_parts(40_000_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 105072).saturating_mul(n.into()))
+	}
+	fn verify_bls12_377() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(27_000_000_000, 0)
+	}
+	fn verify_bls12_381() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(25_000_000_000, 0)
+	}
+	fn verify_rs256() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		Weight::from_parts(8_000_000_000, 0)
+	}
+}
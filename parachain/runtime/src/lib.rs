@@ -0,0 +1,912 @@
+//! A parachain runtime for DOT Login.
+//!
+//! It runs the DOT Login pallets of the solochain runtime, configured alike, on top of the Cumulus
+//! pallets making a parachain: blocks are authored by the collators of `pallet-collator-selection`
+//! with Aura and validated by the relay chain, which also finalizes them.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// `construct_runtime!` does a lot of recursion and requires us to increase the limit to 256.
+#![recursion_limit = "256"]
+
+#[cfg(feature = "std")]
+include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
+
+mod xcm_config;
+
+use cumulus_pallet_parachain_system::RelayNumberStrictlyIncreases;
+use cumulus_primitives_core::{AggregateMessageOrigin, ParaId};
+use parachains_common::message_queue::{NarrowOriginToSibling, ParaIdToSibling};
+use polkadot_runtime_common::xcm_sender::NoPriceForMessageDelivery;
+use sp_api::impl_runtime_apis;
+pub use sp_consensus_aura::sr25519::AuthorityId as AuraId;
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_runtime::{
+	create_runtime_str, generic, impl_opaque_keys,
+	traits::{BlakeTwo256, Block as BlockT, IdentifyAccount, One, Verify},
+	transaction_validity::{TransactionSource, TransactionValidity},
+	ApplyExtrinsicResult, MultiSignature,
+};
+use sp_std::prelude::*;
+#[cfg(feature = "std")]
+use sp_version::NativeVersion;
+use sp_version::RuntimeVersion;
+
+use frame_support::genesis_builder_helper::{build_config, create_default_config};
+pub use frame_support::{
+	derive_impl, parameter_types,
+	traits::{ConstBool, ConstU128, ConstU16, ConstU32, ConstU64, ConstU8, TransformOrigin},
+	weights::{
+		constants::{RocksDbWeight, WEIGHT_REF_TIME_PER_SECOND},
+		IdentityFee, Weight,
+	},
+	PalletId,
+};
+use frame_system::EnsureRoot;
+use pallet_transaction_payment::{ConstFeeMultiplier, CurrencyAdapter, Multiplier};
+#[cfg(any(feature = "std", test))]
+pub use sp_runtime::BuildStorage;
+pub use sp_runtime::{Perbill, Permill};
+
+/// Import the zkproof pallet.
+pub use pallet_zkproof;
+
+/// Import the session pallet.
+pub use pallet_zk_session;
+
+/// Import the sub-accounts pallet.
+pub use pallet_zk_accounts;
+
+/// Import the claims pallet.
+pub use pallet_zk_claims;
+
+/// An index to a block.
+pub type BlockNumber = u32;
+
+/// Alias to 512-bit hash when used in the context of a transaction signature on the chain.
+pub type Signature = MultiSignature;
+
+/// Some way of identifying an account on the chain. We intentionally make it equivalent
+/// to the public key of our transaction signing scheme.
+pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+
+/// Balance of an account.
+pub type Balance = u128;
+
+/// Index of a transaction in the chain.
+pub type Nonce = u32;
+
+/// A hash of some data used by the chain.
+pub type Hash = sp_core::H256;
+
+/// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
+/// the specifics of the runtime.
+pub mod opaque {
+	use super::*;
+
+	pub use sp_runtime::OpaqueExtrinsic as UncheckedExtrinsic;
+
+	/// Opaque block header type.
+	pub type Header = generic::Header<BlockNumber, BlakeTwo256>;
+	/// Opaque block type.
+	pub type Block = generic::Block<Header, UncheckedExtrinsic>;
+	/// Opaque block identifier type.
+	pub type BlockId = generic::BlockId<Block>;
+}
+
+impl_opaque_keys! {
+	pub struct SessionKeys {
+		pub aura: Aura,
+	}
+}
+
+#[sp_version::runtime_version]
+pub const VERSION: RuntimeVersion = RuntimeVersion {
+	spec_name: create_runtime_str!("dot-login-parachain"),
+	impl_name: create_runtime_str!("dot-login-parachain"),
+	authoring_version: 1,
+	spec_version: 101,
+	impl_version: 1,
+	apis: RUNTIME_API_VERSIONS,
+	transaction_version: 2,
+	state_version: 1,
+};
+
+/// The average expected block time. Without asynchronous backing, parachain blocks take two
+/// relay chain blocks.
+pub const MILLISECS_PER_BLOCK: u64 = 12000;
+
+// NOTE: Currently it is not possible to change the slot duration after the chain has started.
+//       Attempting to do so will brick block production.
+pub const SLOT_DURATION: u64 = MILLISECS_PER_BLOCK;
+
+// Time is measured by number of blocks.
+pub const MINUTES: BlockNumber = 60_000 / (MILLISECS_PER_BLOCK as BlockNumber);
+pub const HOURS: BlockNumber = MINUTES * 60;
+pub const DAYS: BlockNumber = HOURS * 24;
+
+/// The version information used to identify this runtime when compiled natively.
+#[cfg(feature = "std")]
+pub fn native_version() -> NativeVersion {
+	NativeVersion { runtime_version: VERSION, can_author_with: Default::default() }
+}
+
+const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
+
+/// Half a second of compute per block, within the proof of validity the relay chain accepts.
+const MAXIMUM_BLOCK_WEIGHT: Weight = Weight::from_parts(
+	WEIGHT_REF_TIME_PER_SECOND.saturating_div(2),
+	cumulus_primitives_core::relay_chain::MAX_POV_SIZE as u64,
+);
+
+parameter_types! {
+	pub const BlockHashCount: BlockNumber = 2400;
+	pub const Version: RuntimeVersion = VERSION;
+	pub BlockWeights: frame_system::limits::BlockWeights =
+		frame_system::limits::BlockWeights::with_sensible_defaults(
+			MAXIMUM_BLOCK_WEIGHT,
+			NORMAL_DISPATCH_RATIO,
+		);
+	pub BlockLength: frame_system::limits::BlockLength = frame_system::limits::BlockLength
+		::max_with_normal_ratio(5 * 1024 * 1024, NORMAL_DISPATCH_RATIO);
+	pub const SS58Prefix: u16 = 42;
+}
+
+#[derive_impl(frame_system::config_preludes::ParaChainDefaultConfig as frame_system::DefaultConfig)]
+impl frame_system::Config for Runtime {
+	/// The block type for the runtime.
+	type Block = Block;
+	/// Block & extrinsics weights: base values and limits.
+	type BlockWeights = BlockWeights;
+	/// The maximum length of a block (in bytes).
+	type BlockLength = BlockLength;
+	/// The identifier used to distinguish between accounts.
+	type AccountId = AccountId;
+	/// The type for storing how many extrinsics an account has signed.
+	type Nonce = Nonce;
+	/// The type for hashing blocks and tries.
+	type Hash = Hash;
+	/// Maximum number of block number to block hash mappings to keep (oldest pruned first).
+	type BlockHashCount = BlockHashCount;
+	/// The weight of database operations that the runtime can invoke.
+	type DbWeight = RocksDbWeight;
+	/// Version of the runtime.
+	type Version = Version;
+	/// The data to be stored in an account.
+	type AccountData = pallet_balances::AccountData<Balance>;
+	/// This is used as an identifier of the chain. 42 is the generic substrate prefix.
+	type SS58Prefix = SS58Prefix;
+	/// Runtime upgrades go through the relay chain.
+	type OnSetCode = cumulus_pallet_parachain_system::ParachainSetCode<Self>;
+	type MaxConsumers = ConstU32<16>;
+}
+
+parameter_types! {
+	pub const ReservedXcmpWeight: Weight = MAXIMUM_BLOCK_WEIGHT.saturating_div(4);
+	pub const ReservedDmpWeight: Weight = MAXIMUM_BLOCK_WEIGHT.saturating_div(4);
+	pub const RelayOrigin: AggregateMessageOrigin = AggregateMessageOrigin::Parent;
+}
+
+impl cumulus_pallet_parachain_system::Config for Runtime {
+	type WeightInfo = ();
+	type RuntimeEvent = RuntimeEvent;
+	type OnSystemEvent = ();
+	type SelfParaId = parachain_info::Pallet<Runtime>;
+	type OutboundXcmpMessageSource = XcmpQueue;
+	type DmpQueue = frame_support::traits::EnqueueWithOrigin<MessageQueue, RelayOrigin>;
+	type ReservedDmpWeight = ReservedDmpWeight;
+	type XcmpMessageHandler = XcmpQueue;
+	type ReservedXcmpWeight = ReservedXcmpWeight;
+	type CheckAssociatedRelayNumber = RelayNumberStrictlyIncreases;
+	type ConsensusHook = cumulus_pallet_parachain_system::consensus_hook::ExpectParentIncluded;
+}
+
+impl parachain_info::Config for Runtime {}
+
+parameter_types! {
+	pub MessageQueueServiceWeight: Weight =
+		Perbill::from_percent(35) * BlockWeights::get().max_block;
+}
+
+impl pallet_message_queue::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type MessageProcessor = xcm_builder::ProcessXcmMessage<
+		AggregateMessageOrigin,
+		xcm_executor::XcmExecutor<xcm_config::XcmConfig>,
+		RuntimeCall,
+	>;
+	type Size = u32;
+	// The XCMP queue pallet is only ever able to handle the `Sibling(ParaId)` origin:
+	type QueueChangeHandler = NarrowOriginToSibling<XcmpQueue>;
+	type QueuePausedQuery = NarrowOriginToSibling<XcmpQueue>;
+	type HeapSize = ConstU32<{ 64 * 1024 }>;
+	type MaxStale = ConstU32<8>;
+	type ServiceWeight = MessageQueueServiceWeight;
+}
+
+impl cumulus_pallet_aura_ext::Config for Runtime {}
+
+impl cumulus_pallet_xcmp_queue::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type ChannelInfo = ParachainSystem;
+	type VersionWrapper = ();
+	// Enqueue XCMP messages from siblings for later processing.
+	type XcmpQueue = TransformOrigin<MessageQueue, AggregateMessageOrigin, ParaId, ParaIdToSibling>;
+	type MaxInboundSuspended = ConstU32<1_000>;
+	type ControllerOrigin = EnsureRoot<AccountId>;
+	type ControllerOriginConverter = xcm_config::XcmOriginToTransactDispatchOrigin;
+	type WeightInfo = ();
+	type PriceForSiblingDelivery = NoPriceForMessageDelivery<ParaId>;
+}
+
+impl pallet_timestamp::Config for Runtime {
+	/// A timestamp: milliseconds since the unix epoch.
+	type Moment = u64;
+	type OnTimestampSet = Aura;
+	type MinimumPeriod = ConstU64<{ SLOT_DURATION / 2 }>;
+	type WeightInfo = ();
+}
+
+impl pallet_authorship::Config for Runtime {
+	type FindAuthor = pallet_session::FindAccountFromAuthorIndex<Self, Aura>;
+	type EventHandler = (CollatorSelection,);
+}
+
+parameter_types! {
+	pub const Period: BlockNumber = 6 * HOURS;
+	pub const Offset: BlockNumber = 0;
+}
+
+impl pallet_session::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type ValidatorId = <Self as frame_system::Config>::AccountId;
+	// We don't have stash and controller, thus we don't need the convert as well.
+	type ValidatorIdOf = pallet_collator_selection::IdentityCollator;
+	type ShouldEndSession = pallet_session::PeriodicSessions<Period, Offset>;
+	type NextSessionRotation = pallet_session::PeriodicSessions<Period, Offset>;
+	type SessionManager = CollatorSelection;
+	// Essentially just Aura, but let's be pedantic.
+	type SessionHandler = <SessionKeys as sp_runtime::traits::OpaqueKeys>::KeyTypeIdProviders;
+	type Keys = SessionKeys;
+	type WeightInfo = ();
+}
+
+impl pallet_aura::Config for Runtime {
+	type AuthorityId = AuraId;
+	type DisabledValidators = ();
+	type MaxAuthorities = ConstU32<100>;
+	type AllowMultipleBlocksPerSlot = ConstBool<false>;
+
+	#[cfg(feature = "experimental")]
+	type SlotDuration = pallet_aura::MinimumPeriodTimesTwo<Runtime>;
+}
+
+parameter_types! {
+	pub const PotId: PalletId = PalletId(*b"PotStake");
+}
+
+impl pallet_collator_selection::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+	type PotId = PotId;
+	type MaxCandidates = ConstU32<100>;
+	type MinEligibleCollators = ConstU32<4>;
+	type MaxInvulnerables = ConstU32<20>;
+	// Should be a multiple of session or things will get inconsistent.
+	type KickThreshold = Period;
+	type ValidatorId = <Self as frame_system::Config>::AccountId;
+	type ValidatorIdOf = pallet_collator_selection::IdentityCollator;
+	type ValidatorRegistration = Session;
+	type WeightInfo = ();
+}
+
+/// Existential deposit.
+pub const EXISTENTIAL_DEPOSIT: u128 = 500;
+
+impl pallet_balances::Config for Runtime {
+	type MaxLocks = ConstU32<50>;
+	/// Collator candidates reserve their bond.
+	type MaxReserves = ConstU32<50>;
+	type ReserveIdentifier = [u8; 8];
+	/// The type for recording an account's balance.
+	type Balance = Balance;
+	/// The ubiquitous event type.
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU128<EXISTENTIAL_DEPOSIT>;
+	type AccountStore = System;
+	type WeightInfo = pallet_balances::weights::SubstrateWeight<Runtime>;
+	type FreezeIdentifier = ();
+	type MaxFreezes = ();
+	type RuntimeHoldReason = RuntimeHoldReason;
+	type RuntimeFreezeReason = ();
+}
+
+/// Fees are charged one unit per unit of weight, as on the solochain.
+pub type WeightToFee = IdentityFee<Balance>;
+
+parameter_types! {
+	pub FeeMultiplier: Multiplier = Multiplier::one();
+}
+
+impl pallet_transaction_payment::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+	type OperationalFeeMultiplier = ConstU8<5>;
+	type WeightToFee = WeightToFee;
+	type LengthToFee = IdentityFee<Balance>;
+	type FeeMultiplierUpdate = ConstFeeMultiplier<FeeMultiplier>;
+}
+
+impl pallet_sudo::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeCall = RuntimeCall;
+	type WeightInfo = pallet_sudo::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+	/// Google ID tokens only: the local development issuer has no place on a relay chain.
+	pub AllowedIssuers: Vec<pallet_zkproof::IssuerId> = vec![
+		pallet_zkproof::IssuerId::truncate_from(pallet_zkproof::GOOGLE_ISSUER.to_vec()),
+	];
+	/// Logins may use at most a quarter of each block.
+	pub const ProofWeightShare: Perbill = Perbill::from_percent(25);
+}
+
+/// The value calls move out of the signer, for the spending limits of device keys. Calls made
+/// through a sub-account or the zkLogin account count as if made by the signer, and sweeping
+/// transfers exceed any limit.
+pub struct TransferredValue;
+
+impl pallet_zkproof::CallSpending<RuntimeCall> for TransferredValue {
+	fn spending(call: &RuntimeCall) -> u128 {
+		match call {
+			RuntimeCall::Balances(
+				pallet_balances::Call::transfer_allow_death { value, .. } |
+				pallet_balances::Call::transfer_keep_alive { value, .. },
+			) => *value,
+			RuntimeCall::Balances(pallet_balances::Call::transfer_all { .. }) => Balance::MAX,
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::fund_sub_account { amount, .. }) =>
+				*amount,
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::sub_account_call { call, .. }) =>
+				Self::spending(call),
+			RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { call, .. }) =>
+				Self::spending(call),
+			RuntimeCall::ZkClaims(pallet_zk_claims::Call::create_distribution {
+				leaves,
+				amount,
+				..
+			}) => amount.saturating_mul(*leaves as Balance),
+			_ => 0,
+		}
+	}
+
+	fn wrapped(call: &RuntimeCall) -> Option<&RuntimeCall> {
+		match call {
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::sub_account_call { call, .. }) =>
+				Some(call),
+			RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { call, .. }) =>
+				Some(call),
+			_ => None,
+		}
+	}
+}
+
+/// The calls the zkLogin accounts of identities dispatch with `zkProofModule.zkloginExecute`:
+/// any but the calls of `sudo`, and another `zkloginExecute`.
+pub struct ZkLoginCalls;
+
+impl frame_support::traits::Contains<RuntimeCall> for ZkLoginCalls {
+	fn contains(call: &RuntimeCall) -> bool {
+		!matches!(
+			call,
+			RuntimeCall::Sudo(_) |
+				RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { .. })
+		)
+	}
+}
+
+impl pallet_zkproof::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type OnboardingOrigin = EnsureRoot<AccountId>;
+	type MaxJsonLength = ConstU32<100000>;
+	type MaxJwtLength = ConstU32<4096>;
+	type MaxPublicInputs = ConstU32<16>;
+	type MaxJwksKeys = ConstU32<16>;
+	type MaxOracles = ConstU32<16>;
+	type JwksQuorum = ConstU32<1>;
+	type JwksSubmissionWindow = ConstU32<{ 10 * MINUTES }>;
+	type UnixTime = Timestamp;
+	type JwksGracePeriod = ConstU64<3600>;
+	type JwksFetchInterval = ConstU32<{ 10 * MINUTES }>;
+	type AuthorityId = pallet_zkproof::crypto::OracleAuthId;
+	type AllowedIssuers = AllowedIssuers;
+	type ProviderAdapters = pallet_zkproof::adapters::StandardAdapters;
+	type AllowProofOverwrite = ConstBool<false>;
+	type InsecureSkipVerification = ConstBool<false>;
+	type ProofWeightShare = ProofWeightShare;
+	type EmitProofPayloads = ConstBool<false>;
+	type ProofLifetime = ConstU64<{ 24 * 3600 }>;
+	type ClockSkewTolerance = ConstU64<60>;
+	type NonceBlockWindow = ConstU32<{ 10 * MINUTES }>;
+	type MaxTokenAge = ConstU64<600>;
+	type RevealWindow = ConstU32<{ 2 * MINUTES }>;
+	type PrivacyMode = ConstBool<false>;
+	type MaxErasedProofs = ConstU32<64>;
+	type MaxScheduledActivations = ConstU32<16>;
+	type MaxRecentFailures = ConstU32<32>;
+	type OnProofVerified = ();
+	type MaxVerificationsPerBlock = ConstU32<64>;
+	// Collators are not trusted with the keys of the providers.
+	type AuthoredJwks = ConstBool<false>;
+	type IdentityRootPeriod = ConstU32<DAYS>;
+	type MaxIdentityRootLeaves = ConstU32<4096>;
+	type MaxDeviceKeys = ConstU32<8>;
+	type CallSpending = TransferredValue;
+	type UnlockDelay = ConstU32<{ 2 * DAYS }>;
+	type OnIdentityLocked = ZkSession;
+	type AttestationGate = ();
+	type MaxMetadataLength = ConstU32<128>;
+	type CircuitWarningPeriod = ConstU32<DAYS>;
+	type StepUpCalls = ();
+	type MaxFailedAttempts = ConstU32<5>;
+	type LockoutPeriod = ConstU32<{ 10 * MINUTES }>;
+	type ZkLoginCall = RuntimeCall;
+	type ZkLoginCalls = ZkLoginCalls;
+	type RenewalNotice = ConstU64<3600>;
+	type MaxRenewalReminders = ConstU32<64>;
+	type WeightInfo = pallet_zkproof::weights::SubstrateWeight<Runtime>;
+}
+
+impl pallet_zk_session::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Attestations = ZkProofModule;
+	type UnixTime = Timestamp;
+	type SessionLifetime = ConstU64<{ 8 * 3600 }>;
+	type MaxSessions = ConstU32<32>;
+	type MaxPrunedSessions = ConstU32<64>;
+}
+
+impl pallet_zk_accounts::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeCall = RuntimeCall;
+	type Attestations = ZkProofModule;
+	type Currency = Balances;
+	type MaxSubAccounts = ConstU16<64>;
+}
+
+parameter_types! {
+	pub const ClaimsPalletId: PalletId = PalletId(*b"zk/claim");
+}
+
+impl pallet_zk_claims::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Attestations = ZkProofModule;
+	type Currency = Balances;
+	type PalletId = ClaimsPalletId;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
+where
+	RuntimeCall: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: RuntimeCall,
+		public: <Signature as Verify>::Signer,
+		account: AccountId,
+		nonce: Nonce,
+	) -> Option<(RuntimeCall, <UncheckedExtrinsic as sp_runtime::traits::Extrinsic>::SignaturePayload)> {
+		use codec::Encode;
+		use sp_runtime::{traits::StaticLookup, SaturatedConversion};
+
+		let period =
+			BlockHashCount::get().checked_next_power_of_two().map(|c| c / 2).unwrap_or(2) as u64;
+		let current_block = System::block_number().saturated_into::<u64>().saturating_sub(1);
+		let extra: SignedExtra = (
+			frame_system::CheckNonZeroSender::<Runtime>::new(),
+			frame_system::CheckSpecVersion::<Runtime>::new(),
+			frame_system::CheckTxVersion::<Runtime>::new(),
+			frame_system::CheckGenesis::<Runtime>::new(),
+			frame_system::CheckEra::<Runtime>::from(generic::Era::mortal(period, current_block)),
+			frame_system::CheckNonce::<Runtime>::from(nonce),
+			frame_system::CheckWeight::<Runtime>::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(0),
+			pallet_zkproof::CheckProofStructure::<Runtime>::new(),
+			pallet_zkproof::CheckSpendingLimit::<Runtime>::new(),
+		);
+		let raw_payload = SignedPayload::new(call, extra).ok()?;
+		let signature = raw_payload.using_encoded(|payload| C::sign(payload, public))?;
+		let address = <Runtime as frame_system::Config>::Lookup::unlookup(account);
+		let (call, extra, _) = raw_payload.deconstruct();
+		Some((call, (address, signature, extra)))
+	}
+}
+
+impl frame_system::offchain::SigningTypes for Runtime {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+	RuntimeCall: From<C>,
+{
+	type OverarchingCall = RuntimeCall;
+	type Extrinsic = UncheckedExtrinsic;
+}
+
+// Create the runtime by composing the FRAME pallets that were previously configured.
+#[frame_support::runtime]
+mod runtime {
+	#[runtime::runtime]
+	#[runtime::derive(
+		RuntimeCall,
+		RuntimeEvent,
+		RuntimeError,
+		RuntimeOrigin,
+		RuntimeFreezeReason,
+		RuntimeHoldReason,
+		RuntimeSlashReason,
+		RuntimeLockId,
+		RuntimeTask
+	)]
+	pub struct Runtime;
+
+	// System support stuff.
+	#[runtime::pallet_index(0)]
+	pub type System = frame_system;
+
+	#[runtime::pallet_index(1)]
+	pub type ParachainSystem = cumulus_pallet_parachain_system;
+
+	#[runtime::pallet_index(2)]
+	pub type Timestamp = pallet_timestamp;
+
+	#[runtime::pallet_index(3)]
+	pub type ParachainInfo = parachain_info;
+
+	// Monetary stuff.
+	#[runtime::pallet_index(10)]
+	pub type Balances = pallet_balances;
+
+	#[runtime::pallet_index(11)]
+	pub type TransactionPayment = pallet_transaction_payment;
+
+	// Governance
+	#[runtime::pallet_index(15)]
+	pub type Sudo = pallet_sudo;
+
+	// Collator support. The order of these 4 are important and shall not change.
+	#[runtime::pallet_index(20)]
+	pub type Authorship = pallet_authorship;
+
+	#[runtime::pallet_index(21)]
+	pub type CollatorSelection = pallet_collator_selection;
+
+	#[runtime::pallet_index(22)]
+	pub type Session = pallet_session;
+
+	#[runtime::pallet_index(23)]
+	pub type Aura = pallet_aura;
+
+	#[runtime::pallet_index(24)]
+	pub type AuraExt = cumulus_pallet_aura_ext;
+
+	// XCM helpers.
+	#[runtime::pallet_index(30)]
+	pub type XcmpQueue = cumulus_pallet_xcmp_queue;
+
+	#[runtime::pallet_index(31)]
+	pub type PolkadotXcm = pallet_xcm;
+
+	#[runtime::pallet_index(32)]
+	pub type CumulusXcm = cumulus_pallet_xcm;
+
+	#[runtime::pallet_index(33)]
+	pub type MessageQueue = pallet_message_queue;
+
+	// DOT Login, under the names of the solochain runtime.
+	#[runtime::pallet_index(40)]
+	pub type ZkProofModule = pallet_zkproof;
+
+	#[runtime::pallet_index(41)]
+	pub type ZkSession = pallet_zk_session;
+
+	#[runtime::pallet_index(42)]
+	pub type ZkAccounts = pallet_zk_accounts;
+
+	#[runtime::pallet_index(43)]
+	pub type ZkClaims = pallet_zk_claims;
+}
+
+/// The address format for describing accounts.
+pub type Address = sp_runtime::MultiAddress<AccountId, ()>;
+/// Block header type as expected by this runtime.
+pub type Header = generic::Header<BlockNumber, BlakeTwo256>;
+/// Block type as expected by this runtime.
+pub type Block = generic::Block<Header, UncheckedExtrinsic>;
+/// The SignedExtension to the basic transaction logic, the same as the solochain's.
+pub type SignedExtra = (
+	frame_system::CheckNonZeroSender<Runtime>,
+	frame_system::CheckSpecVersion<Runtime>,
+	frame_system::CheckTxVersion<Runtime>,
+	frame_system::CheckGenesis<Runtime>,
+	frame_system::CheckEra<Runtime>,
+	frame_system::CheckNonce<Runtime>,
+	frame_system::CheckWeight<Runtime>,
+	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_zkproof::CheckProofStructure<Runtime>,
+	pallet_zkproof::CheckSpendingLimit<Runtime>,
+);
+
+/// Unchecked extrinsic type as expected by this runtime.
+pub type UncheckedExtrinsic =
+	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, SignedExtra>;
+/// The payload being signed in transactions.
+pub type SignedPayload = generic::SignedPayload<RuntimeCall, SignedExtra>;
+/// Executive: handles dispatch to the various modules.
+pub type Executive = frame_executive::Executive<
+	Runtime,
+	Block,
+	frame_system::ChainContext<Runtime>,
+	Runtime,
+	AllPalletsWithSystem,
+>;
+
+impl_runtime_apis! {
+	impl sp_api::Core<Block> for Runtime {
+		fn version() -> RuntimeVersion {
+			VERSION
+		}
+
+		fn execute_block(block: Block) {
+			Executive::execute_block(block);
+		}
+
+		fn initialize_block(header: &<Block as BlockT>::Header) -> sp_runtime::ExtrinsicInclusionMode {
+			Executive::initialize_block(header)
+		}
+	}
+
+	impl sp_api::Metadata<Block> for Runtime {
+		fn metadata() -> OpaqueMetadata {
+			OpaqueMetadata::new(Runtime::metadata().into())
+		}
+
+		fn metadata_at_version(version: u32) -> Option<OpaqueMetadata> {
+			Runtime::metadata_at_version(version)
+		}
+
+		fn metadata_versions() -> sp_std::vec::Vec<u32> {
+			Runtime::metadata_versions()
+		}
+	}
+
+	impl sp_block_builder::BlockBuilder<Block> for Runtime {
+		fn apply_extrinsic(extrinsic: <Block as BlockT>::Extrinsic) -> ApplyExtrinsicResult {
+			Executive::apply_extrinsic(extrinsic)
+		}
+
+		fn finalize_block() -> <Block as BlockT>::Header {
+			Executive::finalize_block()
+		}
+
+		fn inherent_extrinsics(data: sp_inherents::InherentData) -> Vec<<Block as BlockT>::Extrinsic> {
+			data.create_extrinsics()
+		}
+
+		fn check_inherents(
+			block: Block,
+			data: sp_inherents::InherentData,
+		) -> sp_inherents::CheckInherentsResult {
+			data.check_extrinsics(&block)
+		}
+	}
+
+	impl sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block> for Runtime {
+		fn validate_transaction(
+			source: TransactionSource,
+			tx: <Block as BlockT>::Extrinsic,
+			block_hash: <Block as BlockT>::Hash,
+		) -> TransactionValidity {
+			Executive::validate_transaction(source, tx, block_hash)
+		}
+	}
+
+	impl sp_offchain::OffchainWorkerApi<Block> for Runtime {
+		fn offchain_worker(header: &<Block as BlockT>::Header) {
+			Executive::offchain_worker(header)
+		}
+	}
+
+	impl sp_consensus_aura::AuraApi<Block, AuraId> for Runtime {
+		fn slot_duration() -> sp_consensus_aura::SlotDuration {
+			sp_consensus_aura::SlotDuration::from_millis(Aura::slot_duration())
+		}
+
+		fn authorities() -> Vec<AuraId> {
+			Aura::authorities().into_inner()
+		}
+	}
+
+	impl sp_session::SessionKeys<Block> for Runtime {
+		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
+			SessionKeys::generate(seed)
+		}
+
+		fn decode_session_keys(
+			encoded: Vec<u8>,
+		) -> Option<Vec<(Vec<u8>, KeyTypeId)>> {
+			SessionKeys::decode_into_raw_public_keys(&encoded)
+		}
+	}
+
+	impl cumulus_primitives_core::CollectCollationInfo<Block> for Runtime {
+		fn collect_collation_info(header: &<Block as BlockT>::Header) -> cumulus_primitives_core::CollationInfo {
+			ParachainSystem::collect_collation_info(header)
+		}
+	}
+
+	impl frame_system_rpc_runtime_api::AccountNonceApi<Block, AccountId, Nonce> for Runtime {
+		fn account_nonce(account: AccountId) -> Nonce {
+			System::account_nonce(account)
+		}
+	}
+
+	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance> for Runtime {
+		fn query_info(
+			uxt: <Block as BlockT>::Extrinsic,
+			len: u32,
+		) -> pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo<Balance> {
+			TransactionPayment::query_info(uxt, len)
+		}
+		fn query_fee_details(
+			uxt: <Block as BlockT>::Extrinsic,
+			len: u32,
+		) -> pallet_transaction_payment::FeeDetails<Balance> {
+			TransactionPayment::query_fee_details(uxt, len)
+		}
+		fn query_weight_to_fee(weight: Weight) -> Balance {
+			TransactionPayment::weight_to_fee(weight)
+		}
+		fn query_length_to_fee(length: u32) -> Balance {
+			TransactionPayment::length_to_fee(length)
+		}
+	}
+
+	#[api_version(9)]
+	impl pallet_zkproof::runtime_api::ZkProofApi<Block, AccountId, BlockNumber> for Runtime {
+		fn recent_failures() -> Vec<pallet_zkproof::FailureRecord<AccountId, BlockNumber>> {
+			ZkProofModule::recent_failures()
+		}
+
+		fn provider_stats() -> Vec<(pallet_zkproof::IssuerId, pallet_zkproof::ProviderStats<BlockNumber>)> {
+			ZkProofModule::provider_stats()
+		}
+
+		fn providers() -> Vec<(pallet_zkproof::IssuerId, pallet_zkproof::ProviderInfo)> {
+			ZkProofModule::providers()
+		}
+
+		fn jwks(provider: pallet_zkproof::IssuerId) -> Vec<pallet_zkproof::JwkRecord> {
+			ZkProofModule::accepted_jwks(&provider)
+		}
+
+		fn jwks_expiry(provider: pallet_zkproof::IssuerId) -> Option<u64> {
+			pallet_zkproof::JwksExpiry::<Runtime>::get(provider)
+		}
+
+		fn check_proof(
+			proof: Vec<u8>,
+		) -> Result<pallet_zkproof::VerificationReport, sp_runtime::DispatchError> {
+			ZkProofModule::check_proof(&proof)
+		}
+
+		fn identity_root() -> Option<pallet_zkproof::IdentityRoot<BlockNumber>> {
+			ZkProofModule::identity_root()
+		}
+
+		fn identity_proof(
+			commitment: pallet_zkproof::Commitment,
+		) -> Option<pallet_zkproof::IdentityProof> {
+			ZkProofModule::identity_proof(&commitment)
+		}
+
+		fn device_keys(
+			account: AccountId,
+		) -> Vec<(AccountId, pallet_zkproof::DeviceKey<BlockNumber>)> {
+			ZkProofModule::device_keys(&account)
+		}
+
+		fn proofs_in_range(from: BlockNumber, to: BlockNumber) -> Vec<(BlockNumber, Hash)> {
+			ZkProofModule::proofs_in_range(from, to)
+		}
+
+		fn proof_metadata(proof_hash: Hash) -> Option<Vec<u8>> {
+			pallet_zkproof::ProofMetadata::<Runtime>::get(proof_hash)
+				.map(|metadata| metadata.into_inner())
+		}
+
+		fn proofs_with_tag(
+			tag: pallet_zkproof::ProofTag,
+			start_key: Option<Hash>,
+			limit: u32,
+		) -> Vec<Hash> {
+			ZkProofModule::proofs_with_tag(&tag, start_key, limit)
+		}
+
+		fn namespace_proofs(
+			client_id: pallet_zkproof::ClientId,
+			start_key: Option<Hash>,
+			limit: u32,
+		) -> Vec<Hash> {
+			ZkProofModule::namespace_proofs(&client_id, start_key, limit)
+		}
+
+		fn circuit_sunsets() -> Vec<(u32, BlockNumber)> {
+			ZkProofModule::circuit_sunsets()
+		}
+	}
+
+	impl pallet_zk_session::runtime_api::ZkSessionApi<Block, AccountId, BlockNumber> for Runtime {
+		fn session(
+			who: AccountId,
+			dapp: AccountId,
+		) -> Option<pallet_zk_session::Session<BlockNumber>> {
+			ZkSession::active_session(&who, &dapp)
+		}
+
+		fn sessions(who: AccountId) -> Vec<(AccountId, pallet_zk_session::Session<BlockNumber>)> {
+			ZkSession::active_sessions(&who)
+		}
+
+		fn total_sessions() -> u64 {
+			ZkSession::total_sessions()
+		}
+	}
+
+	impl pallet_zk_accounts::runtime_api::ZkAccountsApi<Block, AccountId, BlockNumber> for Runtime {
+		fn sub_accounts(
+			who: AccountId,
+		) -> Vec<(
+			pallet_zk_accounts::SubAccountIndex,
+			pallet_zk_accounts::SubAccount<AccountId, BlockNumber>,
+		)> {
+			ZkAccounts::sub_accounts(&who)
+		}
+	}
+
+	#[cfg(feature = "try-runtime")]
+	impl frame_try_runtime::TryRuntime<Block> for Runtime {
+		fn on_runtime_upgrade(checks: frame_try_runtime::UpgradeCheckSelect) -> (Weight, Weight) {
+			let weight = Executive::try_runtime_upgrade(checks).unwrap();
+			(weight, BlockWeights::get().max_block)
+		}
+
+		fn execute_block(
+			block: Block,
+			state_root_check: bool,
+			signature_check: bool,
+			select: frame_try_runtime::TryStateSelect
+		) -> Weight {
+			Executive::try_execute_block(block, state_root_check, signature_check, select).expect("execute-block failed")
+		}
+	}
+
+	impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+		fn create_default_config() -> Vec<u8> {
+			create_default_config::<RuntimeGenesisConfig>()
+		}
+
+		fn build_config(config: Vec<u8>) -> sp_genesis_builder::Result {
+			build_config::<RuntimeGenesisConfig>(config)
+		}
+	}
+}
+
+cumulus_pallet_parachain_system::register_validate_block! {
+	Runtime = Runtime,
+	BlockExecutor = cumulus_pallet_aura_ext::BlockExecutor::<Runtime, Executive>,
+}
@@ -0,0 +1,47 @@
+//! Read access to DOT Login attestations for ink! contracts.
+//!
+//! Contracts call the extension with [`EXTENSION_ID`] and one of the function ids below. See
+//! `contracts/attestation-gate` for a contract using it.
+
+use crate::{AccountId, Runtime, ZkProofModule};
+use codec::Encode;
+use frame_support::{traits::Get, weights::constants::RocksDbWeight};
+use pallet_contracts::chain_extension::{ChainExtension, Environment, Ext, InitState, RetVal};
+use sp_runtime::DispatchError;
+
+/// The id contracts address the extension with.
+pub const EXTENSION_ID: u16 = 1;
+
+/// `attestation(account: AccountId) -> Option<(Nullifier, u64)>`: the nullifier of the identity
+/// the latest proof stored by `account` attests to, and when (unix seconds) the proof expires, if
+/// it is still valid.
+pub const ATTESTATION: u16 = 1;
+
+/// Lets contracts read the attestations of `pallet-zkproof`.
+#[derive(Default)]
+pub struct DotLoginExtension;
+
+impl ChainExtension<Runtime> for DotLoginExtension {
+	fn call<E: Ext<T = Runtime>>(
+		&mut self,
+		env: Environment<E, InitState>,
+	) -> Result<RetVal, DispatchError> {
+		if env.ext_id() != EXTENSION_ID {
+			return Err(DispatchError::Other("Unknown chain extension"));
+		}
+
+		let mut env = env.buf_in_buf_out();
+		match env.func_id() {
+			ATTESTATION => {
+				// The account's latest proof, its attestation, the lock of its identity and the clock.
+				env.charge_weight(RocksDbWeight::get().reads(4))?;
+				let account: AccountId = env.read_as()?;
+				let attestation = ZkProofModule::attestation_of(&account)
+					.map(|attestation| (attestation.nullifier, attestation.expires_at));
+				env.write(&attestation.encode(), false, None)?;
+				Ok(RetVal::Converging(0))
+			},
+			_ => Err(DispatchError::Other("Unknown chain extension function")),
+		}
+	}
+}
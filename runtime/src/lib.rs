@@ -22,8 +22,8 @@ use frame_support::genesis_builder_helper::{build_config, create_default_config}
 pub use frame_support::{
 	construct_runtime, derive_impl, parameter_types,
 	traits::{
-		ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem, Randomness,
-		StorageInfo,
+		ConstBool, ConstU128, ConstU16, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem,
+		Randomness, StorageInfo,
 	},
 	weights::{
 		constants::{
@@ -31,7 +31,7 @@ pub use frame_support::{
 		},
 		IdentityFee, Weight,
 	},
-	StorageValue,
+	PalletId, StorageValue,
 };
 pub use frame_system::Call as SystemCall;
 pub use pallet_balances::Call as BalancesCall;
@@ -47,6 +47,17 @@ pub use pallet_template;
 /// Import the zkproof pallet.
 pub use pallet_zkproof;
 
+/// Import the session pallet.
+pub use pallet_zk_session;
+
+/// Import the sub-accounts pallet.
+pub use pallet_zk_accounts;
+
+/// Import the claims pallet.
+pub use pallet_zk_claims;
+
+pub mod chain_extension;
+
 /// An index to a block.
 pub type BlockNumber = u32;
 
@@ -102,10 +113,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 2,
 	state_version: 1,
 };
 
@@ -176,6 +187,8 @@ impl frame_system::Config for Runtime {
 	/// This is used as an identifier of the chain. 42 is the generic substrate prefix.
 	type SS58Prefix = SS58Prefix;
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
+	/// Storage migrations spanning several blocks.
+	type MultiBlockMigrator = MultiBlockMigrations;
 }
 
 impl pallet_aura::Config for Runtime {
@@ -225,7 +238,7 @@ impl pallet_balances::Config for Runtime {
 	type WeightInfo = pallet_balances::weights::SubstrateWeight<Runtime>;
 	type FreezeIdentifier = ();
 	type MaxFreezes = ();
-	type RuntimeHoldReason = ();
+	type RuntimeHoldReason = RuntimeHoldReason;
 	type RuntimeFreezeReason = ();
 }
 
@@ -254,9 +267,398 @@ impl pallet_template::Config for Runtime {
 	type WeightInfo = pallet_template::weights::SubstrateWeight<Runtime>;
 }
 
+/// The issuer served by nodes started with `--dev-oidc`. Its keys are only registered in the
+/// genesis of development chains, so its tokens are rejected everywhere else.
+pub const DEV_OIDC_ISSUER: &[u8] = b"http://127.0.0.1:9955";
+
+parameter_types! {
+	/// Google ID tokens, and those of the local development issuer.
+	pub AllowedIssuers: Vec<pallet_zkproof::IssuerId> = vec![
+		pallet_zkproof::IssuerId::truncate_from(pallet_zkproof::GOOGLE_ISSUER.to_vec()),
+		pallet_zkproof::IssuerId::truncate_from(DEV_OIDC_ISSUER.to_vec()),
+	];
+	/// Logins may use at most a quarter of each block.
+	pub const ProofWeightShare: Perbill = Perbill::from_percent(25);
+}
+
+/// The value calls move out of the signer, for the spending limits of device keys. Calls made
+/// through a sub-account or the zkLogin account count as if made by the signer, and sweeping
+/// transfers exceed any limit.
+pub struct TransferredValue;
+
+impl pallet_zkproof::CallSpending<RuntimeCall> for TransferredValue {
+	fn spending(call: &RuntimeCall) -> u128 {
+		match call {
+			RuntimeCall::Balances(
+				pallet_balances::Call::transfer_allow_death { value, .. } |
+				pallet_balances::Call::transfer_keep_alive { value, .. },
+			) => *value,
+			RuntimeCall::Balances(pallet_balances::Call::transfer_all { .. }) => Balance::MAX,
+			RuntimeCall::Contracts(
+				pallet_contracts::Call::call { value, .. } |
+				pallet_contracts::Call::instantiate { value, .. } |
+				pallet_contracts::Call::instantiate_with_code { value, .. },
+			) => *value,
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::fund_sub_account { amount, .. }) =>
+				*amount,
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::sub_account_call { call, .. }) =>
+				Self::spending(call),
+			RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { call, .. }) =>
+				Self::spending(call),
+			RuntimeCall::ZkClaims(pallet_zk_claims::Call::create_distribution {
+				leaves,
+				amount,
+				..
+			}) => amount.saturating_mul(*leaves as Balance),
+			_ => 0,
+		}
+	}
+
+	fn wrapped(call: &RuntimeCall) -> Option<&RuntimeCall> {
+		match call {
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::sub_account_call { call, .. }) =>
+				Some(call),
+			RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { call, .. }) =>
+				Some(call),
+			_ => None,
+		}
+	}
+}
+
+parameter_types! {
+	/// The most weight a proposal of the security council may dispatch with.
+	pub MaxSecurityCouncilProposalWeight: Weight =
+		Perbill::from_percent(50) * BlockWeights::get().max_block;
+}
+
+/// The security council, which onboards the providers, provider keys and circuits logins trust.
+type SecurityCouncilInstance = pallet_collective::Instance1;
+
+impl pallet_collective::Config<SecurityCouncilInstance> for Runtime {
+	type RuntimeOrigin = RuntimeOrigin;
+	type Proposal = RuntimeCall;
+	type RuntimeEvent = RuntimeEvent;
+	type MotionDuration = ConstU32<{ 3 * DAYS }>;
+	type MaxProposals = ConstU32<16>;
+	type MaxMembers = ConstU32<16>;
+	type DefaultVote = pallet_collective::PrimeDefaultVote;
+	type WeightInfo = pallet_collective::weights::SubstrateWeight<Runtime>;
+	type SetMembersOrigin = frame_system::EnsureRoot<AccountId>;
+	type MaxProposalWeight = MaxSecurityCouncilProposalWeight;
+}
+
+/// Onboarding takes root or two thirds of the security council, apart from the routine operations
+/// of `AdminOrigin`.
+pub type OnboardingOrigin = frame_support::traits::EitherOfDiverse<
+	frame_system::EnsureRoot<AccountId>,
+	pallet_collective::EnsureProportionAtLeast<AccountId, SecurityCouncilInstance, 2, 3>,
+>;
+
 impl pallet_zkproof::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	type AdminOrigin = frame_system::EnsureRoot<AccountId>;
+	type OnboardingOrigin = OnboardingOrigin;
 	type MaxJsonLength = ConstU32<100000>;
+	type MaxJwtLength = ConstU32<4096>;
+	type MaxPublicInputs = ConstU32<16>;
+	type MaxJwksKeys = ConstU32<16>;
+	type MaxOracles = ConstU32<16>;
+	// A single oracle is enough for the dev chain; raise this once more oracles are registered.
+	type JwksQuorum = ConstU32<1>;
+	type JwksSubmissionWindow = ConstU32<{ 10 * MINUTES }>;
+	type UnixTime = Timestamp;
+	type JwksGracePeriod = ConstU64<3600>;
+	type JwksFetchInterval = ConstU32<{ 10 * MINUTES }>;
+	type AuthorityId = pallet_zkproof::crypto::OracleAuthId;
+	type AllowedIssuers = AllowedIssuers;
+	type ProviderAdapters = pallet_zkproof::adapters::StandardAdapters;
+	type AllowProofOverwrite = ConstBool<false>;
+	// Never enable outside of local development chains.
+	type InsecureSkipVerification = ConstBool<false>;
+	type ProofWeightShare = ProofWeightShare;
+	type EmitProofPayloads = ConstBool<false>;
+	type ProofLifetime = ConstU64<{ 24 * 3600 }>;
+	type ClockSkewTolerance = ConstU64<60>;
+	type NonceBlockWindow = ConstU32<{ 10 * MINUTES }>;
+	type MaxTokenAge = ConstU64<600>;
+	type RevealWindow = ConstU32<{ 2 * MINUTES }>;
+	// Set where OAuth tokens and emails may not be stored on a public ledger.
+	type PrivacyMode = ConstBool<false>;
+	type MaxErasedProofs = ConstU32<64>;
+	type MaxScheduledActivations = ConstU32<16>;
+	type MaxRecentFailures = ConstU32<32>;
+	type OnProofVerified = ();
+	type MaxVerificationsPerBlock = ConstU32<64>;
+	// Set to let block authors replace the providers' keys, see `--authored-jwks`.
+	type AuthoredJwks = ConstBool<false>;
+	type IdentityRootPeriod = ConstU32<DAYS>;
+	type MaxIdentityRootLeaves = ConstU32<4096>;
+	type MaxDeviceKeys = ConstU32<8>;
+	type CallSpending = TransferredValue;
+	type UnlockDelay = ConstU32<{ 2 * DAYS }>;
+	type OnIdentityLocked = ZkSession;
+	type AttestationGate = VerifiedAssetOperations;
+	type MaxMetadataLength = ConstU32<128>;
+	type CircuitWarningPeriod = ConstU32<DAYS>;
+	type StepUpCalls = SensitiveCalls;
+	type MaxFailedAttempts = ConstU32<5>;
+	type LockoutPeriod = ConstU32<{ 10 * MINUTES }>;
+	type ZkLoginCall = RuntimeCall;
+	type ZkLoginCalls = ZkLoginCalls;
+	type RenewalNotice = ConstU64<3600>;
+	type MaxRenewalReminders = ConstU32<64>;
+	type WeightInfo = pallet_zkproof::weights::SubstrateWeight<Runtime>;
+}
+
+impl pallet_zk_session::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Attestations = ZkProofModule;
+	type UnixTime = Timestamp;
+	type SessionLifetime = ConstU64<{ 8 * 3600 }>;
+	type MaxSessions = ConstU32<32>;
+	type MaxPrunedSessions = ConstU32<64>;
+}
+
+impl pallet_zk_accounts::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeCall = RuntimeCall;
+	type Attestations = ZkProofModule;
+	type Currency = Balances;
+	type MaxSubAccounts = ConstU16<64>;
+}
+
+parameter_types! {
+	pub const ClaimsPalletId: PalletId = PalletId(*b"zk/claim");
+}
+
+impl pallet_zk_claims::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Attestations = ZkProofModule;
+	type Currency = Balances;
+	type PalletId = ClaimsPalletId;
+}
+
+parameter_types! {
+	/// The "KYC-lite" assets, which only accounts holding a valid attestation may receive. Root
+	/// changes them with `system.setStorage`.
+	pub storage VerifiedOnlyAssets: Vec<u32> = Vec::new();
+}
+
+impl pallet_assets::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Balance = Balance;
+	type RemoveItemsLimit = ConstU32<1000>;
+	type AssetId = u32;
+	type AssetIdParameter = codec::Compact<u32>;
+	type Currency = Balances;
+	/// Anyone may create an asset; `VerifiedAssetOperations` reserves it to verified users.
+	type CreateOrigin =
+		frame_support::traits::AsEnsureOriginWithArg<frame_system::EnsureSigned<AccountId>>;
+	type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+	type AssetDeposit = ConstU128<{ deposit(1, 190) }>;
+	type AssetAccountDeposit = ConstU128<{ deposit(1, 16) }>;
+	type MetadataDepositBase = ConstU128<{ deposit(1, 68) }>;
+	type MetadataDepositPerByte = ConstU128<{ deposit(0, 1) }>;
+	type ApprovalDeposit = ConstU128<EXISTENTIAL_DEPOSIT>;
+	type StringLimit = ConstU32<50>;
+	type Freezer = ();
+	type Extra = ();
+	type CallbackHandle = ();
+	type WeightInfo = pallet_assets::weights::SubstrateWeight<Runtime>;
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = ();
+}
+
+/// Reserves creating and minting assets to accounts holding a valid attestation, and so receiving
+/// `VerifiedOnlyAssets`. Calls made through a sub-account or the zkLogin account are checked as if
+/// made by the signer.
+pub struct VerifiedAssetOperations;
+
+impl pallet_zkproof::AttestationGate<AccountId, RuntimeCall> for VerifiedAssetOperations {
+	fn gated_accounts(who: &AccountId, call: &RuntimeCall) -> Vec<AccountId> {
+		use sp_runtime::traits::StaticLookup;
+		let verified_only = |id: &codec::Compact<u32>| VerifiedOnlyAssets::get().contains(&id.0);
+		// Unknown recipients fail the call anyway.
+		let recipient = |address: &Address| {
+			<Runtime as frame_system::Config>::Lookup::lookup(address.clone()).ok()
+		};
+		match call {
+			RuntimeCall::Assets(pallet_assets::Call::create { .. }) => vec![who.clone()],
+			RuntimeCall::Assets(pallet_assets::Call::mint { id, beneficiary, .. }) => {
+				let beneficiary = verified_only(id).then(|| recipient(beneficiary)).flatten();
+				core::iter::once(who.clone()).chain(beneficiary).collect()
+			},
+			RuntimeCall::Assets(
+				pallet_assets::Call::transfer { id, target, .. } |
+				pallet_assets::Call::transfer_keep_alive { id, target, .. } |
+				pallet_assets::Call::transfer_approved { id, destination: target, .. },
+			) if verified_only(id) => recipient(target).into_iter().collect(),
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::sub_account_call { call, .. }) =>
+				Self::gated_accounts(who, call),
+			RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { call, .. }) =>
+				Self::gated_accounts(who, call),
+			_ => Vec::new(),
+		}
+	}
+}
+
+/// Requires a proof stored in the last ten minutes to sweep an account or revoke every device
+/// key, even from an active device key. Calls made through a sub-account or the zkLogin account
+/// are checked as if made by the signer.
+pub struct SensitiveCalls;
+
+impl pallet_zkproof::StepUpCalls<RuntimeCall, BlockNumber> for SensitiveCalls {
+	fn max_proof_age(call: &RuntimeCall) -> Option<BlockNumber> {
+		match call {
+			RuntimeCall::Balances(pallet_balances::Call::transfer_all { .. }) |
+			RuntimeCall::ZkProofModule(pallet_zkproof::Call::revoke_all { .. }) => Some(10 * MINUTES),
+			RuntimeCall::ZkAccounts(pallet_zk_accounts::Call::sub_account_call { call, .. }) =>
+				Self::max_proof_age(call),
+			RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { call, .. }) =>
+				Self::max_proof_age(call),
+			_ => None,
+		}
+	}
+}
+
+/// The calls the zkLogin accounts of identities dispatch with `zkProofModule.zkloginExecute`:
+/// any but the calls of `sudo`, and another `zkloginExecute`.
+pub struct ZkLoginCalls;
+
+impl frame_support::traits::Contains<RuntimeCall> for ZkLoginCalls {
+	fn contains(call: &RuntimeCall) -> bool {
+		!matches!(
+			call,
+			RuntimeCall::Sudo(_) |
+				RuntimeCall::ZkProofModule(pallet_zkproof::Call::zklogin_execute { .. })
+		)
+	}
+}
+
+parameter_types! {
+	/// The share of each block storage migrations may use.
+	pub MbmServiceWeight: Weight = Perbill::from_percent(80) * BlockWeights::get().max_block;
+}
+
+impl pallet_migrations::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	#[cfg(not(feature = "runtime-benchmarks"))]
+	type Migrations = (
+		pallet_zkproof::migrations::v1::IndexIdentityProofs<Runtime>,
+		pallet_zkproof::migrations::v2::ExtendAttestations<Runtime>,
+		pallet_zkproof::migrations::v3::CountStorage<Runtime>,
+		pallet_zk_session::migrations::v1::CountSessions<Runtime>,
+	);
+	// Benchmarks need mocked migrations to guarantee that they succeed.
+	#[cfg(feature = "runtime-benchmarks")]
+	type Migrations = pallet_migrations::mock_helpers::MockedMigrations;
+	type CursorMaxLen = ConstU32<65_536>;
+	type IdentifierMaxLen = ConstU32<256>;
+	type MigrationStatusHandler = ();
+	type FailedMigrationHandler = frame_support::migrations::FreezeChainOnFailedMigration;
+	type MaxServiceWeight = MbmServiceWeight;
+	type WeightInfo = pallet_migrations::weights::SubstrateWeight<Runtime>;
+}
+
+impl pallet_insecure_randomness_collective_flip::Config for Runtime {}
+
+/// The deposit for `items` storage items taking `bytes` bytes.
+const fn deposit(items: u32, bytes: u32) -> Balance {
+	(items as Balance * 100 + bytes as Balance) * EXISTENTIAL_DEPOSIT
+}
+
+parameter_types! {
+	pub const DepositPerItem: Balance = deposit(1, 0);
+	pub const DepositPerByte: Balance = deposit(0, 1);
+	pub const DefaultDepositLimit: Balance = deposit(1024, 1024 * 1024);
+	pub Schedule: pallet_contracts::Schedule<Runtime> = Default::default();
+	pub CodeHashLockupDepositPercent: Perbill = Perbill::from_percent(30);
+}
+
+impl pallet_contracts::Config for Runtime {
+	type Time = Timestamp;
+	type Randomness = RandomnessCollectiveFlip;
+	type Currency = Balances;
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeCall = RuntimeCall;
+	/// Contracts may not dispatch calls, only read attestations through the chain extension.
+	type CallFilter = frame_support::traits::Nothing;
+	type DepositPerItem = DepositPerItem;
+	type DepositPerByte = DepositPerByte;
+	type DefaultDepositLimit = DefaultDepositLimit;
+	type CallStack = [pallet_contracts::Frame<Self>; 5];
+	type WeightPrice = pallet_transaction_payment::Pallet<Self>;
+	type WeightInfo = pallet_contracts::weights::SubstrateWeight<Self>;
+	/// Read access to the attestations of `pallet-zkproof`.
+	type ChainExtension = chain_extension::DotLoginExtension;
+	type Schedule = Schedule;
+	type AddressGenerator = pallet_contracts::DefaultAddressGenerator;
+	type MaxCodeLen = ConstU32<{ 123 * 1024 }>;
+	type MaxStorageKeyLen = ConstU32<128>;
+	type UnsafeUnstableInterface = ConstBool<false>;
+	type UploadOrigin = frame_system::EnsureSigned<AccountId>;
+	type InstantiateOrigin = frame_system::EnsureSigned<AccountId>;
+	type MaxDebugBufferLen = ConstU32<{ 2 * 1024 * 1024 }>;
+	type RuntimeHoldReason = RuntimeHoldReason;
+	#[cfg(not(feature = "runtime-benchmarks"))]
+	type Migrations = ();
+	#[cfg(feature = "runtime-benchmarks")]
+	type Migrations = pallet_contracts::migration::codegen::BenchMigrations;
+	type MaxDelegateDependencies = ConstU32<32>;
+	type CodeHashLockupDepositPercent = CodeHashLockupDepositPercent;
+	type Debug = ();
+	type Environment = ();
+	type ApiVersion = ();
+	type Xcm = ();
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
+where
+	RuntimeCall: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: RuntimeCall,
+		public: <Signature as Verify>::Signer,
+		account: AccountId,
+		nonce: Nonce,
+	) -> Option<(RuntimeCall, <UncheckedExtrinsic as sp_runtime::traits::Extrinsic>::SignaturePayload)> {
+		use codec::Encode;
+		use sp_runtime::{traits::StaticLookup, SaturatedConversion};
+
+		let period =
+			BlockHashCount::get().checked_next_power_of_two().map(|c| c / 2).unwrap_or(2) as u64;
+		let current_block = System::block_number().saturated_into::<u64>().saturating_sub(1);
+		let extra: SignedExtra = (
+			frame_system::CheckNonZeroSender::<Runtime>::new(),
+			frame_system::CheckSpecVersion::<Runtime>::new(),
+			frame_system::CheckTxVersion::<Runtime>::new(),
+			frame_system::CheckGenesis::<Runtime>::new(),
+			frame_system::CheckEra::<Runtime>::from(generic::Era::mortal(period, current_block)),
+			frame_system::CheckNonce::<Runtime>::from(nonce),
+			frame_system::CheckWeight::<Runtime>::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(0),
+			pallet_zkproof::CheckProofStructure::<Runtime>::new(),
+			pallet_zkproof::CheckSpendingLimit::<Runtime>::new(),
+		);
+		let raw_payload = SignedPayload::new(call, extra).ok()?;
+		let signature = raw_payload.using_encoded(|payload| C::sign(payload, public))?;
+		let address = <Runtime as frame_system::Config>::Lookup::unlookup(account);
+		let (call, extra, _) = raw_payload.deconstruct();
+		Some((call, (address, signature, extra)))
+	}
+}
+
+impl frame_system::offchain::SigningTypes for Runtime {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+	RuntimeCall: From<C>,
+{
+	type OverarchingCall = RuntimeCall;
+	type Extrinsic = UncheckedExtrinsic;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -304,6 +706,30 @@ mod runtime {
 	// Include the custom logic from the pallet-template in the runtime.
 	#[runtime::pallet_index(8)]
 	pub type ZkProofModule = pallet_zkproof;
+
+	#[runtime::pallet_index(9)]
+	pub type MultiBlockMigrations = pallet_migrations;
+
+	#[runtime::pallet_index(10)]
+	pub type RandomnessCollectiveFlip = pallet_insecure_randomness_collective_flip;
+
+	#[runtime::pallet_index(11)]
+	pub type Contracts = pallet_contracts;
+
+	#[runtime::pallet_index(12)]
+	pub type ZkSession = pallet_zk_session;
+
+	#[runtime::pallet_index(13)]
+	pub type ZkAccounts = pallet_zk_accounts;
+
+	#[runtime::pallet_index(14)]
+	pub type ZkClaims = pallet_zk_claims;
+
+	#[runtime::pallet_index(15)]
+	pub type Assets = pallet_assets;
+
+	#[runtime::pallet_index(16)]
+	pub type SecurityCouncil = pallet_collective<Instance1>;
 }
 
 /// The address format for describing accounts.
@@ -322,6 +748,8 @@ pub type SignedExtra = (
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
 	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_zkproof::CheckProofStructure<Runtime>,
+	pallet_zkproof::CheckSpendingLimit<Runtime>,
 );
 
 /// All migrations of the runtime, aside from the ones declared in the pallets.
@@ -333,6 +761,8 @@ type Migrations = ();
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic =
 	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, SignedExtra>;
+/// The events recorded by `frame_system`, as returned by contract dry runs.
+pub type EventRecord = frame_system::EventRecord<RuntimeEvent, Hash>;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<RuntimeCall, SignedExtra>;
 /// Executive: handles dispatch to the various modules.
@@ -351,9 +781,13 @@ mod benches {
 		[frame_benchmarking, BaselineBench::<Runtime>]
 		[frame_system, SystemBench::<Runtime>]
 		[pallet_balances, Balances]
+		[pallet_assets, Assets]
 		[pallet_timestamp, Timestamp]
 		[pallet_sudo, Sudo]
 		[pallet_template, TemplateModule]
+		[pallet_zkproof, ZkProofModule]
+		[pallet_migrations, MultiBlockMigrations]
+		[pallet_contracts, Contracts]
 	);
 }
 
@@ -525,6 +959,174 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[api_version(9)]
+	impl pallet_zkproof::runtime_api::ZkProofApi<Block, AccountId, BlockNumber> for Runtime {
+		fn recent_failures() -> Vec<pallet_zkproof::FailureRecord<AccountId, BlockNumber>> {
+			ZkProofModule::recent_failures()
+		}
+
+		fn provider_stats() -> Vec<(pallet_zkproof::IssuerId, pallet_zkproof::ProviderStats<BlockNumber>)> {
+			ZkProofModule::provider_stats()
+		}
+
+		fn providers() -> Vec<(pallet_zkproof::IssuerId, pallet_zkproof::ProviderInfo)> {
+			ZkProofModule::providers()
+		}
+
+		fn jwks(provider: pallet_zkproof::IssuerId) -> Vec<pallet_zkproof::JwkRecord> {
+			ZkProofModule::accepted_jwks(&provider)
+		}
+
+		fn jwks_expiry(provider: pallet_zkproof::IssuerId) -> Option<u64> {
+			pallet_zkproof::JwksExpiry::<Runtime>::get(provider)
+		}
+
+		fn check_proof(
+			proof: Vec<u8>,
+		) -> Result<pallet_zkproof::VerificationReport, sp_runtime::DispatchError> {
+			ZkProofModule::check_proof(&proof)
+		}
+
+		fn identity_root() -> Option<pallet_zkproof::IdentityRoot<BlockNumber>> {
+			ZkProofModule::identity_root()
+		}
+
+		fn identity_proof(
+			commitment: pallet_zkproof::Commitment,
+		) -> Option<pallet_zkproof::IdentityProof> {
+			ZkProofModule::identity_proof(&commitment)
+		}
+
+		fn device_keys(
+			account: AccountId,
+		) -> Vec<(AccountId, pallet_zkproof::DeviceKey<BlockNumber>)> {
+			ZkProofModule::device_keys(&account)
+		}
+
+		fn proofs_in_range(from: BlockNumber, to: BlockNumber) -> Vec<(BlockNumber, Hash)> {
+			ZkProofModule::proofs_in_range(from, to)
+		}
+
+		fn proof_metadata(proof_hash: Hash) -> Option<Vec<u8>> {
+			pallet_zkproof::ProofMetadata::<Runtime>::get(proof_hash)
+				.map(|metadata| metadata.into_inner())
+		}
+
+		fn proofs_with_tag(
+			tag: pallet_zkproof::ProofTag,
+			start_key: Option<Hash>,
+			limit: u32,
+		) -> Vec<Hash> {
+			ZkProofModule::proofs_with_tag(&tag, start_key, limit)
+		}
+
+		fn namespace_proofs(
+			client_id: pallet_zkproof::ClientId,
+			start_key: Option<Hash>,
+			limit: u32,
+		) -> Vec<Hash> {
+			ZkProofModule::namespace_proofs(&client_id, start_key, limit)
+		}
+
+		fn circuit_sunsets() -> Vec<(u32, BlockNumber)> {
+			ZkProofModule::circuit_sunsets()
+		}
+
+		fn storage_stats() -> pallet_zkproof::StorageStats {
+			ZkProofModule::storage_stats()
+		}
+	}
+
+	impl pallet_zk_session::runtime_api::ZkSessionApi<Block, AccountId, BlockNumber> for Runtime {
+		fn session(
+			who: AccountId,
+			dapp: AccountId,
+		) -> Option<pallet_zk_session::Session<BlockNumber>> {
+			ZkSession::active_session(&who, &dapp)
+		}
+
+		fn sessions(who: AccountId) -> Vec<(AccountId, pallet_zk_session::Session<BlockNumber>)> {
+			ZkSession::active_sessions(&who)
+		}
+
+		fn total_sessions() -> u64 {
+			ZkSession::total_sessions()
+		}
+	}
+
+	impl pallet_zk_accounts::runtime_api::ZkAccountsApi<Block, AccountId, BlockNumber> for Runtime {
+		fn sub_accounts(
+			who: AccountId,
+		) -> Vec<(
+			pallet_zk_accounts::SubAccountIndex,
+			pallet_zk_accounts::SubAccount<AccountId, BlockNumber>,
+		)> {
+			ZkAccounts::sub_accounts(&who)
+		}
+	}
+
+	impl pallet_contracts::ContractsApi<Block, AccountId, Balance, BlockNumber, Hash, EventRecord>
+		for Runtime
+	{
+		fn call(
+			origin: AccountId,
+			dest: AccountId,
+			value: Balance,
+			gas_limit: Option<Weight>,
+			storage_deposit_limit: Option<Balance>,
+			input_data: Vec<u8>,
+		) -> pallet_contracts::ContractExecResult<Balance, EventRecord> {
+			let gas_limit = gas_limit.unwrap_or(BlockWeights::get().max_block);
+			Contracts::bare_call(
+				origin,
+				dest,
+				value,
+				gas_limit,
+				storage_deposit_limit,
+				input_data,
+				pallet_contracts::DebugInfo::UnsafeDebug,
+				pallet_contracts::CollectEvents::UnsafeCollect,
+				pallet_contracts::Determinism::Enforced,
+			)
+		}
+
+		fn instantiate(
+			origin: AccountId,
+			value: Balance,
+			gas_limit: Option<Weight>,
+			storage_deposit_limit: Option<Balance>,
+			code: pallet_contracts::Code<Hash>,
+			data: Vec<u8>,
+			salt: Vec<u8>,
+		) -> pallet_contracts::ContractInstantiateResult<AccountId, Balance, EventRecord> {
+			let gas_limit = gas_limit.unwrap_or(BlockWeights::get().max_block);
+			Contracts::bare_instantiate(
+				origin,
+				value,
+				gas_limit,
+				storage_deposit_limit,
+				code,
+				data,
+				salt,
+				pallet_contracts::DebugInfo::UnsafeDebug,
+				pallet_contracts::CollectEvents::UnsafeCollect,
+			)
+		}
+
+		fn upload_code(
+			origin: AccountId,
+			code: Vec<u8>,
+			storage_deposit_limit: Option<Balance>,
+			determinism: pallet_contracts::Determinism,
+		) -> pallet_contracts::CodeUploadResult<Hash, Balance> {
+			Contracts::bare_upload_code(origin, code, storage_deposit_limit, determinism)
+		}
+
+		fn get_storage(address: AccountId, key: Vec<u8>) -> pallet_contracts::GetStorageResult {
+			Contracts::get_storage(address, key)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
@@ -0,0 +1,76 @@
+//! Browser build of the proof checks run by the zkproof pallet.
+//!
+//! Web wallets can call [`verify_proof_json`] on a proof envelope to get the same verdict the chain
+//! would reach on its structure and Groth16 proof, with a precise error, before submitting it.
+//! Checks depending on chain state (trusted issuers, signing keys, expiry, the chain id) are left to
+//! the chain.
+//!
+//! Build with `wasm-pack build verifier-wasm --target web`.
+
+use pallet_zkproof::envelope::validate_envelope;
+use wasm_bindgen::prelude::*;
+
+/// The outcome of checking a proof envelope.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Default)]
+pub struct VerificationReport {
+	/// Whether the envelope is well-formed.
+	pub envelope_valid: bool,
+	/// Whether the Groth16 proof verifies against the embedded verifying key.
+	pub proof_valid: bool,
+	/// Why the envelope is malformed or the proof invalid.
+	pub error: Option<String>,
+	/// The `iss` claim of the token.
+	pub issuer: Option<String>,
+	/// The key id of the token header.
+	pub kid: Option<String>,
+	/// The `exp` claim of the token, in unix seconds.
+	pub expires_at: Option<i64>,
+	/// The hex-encoded commitment the proof is bound to.
+	pub commitment: Option<String>,
+	/// The hex-encoded genesis hash of the chain the proof is meant for.
+	pub chain_id: Option<String>,
+}
+
+#[wasm_bindgen]
+impl VerificationReport {
+	/// Whether the chain would accept the proof, as far as can be told off-chain.
+	#[wasm_bindgen(getter)]
+	pub fn ok(&self) -> bool {
+		self.envelope_valid && self.proof_valid
+	}
+}
+
+/// Check a proof envelope, as submitted to `store_zk_proof`.
+#[wasm_bindgen(js_name = verifyProofJson)]
+pub fn verify_proof_json(bytes: &[u8]) -> VerificationReport {
+	let parsed = match validate_envelope(bytes) {
+		Ok(parsed) => parsed,
+		Err(e) => return VerificationReport { error: Some(e.to_string()), ..Default::default() },
+	};
+
+	let proof_valid = parsed.verify();
+	VerificationReport {
+		envelope_valid: true,
+		proof_valid,
+		error: (!proof_valid).then(|| "the proof does not verify against its verifying key".into()),
+		issuer: Some(parsed.claims.iss.clone()),
+		kid: Some(parsed.kid.clone()),
+		expires_at: Some(parsed.claims.exp),
+		commitment: Some(hex::encode(parsed.commitment())),
+		chain_id: Some(hex::encode(parsed.chain_id)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn malformed_envelopes_are_reported() {
+		let report = verify_proof_json(b"not json");
+		assert!(!report.ok());
+		assert!(!report.envelope_valid);
+		assert_eq!(report.error.as_deref(), Some("envelope is not a valid proof JSON"));
+	}
+}